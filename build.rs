@@ -4,4 +4,7 @@ fn main() {
     println!("cargo:rerun-if-changed=templates/gitbook.css");
     println!("cargo:rerun-if-changed=templates/gitbook.js");
     println!("cargo:rerun-if-changed=templates/collapsible.js");
+    println!("cargo:rerun-if-changed=templates/book.schema.json");
+    println!("cargo:rerun-if-changed=templates/pdf.css");
+    println!("cargo:rerun-if-changed=templates/epub.css");
 }
@@ -0,0 +1,14 @@
+//! Render arbitrary text as a page through every markdown pass.
+//! Run with `cargo +nightly fuzz run render_markdown` from the repository root.
+
+#![no_main]
+
+use guidebook::builder::render_markdown_with_path;
+use guidebook::parser::AutolinkConfig;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|content: &str| {
+    let autolink = AutolinkConfig { exclude: vec!["https://*.test/*".to_string()], ..Default::default() };
+    render_markdown_with_path(content, None, false, &AutolinkConfig::default());
+    render_markdown_with_path(content, Some("guide/chapter.md"), true, &autolink);
+});
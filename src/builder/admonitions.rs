@@ -0,0 +1,255 @@
+//! Admonition blocks (callouts)
+//!
+//! Blockquotes starting with `[!type]` are rendered as admonitions, the
+//! callout syntax of Obsidian (and GitHub alerts, which use the same form):
+//!
+//! ```markdown
+//! > [!tip] Optional title
+//! > Content, with any Markdown.
+//! ```
+//!
+//! `[!type]-` makes the admonition foldable and collapsed, `[!type]+`
+//! foldable and open. Types use Obsidian's names and aliases (`hint` is a
+//! `tip`, `caution` a `warning`, ...); unknown types are styled as notes.
+//! Admonitions can be nested.
+//!
+//! A blockquote whose last line is an attribution (`> — Author`, also with
+//! `―` or `--`) becomes a `<figure>` with the quote and a `<figcaption>`.
+
+use super::renderer::html_escape;
+use regex::Regex;
+
+/// `[!type]`, fold marker and title on the first line of a blockquote
+const CALLOUT_PATTERN: &str = r"^\[!([A-Za-z][\w-]*)\]([+-]?)(?:\s+(.*?))?\s*$";
+
+/// Attribution line ending a blockquote
+const ATTRIBUTION_PATTERN: &str = r"^(?:[—―]|--\s)\s*(\S.*?)\s*$";
+
+/// Type aliases -> the type they are styled as
+const ALIASES: &[(&str, &str)] = &[
+    ("summary", "abstract"),
+    ("tldr", "abstract"),
+    ("hint", "tip"),
+    ("important", "tip"),
+    ("check", "success"),
+    ("done", "success"),
+    ("help", "question"),
+    ("faq", "question"),
+    ("caution", "warning"),
+    ("attention", "warning"),
+    ("fail", "failure"),
+    ("missing", "failure"),
+    ("error", "danger"),
+    ("cite", "quote"),
+];
+
+/// Types with their own style
+const TYPES: &[&str] = &[
+    "note", "abstract", "info", "todo", "tip", "success", "question", "warning", "failure", "danger", "bug", "example",
+    "quote",
+];
+
+/// Replace callout blockquotes in Markdown with admonition HTML blocks whose
+/// content stays Markdown
+pub fn convert_callouts(content: &str) -> String {
+    if !content.contains("[!") {
+        return content.to_string();
+    }
+    let re = Regex::new(CALLOUT_PATTERN).unwrap();
+    let lines: Vec<&str> = content.split('\n').collect();
+    let mut result: Vec<String> = Vec::with_capacity(lines.len());
+    let mut fence: Option<&str> = None;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+        } else if let Some(callout) = quote_text(line).and_then(|text| re.captures(text)) {
+            let indent = &line[..line.len() - trimmed.len()];
+            let mut body = Vec::new();
+            i += 1;
+            while let Some(text) = lines.get(i).and_then(|l| l.strip_prefix(indent)).and_then(quote_text) {
+                body.push(text);
+                i += 1;
+            }
+
+            let kind = callout[1].to_lowercase();
+            let style = ALIASES.iter().find(|(alias, _)| *alias == kind).map(|(_, t)| *t).unwrap_or(&kind);
+            let style = if TYPES.contains(&style) { style } else { "note" };
+            let title = match callout.get(3).map(|m| m.as_str()).filter(|t| !t.is_empty()) {
+                Some(title) => title.to_string(),
+                None => capitalize(&kind),
+            };
+            let class = format!("admonition admonition-{}", style);
+            let (open, title_html, close) = match &callout[2] {
+                "" => (
+                    format!("<div class=\"{}\">", class),
+                    format!("<p class=\"admonition-title\">{}</p>", html_escape(&title)),
+                    "</div>",
+                ),
+                fold => (
+                    format!("<details class=\"{}\"{}>", class, if fold == "+" { " open" } else { "" }),
+                    format!("<summary class=\"admonition-title\">{}</summary>", html_escape(&title)),
+                    "</details>",
+                ),
+            };
+
+            let body = convert_callouts(&body.join("\n"));
+            result.push(format!("{}{}", indent, open));
+            result.push(format!("{}{}", indent, title_html));
+            result.push(String::new());
+            if !body.trim().is_empty() {
+                result.extend(body.split('\n').map(|l| format!("{}{}", indent, l).trim_end().to_string()));
+                result.push(String::new());
+            }
+            result.push(format!("{}{}", indent, close));
+            continue;
+        }
+        result.push(line.to_string());
+        i += 1;
+    }
+    result.join("\n")
+}
+
+/// Replace blockquotes ending with an attribution line with figures whose
+/// quote stays Markdown
+pub fn convert_attributions(content: &str) -> String {
+    if !content.contains('>') || !(content.contains('—') || content.contains('―') || content.contains("--")) {
+        return content.to_string();
+    }
+    let re = Regex::new(ATTRIBUTION_PATTERN).unwrap();
+    let lines: Vec<&str> = content.split('\n').collect();
+    let mut result: Vec<String> = Vec::with_capacity(lines.len());
+    let mut fence: Option<&str> = None;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+        } else if quote_text(line).is_some() {
+            let indent = &line[..line.len() - trimmed.len()];
+            let mut quote = Vec::new();
+            let mut end = i;
+            while let Some(text) = lines.get(end).and_then(|l| l.strip_prefix(indent)).and_then(quote_text) {
+                quote.push(text);
+                end += 1;
+            }
+            while quote.last().is_some_and(|text| text.trim().is_empty()) {
+                quote.pop();
+            }
+
+            let attribution = quote.last().and_then(|text| re.captures(text.trim()));
+            let body = &quote[..quote.len().saturating_sub(1)];
+            match attribution {
+                Some(attribution) if body.iter().any(|text| !text.trim().is_empty()) => {
+                    let body = convert_attributions(&body.join("\n"));
+                    result.push(format!("{}<figure class=\"quote\">", indent));
+                    result.push(format!("{}<blockquote>", indent));
+                    result.push(String::new());
+                    result.extend(body.split('\n').map(|l| format!("{}{}", indent, l).trim_end().to_string()));
+                    result.push(String::new());
+                    result.push(format!("{}</blockquote>", indent));
+                    result.push(format!("{}<figcaption>— {}</figcaption>", indent, html_escape(&attribution[1])));
+                    result.push(format!("{}</figure>", indent));
+                }
+                _ => result.extend(lines[i..end].iter().map(|l| l.to_string())),
+            }
+            i = end;
+            continue;
+        }
+        result.push(line.to_string());
+        i += 1;
+    }
+    result.join("\n")
+}
+
+/// Text of a blockquote line after its `>` marker (and the space after it),
+/// None if the line isn't quoted
+fn quote_text(line: &str) -> Option<&str> {
+    let rest = line.trim_start().strip_prefix('>')?;
+    Some(rest.strip_prefix(' ').unwrap_or(rest))
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::renderer::render_markdown;
+
+    #[test]
+    fn test_callouts() {
+        let html = render_markdown("> [!tip] Use **this**\n> First line\n>\n> - item\n\nAfter\n");
+        assert!(html.contains("<div class=\"admonition admonition-tip\">"), "{}", html);
+        assert!(html.contains("<p class=\"admonition-title\">Use **this**</p>"), "{}", html);
+        assert!(html.contains("<p>First line</p>"), "{}", html);
+        assert!(html.contains("<li>item</li>"), "{}", html);
+        assert!(html.contains("</div>\n<p>After</p>"), "{}", html);
+        assert!(!html.contains("blockquote"), "{}", html);
+
+        // Aliases, default titles and unknown types
+        let html = render_markdown("> [!CAUTION]\n> Careful\n\n> [!custom]\n> Text\n");
+        assert!(html.contains("admonition-warning\">\n<p class=\"admonition-title\">Caution</p>"), "{}", html);
+        assert!(html.contains("admonition-note\">\n<p class=\"admonition-title\">Custom</p>"), "{}", html);
+
+        // Plain blockquotes and code are left alone
+        let html = render_markdown("> Just a quote [!tip]\n\n```\n> [!tip]\n```\n");
+        assert!(html.contains("<blockquote>"), "{}", html);
+        assert!(html.contains("<code>&gt; [!tip]\n</code>"), "{}", html);
+    }
+
+    #[test]
+    fn test_foldable_and_nested_callouts() {
+        let html = render_markdown("> [!faq]- Why?\n> Because\n> > [!note]+\n> > Inner\n");
+        assert!(html.contains("<details class=\"admonition admonition-question\">"), "{}", html);
+        assert!(html.contains("<summary class=\"admonition-title\">Why?</summary>"), "{}", html);
+        assert!(html.contains("<details class=\"admonition admonition-note\" open>"), "{}", html);
+        assert!(html.contains("<p>Inner</p>"), "{}", html);
+        assert_eq!(html.matches("</details>").count(), 2, "{}", html);
+    }
+
+    #[test]
+    fn test_blockquote_attribution() {
+        let html = render_markdown("> Simplicity is *prerequisite* for reliability.\n> — Edsger W. Dijkstra\n\n> Plain quote\n");
+        assert!(
+            html.contains("<figure class=\"quote\">\n<blockquote>\n<p>Simplicity is <em>prerequisite</em> for reliability.</p>\n</blockquote>\n<figcaption>— Edsger W. Dijkstra</figcaption>\n</figure>"),
+            "{}",
+            html
+        );
+        assert!(html.contains("<blockquote>\n<p>Plain quote</p>"), "{}", html);
+        assert_eq!(html.matches("<figure").count(), 1, "{}", html);
+
+        // Other markers, nested quotes; a lone attribution or code is left alone
+        let converted = convert_attributions("> Outer\n> > Inner\n> > -- A & B\n>\n> ― C\n\n> — Nobody\n\n```\n> x\n> — y\n```");
+        assert!(converted.contains("<figcaption>— A &amp; B</figcaption>"), "{}", converted);
+        assert!(converted.contains("<figcaption>— C</figcaption>"), "{}", converted);
+        assert!(converted.contains("\n> — Nobody\n") && converted.ends_with("> x\n> — y\n```"), "{}", converted);
+    }
+
+    #[test]
+    fn test_callout_in_list_keeps_indentation() {
+        let converted = convert_callouts("- item\n\n  > [!note]\n  > Text\n");
+        assert_eq!(
+            converted,
+            "- item\n\n  <div class=\"admonition admonition-note\">\n  <p class=\"admonition-title\">Note</p>\n\n  Text\n\n  </div>\n"
+        );
+    }
+}
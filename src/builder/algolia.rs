@@ -0,0 +1,200 @@
+//! Algolia DocSearch (`search.algolia` in book.json)
+//!
+//! Pages search with the DocSearch UI, against the Algolia index, instead of
+//! search.js and search_index.json. The index is filled either way:
+//!
+//! - by the DocSearch crawler: the build writes its configuration to
+//!   `docsearch.json` (selectors of the page layout, start and sitemap URLs
+//!   under `deploy.siteUrl`)
+//! - by the build itself: `build --push-search` sends the entries of the
+//!   search index as DocSearch records, with the admin API key read from the
+//!   environment (`ALGOLIA_ADMIN_KEY` unless `adminKeyEnv` names another).
+//!   Records go to a temporary copy of the index that then replaces it, so
+//!   searches never see a half-written index.
+
+use super::search::SearchEntry;
+use super::sitemap::SITEMAP_FILE;
+use crate::parser::{AlgoliaConfig, BookConfig};
+use anyhow::{anyhow, bail, Result};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// File name of the crawler configuration
+pub const CRAWLER_CONFIG_FILE: &str = "docsearch.json";
+
+/// Environment variable of the admin API key unless `adminKeyEnv` is set
+const DEFAULT_ADMIN_KEY_ENV: &str = "ALGOLIA_ADMIN_KEY";
+
+/// Records per batch request
+const BATCH_SIZE: usize = 1000;
+
+/// Longest record content (bytes); Algolia plans limit the size of records
+const MAX_CONTENT: usize = 8000;
+
+/// Public URL of the book (of a language's directory), with a trailing slash;
+/// None without `deploy.siteUrl`
+fn book_url(config: &BookConfig, language: Option<&str>) -> Option<String> {
+    let site_url = config.deploy.site_url.trim_end_matches('/');
+    if site_url.is_empty() {
+        return None;
+    }
+    Some(match language.map(|code| config.languages.output_dir(code)).filter(|dir| !dir.is_empty()) {
+        Some(dir) => format!("{}/{}/", site_url, dir),
+        None => format!("{}/", site_url),
+    })
+}
+
+/// Write `docsearch.json` for the DocSearch crawler; returns false without
+/// `deploy.siteUrl`, which it starts from
+pub fn write_crawler_config(output: &Path, config: &BookConfig, algolia: &AlgoliaConfig, language: Option<&str>) -> Result<bool> {
+    let Some(url) = book_url(config, language) else {
+        return Ok(false);
+    };
+    let crawler = json!({
+        "index_name": algolia.index(language),
+        "start_urls": [url],
+        "sitemap_urls": [format!("{}{}", url, SITEMAP_FILE)],
+        "selectors": {
+            "lvl0": { "selector": ".breadcrumbs li:first-child span", "global": true, "default_value": config.title },
+            "lvl1": ".markdown-section h1",
+            "lvl2": ".markdown-section h2",
+            "lvl3": ".markdown-section h3",
+            "lvl4": ".markdown-section h4",
+            "lvl5": ".markdown-section h5",
+            "text": ".markdown-section p, .markdown-section li, .markdown-section td",
+        },
+        "selectors_exclude": [".breadcrumbs", ".page-footer-nav", ".page-last-updated"],
+    });
+    fs::write(output.join(CRAWLER_CONFIG_FILE), serde_json::to_string_pretty(&crawler)?)?;
+    Ok(true)
+}
+
+/// DocSearch records of the search index entries: pages are `lvl1`, their
+/// heading sections `lvl2`, under their part (or the book title) as `lvl0`
+fn records(entries: &[SearchEntry], config: &BookConfig, book_url: &str) -> Vec<Value> {
+    entries
+        .iter()
+        .map(|entry| {
+            let url = format!("{}{}", book_url, entry.path);
+            let (page_url, anchor) = match url.split_once('#') {
+                Some((page, anchor)) => (page.to_string(), Some(anchor.to_string())),
+                None => (url.clone(), None),
+            };
+            json!({
+                "objectID": url,
+                "url": url,
+                "url_without_anchor": page_url,
+                "anchor": anchor,
+                "type": if entry.section.is_some() { "lvl2" } else { "lvl1" },
+                "hierarchy": {
+                    "lvl0": entry.part.as_deref().unwrap_or(&config.title),
+                    "lvl1": entry.title,
+                    "lvl2": entry.section,
+                    "lvl3": null,
+                    "lvl4": null,
+                    "lvl5": null,
+                    "lvl6": null,
+                },
+                "content": truncate(&entry.content, MAX_CONTENT),
+            })
+        })
+        .collect()
+}
+
+/// `text` cut to at most `max` bytes, at a character boundary
+fn truncate(text: &str, max: usize) -> &str {
+    if text.len() <= max {
+        return text;
+    }
+    let end = (0..=max).rev().find(|&i| text.is_char_boundary(i)).unwrap_or(0);
+    &text[..end]
+}
+
+/// Replace the records of the index with the book's search index entries;
+/// returns the number of records sent
+pub fn push(entries: &[SearchEntry], config: &BookConfig, algolia: &AlgoliaConfig, language: Option<&str>) -> Result<usize> {
+    let Some(url) = book_url(config, language) else {
+        bail!("Set deploy.siteUrl in book.json to push search records to Algolia");
+    };
+    let key_env = algolia.admin_key_env.as_deref().unwrap_or(DEFAULT_ADMIN_KEY_ENV);
+    let admin_key = std::env::var(key_env).map_err(|_| anyhow!("Set {} to the Algolia admin API key to push search records", key_env))?;
+
+    let index = algolia.index(language);
+    let temporary = format!("{}_tmp", index);
+    let indexes = format!("https://{}.algolia.net/1/indexes", algolia.app_id);
+    // missing_ok: a missing index is not an error
+    let post = |path: &str, body: &Value, missing_ok: bool| {
+        let response = ureq::post(&format!("{}/{}", indexes, path))
+            .set("X-Algolia-Application-Id", &algolia.app_id)
+            .set("X-Algolia-API-Key", &admin_key)
+            .set("Content-Type", "application/json; charset=utf-8")
+            .timeout(Duration::from_secs(60))
+            .send_string(&body.to_string());
+        match response {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(404, _)) if missing_ok => Ok(()),
+            Err(e) => Err(anyhow!("Algolia request failed: {}", e)),
+        }
+    };
+    let encode = |name: &str| utf8_percent_encode(name, NON_ALPHANUMERIC).to_string();
+
+    // The temporary index keeps the settings, synonyms and rules of the
+    // index, which doesn't exist before the first push
+    let scope = json!({ "operation": "copy", "destination": temporary, "scope": ["settings", "synonyms", "rules"] });
+    post(&format!("{}/operation", encode(&index)), &scope, true)?;
+    let records = records(entries, config, &url);
+    for chunk in records.chunks(BATCH_SIZE) {
+        let requests: Vec<Value> = chunk.iter().map(|record| json!({ "action": "addObject", "body": record })).collect();
+        post(&format!("{}/batch", encode(&temporary)), &json!({ "requests": requests }), false)?;
+    }
+    post(&format!("{}/operation", encode(&temporary)), &json!({ "operation": "move", "destination": index }), false)?;
+    Ok(records.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_and_crawler_config() {
+        let config: BookConfig = serde_json::from_str(
+            r#"{"title": "Handbook", "deploy": {"siteUrl": "https://docs.example.com/"}, "languages": {"default": "en"},
+                "search": {"algolia": {"appId": "APP", "apiKey": "search-key", "indexName": "handbook"}}}"#,
+        )
+        .unwrap();
+        let entries = vec![
+            SearchEntry { title: "Start".to_string(), section: None, path: "guide/start.html".to_string(), part: None, content: "Begin here.".to_string(), reading_minutes: Some(1) },
+            SearchEntry {
+                title: "Start".to_string(),
+                section: Some("Install".to_string()),
+                path: "guide/start.html#install".to_string(),
+                part: Some("Guide".to_string()),
+                content: "é".repeat(MAX_CONTENT),
+                reading_minutes: None,
+            },
+        ];
+        let records = records(&entries, &config, "https://docs.example.com/ja/");
+        assert_eq!(records[0]["url"], "https://docs.example.com/ja/guide/start.html");
+        assert_eq!(records[0]["type"], "lvl1");
+        assert_eq!(records[0]["hierarchy"]["lvl0"], "Handbook");
+        assert_eq!(records[1]["url_without_anchor"], "https://docs.example.com/ja/guide/start.html");
+        assert_eq!(records[1]["anchor"], "install");
+        assert_eq!(records[1]["hierarchy"]["lvl0"], "Guide");
+        assert_eq!(records[1]["hierarchy"]["lvl2"], "Install");
+        assert_eq!(records[1]["content"].as_str().unwrap().len(), MAX_CONTENT);
+
+        let dir = tempfile::tempdir().unwrap();
+        let algolia = config.search.algolia.as_ref().unwrap();
+        assert!(write_crawler_config(dir.path(), &config, algolia, Some("ja")).unwrap());
+        let crawler: Value = serde_json::from_str(&fs::read_to_string(dir.path().join(CRAWLER_CONFIG_FILE)).unwrap()).unwrap();
+        assert_eq!(crawler["index_name"], "handbook_ja");
+        assert_eq!(crawler["start_urls"], json!(["https://docs.example.com/ja/"]));
+        assert_eq!(crawler["sitemap_urls"], json!(["https://docs.example.com/ja/sitemap.xml"]));
+
+        // The crawler starts from the site URL
+        assert!(!write_crawler_config(dir.path(), &BookConfig::default(), algolia, None).unwrap());
+    }
+}
@@ -0,0 +1,171 @@
+//! Downloadable attachments
+//!
+//! The `{% file %}` tag renders a download card for a file of the book, with
+//! its type and size:
+//!
+//! ```text
+//! {% file "downloads/template.xlsx" title="Report template" %}
+//! ```
+//!
+//! The path is relative to the book root, like Markdown links. Tags are
+//! expanded before the page's templates are rendered (code blocks and raw
+//! blocks are left alone), and the files of the cards on a page are copied to
+//! the output when it is written. Cards carry the file's path in
+//! `data-attachment`, which the build manifest lists them by.
+
+use super::media::copy_file;
+use super::nunjucks::find_protected_regions;
+use super::renderer::html_escape;
+use crate::{paths, warning};
+use anyhow::Result;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+/// `{% file "path" key="value" ... %}`
+const FILE_TAG_PATTERN: &str = r#"\{%-?\s*file\s+"([^"]+)"((?:\s+[A-Za-z_]\w*="[^"]*")*)\s*-?%\}"#;
+
+/// Attribute holding the book path of an attachment card's file
+pub const ATTACHMENT_ATTR: &str = "data-attachment";
+
+/// Replace `{% file %}` tags in a page's Markdown with download cards
+/// book_dir: book source directory the paths are resolved in
+/// source_path: the page, named in warnings
+pub fn expand_file_tags(content: &str, book_dir: &Path, source_path: &str) -> String {
+    if !content.contains("file") || !content.contains("{%") {
+        return content.to_string();
+    }
+    let tag_re = Regex::new(FILE_TAG_PATTERN).unwrap();
+    let attr_re = Regex::new(r#"([A-Za-z_]\w*)="([^"]*)""#).unwrap();
+
+    let mut result = String::with_capacity(content.len());
+    let mut last = 0;
+    let expand = |segment: &str, result: &mut String| {
+        let expanded = tag_re.replace_all(segment, |tag: &regex::Captures| {
+            let path = paths::normalize(&tag[1]).trim_start_matches('/').to_string();
+            let mut title = None;
+            for attr in attr_re.captures_iter(&tag[2]) {
+                match &attr[1] {
+                    "title" => title = Some(attr[2].to_string()),
+                    other => warning!("{}: unknown `{}` in {{% file \"{}\" %}}", source_path, other, &tag[1]),
+                }
+            }
+            let size = fs::metadata(book_dir.join(&path)).ok().filter(|m| m.is_file()).map(|m| m.len());
+            if size.is_none() {
+                warning!("{}: attachment {} not found", source_path, path);
+            }
+            card_html(&path, title.as_deref(), size)
+        });
+        result.push_str(&expanded);
+    };
+
+    for region in find_protected_regions(content) {
+        expand(&content[last..region.start], &mut result);
+        result.push_str(&content[region.start..region.end]);
+        last = region.end;
+    }
+    expand(&content[last..], &mut result);
+    result
+}
+
+/// Download card of a book file, an HTML block followed by a blank line so
+/// the Markdown after it is rendered
+fn card_html(path: &str, title: Option<&str>, size: Option<u64>) -> String {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let kind = name.rsplit_once('.').map(|(_, ext)| ext.to_uppercase()).unwrap_or_else(|| "FILE".to_string());
+    let meta = match size {
+        Some(size) => format!("{} · {}", name, format_size(size)),
+        None => name.to_string(),
+    };
+    format!(
+        "<div class=\"file-card\"><a href=\"{}\" download {}=\"{}\"><span class=\"file-card-type\">{}</span><span class=\"file-card-info\"><span class=\"file-card-title\">{}</span><span class=\"file-card-meta\">{}</span></span></a></div>\n",
+        html_escape(&paths::encode_href(path)),
+        ATTACHMENT_ATTR,
+        html_escape(path),
+        html_escape(&kind),
+        html_escape(title.unwrap_or(name)),
+        html_escape(&meta)
+    )
+}
+
+/// Human-readable file size ("512 B", "12.4 KB", "3.0 MB")
+pub(super) fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Paths in the `data-attachment` attributes of a page's HTML: book paths
+/// while rendering, relative to the page once published
+pub fn attachment_paths(html: &str) -> Vec<String> {
+    if !html.contains(ATTACHMENT_ATTR) {
+        return Vec::new();
+    }
+    let re = Regex::new(&format!(r#"\b{}="([^"]*)""#, ATTACHMENT_ATTR)).unwrap();
+    re.captures_iter(html)
+        .map(|c| c[1].replace("&quot;", "\"").replace("&lt;", "<").replace("&gt;", ">").replace("&amp;", "&"))
+        .collect()
+}
+
+/// Copy the files of a page's attachment cards from the book source to the
+/// output, and make their `data-attachment` paths relative to the page
+/// html_path: output path of the page
+pub fn publish_attachments(html: String, source: &Path, output: &Path, html_path: &str) -> Result<String> {
+    let paths = attachment_paths(&html);
+    if paths.is_empty() {
+        return Ok(html);
+    }
+    for path in &paths {
+        copy_file(source, output, path)?;
+    }
+    let root = "../".repeat(paths::depth(html_path));
+    Ok(html.replace(&format!("{}=\"", ATTACHMENT_ATTR), &format!("{}=\"{}", ATTACHMENT_ATTR, root)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_tags_render_cards() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("downloads")).unwrap();
+        fs::write(dir.path().join("downloads/report template.xlsx"), vec![0u8; 12_700]).unwrap();
+
+        let content = concat!(
+            "Intro\n\n{% file \"/downloads/report template.xlsx\" title=\"Report <template>\" %}\nAfter\n\n",
+            "```\n{% file \"downloads/report template.xlsx\" %}\n```\n",
+            "{%- file \"missing.pdf\" -%}\n"
+        );
+        let result = expand_file_tags(content, dir.path(), "guide.md");
+        assert!(
+            result.contains(concat!(
+                "<div class=\"file-card\"><a href=\"downloads/report%20template.xlsx\" download data-attachment=\"downloads/report template.xlsx\">",
+                "<span class=\"file-card-type\">XLSX</span><span class=\"file-card-info\"><span class=\"file-card-title\">Report &lt;template&gt;</span>",
+                "<span class=\"file-card-meta\">report template.xlsx · 12.4 KB</span></span></a></div>\n\nAfter"
+            )),
+            "{}",
+            result
+        );
+        // Code blocks keep the tag
+        assert!(result.contains("```\n{% file \"downloads/report template.xlsx\" %}\n```"), "{}", result);
+        // Missing files still get a card, named after the file
+        assert!(result.contains("<span class=\"file-card-title\">missing.pdf</span><span class=\"file-card-meta\">missing.pdf</span>"), "{}", result);
+        assert_eq!(attachment_paths(&result), vec!["downloads/report template.xlsx", "missing.pdf"]);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+    }
+}
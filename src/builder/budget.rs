@@ -0,0 +1,74 @@
+//! Output size budgets (`budget` section of book.json)
+//!
+//! After a build, every HTML page larger than `maxPageSize`, every other file
+//! larger than `maxAssetSize` and an output directory larger than
+//! `maxTotalSize` get a warning, so an accidental 50 MB image is caught
+//! before it is published (`build --strict` fails on them). Hidden entries
+//! of the output (.git, ...) are not counted.
+
+use super::attachments::format_size;
+use crate::parser::BudgetConfig;
+use crate::{paths, warning};
+use std::path::Path;
+
+/// Warn about the files of `output` over budget; returns the number of warnings
+pub fn check_budget(output: &Path, budget: &BudgetConfig) -> usize {
+    let (max_page, max_asset, max_total) = (budget.max_page_size, budget.max_asset_size, budget.max_total_size);
+    if max_page.is_none() && max_asset.is_none() && max_total.is_none() {
+        return 0;
+    }
+
+    let mut warnings = 0;
+    let mut total = 0;
+    for entry in paths::walk(output)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(paths::readable)
+        .filter(|e| e.file_type().is_file())
+    {
+        let Ok(metadata) = entry.metadata() else { continue };
+        let size = metadata.len();
+        total += size;
+        let Some(path) = paths::relative(output, entry.path()) else { continue };
+        let (kind, limit) = if path.ends_with(".html") { ("page", max_page) } else { ("file", max_asset) };
+        if let Some(limit) = limit.filter(|limit| size > limit.0) {
+            warning!("{}: {} is {}, over the budget of {}", path, kind, format_size(size), format_size(limit.0));
+            warnings += 1;
+        }
+    }
+    if let Some(limit) = max_total.filter(|limit| total > limit.0) {
+        warning!("output is {}, over the budget of {}", format_size(total), format_size(limit.0));
+        warnings += 1;
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ByteSize;
+    use std::fs;
+
+    #[test]
+    fn test_check_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path();
+        fs::create_dir_all(output.join("images")).unwrap();
+        fs::create_dir_all(output.join(".git")).unwrap();
+        fs::write(output.join("index.html"), "x".repeat(100)).unwrap();
+        fs::write(output.join("big.html"), "x".repeat(3000)).unwrap();
+        fs::write(output.join("images/huge.png"), vec![0; 5000]).unwrap();
+        fs::write(output.join(".git/pack"), vec![0; 100_000]).unwrap();
+
+        let budget = |page: Option<u64>, asset: Option<u64>, total: Option<u64>| BudgetConfig {
+            max_page_size: page.map(ByteSize),
+            max_asset_size: asset.map(ByteSize),
+            max_total_size: total.map(ByteSize),
+        };
+        assert_eq!(check_budget(output, &budget(None, None, None)), 0);
+        assert_eq!(check_budget(output, &budget(Some(2048), None, None)), 1);
+        assert_eq!(check_budget(output, &budget(Some(2048), Some(4096), Some(8100))), 2);
+        assert_eq!(check_budget(output, &budget(None, Some(4096), Some(8000))), 2);
+    }
+}
@@ -0,0 +1,243 @@
+//! Page cache of incremental builds
+//!
+//! `build --incremental`, `watch` and the preview server record a hash of the
+//! inputs of every chapter page in a cache file next to the output directory:
+//! the page's source files and the files they @import. Everything else pages
+//! are rendered from (book.json and the build options, SUMMARY.md, the
+//! glossary, page templates, front matter defaults, data files, images,
+//! downloads, ...) goes into one hash of the book; files other than Markdown
+//! and AsciiDoc sources are compared by size and modification time.
+//!
+//! The next build doesn't render a page again when neither hash changed and
+//! its files are still in the output: they are kept, and its search entries
+//! are taken from the cache. Files of chapters removed from SUMMARY.md stay
+//! until the next full build.
+
+use super::search::SearchEntry;
+use super::{BookContext, BuildOptions, IMPORT_PATTERN};
+use crate::paths;
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Extensions of the sources hashed with the pages that use them
+const SOURCE_EXTENSIONS: [&str; 3] = ["md", "adoc", "asciidoc"];
+
+/// Markdown files of the book every page depends on
+const BOOK_FILES: [&str; 3] = ["SUMMARY.md", "GLOSSARY.md", "LANGS.md"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedPage {
+    /// Hash of the page's source files and their @imports
+    inputs: String,
+    /// Output files of the page (one per section of a split page)
+    outputs: Vec<String>,
+    search: Vec<SearchEntry>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BookCache {
+    /// Hash of the inputs every page shares
+    book: String,
+    /// Pages by SUMMARY.md source path
+    pages: BTreeMap<String, CachedPage>,
+}
+
+/// Books of the cache file by language code ("" for a single-language book)
+type CacheFile = BTreeMap<String, BookCache>;
+
+/// Pages of the previous build a book can keep, and the pages of this build
+pub struct PageCache {
+    path: PathBuf,
+    language: String,
+    book: String,
+    /// Pages of the previous build (none when the book hash changed)
+    previous: BTreeMap<String, CachedPage>,
+    pages: BTreeMap<String, CachedPage>,
+    /// Number of output pages kept from the previous build
+    pub reused: usize,
+}
+
+impl PageCache {
+    /// Cache of the book (or language) from the cache file at `path`
+    /// book: hash of the book's shared inputs (see `book_hash`)
+    pub fn load(path: &Path, language: Option<&str>, book: String) -> Self {
+        let language = language.unwrap_or_default().to_string();
+        let previous = read_cache(path)
+            .remove(&language)
+            .filter(|cache| cache.book == book)
+            .map(|cache| cache.pages)
+            .unwrap_or_default();
+        PageCache { path: path.to_path_buf(), language, book, previous, pages: BTreeMap::new(), reused: 0 }
+    }
+
+    /// Search entries of a page whose inputs are unchanged since the previous
+    /// build and whose files are still in `output`; None if it must be rendered
+    pub fn reuse(&mut self, source_path: &str, inputs: &str, output: &Path) -> Option<Vec<SearchEntry>> {
+        let page = self
+            .previous
+            .get(source_path)
+            .filter(|page| page.inputs == inputs && page.outputs.iter().all(|file| output.join(file).is_file()))?
+            .clone();
+        self.reused += page.outputs.len();
+        let search = page.search.clone();
+        self.pages.insert(source_path.to_string(), page);
+        Some(search)
+    }
+
+    /// Record a rendered page
+    pub fn record(&mut self, source_path: &str, inputs: String, outputs: Vec<String>, search: Vec<SearchEntry>) {
+        self.pages.insert(source_path.to_string(), CachedPage { inputs, outputs, search });
+    }
+
+    /// Write the cache file; pages the build didn't reach (`build --only`)
+    /// keep their entries
+    pub fn save(self) -> Result<()> {
+        let mut file = read_cache(&self.path);
+        let mut pages = self.previous;
+        pages.extend(self.pages);
+        file.insert(self.language, BookCache { book: self.book, pages });
+        fs::write(&self.path, serde_json::to_string(&file)?)?;
+        Ok(())
+    }
+}
+
+/// Books of a cache file; empty when it is missing or unreadable
+fn read_cache(path: &Path) -> CacheFile {
+    fs::read_to_string(path).ok().and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default()
+}
+
+/// Hash of the inputs every page of a book shares
+/// output: build output directory, left out when it is inside the book
+pub fn book_hash(book: &BookContext, output: &Path, options: &BuildOptions) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{}\n{:?}\n{:?}\n{:?}\n", env!("CARGO_PKG_VERSION"), book.language, options.variables, options.base_url));
+    if let Some(config_file) = &options.config_file {
+        hasher.update(fs::read(config_file).unwrap_or_default());
+    }
+    // Sidebar, prev/next links and published paths
+    hasher.update(format!("{:?}\n", book.summary.items));
+    hasher.update(format!("{:?}\n", book.summary.remapped.iter().collect::<BTreeMap<_, _>>()));
+    for (name, target) in book.wiki_links.entries() {
+        hasher.update(format!("{}\t{}\n", name, target));
+    }
+
+    let output = output.canonicalize().unwrap_or_else(|_| output.to_path_buf());
+    for entry in paths::walk(book.source)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| {
+            let name = e.file_name().to_string_lossy();
+            e.depth() == 0 || !(name.starts_with('.') || name == "_book" || name == "node_modules" || e.path() == output)
+        })
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+    {
+        let Some(path) = paths::relative(book.source, entry.path()) else { continue };
+        if !is_page_source(&path) {
+            hash_metadata(&mut hasher, &path, entry.path());
+        }
+    }
+    // book.json and LANGS.md of a multi-language book
+    if let Some(root) = book.source.parent().filter(|_| book.language.is_some()) {
+        let mut files: Vec<PathBuf> =
+            fs::read_dir(root).into_iter().flatten().filter_map(Result::ok).map(|e| e.path()).filter(|p| p.is_file()).collect();
+        files.sort();
+        for file in files {
+            hash_metadata(&mut hasher, &file.file_name().unwrap_or_default().to_string_lossy(), &file);
+        }
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Whether a book file is a page source (hashed with its pages) rather than
+/// a file every page depends on
+fn is_page_source(path: &str) -> bool {
+    let extension = path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+    SOURCE_EXTENSIONS.contains(&extension.as_str()) && !BOOK_FILES.contains(&path) && !path.starts_with("_templates/")
+}
+
+fn hash_metadata(hasher: &mut Sha256, name: &str, path: &Path) {
+    let Ok(metadata) = fs::metadata(path) else { return };
+    let modified = metadata.modified().ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok()).unwrap_or_default();
+    hasher.update(format!("{}\t{}\t{}\n", name, metadata.len(), modified.as_nanos()));
+}
+
+/// Hash of the source files of the page at `path` and the files they @import
+pub fn page_inputs(book: &BookContext, path: &str) -> String {
+    let mut hasher = Sha256::new();
+    let import_re = Regex::new(IMPORT_PATTERN).unwrap();
+    for file in book.summary.page_sources(path) {
+        // A missing source is hashed as empty (the page warns about it)
+        let content = book.summary.read_source(book.source, &file).unwrap_or_default();
+        hasher.update(format!("{}\t{}\n", file, content.len()));
+        hasher.update(&content);
+        hash_imports(&mut hasher, &import_re, &content, &book.source.join(&file), &mut HashSet::new());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Add the files @imported by `content` of `file`, recursively
+fn hash_imports(hasher: &mut Sha256, import_re: &Regex, content: &str, file: &Path, visited: &mut HashSet<PathBuf>) {
+    let dir = file.parent().unwrap_or(Path::new("."));
+    for caps in import_re.captures_iter(content) {
+        let path = dir.join(paths::normalize(&caps[1]));
+        hasher.update(format!("{}\n", path.display()));
+        // A missing import is hashed as such, so adding it renders the page again
+        let Ok(imported) = fs::read_to_string(&path) else { continue };
+        if !visited.insert(path.clone()) {
+            continue;
+        }
+        hasher.update(format!("{}\n", imported.len()));
+        hasher.update(&imported);
+        hash_imports(hasher, import_re, &imported, &path, visited);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str) -> SearchEntry {
+        SearchEntry { title: title.to_string(), section: None, path: "a.html".to_string(), part: None, content: String::new(), reading_minutes: None }
+    }
+
+    #[test]
+    fn test_page_cache_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, output) = (dir.path().join("._book.cache"), dir.path().join("_book"));
+        fs::create_dir_all(&output).unwrap();
+        fs::write(output.join("a.html"), "a").unwrap();
+
+        let mut cache = PageCache::load(&path, None, "book1".to_string());
+        assert!(cache.reuse("a.md", "h1", &output).is_none());
+        cache.record("a.md", "h1".to_string(), vec!["a.html".to_string()], vec![entry("A")]);
+        cache.record("b.md", "h2".to_string(), vec!["b.html".to_string()], Vec::new());
+        cache.save().unwrap();
+
+        let mut cache = PageCache::load(&path, None, "book1".to_string());
+        assert_eq!(cache.reuse("a.md", "h1", &output).unwrap()[0].title, "A");
+        // Changed inputs, or output files that are gone
+        assert!(cache.reuse("a.md", "h3", &output).is_none());
+        assert!(cache.reuse("b.md", "h2", &output).is_none());
+        assert_eq!(cache.reused, 1);
+
+        // Another language of the same output, and a changed book
+        assert!(PageCache::load(&path, Some("ja"), "book1".to_string()).reuse("a.md", "h1", &output).is_none());
+        assert!(PageCache::load(&path, None, "book2".to_string()).reuse("a.md", "h1", &output).is_none());
+    }
+
+    #[test]
+    fn test_is_page_source() {
+        assert!(is_page_source("guide/start.md"));
+        assert!(is_page_source("parts/intro.adoc"));
+        assert!(!is_page_source("SUMMARY.md"));
+        assert!(!is_page_source("_templates/api.md"));
+        assert!(!is_page_source("images/logo.png"));
+    }
+}
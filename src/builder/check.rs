@@ -0,0 +1,369 @@
+//! Link validation for `guidebook check`
+//!
+//! Scans SUMMARY.md and every Markdown chapter for links and images, resolves
+//! internal targets against the book sources and reports the broken ones with
+//! their source file and line. Anchors are checked against the ids of the
+//! rendered target page. AsciiDoc chapters are only checked as link targets.
+//!
+//! Like the renderer, plain link targets (`guide/start.md`) are resolved from
+//! the book root, while `./` and `../` links and image sources are relative
+//! to the page.
+
+use super::nunjucks::ContentTemplates;
+use super::renderer::resolve_book_path;
+use super::wikilinks::WikiLinks;
+use super::{generate, render_source_page, BookContext, RenderedPage};
+use crate::paths;
+use crate::parser::{self, frontmatter::parse_front_matter, BookConfig, FrontMatterDefaults, Glossary, Summary, SummaryItem};
+use anyhow::{Context, Result};
+use percent_encoding::percent_decode_str;
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+/// A link whose target does not exist
+#[derive(Debug, PartialEq)]
+pub struct BrokenLink {
+    /// Book-relative source file containing the link
+    pub file: String,
+    /// 1-based line of the link in the source file
+    pub line: usize,
+    pub url: String,
+    pub reason: String,
+}
+
+impl fmt::Display for BrokenLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {} ({})", self.file, self.line, self.url, self.reason)
+    }
+}
+
+/// Result of checking a book
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    /// Number of source files scanned for links
+    pub files: usize,
+    pub broken: Vec<BrokenLink>,
+}
+
+/// Check every internal link of the book at `source`
+pub fn check_book(source: &Path) -> Result<CheckReport> {
+    let source = source.canonicalize().context("Source directory not found")?;
+    let languages = parser::langs::parse_langs(&source)?;
+    if languages.is_empty() {
+        let config = BookConfig::load(&source)?;
+        return check_single_book(&source, &config, "");
+    }
+
+    let root_config = BookConfig::load(&source)?;
+    let mut report = CheckReport::default();
+    for lang in languages {
+        let lang_source = source.join(&lang.code);
+        let lang_config = if lang_source.join("book.json").exists() {
+            BookConfig::load(&lang_source)?
+        } else {
+            root_config.clone()
+        };
+        let lang_report = check_single_book(&lang_source, &lang_config, &format!("{}/", lang.code))?;
+        report.files += lang_report.files;
+        report.broken.extend(lang_report.broken);
+    }
+    Ok(report)
+}
+
+fn check_single_book(source: &Path, config: &BookConfig, file_prefix: &str) -> Result<CheckReport> {
+    let summary = generate::load_summary(source, config)?;
+    let mut checker = Checker {
+        source,
+        config,
+        glossary: Glossary::load(source)?,
+        defaults: FrontMatterDefaults::load(source)?,
+        wiki_links: WikiLinks::load(source, config, &summary),
+        templates: ContentTemplates::new(config),
+        pages: HashSet::new(),
+        anchors: HashMap::new(),
+        summary,
+    };
+    collect_pages(source, &checker.summary.items, &checker.summary, &mut checker.pages);
+    checker.pages.extend(checker.summary.merged.values().flatten().cloned());
+    if source.join("README.md").is_file() {
+        checker.pages.insert("README.md".to_string());
+    }
+
+    // Chapters shared from another language are checked with that language
+    let mut files: Vec<String> =
+        checker.pages.iter().filter(|p| p.ends_with(".md") && parser::shared_source(p).is_none()).cloned().collect();
+    files.sort();
+    if source.join("SUMMARY.md").is_file() {
+        files.insert(0, "SUMMARY.md".to_string());
+    }
+
+    let mut report = CheckReport::default();
+    for file in &files {
+        let raw = checker.summary.read_source(source, file).with_context(|| format!("Failed to read {}", file))?;
+        let parsed = parse_front_matter(&raw);
+        let line_offset = parsed.body_line_offset(&raw);
+        for link in extract_links(&parsed.content) {
+            if let Some(reason) = checker.check_url(file, &link)? {
+                report.broken.push(BrokenLink {
+                    file: format!("{}{}", file_prefix, file),
+                    line: link.line + line_offset,
+                    url: link.url,
+                    reason,
+                });
+            }
+        }
+    }
+    report.files = files.len();
+    Ok(report)
+}
+
+/// Chapter source files listed in SUMMARY.md
+fn collect_pages(source: &Path, items: &[SummaryItem], summary: &Summary, pages: &mut HashSet<String>) {
+    for item in items {
+        if let SummaryItem::Link { path, children, .. } = item {
+            if let Some(md_path) = path {
+                let base_path = paths::split_fragment(md_path).0.trim_start_matches('/');
+                if !base_path.is_empty() && summary.has_source(source, base_path) {
+                    pages.insert(base_path.to_string());
+                }
+            }
+            collect_pages(source, children, summary, pages);
+        }
+    }
+}
+
+struct Checker<'a> {
+    source: &'a Path,
+    config: &'a BookConfig,
+    glossary: Glossary,
+    defaults: FrontMatterDefaults,
+    wiki_links: WikiLinks,
+    templates: ContentTemplates,
+    /// Chapter sources that are built
+    pages: HashSet<String>,
+    /// Element ids of rendered chapters, by source path
+    anchors: HashMap<String, HashSet<String>>,
+    summary: Summary,
+}
+
+impl Checker<'_> {
+    /// Why a link found in `file` is broken; None if it resolves (or is external)
+    fn check_url(&mut self, file: &str, link: &Link) -> Result<Option<String>> {
+        let url = link.url.as_str();
+        let lower = url.to_lowercase();
+        if url.is_empty()
+            || url.starts_with("//")
+            || lower.contains("://")
+            || ["mailto:", "tel:", "javascript:", "data:"].iter().any(|s| lower.starts_with(s))
+            // Resolved by Nunjucks at build time
+            || url.contains("{{")
+            || url.contains("{%")
+        {
+            return Ok(None);
+        }
+        if paths::has_drive_letter(url) {
+            return Ok(Some("points to a local file path".to_string()));
+        }
+
+        let (path, fragment) = url.split_once('#').unwrap_or((url, ""));
+        let path = path.split('?').next().unwrap_or(path);
+        // Windows separators work in the browser, as they are normalized when rendering
+        let path = percent_decode_str(path).decode_utf8_lossy().replace('\\', "/");
+        let fragment = percent_decode_str(fragment).decode_utf8_lossy();
+
+        let target = if path.is_empty() {
+            file.to_string()
+        } else {
+            let page_relative = link.is_image || path.starts_with("./") || path.starts_with("../");
+            let resolved = match path.strip_prefix('/') {
+                Some(absolute) => resolve_book_path("", absolute),
+                None if page_relative => resolve_book_path(file, &path),
+                None => resolve_book_path("", &path),
+            };
+            match resolved {
+                Some(resolved) => resolved,
+                // A chapter shared from another language
+                None if parser::shared_source(&path).is_some() => {
+                    return Ok(if self.pages.contains(path.as_str()) {
+                        None
+                    } else if self.source.join(&path).is_file() {
+                        Some(format!("{} is not listed in SUMMARY.md", path))
+                    } else {
+                        Some("file not found".to_string())
+                    });
+                }
+                None => return Ok(Some("points outside the book".to_string())),
+            }
+        };
+
+        match self.chapter_source(&target) {
+            Some(chapter) => {
+                if !self.pages.contains(&chapter) {
+                    return Ok(Some(format!("{} is not listed in SUMMARY.md", chapter)));
+                }
+                if !fragment.is_empty() && !self.anchors(&chapter)?.contains(fragment.as_ref()) {
+                    return Ok(Some(format!("no anchor #{} in {}", fragment, chapter)));
+                }
+                Ok(None)
+            }
+            None if target.is_empty() || self.source.join(&target).exists() => Ok(None),
+            None => Ok(Some("file not found".to_string())),
+        }
+    }
+
+    /// Chapter source file for a link target (a source file or its .html output)
+    fn chapter_source(&self, target: &str) -> Option<String> {
+        if [".md", ".adoc", ".asciidoc"].iter().any(|ext| target.ends_with(ext)) {
+            return self.summary.has_source(self.source, target).then(|| target.to_string());
+        }
+        if target == "index.html" {
+            return self.source.join("README.md").is_file().then(|| "README.md".to_string());
+        }
+        let stem = target.strip_suffix(".html")?;
+        ["md", "adoc", "asciidoc"]
+            .iter()
+            .map(|ext| format!("{}.{}", stem, ext))
+            .find(|candidate| self.summary.has_source(self.source, candidate))
+    }
+
+    /// Ids (and legacy `name` anchors) of a rendered chapter
+    fn anchors(&mut self, chapter: &str) -> Result<&HashSet<String>> {
+        if !self.anchors.contains_key(chapter) {
+            let book = BookContext {
+                source: self.source,
+                config: self.config,
+                summary: &self.summary,
+                glossary: &self.glossary,
+                defaults: &self.defaults,
+                wiki_links: &self.wiki_links,
+                templates: &self.templates,
+                language: None,
+                last_modified: None,
+                social_cards: None,
+            };
+            let RenderedPage { html, .. } =
+                render_source_page(&book, chapter).with_context(|| format!("Failed to render {}", chapter))?;
+            let id_re = Regex::new(r#"\b(?:id|name)="([^"]+)""#).unwrap();
+            let ids = id_re.captures_iter(&html).map(|cap| cap[1].to_string()).collect();
+            self.anchors.insert(chapter.to_string(), ids);
+        }
+        Ok(&self.anchors[chapter])
+    }
+}
+
+/// A link or image URL in a source file
+#[derive(Debug, PartialEq)]
+pub(super) struct Link {
+    /// 1-based line in the Markdown content
+    pub(super) line: usize,
+    pub(super) url: String,
+    /// Image sources are always relative to the page
+    pub(super) is_image: bool,
+}
+
+/// Link and image URLs in Markdown content
+/// Includes `href`/`src` attributes of inline HTML
+pub(super) fn extract_links(content: &str) -> Vec<Link> {
+    let line_at = |offset: usize| content[..offset].matches('\n').count() + 1;
+    let attr_re = Regex::new(r#"\b(href|src)="([^"]*)""#).unwrap();
+
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+
+    let mut links = Vec::new();
+    for (event, range) in Parser::new_ext(content, options).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                links.push(Link { line: line_at(range.start), url: dest_url.to_string(), is_image: false });
+            }
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                links.push(Link { line: line_at(range.start), url: dest_url.to_string(), is_image: true });
+            }
+            Event::Html(html) | Event::InlineHtml(html) => {
+                for cap in attr_re.captures_iter(&html) {
+                    let offset = range.start + cap.get(0).unwrap().start();
+                    links.push(Link {
+                        line: line_at(offset.min(content.len())),
+                        url: cap[2].to_string(),
+                        is_image: &cap[1] == "src",
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_extract_links() {
+        let content = "# Title\n\nSee [a](a.md) and ![img](img/x.png).\n\n<a href=\"b.html#top\">b</a>\n\n```\n[not](a link)\n```\n";
+        assert_eq!(
+            extract_links(content),
+            vec![
+                Link { line: 3, url: "a.md".to_string(), is_image: false },
+                Link { line: 3, url: "img/x.png".to_string(), is_image: true },
+                Link { line: 5, url: "b.html#top".to_string(), is_image: false },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_book_reports_broken_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n* [Guide](guide/start.md)\n* [Gone](gone.md)\n").unwrap();
+        fs::write(root.join("README.md"), "# Intro\n\n[guide](guide/start.html#setup)\n").unwrap();
+        fs::create_dir_all(root.join("guide")).unwrap();
+        fs::create_dir_all(root.join("img")).unwrap();
+        fs::write(root.join("img/logo.png"), "png").unwrap();
+        fs::write(root.join("orphan.md"), "# Orphan\n").unwrap();
+        fs::write(
+            root.join("guide/start.md"),
+            "---\ntitle: Start\n---\n# Start\n\n## Setup\n\n![logo](../img/logo.png)\n[top](#start) [missing](#nope)\n[intro](/README.md#intro) [orphan](../orphan.md)\n[away](../../x.md) [ext](https://example.com) [var]({{ book.url }})\n![x](missing.png)\n[root](README.md) [sibling](start.md)\n",
+        )
+        .unwrap();
+
+        let report = check_book(root).unwrap();
+        assert_eq!(report.files, 3);
+        let found: Vec<String> = report.broken.iter().map(|b| b.to_string()).collect();
+        assert_eq!(
+            found,
+            vec![
+                "SUMMARY.md:5: gone.md (file not found)",
+                "guide/start.md:9: #nope (no anchor #nope in guide/start.md)",
+                "guide/start.md:10: ../orphan.md (orphan.md is not listed in SUMMARY.md)",
+                "guide/start.md:11: ../../x.md (points outside the book)",
+                "guide/start.md:12: missing.png (file not found)",
+                "guide/start.md:13: start.md (file not found)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_book_with_shared_chapters() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("en/api")).unwrap();
+        fs::create_dir_all(root.join("ja")).unwrap();
+        fs::write(root.join("LANGS.md"), "# Languages\n\n* [English](en/)\n* [日本語](ja/)\n").unwrap();
+        fs::write(root.join("en/SUMMARY.md"), "# Summary\n\n* [API](api/ref.md)\n* [Errors](api/errors.md)\n").unwrap();
+        fs::write(root.join("en/api/ref.md"), "# API\n\n[Errors](/api/errors.md)\n").unwrap();
+        fs::write(root.join("en/api/errors.md"), "# Errors\n").unwrap();
+        fs::write(root.join("ja/SUMMARY.md"), "# Summary\n\n* [API](../en/api/ref.md)\n* [Gone](../en/gone.md)\n").unwrap();
+
+        let report = check_book(root).unwrap();
+        let found: Vec<String> = report.broken.iter().map(|b| b.to_string()).collect();
+        assert_eq!(found, vec!["ja/SUMMARY.md:4: ../en/gone.md (file not found)"]);
+    }
+}
@@ -0,0 +1,487 @@
+//! EPUB export
+//!
+//! Packages the rendered chapters, their images and metadata from book.json
+//! into an EPUB3 file. Each chapter becomes an XHTML document in SUMMARY.md
+//! order, and `nav.xhtml` mirrors SUMMARY.md (parts become nested entries).
+//!
+//! Rendered HTML is converted to XHTML with lightweight fixups (void
+//! elements, boolean attributes, named entities); hand-written HTML in
+//! Markdown that isn't well-formed may still need fixing in the source.
+
+use super::export::{classify_url, Chapter, ExportBook, UrlTarget};
+use super::renderer::html_escape;
+use crate::parser::{default_html_path, SummaryItem};
+use crate::{info, paths};
+use anyhow::Result;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use regex::{Captures, Regex};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const EPUB_CSS: &str = include_str!("../../templates/epub.css");
+
+/// Characters escaped in package-relative hrefs
+const HREF_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?');
+
+/// Directory inside OEBPS/ holding images copied from the book
+const ASSETS_DIR: &str = "assets";
+
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+/// Export the book as an EPUB3 file
+pub fn export_epub(source: &Path, output: &Path) -> Result<()> {
+    info!("Loading book configuration...");
+    let book = ExportBook::load(source)?;
+    write_epub(&book, output)
+}
+
+/// Package a loaded book as an EPUB3 file
+pub(crate) fn write_epub(book: &ExportBook, output: &Path) -> Result<()> {
+    let chapters = book.chapters();
+    let chapter_files: HashMap<String, String> = chapters
+        .iter()
+        .map(|c| (default_html_path(&c.source_path), format!("{}.xhtml", c.id)))
+        .collect();
+
+    info!("Rendering {} chapters...", chapters.len());
+    let lang = if book.config.language.is_empty() { "en" } else { &book.config.language };
+    // Book-relative path -> package path of images referenced by chapters
+    let mut resources: BTreeMap<String, String> = BTreeMap::new();
+    let mut documents = Vec::new();
+    for chapter in &chapters {
+        let html = book.render_chapter(chapter)?;
+        let html = rewrite_chapter_links(&html, chapter, &chapter_files, &book.source, &mut resources);
+        documents.push((format!("{}.xhtml", chapter.id), chapter_document(&chapter.title, lang, &to_xhtml(&html))));
+    }
+
+    let cover = ["cover.jpg", "cover.png"]
+        .iter()
+        .find(|name| book.source.join(name).is_file())
+        .map(|name| name.to_string());
+
+    let file = fs::File::create(output)?;
+    let mut zip = ZipWriter::new(file);
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // The mimetype entry must come first and be stored uncompressed
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(b"application/epub+zip")?;
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(CONTAINER_XML.as_bytes())?;
+
+    zip.start_file("OEBPS/content.opf", deflated)?;
+    zip.write_all(package_document(book, lang, &chapters, &resources, cover.as_deref()).as_bytes())?;
+    zip.start_file("OEBPS/nav.xhtml", deflated)?;
+    zip.write_all(nav_document(book, lang, &chapter_files).as_bytes())?;
+    zip.start_file("OEBPS/style.css", deflated)?;
+    zip.write_all(EPUB_CSS.as_bytes())?;
+
+    for (name, content) in &documents {
+        zip.start_file(format!("OEBPS/{}", name), deflated)?;
+        zip.write_all(content.as_bytes())?;
+    }
+    for (book_path, package_path) in &resources {
+        zip.start_file(format!("OEBPS/{}", package_path), deflated)?;
+        zip.write_all(&fs::read(book.source.join(book_path))?)?;
+    }
+    if let Some(cover) = &cover {
+        zip.start_file(format!("OEBPS/{}", cover), deflated)?;
+        zip.write_all(&fs::read(book.source.join(cover))?)?;
+    }
+    zip.finish()?;
+
+    info!();
+    info!(">> EPUB written to {:?} ({} chapters, {} images)", output, chapters.len(), resources.len());
+    Ok(())
+}
+
+/// Point chapter links at chapter documents and images at packaged copies
+fn rewrite_chapter_links(
+    html: &str,
+    chapter: &Chapter,
+    chapter_files: &HashMap<String, String>,
+    source: &Path,
+    resources: &mut BTreeMap<String, String>,
+) -> String {
+    let page_path = default_html_path(&chapter.source_path);
+    let url_re = Regex::new(r#"\b(href|src)="([^"]*)""#).unwrap();
+    url_re
+        .replace_all(html, |caps: &Captures| {
+            let rewritten = match classify_url(&caps[2], &page_path) {
+                UrlTarget::Book { path, fragment } => {
+                    if let Some(file) = chapter_files.get(&path) {
+                        Some(if fragment.is_empty() {
+                            file.clone()
+                        } else {
+                            format!("{}#{}", file, fragment)
+                        })
+                    } else if media_type(&path).is_some() && source.join(&path).is_file() {
+                        let package_path = format!("{}/{}", ASSETS_DIR, path);
+                        let href = utf8_percent_encode(&package_path, HREF_ENCODE_SET).to_string();
+                        resources.insert(path, package_path);
+                        Some(href)
+                    } else {
+                        None
+                    }
+                }
+                UrlTarget::Fragment(_) | UrlTarget::Other => None,
+            };
+            match rewritten {
+                Some(url) => format!("{}=\"{}\"", &caps[1], url),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Media type of a packaged resource, None for types EPUB readers don't support
+fn media_type(path: &str) -> Option<&'static str> {
+    let ext = path.rsplit('.').next()?.to_lowercase();
+    match ext.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "svg" => Some("image/svg+xml"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// Convert rendered HTML to well-formed XHTML
+fn to_xhtml(html: &str) -> String {
+    let tag_re = Regex::new(r"<([a-zA-Z][a-zA-Z0-9]*)(\s[^<>]*?)?\s*(/?)>").unwrap();
+    let bool_attr_re = Regex::new(r"\s(checked|disabled|open|selected|hidden|readonly|controls)(\s|$)").unwrap();
+    let void_elements = [
+        "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+    ];
+
+    let html = tag_re.replace_all(html, |caps: &Captures| {
+        let name = &caps[1];
+        let mut attrs = caps.get(2).map(|m| m.as_str().to_string()).unwrap_or_default();
+        // Attribute minimization isn't allowed in XHTML (checked -> checked="checked");
+        // run twice since adjacent matches share the separating whitespace
+        for _ in 0..2 {
+            attrs = bool_attr_re.replace_all(&attrs, " $1=\"$1\"$2").into_owned();
+        }
+        let attrs = attrs.trim_end();
+        if void_elements.contains(&name.to_lowercase().as_str()) || &caps[3] == "/" {
+            format!("<{}{} />", name, attrs)
+        } else {
+            format!("<{}{}>", name, attrs)
+        }
+    });
+
+    // XML only knows the five predefined entities
+    let entity_re = Regex::new(r"&([a-zA-Z][a-zA-Z0-9]*);").unwrap();
+    entity_re
+        .replace_all(&html, |caps: &Captures| {
+            let code = match &caps[1] {
+                "amp" | "lt" | "gt" | "quot" | "apos" => return caps[0].to_string(),
+                "nbsp" => 160,
+                "copy" => 169,
+                "reg" => 174,
+                "laquo" => 171,
+                "raquo" => 187,
+                "middot" => 183,
+                "times" => 215,
+                "ndash" => 8211,
+                "mdash" => 8212,
+                "lsquo" => 8216,
+                "rsquo" => 8217,
+                "ldquo" => 8220,
+                "rdquo" => 8221,
+                "bull" => 8226,
+                "hellip" => 8230,
+                "trade" => 8482,
+                "larr" => 8592,
+                "rarr" => 8594,
+                _ => return format!("&amp;{};", &caps[1]),
+            };
+            format!("&#{};", code)
+        })
+        .into_owned()
+}
+
+/// Wrap chapter content in an XHTML document
+fn chapter_document(title: &str, lang: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" lang=\"{lang}\" xml:lang=\"{lang}\">\n<head>\n<meta charset=\"utf-8\" />\n<title>{title}</title>\n<link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\" />\n</head>\n<body>\n{body}\n</body>\n</html>\n",
+        lang = html_escape(lang),
+        title = html_escape(title),
+        body = body,
+    )
+}
+
+/// content.opf: metadata, manifest of every packaged file, and reading order
+fn package_document(
+    book: &ExportBook,
+    lang: &str,
+    chapters: &[Chapter],
+    resources: &BTreeMap<String, String>,
+    cover: Option<&str>,
+) -> String {
+    let config = &book.config;
+    let title = if config.title.is_empty() { "Untitled" } else { &config.title };
+
+    let mut metadata = format!(
+        "    <dc:identifier id=\"book-id\">{}</dc:identifier>\n    <dc:title>{}</dc:title>\n    <dc:language>{}</dc:language>\n",
+        book_identifier(&config.title, &config.author),
+        html_escape(title),
+        html_escape(lang),
+    );
+    if !config.author.is_empty() {
+        metadata.push_str(&format!("    <dc:creator>{}</dc:creator>\n", html_escape(&config.author)));
+    }
+    if !config.description.is_empty() {
+        metadata.push_str(&format!("    <dc:description>{}</dc:description>\n", html_escape(&config.description)));
+    }
+    metadata.push_str(&format!(
+        "    <meta property=\"dcterms:modified\">{}</meta>\n",
+        format_utc(modified_epoch())
+    ));
+
+    let mut manifest = String::from(
+        "    <item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n    <item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>\n",
+    );
+    for chapter in chapters {
+        manifest.push_str(&format!(
+            "    <item id=\"{id}\" href=\"{id}.xhtml\" media-type=\"application/xhtml+xml\"/>\n",
+            id = chapter.id
+        ));
+    }
+    for (index, (book_path, package_path)) in resources.iter().enumerate() {
+        manifest.push_str(&format!(
+            "    <item id=\"res{}\" href=\"{}\" media-type=\"{}\"/>\n",
+            index + 1,
+            utf8_percent_encode(package_path, HREF_ENCODE_SET),
+            media_type(book_path).unwrap_or("application/octet-stream"),
+        ));
+    }
+    if let Some(cover) = cover {
+        manifest.push_str(&format!(
+            "    <item id=\"cover-image\" href=\"{}\" media-type=\"{}\" properties=\"cover-image\"/>\n",
+            cover,
+            media_type(cover).unwrap_or("image/jpeg"),
+        ));
+    }
+
+    let spine: String = chapters
+        .iter()
+        .map(|c| format!("    <itemref idref=\"{}\"/>\n", c.id))
+        .collect();
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\" xml:lang=\"{lang}\">\n  <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n{metadata}  </metadata>\n  <manifest>\n{manifest}  </manifest>\n  <spine>\n{spine}  </spine>\n</package>\n",
+        lang = html_escape(lang),
+        metadata = metadata,
+        manifest = manifest,
+        spine = spine,
+    )
+}
+
+/// nav.xhtml generated from SUMMARY.md
+fn nav_document(book: &ExportBook, lang: &str, chapter_files: &HashMap<String, String>) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" lang=\"{lang}\" xml:lang=\"{lang}\">\n<head>\n<meta charset=\"utf-8\" />\n<title>Contents</title>\n<link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\" />\n</head>\n<body>\n<nav epub:type=\"toc\" id=\"toc\">\n<h1>Contents</h1>\n{list}</nav>\n</body>\n</html>\n",
+        lang = html_escape(lang),
+        list = nav_list(&book.summary.items, chapter_files).unwrap_or_else(|| "<ol>\n</ol>\n".to_string()),
+    )
+}
+
+/// Nested <ol> for nav.xhtml; None if no entry has a target
+/// Parts group the items that follow them, since a label without a link
+/// must be followed by a nested list.
+fn nav_list(items: &[SummaryItem], chapter_files: &HashMap<String, String>) -> Option<String> {
+    let mut html = String::new();
+    let mut part: Option<(&str, Vec<SummaryItem>)> = None;
+
+    let flush_part = |part: &mut Option<(&str, Vec<SummaryItem>)>, html: &mut String| {
+        if let Some((title, part_items)) = part.take() {
+            if let Some(list) = nav_list(&part_items, chapter_files) {
+                html.push_str(&format!("<li><span>{}</span>\n{}</li>\n", html_escape(title), list));
+            }
+        }
+    };
+
+    for item in items {
+        match item {
+            SummaryItem::PartTitle(title) => {
+                flush_part(&mut part, &mut html);
+                part = Some((title, Vec::new()));
+            }
+            SummaryItem::Link { .. } if part.is_some() => {
+                if let Some((_, part_items)) = part.as_mut() {
+                    part_items.push(item.clone());
+                }
+            }
+            SummaryItem::Link { title, path, children } => {
+                let target = path.as_deref().and_then(|p| {
+                    let (base, fragment) = paths::split_fragment(p);
+                    let file = chapter_files.get(&default_html_path(base))?;
+                    Some(format!("{}{}", file, fragment))
+                });
+                let children = nav_list(children, chapter_files);
+                match (target, children) {
+                    (Some(href), children) => html.push_str(&format!(
+                        "<li><a href=\"{}\">{}</a>\n{}</li>\n",
+                        href,
+                        html_escape(title),
+                        children.unwrap_or_default()
+                    )),
+                    (None, Some(children)) => {
+                        html.push_str(&format!("<li><span>{}</span>\n{}</li>\n", html_escape(title), children))
+                    }
+                    (None, None) => {}
+                }
+            }
+            SummaryItem::Separator => {}
+        }
+    }
+    flush_part(&mut part, &mut html);
+
+    (!html.is_empty()).then(|| format!("<ol>\n{}</ol>\n", html))
+}
+
+/// Stable identifier derived from title and author, formatted as a UUID
+fn book_identifier(title: &str, author: &str) -> String {
+    let hash = Sha256::digest(format!("{}\n{}", title, author).as_bytes());
+    let hex: String = hash.iter().take(16).map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "urn:uuid:{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Modification time for the package: SOURCE_DATE_EPOCH if set, otherwise now
+fn modified_epoch() -> u64 {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        })
+}
+
+/// Format seconds since the Unix epoch as "YYYY-MM-DDThh:mm:ssZ"
+pub(crate) fn format_utc(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    // Civil date from days since 1970-01-01 (proleptic Gregorian calendar)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_xhtml() {
+        let html = r#"<p>a<br>b&nbsp;c &amp; &foo;</p><img src="x.png" alt="x"><hr/><input type="checkbox" checked disabled><details open><summary>s</summary></details>"#;
+        let xhtml = to_xhtml(html);
+        assert!(xhtml.contains("a<br />b&#160;c &amp; &amp;foo;"), "{}", xhtml);
+        assert!(xhtml.contains(r#"<img src="x.png" alt="x" />"#), "{}", xhtml);
+        assert!(xhtml.contains("<hr />"), "{}", xhtml);
+        assert!(xhtml.contains(r#"<input type="checkbox" checked="checked" disabled="disabled" />"#), "{}", xhtml);
+        assert!(xhtml.contains(r#"<details open="open">"#), "{}", xhtml);
+    }
+
+    #[test]
+    fn test_rewrite_chapter_links() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("images")).unwrap();
+        fs::write(dir.path().join("images/a b.png"), "png").unwrap();
+
+        let mut chapter_files = HashMap::new();
+        chapter_files.insert("guide/api.html".to_string(), "ch2.xhtml".to_string());
+        let chapter = Chapter {
+            id: "ch1".to_string(),
+            title: "Start".to_string(),
+            source_path: "guide/start.md".to_string(),
+        };
+        let mut resources = BTreeMap::new();
+        let html = r##"<a href="api.html#auth">a</a> <img src="../images/a%20b.png"> <a href="#top">t</a> <a href="https://example.com">e</a>"##;
+        let output = rewrite_chapter_links(html, &chapter, &chapter_files, dir.path(), &mut resources);
+
+        assert!(output.contains(r##"href="ch2.xhtml#auth""##), "{}", output);
+        assert!(output.contains(r#"src="assets/images/a%20b.png""#), "{}", output);
+        assert!(output.contains(r##"href="#top""##), "{}", output);
+        assert!(output.contains(r#"href="https://example.com""#), "{}", output);
+        assert_eq!(resources.get("images/a b.png").unwrap(), "assets/images/a b.png");
+    }
+
+    #[test]
+    fn test_nav_list_groups_parts() {
+        let summary = crate::parser::summary::parse_summary(
+            "# Summary\n\n* [Intro](README.md)\n\n## Guide\n\n* [Start](start.md)\n* Draft\n",
+        )
+        .unwrap();
+        let mut chapter_files = HashMap::new();
+        chapter_files.insert("README.html".to_string(), "ch1.xhtml".to_string());
+        chapter_files.insert("start.html".to_string(), "ch2.xhtml".to_string());
+
+        let nav = nav_list(&summary.items, &chapter_files).unwrap();
+        assert!(nav.contains(r#"<li><a href="ch1.xhtml">Intro</a>"#), "{}", nav);
+        assert!(nav.contains("<li><span>Guide</span>\n<ol>\n<li><a href=\"ch2.xhtml\">Start</a>"), "{}", nav);
+        assert!(!nav.contains("Draft"), "{}", nav);
+    }
+
+    #[test]
+    fn test_format_utc() {
+        assert_eq!(format_utc(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc(1_700_000_000), "2023-11-14T22:13:20Z");
+        assert_eq!(format_utc(951_782_400), "2000-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn test_book_identifier_is_stable() {
+        let id = book_identifier("My Book", "Guide Inc");
+        assert_eq!(id, book_identifier("My Book", "Guide Inc"));
+        assert!(id.starts_with("urn:uuid:"));
+        assert_eq!(id.len(), "urn:uuid:".len() + 36);
+    }
+}
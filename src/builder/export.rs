@@ -0,0 +1,249 @@
+//! Shared helpers for single-file exports (`pdf`, `epub`, `export md`)
+//!
+//! Exports render every chapter listed in SUMMARY.md once, in order, and
+//! rewrite links between chapters for their own packaging. `build --formats`
+//! writes several of them from one `ExportBook`, which renders each chapter
+//! a single time for all of them.
+
+use super::nunjucks::ContentTemplates;
+use super::renderer::resolve_book_path;
+use super::wikilinks::WikiLinks;
+use super::{render_source_markdown, render_summary_page, BookContext, RenderedPage};
+use crate::paths;
+use crate::parser::{self, BookConfig, FrontMatterDefaults, Glossary, Summary, SummaryItem};
+use crate::info;
+use anyhow::{bail, Context, Result};
+use percent_encoding::percent_decode_str;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A single-file format `build --formats` writes into the output directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportTarget {
+    Pdf,
+    Epub,
+}
+
+impl ExportTarget {
+    /// File name in the output directory
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ExportTarget::Pdf => "book.pdf",
+            ExportTarget::Epub => "book.epub",
+        }
+    }
+}
+
+/// Write the `targets` of the book in `source` into the directory `output`
+pub fn export_book(source: &Path, output: &Path, targets: &[ExportTarget]) -> Result<()> {
+    info!("Loading book configuration...");
+    let book = ExportBook::load(source)?;
+    fs::create_dir_all(output)?;
+    write_exports(&book, output, targets)
+}
+
+/// Write the `targets` of a loaded book into the directory `output`
+pub(crate) fn write_exports(book: &ExportBook, output: &Path, targets: &[ExportTarget]) -> Result<()> {
+    for target in targets {
+        let path = output.join(target.file_name());
+        match target {
+            ExportTarget::Pdf => super::pdf::write_pdf(book, &path, None)?,
+            ExportTarget::Epub => super::epub::write_epub(book, &path)?,
+        }
+    }
+    Ok(())
+}
+
+/// A chapter of an exported book
+pub struct Chapter {
+    /// Stable id of the chapter within the export (e.g. "ch3")
+    pub id: String,
+    /// Title from SUMMARY.md
+    pub title: String,
+    /// Book-relative source path (e.g. "guide/start.md")
+    pub source_path: String,
+}
+
+/// Everything needed to render a book for export
+pub struct ExportBook {
+    pub source: PathBuf,
+    pub config: BookConfig,
+    pub summary: Summary,
+    glossary: Glossary,
+    defaults: FrontMatterDefaults,
+    wiki_links: WikiLinks,
+    templates: ContentTemplates,
+    /// Rendered chapters by source path, shared by the exports of the book
+    rendered: RefCell<HashMap<String, String>>,
+}
+
+impl ExportBook {
+    /// Load configuration, SUMMARY.md, glossary and front matter defaults
+    pub fn load(source: &Path) -> Result<Self> {
+        let source = source.canonicalize().context("Source directory not found")?;
+        if !parser::langs::parse_langs(&source)?.is_empty() {
+            bail!("Multi-language books are exported per language: pass a language directory (e.g. en/)");
+        }
+
+        Self::new(&source, BookConfig::load(&source)?)
+    }
+
+    /// Load SUMMARY.md, glossary and front matter defaults of a book whose
+    /// configuration is already loaded (a language of a multi-language book)
+    pub fn new(source: &Path, config: BookConfig) -> Result<Self> {
+        let source = source.to_path_buf();
+        let summary = super::generate::load_summary(&source, &config)?;
+        Ok(Self {
+            wiki_links: WikiLinks::load(&source, &config, &summary),
+            templates: ContentTemplates::new(&config),
+            summary,
+            config,
+            glossary: Glossary::load(&source)?,
+            defaults: FrontMatterDefaults::load(&source)?,
+            rendered: RefCell::new(HashMap::new()),
+            source,
+        })
+    }
+
+    /// Chapters in SUMMARY.md order; README.md comes first even if not listed
+    pub fn chapters(&self) -> Vec<Chapter> {
+        let intro_title = if self.config.title.is_empty() { "Introduction" } else { &self.config.title };
+        collect_chapters(&self.source, &self.summary, intro_title)
+    }
+
+    /// Rendered HTML content of a chapter (links are relative to its source layout)
+    pub fn render_chapter(&self, chapter: &Chapter) -> Result<String> {
+        if let Some(html) = self.rendered.borrow().get(&chapter.source_path) {
+            return Ok(html.clone());
+        }
+        let RenderedPage { html, .. } = render_summary_page(&self.book(), &chapter.source_path)?;
+        self.rendered.borrow_mut().insert(chapter.source_path.clone(), html.clone());
+        Ok(html)
+    }
+
+    /// Markdown of a chapter's source files with @imports and templates
+    /// resolved: (book-relative source path, Markdown) for each file
+    pub fn chapter_markdown(&self, chapter: &Chapter) -> Result<Vec<(String, String)>> {
+        render_source_markdown(&self.book(), &chapter.source_path)
+    }
+
+    fn book(&self) -> BookContext<'_> {
+        BookContext {
+            source: &self.source,
+            config: &self.config,
+            summary: &self.summary,
+            glossary: &self.glossary,
+            defaults: &self.defaults,
+            wiki_links: &self.wiki_links,
+            templates: &self.templates,
+            language: None,
+            last_modified: None,
+            social_cards: None,
+        }
+    }
+}
+
+fn collect_chapters(source: &Path, summary: &Summary, intro_title: &str) -> Vec<Chapter> {
+    fn walk(source: &Path, summary: &Summary, items: &[SummaryItem], chapters: &mut Vec<Chapter>) {
+        for item in items {
+            if let SummaryItem::Link { title, path, children } = item {
+                if let Some(md_path) = path {
+                    let base_path = paths::split_fragment(md_path).0.trim_start_matches('/');
+                    let seen = chapters.iter().any(|c| c.source_path == base_path);
+                    if !base_path.is_empty() && !seen && summary.has_source(source, base_path) {
+                        chapters.push(Chapter {
+                            id: format!("ch{}", chapters.len() + 1),
+                            title: title.clone(),
+                            source_path: base_path.to_string(),
+                        });
+                    }
+                }
+                walk(source, summary, children, chapters);
+            }
+        }
+    }
+
+    let mut chapters = Vec::new();
+    walk(source, summary, &summary.items, &mut chapters);
+    if source.join("README.md").is_file() && !chapters.iter().any(|c| c.source_path == "README.md") {
+        chapters.insert(0, Chapter {
+            id: "ch0".to_string(),
+            title: intro_title.to_string(),
+            source_path: "README.md".to_string(),
+        });
+    }
+    chapters
+}
+
+/// Where a link or image URL in a rendered chapter points
+#[derive(Debug, PartialEq)]
+pub enum UrlTarget<'a> {
+    /// Anchor in the same page ("#install")
+    Fragment(&'a str),
+    /// File in the book (book-relative path, fragment without '#')
+    Book { path: String, fragment: &'a str },
+    /// Anything else (external, mailto:, data:, ...) — left unchanged
+    Other,
+}
+
+/// Classify a URL found in the chapter rendered at `page_path` (e.g. "guide/start.html")
+pub fn classify_url<'a>(url: &'a str, page_path: &str) -> UrlTarget<'a> {
+    if let Some(fragment) = url.strip_prefix('#') {
+        return if fragment.is_empty() { UrlTarget::Other } else { UrlTarget::Fragment(fragment) };
+    }
+    let lower = url.to_lowercase();
+    if url.is_empty()
+        || url.starts_with("//")
+        || lower.contains("://")
+        || lower.starts_with("mailto:")
+        || lower.starts_with("javascript:")
+        || lower.starts_with("data:")
+        || paths::has_drive_letter(url)
+    {
+        return UrlTarget::Other;
+    }
+
+    let (path, fragment) = url.split_once('#').unwrap_or((url, ""));
+    let path = path.split('?').next().unwrap_or(path).trim_start_matches('/');
+    match resolve_book_path(page_path, &percent_decode_str(path).decode_utf8_lossy()) {
+        Some(path) => UrlTarget::Book { path, fragment },
+        None => UrlTarget::Other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_collect_chapters_prepends_readme() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("README.md"), "# Intro").unwrap();
+        fs::write(dir.path().join("a.md"), "# A").unwrap();
+        let summary = parser::summary::parse_summary("# Summary\n\n* [A](a.md)\n* [A again](a.md#x)\n").unwrap();
+
+        let chapters = collect_chapters(dir.path(), &summary, "My Book");
+        let paths: Vec<&str> = chapters.iter().map(|c| c.source_path.as_str()).collect();
+        assert_eq!(paths, vec!["README.md", "a.md"]);
+        assert_eq!(chapters[0].title, "My Book");
+        assert_eq!(chapters[1].id, "ch1");
+    }
+
+    #[test]
+    fn test_classify_url() {
+        assert_eq!(classify_url("#setup", "guide/start.html"), UrlTarget::Fragment("setup"));
+        assert_eq!(classify_url("https://example.com", "guide/start.html"), UrlTarget::Other);
+        assert_eq!(classify_url("../../outside.html", "guide/start.html"), UrlTarget::Other);
+        assert_eq!(
+            classify_url("../img/a%20b.png?v=1", "guide/start.html"),
+            UrlTarget::Book { path: "img/a b.png".to_string(), fragment: "" }
+        );
+        assert_eq!(
+            classify_url("api.html#auth", "guide/start.html"),
+            UrlTarget::Book { path: "guide/api.html".to_string(), fragment: "auth" }
+        );
+    }
+}
@@ -0,0 +1,135 @@
+//! Content-hashed asset names (`"fingerprint": true` in book.json)
+//!
+//! After a book is built, the files the build writes to `gitbook/`
+//! (gitbook.css, gitbook.js, search.js, the custom style.css, ...) and
+//! search_index.json are renamed to carry a hash of their content
+//! (`gitbook/gitbook.3f2a9c1e.css`), and the pages are rewritten to load them
+//! under that name. A deploy can then serve them with a long cache lifetime:
+//! a changed file gets a new name, so browsers and CDNs never hold a stale
+//! copy. Names from previous builds of the same output are replaced.
+
+use crate::paths;
+use anyhow::Result;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Files of `gitbook/` the build writes, fingerprinted when they exist
+const ASSETS: [&str; 7] = ["gitbook.css", "style.css", "gitbook.js", "collapsible.js", "fontsettings.js", "search.js", "nav.js"];
+
+/// Hex digits of the content hash in a name
+const HASH_LENGTH: usize = 8;
+
+/// Fingerprint the assets of the book built in `output` and point its pages
+/// at them. Pages of other books in subdirectories (languages) are left to
+/// their own build.
+/// Returns the new paths by plain path (`gitbook/gitbook.css` -> `gitbook/gitbook.3f2a9c1e.css`)
+pub fn fingerprint_assets(output: &Path) -> Result<BTreeMap<String, String>> {
+    let mut renamed = BTreeMap::new();
+
+    // The index first: search.js loads it by name
+    let index = output.join("search_index.json");
+    let mut index_name = None;
+    if index.is_file() {
+        let name = fingerprint_file(&index, &fs::read(&index)?)?;
+        renamed.insert("search_index.json".to_string(), name.clone());
+        index_name = Some(name);
+    }
+
+    let gitbook_dir = output.join("gitbook");
+    for asset in ASSETS {
+        let path = gitbook_dir.join(asset);
+        let Ok(mut content) = fs::read_to_string(&path) else { continue };
+        if let Some(index_name) = index_name.as_deref().filter(|_| asset == "search.js") {
+            content = content.replace("'search_index.json'", &format!("'{}'", index_name));
+        }
+        let name = fingerprint_file(&path, content.as_bytes())?;
+        renamed.insert(format!("gitbook/{}", asset), format!("gitbook/{}", name));
+    }
+
+    // Pages written by this build or kept from a previous one
+    let asset_re = Regex::new(&format!(r"gitbook/([a-z]+)(?:\.[0-9a-f]{{{}}})?\.(css|js)\b", HASH_LENGTH)).unwrap();
+    for entry in paths::walk(output)
+        .into_iter()
+        .filter_entry(|e| {
+            e.depth() == 0
+                || !(e.file_name().to_string_lossy().starts_with('.') || e.file_type().is_dir() && e.path().join("gitbook").is_dir())
+        })
+        .filter_map(paths::readable)
+        .filter(|e| e.file_type().is_file() && e.path().extension().is_some_and(|ext| ext == "html"))
+    {
+        let Ok(html) = fs::read_to_string(entry.path()) else { continue };
+        let rewritten = asset_re.replace_all(&html, |caps: &regex::Captures| {
+            let plain = format!("gitbook/{}.{}", &caps[1], &caps[2]);
+            renamed.get(&plain).cloned().unwrap_or_else(|| caps[0].to_string())
+        });
+        if rewritten != html {
+            fs::write(entry.path(), rewritten.as_bytes())?;
+        }
+    }
+    Ok(renamed)
+}
+
+/// Write `content` of the file at `path` under its fingerprinted name, remove
+/// the plain file and names of previous builds; returns the new file name
+fn fingerprint_file(path: &Path, content: &[u8]) -> Result<String> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let (stem, extension) = file_name.rsplit_once('.').unwrap_or((&file_name, ""));
+    let hash = format!("{:x}", Sha256::digest(content));
+    let name = format!("{}.{}.{}", stem, &hash[..HASH_LENGTH], extension);
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let previous_re = Regex::new(&format!(r"^{}\.[0-9a-f]{{{}}}\.{}$", regex::escape(stem), HASH_LENGTH, regex::escape(extension))).unwrap();
+    for entry in fs::read_dir(dir)?.filter_map(Result::ok) {
+        if entry.file_name().to_str().is_some_and(|other| previous_re.is_match(other) && other != name) {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    fs::write(dir.join(&name), content)?;
+    fs::remove_file(path)?;
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_assets() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path();
+        fs::create_dir_all(output.join("gitbook")).unwrap();
+        fs::create_dir_all(output.join("guide")).unwrap();
+        fs::create_dir_all(output.join("ja/gitbook")).unwrap();
+        fs::write(output.join("search_index.json"), "[]").unwrap();
+        fs::write(output.join("gitbook/gitbook.css"), "body {}").unwrap();
+        fs::write(output.join("gitbook/gitbook.0badc0de.css"), "old").unwrap();
+        fs::write(output.join("gitbook/search.js"), "fetch(rootPath + 'search_index.json');").unwrap();
+        fs::write(output.join("index.html"), r#"<link href="gitbook/gitbook.css"><script src="gitbook/search.js"></script>"#).unwrap();
+        // Kept from a previous build
+        fs::write(output.join("guide/a.html"), r#"<link href="../gitbook/gitbook.0badc0de.css">"#).unwrap();
+        fs::write(output.join("ja/index.html"), r#"<link href="gitbook/gitbook.css">"#).unwrap();
+
+        let renamed = fingerprint_assets(output).unwrap();
+        assert_eq!(renamed.len(), 3);
+        let css = &renamed["gitbook/gitbook.css"];
+        let index = &renamed["search_index.json"];
+        assert!(Regex::new(r"^gitbook/gitbook\.[0-9a-f]{8}\.css$").unwrap().is_match(css), "{}", css);
+        assert!(output.join(css).is_file() && output.join(index).is_file());
+        assert!(!output.join("gitbook/gitbook.css").exists() && !output.join("gitbook/gitbook.0badc0de.css").exists());
+
+        let search_js = fs::read_to_string(output.join(&renamed["gitbook/search.js"])).unwrap();
+        assert!(search_js.contains(&format!("'{}'", index)), "{}", search_js);
+        let page = fs::read_to_string(output.join("index.html")).unwrap();
+        assert_eq!(page, format!(r#"<link href="{}"><script src="{}"></script>"#, css, renamed["gitbook/search.js"]));
+        assert_eq!(fs::read_to_string(output.join("guide/a.html")).unwrap(), format!(r#"<link href="../{}">"#, css));
+        assert_eq!(fs::read_to_string(output.join("ja/index.html")).unwrap(), r#"<link href="gitbook/gitbook.css">"#);
+
+        // Same content, same names
+        fs::write(output.join("gitbook/gitbook.css"), "body {}").unwrap();
+        assert_eq!(&fingerprint_assets(output).unwrap()["gitbook/gitbook.css"], css);
+        assert!(output.join(css).is_file());
+    }
+}
@@ -0,0 +1,183 @@
+//! HTML fragment export (`guidebook export fragments`)
+//!
+//! For portals (SharePoint, intranets, CMSs) that wrap content in their own
+//! layout: each chapter's rendered content is written without the book's
+//! page (no `<html>`, `<head>`, sidebar or scripts) to its `.html` path in
+//! the source layout, so links between chapters keep working. Images and
+//! other book files the chapters link to are copied along.
+//!
+//! `structure.json` describes the book for the portal's navigation: the
+//! chapters in reading order, and the SUMMARY.md tree with parts holding
+//! the entries listed under them.
+
+use super::export::{classify_url, ExportBook, UrlTarget};
+use crate::parser::{default_html_path, SummaryItem};
+use crate::{info, paths};
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// File name of the structure manifest
+pub const STRUCTURE_FILE: &str = "structure.json";
+
+#[derive(Debug, Serialize)]
+struct Structure<'a> {
+    title: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    language: &'a str,
+    /// Chapters in reading order
+    pages: Vec<StructurePage<'a>>,
+    /// SUMMARY.md entries
+    toc: Vec<TocEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct StructurePage<'a> {
+    id: &'a str,
+    title: &'a str,
+    /// Book-relative source path
+    source: &'a str,
+    /// Fragment file, relative to the output directory
+    file: String,
+}
+
+#[derive(Debug, Serialize)]
+struct TocEntry {
+    title: String,
+    /// Fragment file (with the entry's anchor); none for parts and entries without a page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    part: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<TocEntry>,
+}
+
+/// Export a book as body-only HTML fragments and `structure.json`
+pub fn export_fragments(source: &Path, output: &Path) -> Result<()> {
+    info!("Loading book configuration...");
+    let book = ExportBook::load(source)?;
+    let chapters = book.chapters();
+    let files: HashSet<String> = chapters.iter().map(|c| default_html_path(&c.source_path)).collect();
+
+    info!("Rendering {} chapters...", chapters.len());
+    let url_re = Regex::new(r#"\b(?:href|src)="([^"]*)""#).unwrap();
+    let mut linked = BTreeSet::new();
+    let mut pages = Vec::new();
+    for chapter in &chapters {
+        let html = book.render_chapter(chapter)?;
+        let file = default_html_path(&chapter.source_path);
+        for caps in url_re.captures_iter(&html) {
+            if let UrlTarget::Book { path, .. } = classify_url(&caps[1], &file) {
+                if !files.contains(&path) {
+                    linked.insert(path);
+                }
+            }
+        }
+        let dest = output.join(&file);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(dest, format!("{}\n", html.trim()))?;
+        pages.push(StructurePage { id: &chapter.id, title: &chapter.title, source: &chapter.source_path, file });
+    }
+
+    let mut copied = 0;
+    for path in &linked {
+        let src = book.source.join(path);
+        if !src.is_file() {
+            continue;
+        }
+        let dest = output.join(path);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&src, dest)?;
+        copied += 1;
+    }
+
+    let structure = Structure {
+        title: &book.config.title,
+        language: &book.config.language,
+        pages,
+        toc: toc_entries(&book.summary.items, &files),
+    };
+    fs::write(output.join(STRUCTURE_FILE), serde_json::to_string_pretty(&structure)?)?;
+
+    info!();
+    info!(">> Fragments written to {:?} ({} chapters, {} linked files)", output, chapters.len(), copied);
+    Ok(())
+}
+
+/// Tree of SUMMARY.md entries; the entries after a part title are its children
+fn toc_entries(items: &[SummaryItem], files: &HashSet<String>) -> Vec<TocEntry> {
+    let mut entries: Vec<TocEntry> = Vec::new();
+    let mut in_part = false;
+    for item in items {
+        let entry = match item {
+            SummaryItem::PartTitle(title) => {
+                entries.push(TocEntry { title: title.clone(), file: None, part: true, children: Vec::new() });
+                in_part = true;
+                continue;
+            }
+            SummaryItem::Link { title, path, children } => TocEntry {
+                title: title.clone(),
+                file: path.as_deref().and_then(|p| {
+                    let (base, fragment) = paths::split_fragment(p.trim_start_matches('/'));
+                    let file = default_html_path(base);
+                    files.contains(&file).then(|| format!("{}{}", file, fragment))
+                }),
+                part: false,
+                children: toc_entries(children, files),
+            },
+            SummaryItem::Separator => continue,
+        };
+        match entries.last_mut() {
+            Some(part) if in_part => part.children.push(entry),
+            _ => entries.push(entry),
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_fragments() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        fs::create_dir_all(source.join("guide/img")).unwrap();
+        fs::write(source.join("book.json"), r#"{"title": "Handbook", "variables": {"product": "Acme"}}"#).unwrap();
+        fs::write(source.join("README.md"), "# Intro\n\nAbout {{ book.product }}.\n").unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n\n## Guide\n\n* [Start](guide/start.md)\n    * [Install](guide/start.md#install)\n").unwrap();
+        fs::write(source.join("guide/start.md"), "# Start\n\n![Logo](img/logo.png)\n\n## Install\n\nSee the [intro](../README.md).\n").unwrap();
+        fs::write(source.join("guide/img/logo.png"), "png").unwrap();
+
+        let output = dir.path().join("fragments");
+        export_fragments(&source, &output).unwrap();
+        let intro = fs::read_to_string(output.join("README.html")).unwrap();
+        assert!(intro.starts_with("<h1") && intro.contains("About Acme."), "{}", intro);
+        let start = fs::read_to_string(output.join("guide/start.html")).unwrap();
+        assert!(!start.contains("<html") && !start.contains("<body"), "{}", start);
+        assert!(start.contains(r#"href="../README.html""#), "{}", start);
+        assert!(output.join("guide/img/logo.png").is_file());
+
+        let structure: serde_json::Value = serde_json::from_str(&fs::read_to_string(output.join(STRUCTURE_FILE)).unwrap()).unwrap();
+        assert_eq!(structure["title"], "Handbook");
+        assert_eq!(structure["pages"][1], serde_json::json!({"id": "ch2", "title": "Start", "source": "guide/start.md", "file": "guide/start.html"}));
+        assert_eq!(
+            structure["toc"],
+            serde_json::json!([
+                {"title": "Intro", "file": "README.html"},
+                {"title": "Guide", "part": true, "children": [
+                    {"title": "Start", "file": "guide/start.html", "children": [{"title": "Install", "file": "guide/start.html#install"}]}
+                ]}
+            ])
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! Pages generated from data files
+//!
+//! Each `generate` entry of book.json renders one page per record of a data
+//! file (JSON, YAML or CSV) through a page template and lists the pages in
+//! the table of contents under a SUMMARY.md part. Generated pages only exist
+//! in memory: they are built, searched and exported like chapter files.
+
+use super::{locale, nunjucks, PAGE_TEMPLATES_DIR};
+use crate::parser::{BookConfig, GeneratedPage, GeneratorConfig, Summary, SummaryItem};
+use crate::{info, warning};
+use anyhow::{anyhow, bail, Context, Result};
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+/// Parse SUMMARY.md and add the pages of every `generate` entry
+pub fn load_summary(source: &Path, config: &BookConfig) -> Result<Summary> {
+    let mut summary = Summary::parse(source)?;
+    for generator in &config.generate {
+        let count = generate_pages(source, config, generator, &mut summary)
+            .with_context(|| format!("Failed to generate pages from {}", generator.data))?;
+        info!("  Generated {} pages from {}", count, generator.data);
+    }
+    Ok(summary)
+}
+
+/// Render the pages of one generator into `summary`; returns the number of pages
+fn generate_pages(source: &Path, config: &BookConfig, generator: &GeneratorConfig, summary: &mut Summary) -> Result<usize> {
+    let mut records = load_records(&source.join(&generator.data))?;
+    if !generator.sort.is_empty() {
+        let key = |record: &Map<String, Value>| record.get(&generator.sort).and_then(field_text).unwrap_or_default();
+        records.sort_by(|a, b| locale::compare(&config.language, &key(a), &key(b)));
+    }
+    let template_path = format!("{}/{}.md", PAGE_TEMPLATES_DIR, generator.template);
+    let template = fs::read_to_string(source.join(&template_path))
+        .map_err(|_| anyhow!("Page template {} not found", template_path))?;
+    let templates = nunjucks::ContentTemplates::new(config);
+
+    let mut entries = Vec::new();
+    for (i, record) in records.into_iter().enumerate() {
+        let Some(path) = fill_placeholders(&generator.path, &record) else {
+            warning!("{}: record {} has no value for a field of {}", generator.data, i + 1, generator.path);
+            continue;
+        };
+        let path = path.trim_start_matches('/').to_string();
+        if summary.has_source(source, &path) {
+            warning!("{}: {} already exists, skipping record {}", generator.data, path, i + 1);
+            continue;
+        }
+
+        let title = if generator.title.is_empty() {
+            record.get("title").and_then(field_text)
+        } else {
+            fill_placeholders(&generator.title, &record)
+        };
+        let title = title.unwrap_or_else(|| {
+            let name = path.rsplit('/').next().unwrap_or(&path);
+            name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(name).to_string()
+        });
+
+        let mut page = record;
+        page.insert("title".to_string(), Value::String(title.clone()));
+        let body = templates.render_page_template(&template, &template_path, &Value::Object(page), "")?;
+        // The template output is final Markdown; it is not run through Nunjucks again
+        let content = format!("---\ntitle: {}\ntemplating: false\n---\n{}", Value::String(title.clone()), body);
+
+        summary.generated.insert(path.clone(), GeneratedPage { content, data_file: generator.data.clone() });
+        entries.push(SummaryItem::Link { title, path: Some(path), children: Vec::new() });
+    }
+
+    let count = entries.len();
+    add_to_part(&mut summary.items, &generator.part, entries);
+    Ok(count)
+}
+
+/// Records of a data file, by extension: `.json`, `.yaml`/`.yml` (a list of
+/// objects) or `.csv` (header row, then one record per row)
+fn load_records(path: &Path) -> Result<Vec<Map<String, Value>>> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    let value: Value = match extension.as_str() {
+        "json" => serde_json::from_str(&content)?,
+        "yaml" | "yml" => serde_yaml::from_str(&content)?,
+        "csv" => return Ok(parse_csv(&content)),
+        _ => bail!("Unsupported data file type (expected .json, .yaml, .yml or .csv)"),
+    };
+
+    let Value::Array(items) = value else {
+        bail!("Data file must contain a list of records");
+    };
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| match item {
+            Value::Object(record) => Ok(record),
+            _ => Err(anyhow!("Record {} is not an object", i + 1)),
+        })
+        .collect()
+}
+
+/// Parse CSV with a header row into records of string fields
+/// Quoted fields may contain commas, newlines and doubled quotes
+fn parse_csv(content: &str) -> Vec<Map<String, Value>> {
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = content.trim_start_matches('\u{FEFF}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => row.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' if !in_quotes => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows.retain(|r| r.iter().any(|f| !f.is_empty()));
+
+    let mut rows = rows.into_iter();
+    let Some(header) = rows.next() else {
+        return Vec::new();
+    };
+    rows.map(|row| {
+        header
+            .iter()
+            .zip(row.into_iter().chain(std::iter::repeat(String::new())))
+            .map(|(name, value)| (name.trim().to_string(), Value::String(value)))
+            .collect()
+    })
+    .collect()
+}
+
+/// Replace `{field}` placeholders with record values
+/// None when a field is missing or not a string, number or boolean
+fn fill_placeholders(pattern: &str, record: &Map<String, Value>) -> Option<String> {
+    let re = Regex::new(r"\{(\w+)\}").unwrap();
+    let mut result = String::new();
+    let mut last = 0;
+    for cap in re.captures_iter(pattern) {
+        let whole = cap.get(0).unwrap();
+        result.push_str(&pattern[last..whole.start()]);
+        result.push_str(&record.get(&cap[1]).and_then(field_text)?);
+        last = whole.end();
+    }
+    result.push_str(&pattern[last..]);
+    Some(result)
+}
+
+fn field_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// Append entries to the end of the part titled `part` (a new part at the end
+/// of the table of contents if there is none), or to the end without a part
+fn add_to_part(items: &mut Vec<SummaryItem>, part: &str, entries: Vec<SummaryItem>) {
+    if part.is_empty() {
+        items.extend(entries);
+        return;
+    }
+    let Some(start) = items.iter().position(|item| matches!(item, SummaryItem::PartTitle(t) if t == part)) else {
+        items.push(SummaryItem::PartTitle(part.to_string()));
+        items.extend(entries);
+        return;
+    };
+    // The part ends before the next part title or separator
+    let end = items[start + 1..]
+        .iter()
+        .position(|item| !matches!(item, SummaryItem::Link { .. }))
+        .map(|offset| start + 1 + offset)
+        .unwrap_or(items.len());
+    items.splice(end..end, entries);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(json: &str) -> Map<String, Value> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let records = parse_csv("code,name\r\nE001,\"Bad, \"\"quoted\"\" input\"\nE002\n\n");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["name"], "Bad, \"quoted\" input");
+        assert_eq!(records[1]["code"], "E002");
+        assert_eq!(records[1]["name"], "");
+    }
+
+    #[test]
+    fn test_fill_placeholders() {
+        let r = record(r#"{"code": "E001", "n": 3, "empty": ""}"#);
+        assert_eq!(fill_placeholders("errors/{code}-{n}.md", &r).as_deref(), Some("errors/E001-3.md"));
+        assert_eq!(fill_placeholders("{missing}.md", &r), None);
+        assert_eq!(fill_placeholders("{empty}.md", &r), None);
+    }
+
+    #[test]
+    fn test_add_to_part() {
+        let link = |t: &str| SummaryItem::Link { title: t.to_string(), path: None, children: Vec::new() };
+        let titles = |items: &[SummaryItem]| -> Vec<String> {
+            items
+                .iter()
+                .map(|item| match item {
+                    SummaryItem::Link { title, .. } => title.clone(),
+                    SummaryItem::PartTitle(t) => format!("## {}", t),
+                    SummaryItem::Separator => "---".to_string(),
+                })
+                .collect()
+        };
+
+        let mut items = vec![link("a"), SummaryItem::PartTitle("Ref".into()), link("b"), SummaryItem::Separator, link("c")];
+        add_to_part(&mut items, "Ref", vec![link("g")]);
+        assert_eq!(titles(&items), vec!["a", "## Ref", "b", "g", "---", "c"]);
+
+        add_to_part(&mut items, "Errors", vec![link("e")]);
+        assert_eq!(titles(&items)[6..], ["## Errors", "e"]);
+    }
+
+    #[test]
+    fn test_load_summary_generates_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("_templates")).unwrap();
+        fs::create_dir_all(root.join("data")).unwrap();
+        fs::write(root.join("SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n\n## Reference\n\n* [API](api.md)\n").unwrap();
+        fs::write(root.join("_templates/error.md"), "# {{ page.code }}\n\n{{ page.message }} ({{ book.product }})\n").unwrap();
+        fs::write(root.join("data/errors.yaml"), "- code: E001\n  name: Bad input\n  message: \"Check {{ x }}\"\n- name: No code\n").unwrap();
+        let config: BookConfig = serde_json::from_str(
+            r#"{"variables": {"product": "Acme"}, "generate": [
+                {"data": "data/errors.yaml", "template": "error", "path": "errors/{code}.md", "title": "{code}: {name}", "part": "Reference"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let summary = load_summary(root, &config).unwrap();
+        let page = &summary.generated["errors/E001.md"];
+        assert_eq!(page.data_file, "data/errors.yaml");
+        assert_eq!(
+            page.content,
+            "---\ntitle: \"E001: Bad input\"\ntemplating: false\n---\n# E001\n\nCheck {{ x }} (Acme)\n"
+        );
+        assert!(summary.has_source(root, "errors/E001.md"));
+        match summary.items.last() {
+            Some(SummaryItem::Link { title, path, .. }) => {
+                assert_eq!(title, "E001: Bad input");
+                assert_eq!(path.as_deref(), Some("errors/E001.md"));
+            }
+            other => panic!("unexpected item {:?}", other),
+        }
+        assert_eq!(summary.generated.len(), 1);
+    }
+
+    #[test]
+    fn test_generated_pages_sorted_for_language() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("_templates")).unwrap();
+        fs::write(root.join("SUMMARY.md"), "# Summary\n").unwrap();
+        fs::write(root.join("_templates/term.md"), "# {{ page.title }}\n").unwrap();
+        fs::write(root.join("terms.csv"), "id,title\n1,ハッシュ\n2,キー\n3,ばね\n4,かぎ\n").unwrap();
+        let config: BookConfig = serde_json::from_str(
+            r#"{"language": "ja", "generate": [{"data": "terms.csv", "template": "term", "path": "terms/{id}.md", "sort": "title"}]}"#,
+        )
+        .unwrap();
+
+        let summary = load_summary(root, &config).unwrap();
+        let titles: Vec<&str> = summary
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                SummaryItem::Link { title, .. } => Some(title.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(titles, vec!["かぎ", "キー", "ハッシュ", "ばね"]);
+    }
+}
@@ -0,0 +1,166 @@
+//! Internal link graph for `guidebook graph`
+//!
+//! Every chapter of SUMMARY.md (and README.md) is a node; a link from one
+//! chapter to another is an edge. Links are read from the chapters' Markdown
+//! after @imports and templates are resolved, and resolved like `guidebook
+//! check` does. The graph is printed as Graphviz DOT, or as JSON with
+//! `--json`.
+//!
+//! Chapters that link to no other chapter are dead ends: readers can only
+//! leave them through the sidebar. They are drawn dashed in DOT output.
+//! AsciiDoc chapters are only link targets.
+
+use super::check::extract_links;
+use super::export::{Chapter, ExportBook};
+use super::is_asciidoc_file;
+use super::markdown::book_target;
+use crate::paths;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Debug, Serialize)]
+pub struct GraphNode {
+    /// Book-relative source path
+    pub id: String,
+    pub title: String,
+    /// Published path of the page
+    pub url: String,
+    /// Links to no other chapter
+    pub dead_end: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LinkGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// Link graph of the book at `source`
+pub fn link_graph(source: &Path) -> Result<LinkGraph> {
+    let book = ExportBook::load(source)?;
+    let chapters = book.chapters();
+
+    // Chapters by published path
+    let mut by_url: HashMap<String, &str> = HashMap::new();
+    for chapter in &chapters {
+        by_url.entry(book.summary.html_path(&chapter.source_path)).or_insert(&chapter.source_path);
+        if chapter.source_path == "README.md" {
+            by_url.insert("index.html".to_string(), &chapter.source_path);
+        }
+    }
+
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    for chapter in &chapters {
+        let asciidoc = is_asciidoc_file(&book.source.join(&chapter.source_path));
+        let mut linked = false;
+        if !asciidoc {
+            for (file, markdown) in book.chapter_markdown(chapter)? {
+                for link in extract_links(&markdown).into_iter().filter(|link| !link.is_image) {
+                    let Some(target) = book_target(&link.url, &file, false) else { continue };
+                    let Some(to) = link_chapter(&book, &by_url, &target) else { continue };
+                    if to == chapter.source_path {
+                        continue;
+                    }
+                    linked = true;
+                    let edge = GraphEdge { from: chapter.source_path.clone(), to: to.to_string() };
+                    if !edges.contains(&edge) {
+                        edges.push(edge);
+                    }
+                }
+            }
+        }
+        nodes.push(node(&book, chapter, !asciidoc && !linked));
+    }
+    Ok(LinkGraph { nodes, edges })
+}
+
+/// Source path of the chapter a book-relative link target (with `#fragment`) points at
+fn link_chapter<'a>(book: &ExportBook, by_url: &HashMap<String, &'a str>, target: &str) -> Option<&'a str> {
+    let path = paths::split_fragment(target).0;
+    let url = if path.ends_with(".html") {
+        book.summary.remapped.get(path).cloned().unwrap_or_else(|| path.to_string())
+    } else {
+        book.summary.html_path(path)
+    };
+    by_url.get(&url).copied()
+}
+
+fn node(book: &ExportBook, chapter: &Chapter, dead_end: bool) -> GraphNode {
+    let url = if chapter.source_path == "README.md" { "index.html".to_string() } else { book.summary.html_path(&chapter.source_path) };
+    GraphNode { id: chapter.source_path.clone(), title: chapter.title.clone(), url, dead_end }
+}
+
+impl LinkGraph {
+    /// Graphviz DOT of the graph
+    pub fn to_dot(&self) -> String {
+        let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut dot = String::from("digraph book {\n    node [shape=box];\n");
+        for node in &self.nodes {
+            let style = if node.dead_end { ", style=dashed" } else { "" };
+            dot.push_str(&format!("    {} [label={}, URL={}{}];\n", quote(&node.id), quote(&node.title), quote(&node.url), style));
+        }
+        for edge in &self.edges {
+            dot.push_str(&format!("    {} -> {};\n", quote(&edge.from), quote(&edge.to)));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Nodes that link to no other chapter
+    pub fn dead_ends(&self) -> impl Iterator<Item = &GraphNode> {
+        self.nodes.iter().filter(|node| node.dead_end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_link_graph() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path();
+        fs::create_dir_all(source.join("guide")).unwrap();
+        fs::write(source.join("book.json"), r#"{"variables": {"next": "guide/api.md"}}"#).unwrap();
+        fs::write(source.join("README.md"), "# Intro\n\nStart [here](guide/start.md) or read the [API](guide/api.html#auth).\n").unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Start](guide/start.md)\n* [API \"v2\"](guide/api.md)\n").unwrap();
+        fs::write(
+            source.join("guide/start.md"),
+            "# Start\n\n[Home](../index.html) [again](/README.md) [next]({{ book.next }}) [top](#top) ![logo](logo.png) [site](https://example.com)\n",
+        )
+        .unwrap();
+        fs::write(source.join("guide/api.md"), "# API\n\n[Self](api.md)\n").unwrap();
+
+        let graph = link_graph(source).unwrap();
+        let ids: Vec<(&str, &str, bool)> = graph.nodes.iter().map(|n| (n.id.as_str(), n.url.as_str(), n.dead_end)).collect();
+        assert_eq!(
+            ids,
+            vec![("README.md", "index.html", false), ("guide/start.md", "guide/start.html", false), ("guide/api.md", "guide/api.html", true)]
+        );
+        let edge = |from: &str, to: &str| GraphEdge { from: from.to_string(), to: to.to_string() };
+        assert_eq!(
+            graph.edges,
+            vec![
+                edge("README.md", "guide/start.md"),
+                edge("README.md", "guide/api.md"),
+                edge("guide/start.md", "README.md"),
+                edge("guide/start.md", "guide/api.md"),
+            ]
+        );
+        assert_eq!(graph.dead_ends().map(|n| n.id.as_str()).collect::<Vec<_>>(), vec!["guide/api.md"]);
+
+        let dot = graph.to_dot();
+        assert!(dot.contains(r#"    "guide/api.md" [label="API \"v2\"", URL="guide/api.html", style=dashed];"#), "{}", dot);
+        assert!(dot.contains(r#"    "README.md" -> "guide/start.md";"#), "{}", dot);
+    }
+}
@@ -0,0 +1,269 @@
+//! Extension points for library users
+//!
+//! Preprocessors registered here run on the Markdown source of every page
+//! (after `@import` expansion, before Nunjucks templating and rendering), so
+//! custom directives can be added without changing the renderer.
+//! Postprocessors run on the final HTML of every page (after the glossary and
+//! SVG passes), together with the external commands declared in the
+//! `postprocessors` section of book.json, sorted by their `order`:
+//!
+//! ```no_run
+//! use guidebook::builder::hooks::{register_postprocessor, register_preprocessor, PageContext};
+//!
+//! register_preprocessor("book-title", |content: &str, page: &PageContext| {
+//!     content.replace("<!-- book-title -->", &page.config.title)
+//! });
+//! register_postprocessor("analytics", 10, |html: &str, _: &PageContext| {
+//!     html.replace("</body>", "<script src=\"/stats.js\"></script></body>")
+//! });
+//! guidebook::builder::build("book".as_ref(), "book/_book".as_ref()).unwrap();
+//! ```
+
+use crate::parser::{BookConfig, FrontMatter, SummaryPosition};
+use crate::warning;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::RwLock;
+
+/// The page a hook runs for
+pub struct PageContext<'a> {
+    /// Book source directory (the language directory of multi-language books)
+    pub book_dir: &'a Path,
+    /// Book-relative source path (e.g. "guide/start.md")
+    pub source_path: &'a str,
+    /// Published path of the page (e.g. "guide/start.html")
+    pub html_path: &'a str,
+    pub config: &'a BookConfig,
+    pub front_matter: Option<&'a FrontMatter>,
+    /// Language code of a multi-language book's language directory
+    pub language: Option<&'a str>,
+    /// Where the page sits in SUMMARY.md (None for pages not listed, like the README index)
+    pub position: Option<SummaryPosition>,
+    /// Last change of its source (Unix seconds), with `lastUpdated` in book.json
+    pub updated: Option<u64>,
+}
+
+/// Transforms the Markdown source of a page
+/// Implemented for `fn(&str, &PageContext) -> String` and matching closures
+pub trait Preprocessor: Send + Sync {
+    fn run(&self, content: &str, page: &PageContext) -> String;
+}
+
+impl<F> Preprocessor for F
+where
+    F: Fn(&str, &PageContext) -> String + Send + Sync,
+{
+    fn run(&self, content: &str, page: &PageContext) -> String {
+        self(content, page)
+    }
+}
+
+/// Transforms the final HTML of a page
+/// Implemented for `fn(&str, &PageContext) -> String` and matching closures
+pub trait Postprocessor: Send + Sync {
+    fn run(&self, html: &str, page: &PageContext) -> String;
+}
+
+impl<F> Postprocessor for F
+where
+    F: Fn(&str, &PageContext) -> String + Send + Sync,
+{
+    fn run(&self, html: &str, page: &PageContext) -> String {
+        self(html, page)
+    }
+}
+
+type Registry<T> = RwLock<Vec<(String, Box<T>)>>;
+
+static PREPROCESSORS: Registry<dyn Preprocessor> = RwLock::new(Vec::new());
+/// (name, order, postprocessor)
+type PostprocessorEntry = (String, i32, Box<dyn Postprocessor>);
+
+static POSTPROCESSORS: RwLock<Vec<PostprocessorEntry>> = RwLock::new(Vec::new());
+
+/// Register a preprocessor under `name`, replacing one with the same name
+/// Preprocessors run in registration order
+pub fn register_preprocessor(name: &str, preprocessor: impl Preprocessor + 'static) {
+    let mut registry = PREPROCESSORS.write().unwrap_or_else(|e| e.into_inner());
+    let preprocessor: Box<dyn Preprocessor> = Box::new(preprocessor);
+    match registry.iter_mut().find(|(n, _)| n == name) {
+        Some(entry) => entry.1 = preprocessor,
+        None => registry.push((name.to_string(), preprocessor)),
+    }
+}
+
+/// Remove the preprocessor registered under `name`; returns whether there was one
+pub fn unregister_preprocessor(name: &str) -> bool {
+    let mut registry = PREPROCESSORS.write().unwrap_or_else(|e| e.into_inner());
+    let before = registry.len();
+    registry.retain(|(n, _)| n != name);
+    registry.len() != before
+}
+
+/// Run the registered preprocessors on a page's Markdown source
+pub(crate) fn run_preprocessors(content: String, page: &PageContext) -> String {
+    let registry = PREPROCESSORS.read().unwrap_or_else(|e| e.into_inner());
+    registry
+        .iter()
+        .fold(content, |content, (_, preprocessor)| preprocessor.run(&content, page))
+}
+
+/// Register a postprocessor under `name`, replacing one with the same name
+/// Postprocessors and book.json commands run by ascending `order`, then in
+/// registration order (commands after registered postprocessors)
+pub fn register_postprocessor(name: &str, order: i32, postprocessor: impl Postprocessor + 'static) {
+    let mut registry = POSTPROCESSORS.write().unwrap_or_else(|e| e.into_inner());
+    let postprocessor: Box<dyn Postprocessor> = Box::new(postprocessor);
+    match registry.iter_mut().find(|(n, _, _)| n == name) {
+        Some(entry) => *entry = (name.to_string(), order, postprocessor),
+        None => registry.push((name.to_string(), order, postprocessor)),
+    }
+}
+
+/// Remove the postprocessor registered under `name`; returns whether there was one
+pub fn unregister_postprocessor(name: &str) -> bool {
+    let mut registry = POSTPROCESSORS.write().unwrap_or_else(|e| e.into_inner());
+    let before = registry.len();
+    registry.retain(|(n, _, _)| n != name);
+    registry.len() != before
+}
+
+/// Run the registered postprocessors and book.json commands on a page's final HTML
+/// A failing command leaves the HTML unchanged and prints a warning
+pub(crate) fn run_postprocessors(html: String, page: &PageContext) -> String {
+    let registry = POSTPROCESSORS.read().unwrap_or_else(|e| e.into_inner());
+    if registry.is_empty() && page.config.postprocessors.is_empty() {
+        return html;
+    }
+
+    enum Step<'a> {
+        Hook(&'a dyn Postprocessor),
+        Command(&'a str),
+    }
+    let mut steps: Vec<(i32, Step)> = registry
+        .iter()
+        .map(|(_, order, postprocessor)| (*order, Step::Hook(postprocessor.as_ref())))
+        .chain(page.config.postprocessors.iter().map(|p| (p.order, Step::Command(p.command.as_str()))))
+        .collect();
+    // Stable: equal orders keep registration order
+    steps.sort_by_key(|(order, _)| *order);
+
+    steps.into_iter().fold(html, |html, (_, step)| match step {
+        Step::Hook(postprocessor) => postprocessor.run(&html, page),
+        Step::Command(command) => match run_command(command, &html, page) {
+            Ok(output) => output,
+            Err(e) => {
+                warning!("postprocessor `{}` failed for {}: {}", command, page.source_path, e);
+                html
+            }
+        },
+    })
+}
+
+/// Run a shell command with the page HTML on stdin; its stdout replaces the HTML
+/// The command runs in the book directory with GUIDEBOOK_SOURCE_PATH and
+/// GUIDEBOOK_HTML_PATH set
+fn run_command(command: &str, html: &str, page: &PageContext) -> Result<String, String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let mut child = shell
+        .arg(command)
+        .current_dir(page.book_dir)
+        .env("GUIDEBOOK_SOURCE_PATH", page.source_path)
+        .env("GUIDEBOOK_HTML_PATH", page.html_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    // Write from another thread so a command streaming its output can't block on a full pipe
+    let mut stdin = child.stdin.take().ok_or("no stdin")?;
+    let input = html.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    // A command may exit without reading all of its input
+    let _ = writer.join();
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} {}", output.status, stderr.trim()).trim().to_string());
+    }
+    String::from_utf8(output.stdout).map_err(|_| "output is not UTF-8".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The registry is global: only touch this test's page while other tests build books
+    fn shout(content: &str, page: &PageContext) -> String {
+        content.replace("!!", &format!("({})", page.source_path))
+    }
+
+    fn suffix(tag: &'static str) -> impl Fn(&str, &PageContext) -> String + Send + Sync {
+        move |content: &str, page: &PageContext| {
+            if page.source_path == "hooks-test.md" {
+                format!("{} [{}]", content, tag)
+            } else {
+                content.to_string()
+            }
+        }
+    }
+
+    fn test_page(config: &BookConfig) -> PageContext<'_> {
+        PageContext {
+            book_dir: Path::new("."),
+            source_path: "hooks-test.md",
+            html_path: "hooks-test.html",
+            config,
+            front_matter: None,
+            language: None,
+            position: None,
+            updated: None,
+        }
+    }
+
+    #[test]
+    fn test_preprocessors_run_in_order_and_replace_by_name() {
+        let config = BookConfig::default();
+        let page = test_page(&config);
+
+        register_preprocessor("hooks-test-shout", shout);
+        register_preprocessor("hooks-test-suffix", suffix("a"));
+        register_preprocessor("hooks-test-suffix", suffix("b"));
+        assert_eq!(run_preprocessors("Hi !!".to_string(), &page), "Hi (hooks-test.md) [b]");
+
+        assert!(unregister_preprocessor("hooks-test-shout"));
+        assert!(unregister_preprocessor("hooks-test-suffix"));
+        assert!(!unregister_preprocessor("hooks-test-suffix"));
+        assert_eq!(run_preprocessors("Hi !!".to_string(), &page), "Hi !!");
+    }
+
+    #[test]
+    fn test_postprocessors_run_by_order() {
+        let config: BookConfig = serde_json::from_str(r#"{"postprocessors": [
+            {"command": "sed \"s|x|[cmd $GUIDEBOOK_HTML_PATH]|\"", "order": 5},
+            {"command": "exit 3"}
+        ]}"#)
+        .unwrap();
+        let page = test_page(&config);
+
+        register_postprocessor("hooks-test-late", 10, suffix("late"));
+        register_postprocessor("hooks-test-early", -1, suffix("early"));
+        let html = run_postprocessors("x".to_string(), &page);
+        assert!(unregister_postprocessor("hooks-test-late"));
+        assert!(unregister_postprocessor("hooks-test-early"));
+        if cfg!(unix) {
+            assert_eq!(html, "[cmd hooks-test.html] [early] [late]");
+        }
+    }
+}
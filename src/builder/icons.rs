@@ -0,0 +1,134 @@
+//! Favicon and touch icon (`icons` in book.json)
+//!
+//! Pages link the icon of the browser tab and the iOS home screen icon from
+//! `gitbook/images/`, where the build copies the files `icons.favicon` and
+//! `icons.touchIcon` name. Books without a favicon get the bundled one; there
+//! is no default touch icon, iOS then uses a screenshot of the page.
+
+use super::renderer::html_escape;
+use crate::parser::BookConfig;
+use crate::{paths, warning};
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+/// Bundled favicon of books without `icons.favicon`
+const DEFAULT_FAVICON: &str = include_str!("../../templates/favicon.svg");
+
+/// Output directory of the icons
+const IMAGES_DIR: &str = "gitbook/images";
+
+/// An icon the pages link to
+#[derive(Debug, PartialEq)]
+pub struct Icon {
+    /// Book path of the configured file; none for the bundled favicon
+    pub source: Option<String>,
+    /// Output-relative path
+    pub path: String,
+    /// MIME type, for the `type` attribute
+    pub mime: Option<&'static str>,
+}
+
+/// The favicon: `icons.favicon` or the bundled SVG
+pub fn favicon(config: &BookConfig) -> Icon {
+    match &config.icons.favicon {
+        Some(file) => icon(file, "favicon"),
+        None => Icon { source: None, path: format!("{}/favicon.svg", IMAGES_DIR), mime: Some("image/svg+xml") },
+    }
+}
+
+/// The home screen icon of iOS, with `icons.touchIcon`
+pub fn touch_icon(config: &BookConfig) -> Option<Icon> {
+    config.icons.touch_icon.as_deref().map(|file| icon(file, "apple-touch-icon"))
+}
+
+fn icon(file: &str, name: &str) -> Icon {
+    let source = paths::normalize(file.trim_start_matches("./"));
+    let extension = source.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+    let mime = match extension.as_str() {
+        "ico" => Some("image/x-icon"),
+        "png" => Some("image/png"),
+        "svg" => Some("image/svg+xml"),
+        "gif" => Some("image/gif"),
+        _ => None,
+    };
+    let path = match mime {
+        Some(_) => format!("{}/{}.{}", IMAGES_DIR, name, extension),
+        None => format!("{}/{}", IMAGES_DIR, name),
+    };
+    Icon { source: Some(source), path, mime }
+}
+
+/// Copy the icons (or write the bundled favicon) to `gitbook/images/`;
+/// returns the copied files as (output path, book path)
+pub fn write_icons(source: &Path, output: &Path, config: &BookConfig) -> Result<Vec<(String, String)>> {
+    fs::create_dir_all(output.join(IMAGES_DIR))?;
+    let mut copied = Vec::new();
+    for (key, icon) in [("favicon", Some(favicon(config))), ("touchIcon", touch_icon(config))] {
+        let Some(icon) = icon else { continue };
+        let Some(file) = icon.source else {
+            fs::write(output.join(&icon.path), DEFAULT_FAVICON)?;
+            continue;
+        };
+        if !source.join(&file).is_file() {
+            warning!("icons.{}: {} is not a file of the book", key, file);
+            continue;
+        }
+        if icon.mime.is_none() {
+            warning!("icons.{}: {} is not an ICO, PNG, SVG or GIF image", key, file);
+        }
+        fs::copy(source.join(&file), output.join(&icon.path))?;
+        copied.push((icon.path, file));
+    }
+    Ok(copied)
+}
+
+/// `<link>` tags of the icons, for pages `root_path` away from the book root
+pub fn links(config: &BookConfig, root_path: &str) -> String {
+    let favicon = favicon(config);
+    let mut html = format!(r#"<link rel="icon" href="{}{}""#, root_path, html_escape(&favicon.path));
+    if let Some(mime) = favicon.mime {
+        html.push_str(&format!(r#" type="{}""#, mime));
+    }
+    html.push('>');
+    if let Some(touch_icon) = touch_icon(config) {
+        html.push_str(&format!(r#"<link rel="apple-touch-icon" href="{}{}">"#, root_path, html_escape(&touch_icon.path)));
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_icons() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        let output = dir.path().join("_book");
+        fs::create_dir_all(source.join("assets")).unwrap();
+        fs::write(source.join("assets/icon.PNG"), "png").unwrap();
+
+        // The bundled favicon
+        let config = BookConfig::default();
+        assert!(write_icons(&source, &output, &config).unwrap().is_empty());
+        assert_eq!(fs::read_to_string(output.join("gitbook/images/favicon.svg")).unwrap(), DEFAULT_FAVICON);
+        assert_eq!(links(&config, "../"), r#"<link rel="icon" href="../gitbook/images/favicon.svg" type="image/svg+xml">"#);
+
+        let config: BookConfig =
+            serde_json::from_str(r#"{"icons": {"favicon": "./assets/icon.PNG", "touchIcon": "assets/missing.png"}}"#).unwrap();
+        assert_eq!(
+            write_icons(&source, &output, &config).unwrap(),
+            vec![("gitbook/images/favicon.png".to_string(), "assets/icon.PNG".to_string())]
+        );
+        assert_eq!(fs::read_to_string(output.join("gitbook/images/favicon.png")).unwrap(), "png");
+        assert!(!output.join("gitbook/images/apple-touch-icon.png").exists());
+        assert_eq!(
+            links(&config, "./"),
+            concat!(
+                r#"<link rel="icon" href="./gitbook/images/favicon.png" type="image/png">"#,
+                r#"<link rel="apple-touch-icon" href="./gitbook/images/apple-touch-icon.png">"#
+            )
+        );
+    }
+}
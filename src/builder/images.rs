@@ -3,6 +3,7 @@
 //! Downloads `https://` images at build time and replaces URLs in HTML
 //! with local paths for offline access.
 
+use crate::warning;
 use crc32fast::Hasher;
 use regex::Regex;
 use reqwest::blocking::Client;
@@ -85,7 +86,7 @@ impl ImageDownloader {
                     replacements.push((full_match.to_string(), new_tag));
                 }
                 Err(e) => {
-                    eprintln!("  Warning: Failed to download image {}: {}", url, e);
+                    warning!("Failed to download image {}: {}", url, e);
                     // Keep original URL on failure
                 }
             }
@@ -0,0 +1,134 @@
+//! IndexNow notifications for changed pages
+//!
+//! With `deploy.siteUrl` and `deploy.indexNowKey` set in book.json, the build
+//! writes the `<key>.txt` verification file to the output root and
+//! `guidebook manifest notify` submits the pages that changed between two
+//! build manifests, so search engines recrawl only what was deployed.
+
+use crate::parser::book_config::DeployConfig;
+use anyhow::{anyhow, bail, Result};
+use std::fs;
+use std::path::Path;
+
+/// Endpoint shared by all IndexNow-enabled search engines
+pub const DEFAULT_ENDPOINT: &str = "https://api.indexnow.org/indexnow";
+
+/// Maximum number of URLs per IndexNow request
+const MAX_URLS_PER_REQUEST: usize = 10_000;
+
+/// Write the `<key>.txt` file search engines fetch to verify the key
+/// Returns false when no key is configured
+pub fn write_key_file(output: &Path, deploy: &DeployConfig) -> Result<bool> {
+    if deploy.index_now_key.is_empty() {
+        return Ok(false);
+    }
+    if !deploy.valid_index_now_key() {
+        bail!("Invalid deploy.indexNowKey: use 8 to 128 letters, digits or dashes");
+    }
+    fs::write(output.join(format!("{}.txt", deploy.index_now_key)), &deploy.index_now_key)?;
+    Ok(true)
+}
+
+/// Public URLs of the HTML pages among `paths` (output-relative)
+/// "guide/index.html" is published as "guide/"
+pub fn page_urls(site_url: &str, paths: &[String]) -> Vec<String> {
+    let base = site_url.trim_end_matches('/');
+    paths
+        .iter()
+        .filter(|p| p.ends_with(".html"))
+        .map(|p| {
+            let page = match p.strip_suffix("index.html") {
+                Some(dir) if dir.is_empty() || dir.ends_with('/') => dir,
+                _ => p.as_str(),
+            };
+            format!("{}/{}", base, page)
+        })
+        .collect()
+}
+
+/// Submit URLs to IndexNow
+pub fn submit(deploy: &DeployConfig, urls: &[String]) -> Result<()> {
+    let (site_url, key) = (deploy.site_url.trim_end_matches('/'), &deploy.index_now_key);
+    if site_url.is_empty() || key.is_empty() {
+        bail!("Set deploy.siteUrl and deploy.indexNowKey in book.json to use IndexNow");
+    }
+    let host = site_host(site_url).ok_or_else(|| anyhow!("Invalid deploy.siteUrl: {}", site_url))?;
+    let endpoint = if deploy.index_now_endpoint.is_empty() {
+        DEFAULT_ENDPOINT
+    } else {
+        &deploy.index_now_endpoint
+    };
+
+    for chunk in urls.chunks(MAX_URLS_PER_REQUEST) {
+        let body = serde_json::json!({
+            "host": host,
+            "key": key,
+            "keyLocation": format!("{}/{}.txt", site_url, key),
+            "urlList": chunk,
+        });
+        ureq::post(endpoint)
+            .set("Content-Type", "application/json; charset=utf-8")
+            .timeout(std::time::Duration::from_secs(30))
+            .send_string(&body.to_string())
+            .map_err(|e| anyhow!("IndexNow request failed: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Host part of a site URL ("https://docs.example.com/book" -> "docs.example.com")
+fn site_host(site_url: &str) -> Option<&str> {
+    let rest = site_url.split_once("://")?.1;
+    let host = rest.split(['/', '?', '#']).next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_urls() {
+        let paths = vec![
+            "index.html".to_string(),
+            "guide/index.html".to_string(),
+            "guide/start.html".to_string(),
+            "myindex.html".to_string(),
+            "gitbook/gitbook.js".to_string(),
+        ];
+        assert_eq!(
+            page_urls("https://docs.example.com/book/", &paths),
+            vec![
+                "https://docs.example.com/book/",
+                "https://docs.example.com/book/guide/",
+                "https://docs.example.com/book/guide/start.html",
+                "https://docs.example.com/book/myindex.html",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_site_host() {
+        assert_eq!(site_host("https://docs.example.com/book"), Some("docs.example.com"));
+        assert_eq!(site_host("https://docs.example.com"), Some("docs.example.com"));
+        assert_eq!(site_host("docs.example.com"), None);
+    }
+
+    #[test]
+    fn test_write_key_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut deploy = DeployConfig::default();
+        assert!(!write_key_file(dir.path(), &deploy).unwrap());
+
+        deploy.index_now_key = "0123456789abcdef".to_string();
+        assert!(write_key_file(dir.path(), &deploy).unwrap());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("0123456789abcdef.txt")).unwrap(),
+            "0123456789abcdef"
+        );
+
+        // The key names the file
+        deploy.index_now_key = "../0123456789abcdef".to_string();
+        assert!(write_key_file(dir.path(), &deploy).is_err());
+        assert!(!dir.path().join("../0123456789abcdef.txt").exists());
+    }
+}
@@ -0,0 +1,120 @@
+//! Last-updated dates of pages (`lastUpdated` in book.json)
+//!
+//! Pages show the date their source last changed, and sitemap.xml lists it as
+//! `<lastmod>`. In a git repository that is the date of the file's last
+//! commit, read for every file of the book with a single `git log`. Files
+//! without a commit (new or ignored ones) and books outside a repository use
+//! the file's modification time, or SOURCE_DATE_EPOCH in reproducible builds.
+//! Shallow clones only know the commits they fetched, so CI checkouts need the
+//! full history (`fetch-depth: 0`).
+
+use super::publish::git;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Marks the commit time lines of the `git log` output
+const COMMIT_MARKER: char = '\u{1}';
+
+/// Last commit time of the files of a book
+#[derive(Debug, Default)]
+pub struct LastModified {
+    /// Book source directory the paths are relative to
+    root: PathBuf,
+    /// Book-relative path -> Unix seconds of its last commit
+    commits: HashMap<String, u64>,
+    /// Time of the files without a commit in reproducible builds
+    uncommitted: Option<u64>,
+}
+
+impl LastModified {
+    /// Read the commit times of the files under `source`; none outside a git
+    /// repository (or without git)
+    /// uncommitted: time of the files without a commit instead of their
+    /// modification time (reproducible builds)
+    pub fn load(source: &Path, uncommitted: Option<u64>) -> Self {
+        let args = ["-c", "core.quotePath=false", "log", "--format=%x01%ct", "--name-only", "--no-renames", "--relative", "--", "."];
+        let log = git(source, &args, None).unwrap_or_default();
+        Self { root: source.to_path_buf(), commits: parse_log(&log), uncommitted }
+    }
+
+    /// Last change of the book file `path` under `book_dir` (Unix seconds):
+    /// its last commit, or its modification time
+    pub fn get(&self, book_dir: &Path, path: &str) -> Option<u64> {
+        // Shared chapters live in another language's directory
+        let committed = (book_dir == self.root).then(|| self.commits.get(path)).flatten();
+        if let Some(&time) = committed {
+            return Some(time);
+        }
+        if let Some(time) = self.uncommitted {
+            return book_dir.join(path).is_file().then_some(time);
+        }
+        let modified = fs::metadata(book_dir.join(path)).and_then(|m| m.modified()).ok()?;
+        modified.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+    }
+
+    /// Last change of any of `paths` (the files of a merged entry)
+    pub fn latest(&self, book_dir: &Path, paths: &[String]) -> Option<u64> {
+        paths.iter().filter_map(|path| self.get(book_dir, path)).max()
+    }
+}
+
+/// Files and their last commit time from `git log --name-only`, newest
+/// commit first
+fn parse_log(log: &str) -> HashMap<String, u64> {
+    let mut commits = HashMap::new();
+    let mut time = None;
+    for line in log.lines() {
+        if let Some(commit_time) = line.strip_prefix(COMMIT_MARKER) {
+            time = commit_time.trim().parse().ok();
+        } else if let (Some(time), false) = (time, line.is_empty()) {
+            commits.entry(line.to_string()).or_insert(time);
+        }
+    }
+    commits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_modified() {
+        let dir = tempfile::tempdir().unwrap();
+        let book = dir.path().join("book");
+        fs::create_dir_all(book.join("guide")).unwrap();
+        let git = |args: &[&str], date: Option<&str>| {
+            let mut command = std::process::Command::new("git");
+            command.arg("-C").arg(dir.path()).args(args);
+            if let Some(date) = date {
+                command.env("GIT_AUTHOR_DATE", date).env("GIT_COMMITTER_DATE", date);
+            }
+            assert!(command.status().unwrap().success());
+        };
+        git(&["init", "--quiet"], None);
+        git(&["config", "user.name", "Test"], None);
+        git(&["config", "user.email", "test@example.com"], None);
+        fs::write(book.join("README.md"), "# Intro\n").unwrap();
+        fs::write(book.join("guide/start.md"), "# Start\n").unwrap();
+        git(&["add", "."], None);
+        git(&["commit", "--quiet", "-m", "First"], Some("2024-01-02T10:00:00Z"));
+        fs::write(book.join("guide/start.md"), "# Start here\n").unwrap();
+        git(&["commit", "--quiet", "-am", "Second"], Some("2024-03-04T10:00:00Z"));
+        fs::write(book.join("new.md"), "# New\n").unwrap();
+
+        let times = LastModified::load(&book, None);
+        assert_eq!(times.get(&book, "README.md"), Some(1_704_189_600));
+        assert_eq!(times.get(&book, "guide/start.md"), Some(1_709_546_400));
+        assert_eq!(times.latest(&book, &["README.md".to_string(), "guide/start.md".to_string()]), Some(1_709_546_400));
+        // Not committed yet
+        assert!(times.get(&book, "new.md").is_some_and(|time| time > 1_709_546_400));
+        assert_eq!(times.get(&book, "missing.md"), None);
+
+        // Reproducible builds
+        let times = LastModified::load(&book, Some(1_700_000_000));
+        assert_eq!(times.get(&book, "guide/start.md"), Some(1_709_546_400));
+        assert_eq!(times.get(&book, "new.md"), Some(1_700_000_000));
+        assert_eq!(times.get(&book, "missing.md"), None);
+    }
+}
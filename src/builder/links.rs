@@ -0,0 +1,115 @@
+//! External link decoration (`externalLinks` section of book.json)
+//!
+//! After Markdown rendering, links to other sites (http(s) and
+//! protocol-relative URLs) that don't set a target open in a new tab with
+//! `rel="noopener noreferrer"`; both attributes can be changed. With `icon`
+//! set, every external link also gets the `external-link` class, which
+//! gitbook.css marks with an icon after the link text. Links within the book,
+//! and absolute links to its `deploy.siteUrl`, are left alone.
+//!
+//! This is the `external-links` pipeline stage, so it can be disabled there.
+
+use crate::parser::ExternalLinksConfig;
+use regex::Regex;
+
+/// Class of external links when `icon` is set
+const ICON_CLASS: &str = "external-link";
+
+/// Decorate the external links of rendered page content
+/// site_url: `deploy.siteUrl`; links under it are internal
+pub fn decorate_external_links(html: &str, config: &ExternalLinksConfig, site_url: &str) -> String {
+    let target = config.target.as_deref().unwrap_or("_blank");
+    let rel = config.rel.as_deref().unwrap_or("noopener noreferrer");
+    if target.is_empty() && rel.is_empty() && !config.icon {
+        return html.to_string();
+    }
+
+    let tag_re = Regex::new(r"(?i)<a\s[^>]*>").unwrap();
+    let attr_re = Regex::new(r#"(?i)\s(href|target|rel|class)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+    let site = site_url.trim_end_matches('/').to_lowercase();
+
+    tag_re
+        .replace_all(html, |caps: &regex::Captures| {
+            let tag = &caps[0];
+            let mut href = None;
+            let mut has_target = false;
+            let mut has_rel = false;
+            let mut class = None;
+            for attr in attr_re.captures_iter(tag) {
+                let value = attr.get(2).or_else(|| attr.get(3)).unwrap();
+                match attr[1].to_lowercase().as_str() {
+                    "href" => href = Some(value.as_str()),
+                    "target" => has_target = true,
+                    "rel" => has_rel = true,
+                    _ => class = Some(value),
+                }
+            }
+            if !href.is_some_and(|href| is_external(href, &site)) {
+                return tag.to_string();
+            }
+
+            let mut decorated = tag.trim_end_matches('>').to_string();
+            if config.icon {
+                match class {
+                    Some(class) if class.as_str().split_whitespace().any(|c| c == ICON_CLASS) => {}
+                    Some(class) => decorated.insert_str(class.end(), &format!(" {}", ICON_CLASS)),
+                    None => decorated.push_str(&format!(r#" class="{}""#, ICON_CLASS)),
+                }
+            }
+            // Links that set their own target (raw HTML, autolinks) keep their attributes
+            if !has_target {
+                if !target.is_empty() {
+                    decorated.push_str(&format!(r#" target="{}""#, target));
+                }
+                if !rel.is_empty() && !has_rel {
+                    decorated.push_str(&format!(r#" rel="{}""#, rel));
+                }
+            }
+            decorated.push('>');
+            decorated
+        })
+        .into_owned()
+}
+
+/// Whether a link leaves the book: an absolute or protocol-relative URL not
+/// under the book's site URL (lowercase, without a trailing slash)
+fn is_external(href: &str, site: &str) -> bool {
+    let href = href.trim().to_lowercase();
+    let absolute = href.starts_with("http://") || href.starts_with("https://") || href.starts_with("//");
+    if !absolute {
+        return false;
+    }
+    if site.is_empty() {
+        return true;
+    }
+    // Compare without the scheme, so http:// and // links to the site are internal too
+    let strip = |url: &str| url.split_once("//").map(|(_, rest)| rest.to_string()).unwrap_or_default();
+    let (href, site) = (strip(&href), strip(site));
+    !(href == site || href.starts_with(&format!("{}/", site)) || href.starts_with(&format!("{}?", site)) || href.starts_with(&format!("{}#", site)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decorate_external_links() {
+        let html = r##"<a href="https://example.com">e</a> <a href="guide/a.html">g</a> <a href="#top">t</a> <a href="https://docs.acme.test/api/">s</a> <a class="btn" href='//cdn.test/x'>c</a> <a href="https://x.test" target="_self">x</a>"##;
+        let default = decorate_external_links(html, &ExternalLinksConfig::default(), "https://docs.acme.test/");
+        assert_eq!(
+            default,
+            r##"<a href="https://example.com" target="_blank" rel="noopener noreferrer">e</a> <a href="guide/a.html">g</a> <a href="#top">t</a> <a href="https://docs.acme.test/api/">s</a> <a class="btn" href='//cdn.test/x' target="_blank" rel="noopener noreferrer">c</a> <a href="https://x.test" target="_self">x</a>"##
+        );
+
+        let config = ExternalLinksConfig { icon: true, target: Some(String::new()), rel: Some("nofollow".to_string()) };
+        let decorated = decorate_external_links(html, &config, "");
+        assert_eq!(
+            decorated,
+            r##"<a href="https://example.com" class="external-link" rel="nofollow">e</a> <a href="guide/a.html">g</a> <a href="#top">t</a> <a href="https://docs.acme.test/api/" class="external-link" rel="nofollow">s</a> <a class="btn external-link" href='//cdn.test/x' rel="nofollow">c</a> <a href="https://x.test" target="_self" class="external-link">x</a>"##
+        );
+        assert_eq!(decorate_external_links(&decorated, &config, "").matches(ICON_CLASS).count(), 4);
+
+        let off = ExternalLinksConfig { icon: false, target: Some(String::new()), rel: Some(String::new()) };
+        assert_eq!(decorate_external_links(html, &off, ""), html);
+    }
+}
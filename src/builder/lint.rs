@@ -0,0 +1,248 @@
+//! Authoring checks for `guidebook lint`
+//!
+//! Flags Markdown and SUMMARY.md mistakes that the builder silently works
+//! around. Each rule has a severity set in the `lint` section of book.json
+//! (`off`, `warning` or `error`, default `warning`).
+
+use super::IMPORT_PATTERN;
+use crate::parser::{self, BookConfig, LintSeverity};
+use crate::{paths, warning};
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Lint rules as (name, description)
+pub const RULES: &[(&str, &str)] = &[
+    (FULLWIDTH_HEADING_SPACE, "full-width space (U+3000) after heading markers"),
+    (MISSING_SUMMARY_ENTRY, "chapter files not listed in SUMMARY.md"),
+    (DUPLICATE_TITLE, "several SUMMARY.md entries with the same title"),
+    (IMAGE_PATH_SPACES, "image paths containing spaces"),
+];
+
+const FULLWIDTH_HEADING_SPACE: &str = "fullwidth-heading-space";
+const MISSING_SUMMARY_ENTRY: &str = "missing-summary-entry";
+const DUPLICATE_TITLE: &str = "duplicate-title";
+const IMAGE_PATH_SPACES: &str = "image-path-spaces";
+
+/// Files at the book root that are not chapters
+const SPECIAL_FILES: &[&str] = &["README.md", "SUMMARY.md", "GLOSSARY.md", "LANGS.md", "404.md"];
+
+/// A rule violation
+#[derive(Debug)]
+pub struct LintIssue {
+    /// Book-relative source file
+    pub file: String,
+    /// 1-based line
+    pub line: usize,
+    pub rule: &'static str,
+    pub severity: LintSeverity,
+    pub message: String,
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = if self.severity == LintSeverity::Error { "error" } else { "warning" };
+        write!(f, "{}:{}: {}: {} [{}]", self.file, self.line, severity, self.message, self.rule)
+    }
+}
+
+/// Lint the book at `source`
+pub fn lint_book(source: &Path) -> Result<Vec<LintIssue>> {
+    let source = source.canonicalize().context("Source directory not found")?;
+    let config = BookConfig::load(&source)?;
+    for rule in config.lint.keys() {
+        if !RULES.iter().any(|(name, _)| name == rule) {
+            warning!("Unknown lint rule `{}` in book.json", rule);
+        }
+    }
+
+    let languages = parser::langs::parse_langs(&source)?;
+    if languages.is_empty() {
+        return lint_single_book(&source, &config, "");
+    }
+    let mut issues = Vec::new();
+    for lang in languages {
+        issues.extend(lint_single_book(&source.join(&lang.code), &config, &format!("{}/", lang.code))?);
+    }
+    Ok(issues)
+}
+
+fn lint_single_book(source: &Path, config: &BookConfig, file_prefix: &str) -> Result<Vec<LintIssue>> {
+    let severity = |rule: &str| config.lint.get(rule).copied().unwrap_or(LintSeverity::Warning);
+    let mut issues = Vec::new();
+    let mut report = |file: &str, line: usize, rule: &'static str, message: String| {
+        let severity = severity(rule);
+        if severity != LintSeverity::Off {
+            issues.push(LintIssue { file: format!("{}{}", file_prefix, file), line, rule, severity, message });
+        }
+    };
+
+    let summary_content = fs::read_to_string(source.join("SUMMARY.md")).unwrap_or_default();
+    for (line, message) in duplicate_titles(&summary_content) {
+        report("SUMMARY.md", line, DUPLICATE_TITLE, message);
+    }
+
+    let files = parser::summary::chapter_files(source);
+    for file in &files {
+        let content = fs::read_to_string(source.join(file)).with_context(|| format!("Failed to read {}", file))?;
+        for (line, rule, message) in content_issues(&content) {
+            report(file, line, rule, message);
+        }
+    }
+
+    if severity(MISSING_SUMMARY_ENTRY) != LintSeverity::Off {
+        let summary = parser::Summary::parse(source)?;
+        let listed = listed_files(&summary);
+        let imported = imported_files(source, &files);
+        for file in &files {
+            if !listed.contains(file) && !imported.contains(file) && !SPECIAL_FILES.contains(&file.as_str()) {
+                report(file, 1, MISSING_SUMMARY_ENTRY, "Not listed in SUMMARY.md".to_string());
+            }
+        }
+    }
+
+    issues.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+    Ok(issues)
+}
+
+/// Source files rendered by SUMMARY.md entries (including merged entries)
+fn listed_files(summary: &parser::Summary) -> HashSet<String> {
+    fn walk(items: &[parser::SummaryItem], summary: &parser::Summary, files: &mut HashSet<String>) {
+        for item in items {
+            if let parser::SummaryItem::Link { path, children, .. } = item {
+                if let Some(path) = path {
+                    let base_path = paths::split_fragment(path).0.trim_start_matches('/');
+                    files.extend(summary.page_sources(base_path));
+                }
+                walk(children, summary, files);
+            }
+        }
+    }
+
+    let mut files = HashSet::new();
+    walk(&summary.items, summary, &mut files);
+    files
+}
+
+/// Files pulled into other pages with `<!-- @import("...") -->`
+fn imported_files(source: &Path, files: &[String]) -> HashSet<String> {
+    let re = Regex::new(IMPORT_PATTERN).unwrap();
+    let mut imported = HashSet::new();
+    for file in files {
+        let Ok(content) = fs::read_to_string(source.join(file)) else {
+            continue;
+        };
+        for cap in re.captures_iter(&content) {
+            if let Some(path) = super::renderer::resolve_book_path(file, &cap[1]) {
+                imported.insert(path);
+            }
+        }
+    }
+    imported
+}
+
+/// SUMMARY.md entries whose title was already used, as (line, message)
+fn duplicate_titles(summary: &str) -> Vec<(usize, String)> {
+    let entry_re = Regex::new(r"^\s*[*+-]\s+\[([^\]]+)\]\(").unwrap();
+    let mut first_seen: HashMap<String, usize> = HashMap::new();
+    let mut issues = Vec::new();
+    for (i, line) in summary.lines().enumerate() {
+        if let Some(cap) = entry_re.captures(line) {
+            let title = cap[1].trim().to_string();
+            match first_seen.get(&title) {
+                Some(first) => issues.push((i + 1, format!("Duplicate title \"{}\" (first used on line {})", title, first))),
+                None => {
+                    first_seen.insert(title, i + 1);
+                }
+            }
+        }
+    }
+    issues
+}
+
+/// Heading and image issues in a Markdown file, as (line, rule, message)
+/// Fenced code blocks are skipped
+fn content_issues(content: &str) -> Vec<(usize, &'static str, String)> {
+    let image_re = Regex::new(r"!\[[^\]]*\]\(([^)]*)\)").unwrap();
+    let mut issues = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+
+        let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+        if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with('\u{3000}') {
+            issues.push((i + 1, FULLWIDTH_HEADING_SPACE, "Heading marker followed by a full-width space".to_string()));
+        }
+        for cap in image_re.captures_iter(line) {
+            let url = cap[1].trim();
+            if url.contains(' ') && !url.starts_with('<') {
+                issues.push((i + 1, IMAGE_PATH_SPACES, format!("Image path contains spaces: {} (use %20 or <...>)", url)));
+            }
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_issues() {
+        let content = "#\u{3000}見出し\n\n![a](my image.png) ![b](<ok image.png>) ![c](fine.png)\n\n```\n#\u{3000}code\n![x](in code.png)\n```\n";
+        let issues = content_issues(content);
+        assert_eq!(issues.len(), 2);
+        assert_eq!((issues[0].0, issues[0].1), (1, FULLWIDTH_HEADING_SPACE));
+        assert_eq!(
+            issues[1],
+            (3, IMAGE_PATH_SPACES, "Image path contains spaces: my image.png (use %20 or <...>)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_duplicate_titles() {
+        let summary = "# Summary\n\n* [Overview](a.md)\n* [Guide](b.md)\n    * [Overview](c.md)\n";
+        assert_eq!(
+            duplicate_titles(summary),
+            vec![(5, "Duplicate title \"Overview\" (first used on line 3)".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_lint_book_severity_and_missing_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("book.json"), r#"{"lint": {"duplicate-title": "off", "image-path-spaces": "error"}}"#).unwrap();
+        fs::write(root.join("SUMMARY.md"), "# Summary\n\n* [A](a.md)\n* [A](a.md)\n").unwrap();
+        fs::write(root.join("README.md"), "# Intro\n").unwrap();
+        fs::write(root.join("a.md"), "# A\n\n<!-- @import(\"parts/snippet.md\") -->\n![x](my image.png)\n").unwrap();
+        fs::create_dir_all(root.join("parts")).unwrap();
+        fs::create_dir_all(root.join("_book")).unwrap();
+        fs::write(root.join("parts/snippet.md"), "snippet").unwrap();
+        fs::write(root.join("orphan.md"), "# Orphan\n").unwrap();
+        fs::write(root.join("_book/stale.md"), "# Stale\n").unwrap();
+
+        let issues: Vec<String> = lint_book(root).unwrap().iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            issues,
+            vec![
+                "a.md:4: error: Image path contains spaces: my image.png (use %20 or <...>) [image-path-spaces]",
+                "orphan.md:1: warning: Not listed in SUMMARY.md [missing-summary-entry]",
+            ]
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! Locale-aware formatting and sorting in templates
+//!
+//! Templates get filters that follow the book's language (`language` in
+//! book.json, or the language directory of a multi-language book):
+//!
+//! ```text
+//! {{ page.date | localdate }}                 October 16, 2026 / 2026年10月16日 / 16 tháng 10 năm 2026
+//! {{ page.date | localdate(style="short") }}  10/16/2026 / 2026/10/16 / 16/10/2026
+//! {{ 1234567.5 | localnumber }}               1,234,567.5 / 1,234,567.5 / 1.234.567,5
+//! {% for p in pages | localsort(attribute="title") %}
+//! ```
+//!
+//! Sorting is a small collation rather than a full Unicode one: Japanese
+//! sorts hiragana and katakana together, with small and voiced kana next to
+//! their base kana; Vietnamese follows its alphabet (a ă â b c d đ ...) with
+//! tones compared after the letters.
+
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use tera::Tera;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Locale {
+    English,
+    Japanese,
+    Vietnamese,
+}
+
+impl Locale {
+    /// Locale of a language code ("ja", "ja-JP", and the "jp" and "vn" of
+    /// GitBook's LANGS.md examples); English for others
+    pub fn from_language(language: &str) -> Self {
+        let primary = language.split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+        match primary.as_str() {
+            "ja" | "jp" => Locale::Japanese,
+            "vi" | "vn" => Locale::Vietnamese,
+            _ => Locale::English,
+        }
+    }
+}
+
+/// Register the `localdate`, `localnumber` and `localsort` filters
+pub fn register_filters(tera: &mut Tera, language: &str) {
+    let locale = Locale::from_language(language);
+    tera.register_filter("localdate", move |value: &Value, args: &HashMap<String, Value>| {
+        let style = args.get("style").and_then(Value::as_str).unwrap_or("long");
+        let date = value.as_str().and_then(parse_date).ok_or_else(|| {
+            tera::Error::msg(format!("localdate: expected a YYYY-MM-DD date, got {}", value))
+        })?;
+        Ok(Value::String(format_date(locale, date, style == "short")))
+    });
+    tera.register_filter("localnumber", move |value: &Value, args: &HashMap<String, Value>| {
+        let number = match value {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+        .ok_or_else(|| tera::Error::msg(format!("localnumber: expected a number, got {}", value)))?;
+        let decimals = args.get("decimals").and_then(Value::as_u64).map(|d| d as usize);
+        Ok(Value::String(format_number(locale, number, decimals)))
+    });
+    tera.register_filter("localsort", move |value: &Value, args: &HashMap<String, Value>| {
+        let Value::Array(items) = value else {
+            return Err(tera::Error::msg(format!("localsort: expected a list, got {}", value)));
+        };
+        let attribute = args.get("attribute").and_then(Value::as_str);
+        let mut items = items.clone();
+        items.sort_by_cached_key(|item| {
+            let field = match attribute {
+                Some(attribute) => attribute.split('.').try_fold(item, |v, key| v.get(key)),
+                None => Some(item),
+            };
+            let text = match field {
+                Some(Value::String(s)) => s.clone(),
+                Some(Value::Null) | None => String::new(),
+                Some(other) => other.to_string(),
+            };
+            SortKey::new(locale, &text)
+        });
+        Ok(Value::Array(items))
+    });
+}
+
+/// Year, month and day of a "YYYY-MM-DD" date (a time after it is ignored)
+fn parse_date(text: &str) -> Option<(i32, u32, u32)> {
+    let date = text.trim().get(..10)?;
+    let mut parts = date.splitn(3, '-');
+    let year = parts.next().filter(|y| y.len() == 4)?.parse().ok()?;
+    let month = parts.next().filter(|m| m.len() == 2)?.parse().ok()?;
+    let day = parts.next().filter(|d| d.len() == 2)?.parse().ok()?;
+    ((1..=12).contains(&month) && (1..=31).contains(&day)).then_some((year, month, day))
+}
+
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December",
+];
+
+fn format_date(locale: Locale, (year, month, day): (i32, u32, u32), short: bool) -> String {
+    match (locale, short) {
+        (Locale::English, false) => format!("{} {}, {}", MONTHS[month as usize - 1], day, year),
+        (Locale::English, true) => format!("{:02}/{:02}/{}", month, day, year),
+        (Locale::Japanese, false) => format!("{}年{}月{}日", year, month, day),
+        (Locale::Japanese, true) => format!("{}/{:02}/{:02}", year, month, day),
+        (Locale::Vietnamese, false) => format!("{} tháng {} năm {}", day, month, year),
+        (Locale::Vietnamese, true) => format!("{:02}/{:02}/{}", day, month, year),
+    }
+}
+
+/// Number with grouped thousands; `decimals` rounds it, otherwise its own
+/// decimals are kept
+fn format_number(locale: Locale, number: f64, decimals: Option<usize>) -> String {
+    let (group, point) = match locale {
+        Locale::Vietnamese => ('.', ','),
+        Locale::English | Locale::Japanese => (',', '.'),
+    };
+    let text = match decimals {
+        Some(decimals) => format!("{:.*}", decimals, number.abs()),
+        None => number.abs().to_string(),
+    };
+    let (integer, fraction) = text.split_once('.').unwrap_or((&text, ""));
+
+    let mut result = String::new();
+    if number < 0.0 && text.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        result.push('-');
+    }
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            result.push(group);
+        }
+        result.push(digit);
+    }
+    if !fraction.is_empty() {
+        result.push(point);
+        result.push_str(fraction);
+    }
+    result
+}
+
+/// Kana variants: (variant, base kana, weight after the base)
+const KANA_VARIANTS: &[(&str, &str, u32)] = &[
+    ("ぁぃぅぇぉっゃゅょゎゕゖ", "あいうえおつやゆよわかけ", 1),
+    ("がぎぐげござじずぜぞだぢづでどばびぶべぼゔ", "かきくけこさしすせそたちつてとはひふへほう", 2),
+    ("ぱぴぷぺぽ", "はひふへほ", 3),
+];
+
+/// Vietnamese letters in alphabetical order (with the Latin letters it doesn't use)
+const VIETNAMESE_ALPHABET: &str = "aăâbcdđeêfghijklmnoôơpqrstuưvwxyz";
+
+/// Vietnamese vowels with each tone, in dictionary order: none, grave (huyền),
+/// hook (hỏi), tilde (ngã), acute (sắc), dot below (nặng)
+const VIETNAMESE_TONES: &[&str] = &[
+    "aàảãáạ", "ăằẳẵắặ", "âầẩẫấậ", "eèẻẽéẹ", "êềểễếệ", "iìỉĩíị", "oòỏõóọ", "ôồổỗốộ", "ơờởỡớợ", "uùủũúụ", "ưừửữứự", "yỳỷỹýỵ",
+];
+
+/// Collation key: letters are compared first, then their variants (kana
+/// size and voicing, Vietnamese tones), then case and anything else
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct SortKey {
+    primary: Vec<u32>,
+    secondary: Vec<u32>,
+    text: String,
+}
+
+impl SortKey {
+    fn new(locale: Locale, text: &str) -> Self {
+        let mut primary = Vec::new();
+        let mut secondary = Vec::new();
+        for c in text.chars().flat_map(char::to_lowercase) {
+            let (weight, variant) = match locale {
+                Locale::Japanese => japanese_weight(c),
+                Locale::Vietnamese => vietnamese_weight(c),
+                Locale::English => (c as u32, 0),
+            };
+            primary.push(weight);
+            secondary.push(variant);
+        }
+        SortKey { primary, secondary, text: text.to_string() }
+    }
+}
+
+/// Katakana sort as hiragana, full-width ASCII as ASCII
+fn japanese_weight(c: char) -> (u32, u32) {
+    let c = match c as u32 {
+        code @ 0x30A1..=0x30F6 => char::from_u32(code - 0x60).unwrap_or(c),
+        code @ 0xFF01..=0xFF5E => char::from_u32(code - 0xFEE0).unwrap_or(c).to_ascii_lowercase(),
+        _ => c,
+    };
+    for (variants, bases, weight) in KANA_VARIANTS {
+        if let Some(i) = variants.chars().position(|v| v == c) {
+            let base = bases.chars().nth(i).unwrap_or(c);
+            return (base as u32, *weight);
+        }
+    }
+    (c as u32, 0)
+}
+
+/// Letters in Vietnamese alphabetical order, before other characters
+/// (which keep their code point order after the alphabet)
+fn vietnamese_weight(c: char) -> (u32, u32) {
+    let (letter, tone) = VIETNAMESE_TONES
+        .iter()
+        .find_map(|tones| tones.chars().position(|t| t == c).map(|tone| (tones.chars().next().unwrap_or(c), tone as u32)))
+        .unwrap_or((c, 0));
+    match VIETNAMESE_ALPHABET.chars().position(|l| l == letter) {
+        Some(i) => ('a' as u32 + i as u32, tone),
+        None if c < 'a' => (c as u32, 0),
+        None => (c as u32 + VIETNAMESE_ALPHABET.chars().count() as u32, 0),
+    }
+}
+
+/// Compare two strings in a language's collation order
+pub fn compare(language: &str, a: &str, b: &str) -> Ordering {
+    let locale = Locale::from_language(language);
+    SortKey::new(locale, a).cmp(&SortKey::new(locale, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tera::Context;
+
+    fn render(language: &str, template: &str) -> String {
+        let mut tera = Tera::default();
+        register_filters(&mut tera, language);
+        tera.add_raw_template("t", template).unwrap();
+        let mut context = Context::new();
+        context.insert("date", "2026-10-16");
+        context.insert("pages", &serde_json::json!([{"title": "beta"}, {"title": "Alpha"}, {"title": null}]));
+        tera.render("t", &context).unwrap()
+    }
+
+    #[test]
+    fn test_dates_and_numbers() {
+        assert_eq!(render("en", "{{ date | localdate }} {{ date | localdate(style=\"short\") }}"), "October 16, 2026 10/16/2026");
+        assert_eq!(render("ja", "{{ date | localdate }} {{ date | localdate(style=\"short\") }}"), "2026年10月16日 2026/10/16");
+        assert_eq!(render("vn", "{{ date | localdate }}"), "16 tháng 10 năm 2026");
+        assert_eq!(render("en", "{{ 1234567.5 | localnumber }} {{ -1234 | localnumber(decimals=2) }}"), "1,234,567.5 -1,234.00");
+        assert_eq!(render("vi", "{{ 1234567.5 | localnumber }} {{ \"999\" | localnumber }}"), "1.234.567,5 999");
+    }
+
+    #[test]
+    fn test_localsort() {
+        assert_eq!(render("ja", "{{ [\"は\", \"カ\", \"ぱ\", \"か\", \"が\", \"ば\"] | localsort | join(sep=\" \") }}"), "か カ が は ば ぱ");
+        assert_eq!(render("vi", "{{ [\"đá\", \"ăn\", \"da\", \"ba\", \"an\", \"án\", \"àn\"] | localsort | join(sep=\" \") }}"), "an àn án ăn ba da đá");
+        assert_eq!(
+            render("en", "{% for p in pages | localsort(attribute=\"title\") %}{{ p.title | default(value=\"-\") }} {% endfor %}"),
+            "- Alpha beta "
+        );
+        assert_eq!(compare("vi", "đ", "e"), Ordering::Less);
+    }
+}
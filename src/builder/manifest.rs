@@ -0,0 +1,214 @@
+//! Build manifest for incremental deploys
+//!
+//! Writes `manifest.json` to the output root, mapping every output file to
+//! its SHA-256 content hash, size and the source file it was produced from.
+//! Files offered as `{% file %}` downloads also list the pages offering them.
+//! Deploy tooling can diff two manifests to upload only changed files, or
+//! verify a deploy against the build that produced it.
+//!
+//! `diff_manifests` compares a previously deployed manifest with a new one;
+//! `guidebook manifest diff` prints the result for deploy scripts.
+
+use super::attachments::attachment_paths;
+use super::renderer::resolve_book_path;
+use crate::paths;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// File name of the manifest in the output root
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Manifest {
+    files: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ManifestEntry {
+    hash: String,
+    size: u64,
+    source: Option<String>,
+    /// Pages offering the file for download with `{% file %}`
+    #[serde(default, rename = "attachedTo", skip_serializing_if = "Vec::is_empty")]
+    attached_to: Vec<String>,
+}
+
+/// Write `manifest.json` for everything in `output`
+///
+/// `known_sources` maps generated files (pages, styles) to their sources;
+/// other files are matched to the source file they were copied or linked
+/// from. Files with no source (embedded assets, search index) get `null`.
+/// Returns the number of files listed.
+pub fn write_manifest(source: &Path, output: &Path, known_sources: &BTreeMap<String, String>) -> Result<usize> {
+    let mut files = BTreeMap::new();
+    // Attachment -> pages with a download card for it
+    let mut attachments: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for entry in walkdir::WalkDir::new(output).follow_links(true) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = paths::to_slash(entry.path().strip_prefix(output)?);
+        if relative == MANIFEST_FILE {
+            continue;
+        }
+
+        let content = fs::read(entry.path())?;
+        if relative.ends_with(".html") {
+            for path in attachment_paths(&String::from_utf8_lossy(&content)) {
+                if let Some(target) = resolve_book_path(&relative, &path) {
+                    attachments.entry(target).or_default().push(relative.clone());
+                }
+            }
+        }
+        let source_file = known_sources
+            .get(&relative)
+            .cloned()
+            .or_else(|| linked_source(source, entry.path()))
+            .or_else(|| source.join(&relative).is_file().then(|| relative.clone()));
+
+        files.insert(relative, ManifestEntry {
+            hash: format!("sha256:{:x}", Sha256::digest(&content)),
+            size: content.len() as u64,
+            source: source_file,
+            attached_to: Vec::new(),
+        });
+    }
+    for (path, pages) in attachments {
+        if let Some(entry) = files.get_mut(&path) {
+            entry.attached_to = pages;
+        }
+    }
+
+    let count = files.len();
+    Manifest { files }.save(&output.join(MANIFEST_FILE))?;
+    Ok(count)
+}
+
+impl Manifest {
+    /// Load a manifest.json written by a previous build
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        Self::parse(&content).with_context(|| format!("Invalid manifest {:?}", path))
+    }
+
+    /// Parse the JSON of a manifest.json
+    pub fn parse(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Write the manifest as manifest.json does
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Copy the entries of `paths` from `other` (files still deployed that
+    /// the build no longer has)
+    pub fn keep(&mut self, other: &Manifest, paths: &[String]) {
+        for path in paths {
+            if let Some(entry) = other.files.get(path) {
+                self.files.insert(path.clone(), entry.clone());
+            }
+        }
+    }
+}
+
+/// Changes needed to turn a deployed output into a new one
+#[derive(Debug, Default, PartialEq)]
+pub struct ManifestDiff {
+    /// New or changed files
+    pub upload: Vec<String>,
+    /// Files no longer produced by the build
+    pub delete: Vec<String>,
+    /// Files with identical content
+    pub unchanged: usize,
+}
+
+/// Compare the manifest of the deployed output with the new build's manifest
+pub fn diff_manifests(previous: &Manifest, current: &Manifest) -> ManifestDiff {
+    let mut diff = ManifestDiff::default();
+
+    for (path, entry) in &current.files {
+        match previous.files.get(path) {
+            Some(old) if old.hash == entry.hash => diff.unchanged += 1,
+            _ => diff.upload.push(path.clone()),
+        }
+    }
+    diff.delete = previous
+        .files
+        .keys()
+        .filter(|path| !current.files.contains_key(*path))
+        .cloned()
+        .collect();
+
+    diff
+}
+
+/// Source path of an asset symlinked into the output
+fn linked_source(source: &Path, path: &Path) -> Option<String> {
+    let target = fs::read_link(path).ok()?;
+    paths::relative(source, &target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        let output = dir.path().join("out");
+        fs::create_dir_all(source.join("images")).unwrap();
+        fs::create_dir_all(output.join("images")).unwrap();
+        fs::create_dir_all(output.join("gitbook")).unwrap();
+        fs::write(source.join("images/logo.png"), "png").unwrap();
+        fs::write(output.join("images/logo.png"), "png").unwrap();
+        fs::write(output.join("intro.html"), "hello").unwrap();
+        fs::write(output.join("gitbook/gitbook.js"), "js").unwrap();
+
+        let mut known = BTreeMap::new();
+        known.insert("intro.html".to_string(), "intro.md".to_string());
+        assert_eq!(write_manifest(&source, &output, &known).unwrap(), 3);
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output.join(MANIFEST_FILE)).unwrap()).unwrap();
+        let files = &manifest["files"];
+        assert_eq!(files["intro.html"]["source"], "intro.md");
+        assert_eq!(files["intro.html"]["size"], 5);
+        assert_eq!(
+            files["intro.html"]["hash"],
+            "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(files["images/logo.png"]["source"], "images/logo.png");
+        assert!(files["gitbook/gitbook.js"]["source"].is_null());
+    }
+
+    fn manifest(files: &[(&str, &str)]) -> Manifest {
+        Manifest {
+            files: files
+                .iter()
+                .map(|(path, hash)| {
+                    (path.to_string(), ManifestEntry { hash: hash.to_string(), size: 0, source: None, attached_to: Vec::new() })
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_diff_manifests() {
+        let previous = manifest(&[("index.html", "a"), ("old.html", "b"), ("gitbook/gitbook.js", "c")]);
+        let current = manifest(&[("index.html", "a2"), ("new.html", "d"), ("gitbook/gitbook.js", "c")]);
+
+        let diff = diff_manifests(&previous, &current);
+        assert_eq!(diff.upload, vec!["index.html", "new.html"]);
+        assert_eq!(diff.delete, vec!["old.html"]);
+        assert_eq!(diff.unchanged, 1);
+    }
+}
@@ -0,0 +1,278 @@
+//! Markdown export
+//!
+//! `guidebook export md` writes every chapter of SUMMARY.md, in order, to one
+//! Markdown file for external converters (pandoc, ...) and review tools.
+//! Chapters are taken after @imports and templates are resolved, without
+//! their front matter, each preceded by an `<a id="chN"></a>` anchor.
+//!
+//! Links between chapters point at those anchors (or at the linked heading);
+//! links and images to other files of the book are rewritten relative to the
+//! book root, where the file is meant to be written.
+//!
+//! `guidebook export pages` freezes the chapters instead: each source file is
+//! written, resolved the same way, at its own path in a directory with
+//! SUMMARY.md, so links keep working as written. Images and other book files
+//! the chapters link to are copied along.
+
+use super::export::{Chapter, ExportBook};
+use super::is_asciidoc_file;
+use super::renderer::resolve_book_path;
+use crate::parser::default_html_path;
+use crate::{info, paths, warning};
+use anyhow::Result;
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Export a book as one Markdown file
+pub fn export_markdown(source: &Path, output: &Path) -> Result<()> {
+    info!("Loading book configuration...");
+    let book = ExportBook::load(source)?;
+    let chapters = book.chapters();
+
+    info!("Concatenating {} chapters...", chapters.len());
+    let mut sections = Vec::new();
+    for chapter in &chapters {
+        if is_asciidoc_file(&book.source.join(&chapter.source_path)) {
+            warning!("{}: AsciiDoc chapters aren't exported to Markdown", chapter.source_path);
+            continue;
+        }
+        let mut section = format!("<a id=\"{}\"></a>\n\n", chapter.id);
+        for (file, markdown) in book.chapter_markdown(chapter)? {
+            section.push_str(rewrite_links(&markdown, &file, &chapters).trim());
+            section.push_str("\n\n");
+        }
+        sections.push(section.trim_end().to_string());
+    }
+
+    fs::write(output, sections.join("\n\n") + "\n")?;
+    info!();
+    info!(">> Markdown written to {:?}", output);
+    Ok(())
+}
+
+/// Export a book as a directory of resolved Markdown files in its layout
+pub fn export_markdown_pages(source: &Path, output: &Path) -> Result<()> {
+    info!("Loading book configuration...");
+    let book = ExportBook::load(source)?;
+    let chapters = book.chapters();
+
+    info!("Resolving {} chapters...", chapters.len());
+    let mut written = HashSet::new();
+    let mut linked = BTreeSet::new();
+    for chapter in &chapters {
+        if is_asciidoc_file(&book.source.join(&chapter.source_path)) {
+            warning!("{}: AsciiDoc chapters aren't exported to Markdown", chapter.source_path);
+            continue;
+        }
+        for (file, markdown) in book.chapter_markdown(chapter)? {
+            linked.extend(linked_files(&markdown, &file));
+            let dest = output.join(&file);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(dest, format!("{}\n", markdown.trim()))?;
+            written.insert(file);
+        }
+    }
+
+    let summary = book.source.join("SUMMARY.md");
+    if summary.is_file() {
+        fs::copy(&summary, output.join("SUMMARY.md"))?;
+    }
+    let mut copied = 0;
+    for file in linked.iter().filter(|file| !written.contains(*file)) {
+        let src = book.source.join(file);
+        // Other sources would still hold their template syntax
+        if !src.is_file() || file.ends_with(".md") || is_asciidoc_file(&src) {
+            continue;
+        }
+        let dest = output.join(file);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&src, dest)?;
+        copied += 1;
+    }
+
+    info!();
+    info!(">> Markdown written to {:?} ({} files, {} linked files)", output, written.len(), copied);
+    Ok(())
+}
+
+/// Book-relative paths of the files a chapter file's Markdown links to or embeds
+fn linked_files(markdown: &str, file: &str) -> Vec<String> {
+    Parser::new_ext(markdown, Options::ENABLE_TABLES)
+        .filter_map(|event| match event {
+            Event::Start(Tag::Link { dest_url, .. }) => book_target(&dest_url, file, false),
+            Event::Start(Tag::Image { dest_url, .. }) => book_target(&dest_url, file, true),
+            _ => None,
+        })
+        .map(|target| paths::split_fragment(&target).0.to_string())
+        .collect()
+}
+
+/// Rewrite the link and image destinations of a chapter file's Markdown for
+/// the single file: chapters become anchors, other book files book-relative
+/// file: book-relative source path the Markdown was written in
+fn rewrite_links(markdown: &str, file: &str, chapters: &[Chapter]) -> String {
+    // Chapters by source path and published path
+    let anchors: HashMap<String, &str> = chapters
+        .iter()
+        .flat_map(|c| [(c.source_path.clone(), c.id.as_str()), (default_html_path(&c.source_path), c.id.as_str())])
+        .collect();
+
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+
+    // (start, end, replacement) of destinations as written
+    let mut edits = Vec::new();
+    for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+        let (url, is_image) = match &event {
+            Event::Start(Tag::Link { dest_url, .. }) => (dest_url, false),
+            Event::Start(Tag::Image { dest_url, .. }) => (dest_url, true),
+            _ => continue,
+        };
+        let Some((start, end)) = written_destination(markdown, range.start, range.end) else { continue };
+        let Some(target) = book_target(url, file, is_image) else { continue };
+        let (path, fragment) = paths::split_fragment(&target);
+        let replacement = match anchors.get(path) {
+            Some(_) if fragment.len() > 1 => fragment.to_string(),
+            Some(id) if !is_image => format!("#{}", id),
+            _ => format!("{}{}", paths::link_destination(path), fragment),
+        };
+        edits.push((start, end, replacement));
+    }
+
+    edits.sort_by_key(|(start, _, _)| *start);
+    let mut result = String::with_capacity(markdown.len());
+    let mut last = 0;
+    for (start, end, replacement) in edits {
+        if start < last {
+            continue;
+        }
+        result.push_str(&markdown[last..start]);
+        result.push_str(&replacement);
+        last = end;
+    }
+    result.push_str(&markdown[last..]);
+    result
+}
+
+/// Byte range of the destination of the inline link or image at
+/// `markdown[start..end]`; None for reference links and autolinks
+fn written_destination(markdown: &str, start: usize, end: usize) -> Option<(usize, usize)> {
+    let text = &markdown[start..end];
+    if !text.ends_with(')') {
+        return None;
+    }
+    let open = text.rfind("](")? + 2;
+    let rest = &text[open..text.len() - 1];
+    let leading = rest.len() - rest.trim_start().len();
+    let rest = rest.trim_start();
+    let len = if rest.starts_with('<') {
+        rest.find('>')? + 1
+    } else {
+        rest.find(char::is_whitespace).unwrap_or(rest.len())
+    };
+    let dest_start = start + open + leading;
+    Some((dest_start, dest_start + len))
+}
+
+/// Book-relative path (with `#fragment`) a link or image URL of `file` points
+/// at; None for external URLs, same-page anchors and paths outside the book
+/// Links are relative to the book root unless they start with `./` or `../`,
+/// images are relative to the page
+pub(super) fn book_target(url: &str, file: &str, is_image: bool) -> Option<String> {
+    if url.is_empty() || url.starts_with('#') || url.starts_with("//") || url.contains(':') {
+        return None;
+    }
+    let (path, fragment) = paths::split_fragment(url);
+    let path = paths::decode(path);
+    let page_relative = is_image || path.starts_with("./") || path.starts_with("../");
+    let resolved = match path.strip_prefix('/') {
+        Some(absolute) => resolve_book_path("", absolute),
+        None if page_relative => resolve_book_path(file, &path),
+        None => resolve_book_path("", &path),
+    }?;
+    Some(format!("{}{}", resolved, fragment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(id: &str, source_path: &str) -> Chapter {
+        Chapter { id: id.to_string(), title: String::new(), source_path: source_path.to_string() }
+    }
+
+    #[test]
+    fn test_rewrite_links() {
+        let chapters = vec![chapter("ch0", "README.md"), chapter("ch1", "guide/start.md"), chapter("ch2", "guide/api.md")];
+        let markdown = concat!(
+            "See [API](guide/api.md), [auth](../guide/api.md#auth \"Auth\") and [home](/README.html).\n\n",
+            "![Shot](img/shot%20one.png) [site](https://example.com/a.md) [top](#top) [ref][r]\n\n",
+            "[r]: guide/api.md\n\n",
+            "`[code](guide/api.md)`\n"
+        );
+        assert_eq!(
+            rewrite_links(markdown, "guide/start.md", &chapters),
+            concat!(
+                "See [API](#ch2), [auth](#auth \"Auth\") and [home](#ch0).\n\n",
+                "![Shot](guide/img/shot%20one.png) [site](https://example.com/a.md) [top](#top) [ref][r]\n\n",
+                "[r]: guide/api.md\n\n",
+                "`[code](guide/api.md)`\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_export_markdown_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        fs::create_dir_all(source.join("guide/img")).unwrap();
+        fs::write(source.join("book.json"), r#"{"variables": {"product": "Acme"}}"#).unwrap();
+        fs::write(source.join("README.md"), "# Intro\n\n{% if book.product %}Welcome to {{ book.product }}.{% endif %}\n").unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Start](guide/start.md)\n").unwrap();
+        fs::write(source.join("guide/start.md"), "---\ntitle: Start\n---\n# Start\n\n<!-- @import(\"part.md\") -->\n\nBack to [intro](README.md).\n").unwrap();
+        fs::write(source.join("guide/part.md"), "Imported ![logo](img/logo.png)").unwrap();
+        fs::write(source.join("guide/img/logo.png"), "png").unwrap();
+        fs::write(source.join("guide/img/unused.png"), "png").unwrap();
+
+        let output = dir.path().join("frozen");
+        export_markdown_pages(&source, &output).unwrap();
+        assert_eq!(fs::read_to_string(output.join("README.md")).unwrap(), "# Intro\n\nWelcome to Acme.\n");
+        assert_eq!(
+            fs::read_to_string(output.join("guide/start.md")).unwrap(),
+            "# Start\n\nImported ![logo](img/logo.png)\n\nBack to [intro](README.md).\n"
+        );
+        assert!(output.join("SUMMARY.md").is_file() && output.join("guide/img/logo.png").is_file());
+        assert!(!output.join("guide/img/unused.png").exists() && !output.join("guide/part.md").exists());
+    }
+
+    #[test]
+    fn test_export_markdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path();
+        fs::create_dir_all(source.join("guide")).unwrap();
+        fs::write(source.join("book.json"), r#"{"variables": {"product": "Acme"}}"#).unwrap();
+        fs::write(source.join("README.md"), "# Intro\n\nWelcome to {{ book.product }}.\n").unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Start](guide/start.md)\n").unwrap();
+        fs::write(source.join("guide/start.md"), "---\ntitle: Start\n---\n# Start\n\n<!-- @import(\"part.md\") -->\n\nBack to [intro](README.md).\n").unwrap();
+        fs::write(source.join("guide/part.md"), "Imported ![logo](logo.png)").unwrap();
+
+        let output = source.join("book.md");
+        export_markdown(source, &output).unwrap();
+        assert_eq!(
+            fs::read_to_string(&output).unwrap(),
+            concat!(
+                "<a id=\"ch0\"></a>\n\n# Intro\n\nWelcome to Acme.\n\n",
+                "<a id=\"ch1\"></a>\n\n# Start\n\nImported ![logo](guide/logo.png)\n\nBack to [intro](#ch0).\n"
+            )
+        );
+    }
+}
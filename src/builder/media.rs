@@ -0,0 +1,189 @@
+//! Video and audio players
+//!
+//! Image syntax pointing at a local video or audio file renders a player
+//! instead of a broken image:
+//!
+//! ```markdown
+//! ![Installing the app](media/install.mp4)
+//! ![Episode 1](episode-1.mp3)
+//! ```
+//!
+//! An image next to a video with the same name (`media/install.jpg`) is
+//! shown as its poster. Players keep their sources relative to the page; as
+//! they may sit outside the asset directories, the files a page plays (and
+//! their posters) are copied to the output when the page is written.
+
+use super::renderer::{html_escape, resolve_book_path};
+use crate::paths;
+use anyhow::Result;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "webm"];
+const AUDIO_EXTENSIONS: &[&str] = &["mp3"];
+const POSTER_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MediaKind {
+    Video,
+    Audio,
+}
+
+/// Kind of player for a local media URL, None for other URLs
+pub fn media_kind(url: &str) -> Option<MediaKind> {
+    if url.contains(':') || url.starts_with("//") {
+        return None;
+    }
+    let extension = extension(url)?;
+    if VIDEO_EXTENSIONS.contains(&extension.as_str()) {
+        Some(MediaKind::Video)
+    } else if AUDIO_EXTENSIONS.contains(&extension.as_str()) {
+        Some(MediaKind::Audio)
+    } else {
+        None
+    }
+}
+
+/// Lowercase extension of a URL's path (without query or fragment)
+fn extension(url: &str) -> Option<String> {
+    let path = &url[..url.find(['?', '#']).unwrap_or(url.len())];
+    let name = path.rsplit('/').next()?;
+    name.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase())
+}
+
+/// Player HTML for image syntax: `![alt](url)`
+/// alt is shown by browsers without support for the player
+pub fn player_html(kind: MediaKind, url: &str, alt: &str) -> String {
+    let tag = match kind {
+        MediaKind::Video => "video",
+        MediaKind::Audio => "audio",
+    };
+    let src = html_escape(&paths::encode_href(&paths::decode(url)));
+    let label = if alt.is_empty() { String::new() } else { format!(" aria-label=\"{}\"", html_escape(alt)) };
+    format!("<{tag} controls preload=\"metadata\" src=\"{src}\"{label}>{}</{tag}>", html_escape(alt))
+}
+
+/// Copy the local files played on a page from the book source to the output
+/// (unless the output already has a copy as recent), and give videos the
+/// poster image found next to them
+/// html_path: output path of the page, sources are relative to it
+pub fn publish_media(html: String, source: &Path, output: &Path, html_path: &str) -> Result<String> {
+    if !html.contains("<video") && !html.contains("<audio") {
+        return Ok(html);
+    }
+    let tag_re = Regex::new(r"<(video|audio|source)\b[^>]*>").unwrap();
+    let src_re = Regex::new(r#"\b(src|poster)="([^"]*)""#).unwrap();
+
+    let mut result = String::with_capacity(html.len());
+    let mut last = 0;
+    for tag in tag_re.captures_iter(&html) {
+        let whole = tag.get(0).unwrap();
+        let mut has_poster = false;
+        let mut poster = None;
+        for attr in src_re.captures_iter(whole.as_str()) {
+            has_poster |= &attr[1] == "poster";
+            let url = &attr[2];
+            if url.contains(':') || url.starts_with('/') || url.starts_with('#') {
+                continue;
+            }
+            let Some(path) = resolve_book_path(html_path, &paths::decode(paths::split_fragment(&url.replace("&amp;", "&")).0)) else {
+                continue;
+            };
+            // Missing files are reported by `guidebook check`
+            if !copy_file(source, output, &path)? {
+                continue;
+            }
+            if &tag[1] == "video" && &attr[1] == "src" {
+                poster = find_poster(source, &path)
+                    .map(|poster| (url.rsplit_once('/').map(|(dir, _)| format!("{}/", dir)).unwrap_or_default(), poster));
+            }
+        }
+
+        result.push_str(&html[last..whole.end()]);
+        if let (false, Some((dir, poster))) = (has_poster, poster) {
+            let name = Path::new(&poster).file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            copy_file(source, output, &poster)?;
+            result.truncate(result.len() - 1);
+            result.push_str(&format!(" poster=\"{}{}\">", dir, html_escape(&paths::encode_href(name))));
+        }
+        last = whole.end();
+    }
+    result.push_str(&html[last..]);
+    Ok(result)
+}
+
+/// Book path of the image named like a video, next to it
+fn find_poster(source: &Path, video: &str) -> Option<String> {
+    let stem = video.rsplit_once('.').map(|(stem, _)| stem)?;
+    POSTER_EXTENSIONS.iter().map(|ext| format!("{}.{}", stem, ext)).find(|poster| source.join(poster).is_file())
+}
+
+/// Copy a book file to the same path in the output unless the copy there is
+/// as recent; false if the book has no such file
+pub(super) fn copy_file(source: &Path, output: &Path, path: &str) -> Result<bool> {
+    let from = source.join(path);
+    if !from.is_file() {
+        return Ok(false);
+    }
+    let to = output.join(path);
+    let up_to_date = match (fs::metadata(&to).and_then(|m| m.modified()), fs::metadata(&from).and_then(|m| m.modified())) {
+        (Ok(copy), Ok(original)) => copy >= original,
+        _ => false,
+    };
+    if !up_to_date {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&from, &to)?;
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::renderer::render_markdown;
+
+    #[test]
+    fn test_media_players() {
+        let html = render_markdown("![Install *it*](media/install app.mp4)\n\n![Episode](ep1.MP3)\n\n![Logo](logo.png)\n");
+        assert!(
+            html.contains(r#"<video controls preload="metadata" src="media/install%20app.mp4" aria-label="Install it">Install it</video>"#),
+            "{}",
+            html
+        );
+        assert!(html.contains(r#"<audio controls preload="metadata" src="ep1.MP3" aria-label="Episode">Episode</audio>"#), "{}", html);
+        assert!(html.contains(r#"<img src="logo.png" alt="Logo" />"#), "{}", html);
+
+        assert_eq!(media_kind("clip.webm?t=10"), Some(MediaKind::Video));
+        assert_eq!(media_kind("https://example.com/clip.mp4"), None);
+        assert_eq!(media_kind("notes.md"), None);
+    }
+
+    #[test]
+    fn test_publish_media_copies_files_and_finds_posters() {
+        let dir = tempfile::tempdir().unwrap();
+        let (source, output) = (dir.path().join("book"), dir.path().join("out"));
+        fs::create_dir_all(source.join("guide/media")).unwrap();
+        fs::write(source.join("guide/media/a b.mp4"), "video").unwrap();
+        fs::write(source.join("guide/media/a b.png"), "poster").unwrap();
+        fs::write(source.join("guide/talk.mp3"), "audio").unwrap();
+
+        let html = concat!(
+            r#"<video controls src="media/a%20b.mp4"></video>"#,
+            r#"<audio controls src="talk.mp3"></audio><video src="missing.webm"></video>"#
+        );
+        let result = publish_media(html.to_string(), &source, &output, "guide/start.html").unwrap();
+        assert_eq!(
+            result,
+            concat!(
+                r#"<video controls src="media/a%20b.mp4" poster="media/a%20b.png"></video>"#,
+                r#"<audio controls src="talk.mp3"></audio><video src="missing.webm"></video>"#
+            )
+        );
+        assert_eq!(fs::read_to_string(output.join("guide/media/a b.mp4")).unwrap(), "video");
+        assert!(output.join("guide/media/a b.png").is_file());
+        assert!(output.join("guide/talk.mp3").is_file());
+    }
+}
@@ -0,0 +1,115 @@
+//! Minified output (`"minify": true` in book.json)
+//!
+//! Pages are minified as they are written, after every other rewrite, with
+//! the inline CSS and JavaScript they contain; gitbook.css, gitbook.js and
+//! the other embedded assets are minified as they are written. Files copied
+//! from the book are left as they are.
+//!
+//! Closing tags, quotes that attributes need and the doctype are kept, and
+//! whitespace only where it is significant: `<pre>` and `<textarea>`, and
+//! Mermaid diagrams, whose source is line-based.
+
+use crate::parser::BookConfig;
+use crate::verbose;
+use lightningcss::printer::PrinterOptions;
+use lightningcss::stylesheet::{ParserOptions, StyleSheet};
+use regex::Regex;
+
+/// A page as it is written: minified when the book sets `minify`
+pub fn page(html: String, config: &BookConfig) -> String {
+    if config.minify { minify_html(&html) } else { html }
+}
+
+/// An embedded asset as it is written: minified by its extension when the
+/// book sets `minify`
+pub fn asset(name: &str, content: &str, config: &BookConfig) -> String {
+    match name.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("css") if config.minify => minify_css(content),
+        Some("js") if config.minify => minify_js(content),
+        _ => content.to_string(),
+    }
+}
+
+/// Minified page HTML
+pub fn minify_html(html: &str) -> String {
+    // Diagram sources are set aside, the minifier would join their lines
+    let mermaid_re = Regex::new(r#"(?s)(<div class="mermaid">)(.*?)(</div>)"#).unwrap();
+    let mut diagrams = Vec::new();
+    let html = mermaid_re.replace_all(html, |caps: &regex::Captures| {
+        diagrams.push(caps[2].to_string());
+        format!("{}guidebook-mermaid-{}{}", &caps[1], diagrams.len() - 1, &caps[3])
+    });
+
+    let mut cfg = minify_html::Cfg::spec_compliant();
+    cfg.keep_closing_tags = true;
+    cfg.keep_html_and_head_opening_tags = true;
+    cfg.minify_css = true;
+    cfg.minify_js = true;
+    let mut minified = String::from_utf8_lossy(&minify_html::minify(html.as_bytes(), &cfg)).into_owned();
+
+    for (i, diagram) in diagrams.iter().enumerate().rev() {
+        minified = minified.replacen(&format!("guidebook-mermaid-{}<", i), &format!("{}<", diagram), 1);
+    }
+    minified
+}
+
+/// Minified stylesheet; the stylesheet itself if it doesn't parse
+pub fn minify_css(css: &str) -> String {
+    let minified = StyleSheet::parse(css, ParserOptions::default())
+        .map_err(|e| e.to_string())
+        .and_then(|sheet| sheet.to_css(PrinterOptions { minify: true, ..Default::default() }).map_err(|e| e.to_string()));
+    match minified {
+        Ok(result) => result.code,
+        Err(e) => {
+            verbose!("  Not minifying stylesheet: {}", e);
+            css.to_string()
+        }
+    }
+}
+
+/// Minified script; the script itself if it doesn't parse
+pub fn minify_js(js: &str) -> String {
+    let session = minify_js::Session::new();
+    let mut minified = Vec::new();
+    match minify_js::minify(&session, minify_js::TopLevelMode::Global, js.as_bytes(), &mut minified) {
+        Ok(()) => String::from_utf8_lossy(&minified).into_owned(),
+        Err(e) => {
+            verbose!("  Not minifying script: {:?}", e);
+            js.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minify_html() {
+        let html = concat!(
+            "<!DOCTYPE html>\n<html>\n<head>\n    <title>Page</title>\n    <style>\n  body { color: red; }\n</style>\n</head>\n",
+            "<body class=\"book\">\n    <p>Some   text <!-- note -->\n    here</p>\n",
+            "<pre><code>fn main() {\n    run();\n}</code></pre>\n",
+            "<div class=\"mermaid\">graph TD\n    A --> B\n    B --> C</div>\n",
+            "<div class=\"mermaid\">graph LR\n    X --> Y</div>\n</body>\n</html>\n"
+        );
+        let minified = minify_html(html);
+        assert!(minified.len() < html.len());
+        assert!(minified.contains("<p>Some text") && !minified.contains("note"), "{}", minified);
+        assert!(minified.contains("<style>body{color:red}</style>"), "{}", minified);
+        assert!(minified.contains("<pre><code>fn main() {\n    run();\n}</code></pre>"), "{}", minified);
+        assert!(minified.contains("graph TD\n    A --> B\n    B --> C</div>"), "{}", minified);
+        assert!(minified.contains("graph LR\n    X --> Y</div>"), "{}", minified);
+        assert!(!minified.contains("guidebook-mermaid"));
+    }
+
+    #[test]
+    fn test_minify_assets() {
+        assert_eq!(minify_css(".a {\n    color: #ff0000;\n}\n"), ".a{color:red}");
+        assert_eq!(minify_css("not { css"), "not { css");
+
+        let js = minify_js("(function() {\n    var message = 'hello';\n    console.log(message);\n})();\n");
+        assert!(js.len() < 60 && js.contains("console.log"), "{}", js);
+        assert_eq!(minify_js("function ("), "function (");
+    }
+}
@@ -1,47 +1,176 @@
+mod admonitions;
+mod algolia;
+mod attachments;
+mod budget;
+mod validate;
+mod cache;
+pub mod check;
+pub mod deploy;
+pub mod epub;
+mod export;
+mod fingerprint;
+pub mod fragments;
+mod generate;
+pub mod graph;
+pub mod hooks;
+mod icons;
 mod images;
+mod lastmod;
+mod links;
+mod media;
+mod minify;
+pub mod indexnow;
+pub mod lint;
+mod locale;
+pub mod manifest;
+pub mod markdown;
 mod nunjucks;
+pub mod pdf;
+pub mod pipeline;
+pub mod plan;
+mod precompress;
+mod pwa;
+pub mod publish;
+mod redirects;
 mod renderer;
+pub mod report;
+mod robots;
+pub mod search;
+mod searchserver;
+mod shared;
+mod sitemap;
+mod socialcards;
+pub mod snapshot;
+mod split;
+pub mod stats;
 pub mod svg;
+mod tables;
 mod template;
-
-use crate::parser::{self, apply_glossary, parse_front_matter, BookConfig, Glossary, Language, Summary, SummaryItem};
+mod thumbnails;
+pub mod translate;
+mod wikilinks;
+
+use cache::PageCache;
+use lastmod::LastModified;
+use nunjucks::ContentTemplates;
+use pipeline::Pipeline;
+use search::{SearchEntry, SearchIndex};
+use shared::SharedChapter;
+use socialcards::SocialCards;
+use wikilinks::WikiLinks;
+use crate::parser::{self, default_html_path, parse_front_matter_with_defaults, BookConfig, FrontMatterDefaults, Glossary, Language, PipelineStage, ServePass, Summary, SummaryItem};
+use crate::{info, logging, paths, verbose, warning};
 use anyhow::{Context, Result};
 use regex::Regex;
-use serde::Serialize;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 // nunjucks module is used internally for template processing
 pub use renderer::{
     render_markdown, render_markdown_with_path, render_markdown_with_hardbreaks,
     render_asciidoc, render_asciidoc_with_path,
-    extract_headings, extract_headings_from_asciidoc, TocItem
+    extract_headings, extract_headings_from_asciidoc, rebase_links, rewrite_page_links, TocItem
 };
 pub use template::Templates;
+pub use export::{export_book, ExportTarget};
 
 /// Check if a file is an AsciiDoc file based on its extension
 pub fn is_asciidoc_file(path: &Path) -> bool {
-    match path.extension().and_then(|s| s.to_str()) {
-        Some("adoc") | Some("asciidoc") => true,
-        _ => false,
-    }
+    matches!(path.extension().and_then(|s| s.to_str()), Some("adoc") | Some("asciidoc"))
 }
 
-/// Search index entry
-#[derive(Serialize)]
-struct SearchEntry {
-    title: String,
-    path: String,
-    content: String,
+/// Options controlling a build
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    /// Skip generating search_index.json (hot reload)
+    pub skip_search_index: bool,
+    /// Don't write manifest.json (hot reload)
+    pub skip_manifest: bool,
+    /// Drop files from previous builds (build_atomic only)
+    pub clean: bool,
+    /// Byte-identical output across machines: copy assets instead of
+    /// symlinking them and pin file timestamps to SOURCE_DATE_EPOCH
+    pub reproducible: bool,
+    /// Config file used instead of `book.json` in the source root
+    /// (language directories with their own book.json keep it)
+    pub config_file: Option<PathBuf>,
+    /// Write a JSON build report (pages, assets, warnings, timings) to this
+    /// file, also when the build fails
+    pub report: Option<PathBuf>,
+    /// `book.variables` set with `--define key=value`, overriding book.json
+    /// (in every language of a multi-language book)
+    pub variables: Vec<(String, String)>,
+    /// `baseUrl` set with `--base-url`, overriding book.json (an empty string
+    /// keeps links relative, as for the preview server)
+    pub base_url: Option<String>,
+    /// Render only the chapters matching this path or glob (`guide/*.md`);
+    /// the sidebar still lists every chapter. Other pages are not written,
+    /// so this is meant for builds that keep the previous output (clean: false)
+    pub only: Option<String>,
+    /// Fail the build if it printed any warnings (missing chapters, failed
+    /// @imports, template errors, ...), listing them all
+    pub strict: bool,
+    /// Parse every page written with an HTML5 parser and warn about the
+    /// markup it had to repair (unclosed tags, invalid nesting)
+    pub validate_html: bool,
+    /// Page cache file (see `cache_path`): pages whose inputs are unchanged
+    /// since the build that wrote it are not rendered again, so this is meant
+    /// for builds that keep the previous output
+    pub cache: Option<PathBuf>,
+    /// Don't write .gz/.br copies even when book.json sets `precompress`
+    /// (the preview server)
+    pub skip_precompress: bool,
+    /// Keep the plain names of gitbook/ assets and search_index.json even
+    /// when book.json sets `fingerprint` (the preview server, which loads
+    /// search_index.json itself)
+    pub plain_asset_names: bool,
+    /// Leave out the web app manifest and service worker even when book.json
+    /// sets `pwa` (the preview server, where cached pages would go stale)
+    pub skip_pwa: bool,
+    /// A preview rebuild after a change: skip the passes listed in
+    /// `serve.fastMode` of book.json (by default the search index)
+    pub fast_mode: bool,
+    /// Single-file formats written into the output directory with the pages
+    /// (`build --formats html,pdf,epub`); chapters are rendered once for all of them
+    pub exports: Vec<ExportTarget>,
+    /// Send the search index to the hosted search of book.json (`search.algolia`,
+    /// `search.meilisearch` or `search.typesense`) once the build is in place
+    /// (`build --push-search`)
+    pub push_search: bool,
 }
 
 /// Build statistics
 #[derive(Default)]
 struct BuildStats {
     pages: usize,
+    /// Pages kept from the previous build (`BuildOptions::cache`)
+    reused: usize,
     assets: usize,
+    /// Generated output file -> source file, for the build manifest
+    sources: BTreeMap<String, String>,
+    /// Time spent in each build phase, in the order phases first ran
+    phases: Vec<(&'static str, Duration)>,
+    /// Search indexes to send to the hosted search (`BuildOptions::push_search`)
+    search_pushes: Vec<SearchPush>,
+}
+
+/// Search index entries of a book (or language) for the hosted search
+struct SearchPush {
+    entries: Vec<SearchEntry>,
+    config: BookConfig,
+    language: Option<String>,
+}
+
+impl BuildStats {
+    /// Add time spent in a build phase (summed over the languages of a book)
+    fn add_phase(&mut self, name: &'static str, elapsed: Duration) {
+        match self.phases.iter_mut().find(|(n, _)| *n == name) {
+            Some(phase) => phase.1 += elapsed,
+            None => self.phases.push((name, elapsed)),
+        }
+    }
 }
 
 // Embed static assets at compile time
@@ -53,64 +182,433 @@ const SEARCH_JS: &str = include_str!("../../templates/search.js");
 
 /// Build the book from source directory to output directory
 pub fn build(source: &Path, output: &Path) -> Result<()> {
-    build_with_options(source, output, false)
+    build_with_options(source, output, &BuildOptions::default())
+}
+
+/// Build the book with options (fast_mode for hot reload)
+pub fn build_with_options(source: &Path, output: &Path, options: &BuildOptions) -> Result<()> {
+    let search_pushes = build_and_report(source, output, options)?;
+    push_search(&search_pushes)
 }
 
-/// Build the book with options (skip_search_index for hot reload)
-pub fn build_with_options(source: &Path, output: &Path, skip_search_index: bool) -> Result<()> {
+/// Build and print the closing summary; returns the search indexes to push
+fn build_and_report(source: &Path, output: &Path, options: &BuildOptions) -> Result<Vec<SearchPush>> {
     let start_time = Instant::now();
+    logging::reset_warnings();
+    pipeline::reset_timings();
+
+    let result = build_book(source, output, options).and_then(|stats| {
+        if options.strict {
+            check_strict()?;
+        }
+        Ok(stats)
+    });
+    if let Some(cache) = options.cache.as_ref().filter(|_| result.is_err()) {
+        // Pages may be half-written
+        let _ = fs::remove_file(cache);
+    }
+    if let Some(report_path) = &options.report {
+        let report = report::BuildReport::new(&result, start_time.elapsed());
+        report.write(report_path)?;
+        info!("  Wrote build report to {}", report_path.display());
+    }
+    let stats = result?;
+
+    let elapsed = start_time.elapsed();
+    let elapsed_secs = elapsed.as_secs_f64();
+
+    info!();
+    info!(">> generation finished with success in {:.1}s !", elapsed_secs);
+    info!("   {} pages built, {} asset files copied", stats.pages, stats.assets);
+    if stats.reused > 0 {
+        info!("   {} unchanged pages kept", stats.reused);
+    }
+    match logging::warning_count() {
+        0 => {}
+        1 => info!("   1 warning"),
+        n => info!("   {} warnings", n),
+    }
+    for (stage, elapsed) in pipeline::timings() {
+        verbose!("   {:<15} {:>8.1} ms", stage.name(), elapsed.as_secs_f64() * 1000.0);
+    }
+
+    Ok(stats.search_pushes)
+}
+
+/// Send search indexes to the hosted search of their book.json
+fn push_search(search_pushes: &[SearchPush]) -> Result<()> {
+    for SearchPush { entries, config, language } in search_pushes {
+        let language = language.as_deref();
+        if let Some(algolia_config) = &config.search.algolia {
+            let pushed = algolia::push(entries, config, algolia_config, language)?;
+            info!("  Pushed {} search records to Algolia index {}", pushed, algolia_config.index(language));
+        }
+        if let Some((engine, server)) = searchserver::server(&config.search)? {
+            let pushed = searchserver::push(entries, config, engine, server, language)?;
+            info!("  Pushed {} search documents to {} index {}", pushed, engine.name(), server.index(language));
+        }
+    }
+    Ok(())
+}
+
+/// Fail when the build printed warnings, listing them
+fn check_strict() -> Result<()> {
+    let warnings = logging::warnings();
+    if warnings.is_empty() {
+        return Ok(());
+    }
+    let list: Vec<String> = warnings
+        .iter()
+        .map(|w| match &w.page {
+            Some(page) => format!("{}: {}", page, w.message),
+            None => w.message.clone(),
+        })
+        .collect();
+    anyhow::bail!(
+        "{} warning{} in strict mode:\n  {}",
+        warnings.len(),
+        if warnings.len() == 1 { "" } else { "s" },
+        list.join("\n  ")
+    )
+}
+
+/// Apply `--define` overrides to `book.variables`
+fn define_variables(config: &mut BookConfig, variables: &[(String, String)]) {
+    for (key, value) in variables {
+        config.define_variable(key, value);
+        verbose!("  Variable: {} = {}", key, value);
+    }
+}
+
+/// Leave out the passes `serve.fastMode` skips in preview rebuilds; the
+/// search index is checked by `build_single_book`
+fn skip_fast_mode_passes(config: &mut BookConfig) {
+    for pass in config.serve.fast_mode.clone() {
+        match pass {
+            ServePass::SearchIndex => {}
+            ServePass::RemoteImages => config.fetch_remote_images = false,
+            ServePass::Svg => config.pipeline.disable.push(PipelineStage::Svg),
+            ServePass::Thumbnails => config.pipeline.disable.push(PipelineStage::Thumbnails),
+        }
+    }
+}
+
+/// Build without the closing summary; returns what was built
+fn build_book(source: &Path, output: &Path, options: &BuildOptions) -> Result<BuildStats> {
+    let phase_start = Instant::now();
     let source = source.canonicalize().context("Source directory not found")?;
 
-    println!("Loading book configuration...");
-    let config = BookConfig::load(&source)?;
-    println!("  Title: {}", if config.title.is_empty() { "(untitled)" } else { &config.title });
+    info!("Loading book configuration...");
+    let mut config = match &options.config_file {
+        Some(config_file) => {
+            info!("  Config: {}", config_file.display());
+            BookConfig::load_file(config_file)?
+        }
+        None => BookConfig::load(&source)?,
+    };
+    define_variables(&mut config, &options.variables);
+    if let Some(base_url) = &options.base_url {
+        config.base_url = base_url.clone();
+    }
+    if options.skip_pwa {
+        config.pwa = None;
+    }
+    if options.fast_mode {
+        skip_fast_mode_passes(&mut config);
+    }
+    info!("  Title: {}", if config.title.is_empty() { "(untitled)" } else { &config.title });
 
     // Check for multi-language book
     let languages = parser::langs::parse_langs(&source)?;
+    let config_time = phase_start.elapsed();
 
-    let stats = if languages.is_empty() {
+    let mut stats = if languages.is_empty() {
         // Single language book
-        println!("Building single-language book...");
-        build_single_book(&source, output, &config, skip_search_index)?
+        info!("Building single-language book...");
+        build_single_book(&source, output, &config, None, options)?
     } else {
         // Multi-language book
-        println!("Building multi-language book with {} languages:", languages.len());
+        info!("Building multi-language book with {} languages:", languages.len());
         for lang in &languages {
-            println!("  - {} ({})", lang.title, lang.code);
+            info!("  - {} ({})", lang.title, lang.code);
         }
 
-        build_multi_lang_book(&source, output, &config, &languages, skip_search_index)?
+        build_multi_lang_book(&source, output, &config, &languages, options)?
     };
+    stats.phases.insert(0, ("config", config_time));
+    if let Some(only) = options.only.as_deref().filter(|_| stats.pages + stats.reused == 0) {
+        anyhow::bail!("--only {} matches no chapter", only);
+    }
 
-    let elapsed = start_time.elapsed();
-    let elapsed_secs = elapsed.as_secs_f64();
+    if options.validate_html {
+        let phase_start = Instant::now();
+        let invalid = validate::check_pages(output, &stats.sources);
+        if invalid > 0 {
+            info!("  {} pages with invalid HTML", invalid);
+        }
+        stats.add_phase("validate", phase_start.elapsed());
+    }
 
-    println!();
-    println!(">> generation finished with success in {:.1}s !", elapsed_secs);
-    println!("   {} pages built, {} asset files copied", stats.pages, stats.assets);
+    let phase_start = Instant::now();
+    if indexnow::write_key_file(output, &config.deploy)? {
+        info!("  Wrote IndexNow key file");
+    }
+    if let Some(robots) = &config.robots {
+        robots::write_robots_txt(output, robots)?;
+        info!("  Wrote robots.txt");
+    }
+
+    budget::check_budget(output, &config.budget);
+
+    // Before the manifest, so deploys upload the copies
+    if config.precompress && !options.skip_precompress {
+        let copies = precompress::precompress_output(output)?;
+        info!("  Wrote {} precompressed files (.gz, .br)", copies);
+    }
+
+    if !options.skip_manifest {
+        let files = manifest::write_manifest(&source, output, &stats.sources)?;
+        info!("  Wrote {} with {} files", manifest::MANIFEST_FILE, files);
+    }
+
+    if options.reproducible {
+        let epoch = source_date_epoch();
+        normalize_timestamps(output, epoch)?;
+        info!("  Pinned file timestamps to {} (SOURCE_DATE_EPOCH)", epoch);
+    }
+    stats.add_phase("finish", phase_start.elapsed());
+
+    Ok(stats)
+}
+
+/// Build into a staging directory next to `output` and swap it into place
+/// only when the build succeeds, so a failed build never leaves the served
+/// output half-written
+/// clean: drop files from previous builds that the current build didn't produce.
+/// Hidden entries (.git, .nojekyll, ...) are always carried over.
+pub fn build_atomic(source: &Path, output: &Path, options: &BuildOptions) -> Result<()> {
+    let clean = options.clean;
+    let canonical_source = source.canonicalize().context("Source directory not found")?;
+    if output.is_dir() && canonical_source.starts_with(output.canonicalize()?) {
+        // Swapping would move the book sources themselves
+        if clean {
+            anyhow::bail!(
+                "Refusing to clean output directory {:?}: it contains the book sources (use --no-clean)",
+                output
+            );
+        }
+        return build_with_options(source, output, options);
+    }
+    match &options.cache {
+        // Unchanged pages are kept where they are
+        Some(cache) if cache.exists() && output.is_dir() => return build_with_options(source, output, options),
+        Some(_) => {}
+        // The output no longer matches the pages it recorded
+        None => {
+            let _ = fs::remove_file(cache_path(output));
+        }
+    }
+
+    let staging = sibling_path(output, "staging");
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir_all(&staging)?;
+
+    let search_pushes = match build_and_report(source, &staging, options) {
+        Ok(search_pushes) => search_pushes,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+    };
+
+    if output.is_dir() {
+        let removed = move_leftovers(output, &staging, !clean)?;
+        if clean && removed > 0 {
+            info!("   {} stale entries removed from output", removed);
+        }
+        let previous = sibling_path(output, "previous");
+        if previous.exists() {
+            fs::remove_dir_all(&previous)?;
+        }
+        fs::rename(output, &previous)?;
+        fs::rename(&staging, output)?;
+        fs::remove_dir_all(&previous)?;
+    } else {
+        if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(&staging, output)?;
+    }
+
+    // The hosted search links to the pages now served
+    push_search(&search_pushes)
+}
+
+/// Timestamp for reproducible builds: SOURCE_DATE_EPOCH if set, otherwise 0
+fn source_date_epoch() -> u64 {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(0)
+}
 
+/// Set the modification time of every file in `output` to `epoch`
+fn normalize_timestamps(output: &Path, epoch: u64) -> Result<()> {
+    let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(epoch);
+    for entry in walkdir::WalkDir::new(output) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            fs::OpenOptions::new()
+                .write(true)
+                .open(entry.path())?
+                .set_modified(time)?;
+        }
+    }
     Ok(())
 }
 
-fn build_single_book(source: &Path, output: &Path, config: &BookConfig, skip_search_index: bool) -> Result<BuildStats> {
-    let summary = Summary::parse(source)?;
-    let templates = Templates::new(config)?;
+/// Hidden sibling of `output` used while swapping (e.g. "_book" -> "._book.staging")
+fn sibling_path(output: &Path, suffix: &str) -> PathBuf {
+    let name = output
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "_book".to_string());
+    output.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+/// Whether a directory name is the staging or previous sibling of an output
+/// directory (e.g. "._book.staging")
+fn is_build_sibling(name: &str) -> bool {
+    name.starts_with('.') && (name.ends_with(".staging") || name.ends_with(".previous"))
+}
+
+/// Page cache file of incremental builds of `output` (e.g. "._book.cache")
+pub fn cache_path(output: &Path) -> PathBuf {
+    sibling_path(output, "cache")
+}
+
+/// Existing files a build of `output` leaves behind: the output itself, the
+/// staging/previous siblings of an interrupted build and the page cache
+pub fn output_artifacts(output: &Path) -> Vec<PathBuf> {
+    [output.to_path_buf(), sibling_path(output, "staging"), sibling_path(output, "previous"), cache_path(output)]
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect()
+}
+
+/// Whether `path` is in `output` or one of the staging/previous siblings and
+/// page cache a build of `output` writes to (changes there are the build's own)
+pub fn is_output_path(output: &Path, path: &Path) -> bool {
+    [output.to_path_buf(), sibling_path(output, "staging"), sibling_path(output, "previous"), cache_path(output)]
+        .iter()
+        .any(|dir| path.starts_with(dir))
+}
+
+/// Move entries of a previous output that the new build didn't produce
+/// Only hidden top-level entries are moved unless `keep_all` is set;
+/// returns the number of entries left behind
+fn move_leftovers(from: &Path, to: &Path, keep_all: bool) -> Result<usize> {
+    let mut left_behind = 0;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+
+        if !keep_all && !hidden {
+            if fs::symlink_metadata(&target).is_err() {
+                left_behind += 1;
+            }
+            continue;
+        }
+
+        match fs::symlink_metadata(&target) {
+            Err(_) => fs::rename(entry.path(), &target)?,
+            Ok(meta) if meta.is_dir() && entry.file_type()?.is_dir() => {
+                left_behind += move_leftovers(&entry.path(), &target, true)?;
+            }
+            Ok(_) => {}
+        }
+    }
+
+    Ok(left_behind)
+}
+
+/// Source of the page shown for missing paths
+const NOT_FOUND_SOURCE: &str = "404.md";
+
+/// Title of the default 404 page
+const NOT_FOUND_TITLE: &str = "Page not found";
+
+/// Content of the 404 page of books without a 404.md
+const NOT_FOUND_CONTENT: &str =
+    "# Page not found\n\nThe page you are looking for doesn't exist or has moved. Use the table of contents or search to find it.\n";
+
+/// Absolute path of the book root, for the 404 page: the base URL, else
+/// the path of `deploy.siteUrl`, else the site root
+fn not_found_root(config: &BookConfig, language: Option<&str>) -> String {
+    if !config.base_url.is_empty() {
+        return config.root_path(0);
+    }
+    let site_path = config
+        .deploy
+        .site_url
+        .split_once("://")
+        .map(|(_, rest)| rest.find('/').map(|i| &rest[i..]).unwrap_or(""))
+        .unwrap_or(&config.deploy.site_url);
+    let site_path = site_path.split(['?', '#']).next().unwrap_or("").trim_matches('/');
+    let dir = language.map(|code| config.languages.output_dir(code)).unwrap_or_default();
+    let root: String = [site_path, dir.as_str()].iter().filter(|part| !part.is_empty()).map(|part| format!("/{}", part)).collect();
+    format!("{}/", root)
+}
+
+/// language: language code when building a language directory of a multi-language book
+fn build_single_book(
+    source: &Path,
+    output: &Path,
+    config: &BookConfig,
+    language: Option<&str>,
+    options: &BuildOptions,
+) -> Result<BuildStats> {
     let mut stats = BuildStats::default();
+    let mut phase_start = Instant::now();
+    let summary = generate::load_summary(source, config)?;
+    let templates = Templates::new(config, &summary)?;
+    let pipeline = Pipeline::new(config)?;
+
+    // Output pages -> sources, checked before anything is written
+    let mut page_sources = BTreeMap::new();
+    collect_page_sources(source, &summary.items, &summary, &mut page_sources);
+    if source.join("README.md").exists() {
+        page_sources.entry("index.html".to_string()).or_insert_with(|| "README.md".to_string());
+    }
+    check_case_collisions(&page_sources)?;
 
     // Load glossary if exists
     let glossary = Glossary::load(source)?;
     if !glossary.is_empty() {
-        println!("  Loaded glossary with {} terms", glossary.entries.len());
+        info!("  Loaded glossary with {} terms", glossary.entries.len());
     }
 
+    // Load per-directory front matter defaults (_defaults.yaml)
+    let defaults = FrontMatterDefaults::load(source)?;
+    if !defaults.is_empty() {
+        info!("  Loaded front matter defaults for {} directories", defaults.len());
+    }
+    stats.add_phase("load", phase_start.elapsed());
+    phase_start = Instant::now();
+
     // Create output directory
     fs::create_dir_all(output)?;
 
     // Write embedded static assets
     write_static_assets(output, config)?;
+    templates.write_nav(output, config)?;
 
     // Copy assets
-    stats.assets += copy_assets(source, output)?;
+    stats.assets += copy_assets(source, output, !options.reproducible)?;
 
     // Copy custom styles if configured
     if let Some(style_path) = config.get_website_style() {
@@ -119,63 +617,152 @@ fn build_single_book(source: &Path, output: &Path, config: &BookConfig, skip_sea
             let dest_style = output.join("gitbook/style.css");
             fs::create_dir_all(dest_style.parent().unwrap())?;
             fs::copy(&src_style, &dest_style)?;
+            stats.sources.insert("gitbook/style.css".to_string(), style_path.trim_start_matches("./").to_string());
         }
     }
+    stats.sources.extend(icons::write_icons(source, output, config)?);
+    stats.add_phase("assets", phase_start.elapsed());
+    phase_start = Instant::now();
 
     // Build each chapter
-    stats.pages += build_chapters(source, output, &summary.items, config, &templates, &summary, &glossary)?;
-
-    // Generate index.html from README.md if exists
-    let readme_path = source.join("README.md");
-    if readme_path.exists() {
-        let raw_content = fs::read_to_string(&readme_path)?;
-        // Parse front matter
-        let parsed = parse_front_matter(&raw_content);
+    let wiki_links = WikiLinks::load(source, config, &summary);
+    let content_templates = ContentTemplates::new(config);
+    // Reproducible builds don't depend on when the files were checked out
+    let last_modified = config.last_updated.then(|| LastModified::load(source, options.reproducible.then(source_date_epoch)));
+    let social_cards = SocialCards::new(source, config);
+    let book = BookContext {
+        source,
+        config,
+        summary: &summary,
+        glossary: &glossary,
+        defaults: &defaults,
+        wiki_links: &wiki_links,
+        templates: &content_templates,
+        language,
+        last_modified: last_modified.as_ref(),
+        social_cards: social_cards.as_ref(),
+    };
+    let only = options.only.as_deref().map(|spec| parser::summary::glob_regex(paths::normalize(spec).trim_start_matches('/')));
+    let mut cache = options.cache.as_ref().map(|path| PageCache::load(path, language, cache::book_hash(&book, output, options)));
+    let mut search_entries = Vec::new();
+    let mut page_redirects = Vec::new();
+    stats.pages += build_chapters(&book, output, &templates, only.as_ref(), cache.as_mut(), &mut search_entries, &mut page_redirects)?;
+    if let Some(cache) = cache {
+        stats.reused += cache.reused;
+        cache.save()?;
+    }
+    if !config.deploy.site_url.is_empty() {
+        sitemap::write_sitemap(output, &config.deploy.site_url, &sitemap_pages(&book, &page_sources))?;
+    }
+    stats.sources.extend(page_sources);
+
+    // Generate index.html from README.md if exists, as its SUMMARY.md entry
+    // when it is listed there
+    if summary.has_source(source, "README.md") && only.as_ref().is_none_or(|re| re.is_match("README.md")) {
+        let page_start = Instant::now();
+        logging::set_page(Some("README.md"));
+        let rendered = render_source_page(&book, "README.md")?;
+        // Point links at pages published elsewhere (part prefixes, merged entries)
+        let readme_path = summary.html_path("README.md");
+        let rendered = if summary.remapped.is_empty() {
+            rendered
+        } else {
+            RenderedPage { html: rewrite_page_links(&rendered.html, "README.html", "index.html", &summary.remapped), ..rendered }
+        };
+        let position = summary.position(&readme_path);
+        let title = position.as_ref().map_or(config.title.as_str(), |p| p.entry.title.as_str()).to_string();
+        let target = PageTarget { source_path: "README.md", html_path: "index.html", title: &title, entry_path: &readme_path };
+        // Search results start with the index
+        let mut index_entries = Vec::new();
+        stats.pages += write_page(&book, output, &templates, target, rendered, &mut index_entries, &mut page_redirects)?.len();
+        search_entries.splice(0..0, index_entries);
+        logging::set_page(None);
+        verbose!("  README.md -> index.html ({:.1} ms)", page_start.elapsed().as_secs_f64() * 1000.0);
+        stats.sources.insert("index.html".to_string(), "README.md".to_string());
+    }
+
+    // Generate 404.html, from 404.md if exists
+    // Hosts serve it at any missing path, so its links are absolute
+    if only.as_ref().is_none_or(|re| re.is_match(NOT_FOUND_SOURCE)) {
+        logging::set_page(Some(NOT_FOUND_SOURCE));
+        let not_found_path = source.join(NOT_FOUND_SOURCE);
+        let raw_content = if not_found_path.exists() {
+            stats.sources.insert("404.html".to_string(), NOT_FOUND_SOURCE.to_string());
+            fs::read_to_string(&not_found_path)?
+        } else {
+            NOT_FOUND_CONTENT.to_string()
+        };
+        let parsed = parse_front_matter_with_defaults(&raw_content, &defaults.for_page(NOT_FOUND_SOURCE));
+        let line_offset = parsed.body_line_offset(&raw_content);
         let front_matter = parsed.front_matter;
-        // Process @import directives before template processing
-        let imported_content = process_imports_for_file(&parsed.content, &readme_path)?;
-        // Process Nunjucks templates (conditionals, loops, filters, variables)
-        let content = nunjucks::process_nunjucks_templates(&imported_content, config)
-            .unwrap_or_else(|e| {
-                eprintln!("  Warning: Template error in README.md: {}", e);
-                imported_content.clone()
-            });
-        let html_content = render_markdown_with_hardbreaks(&content, config.hardbreaks);
-        // Apply glossary terms
-        let html_content = apply_glossary(&html_content, &glossary);
-        let toc_items = extract_headings(&content);
-        // Use front matter title if available, otherwise use config title
-        let page_title = front_matter.as_ref()
-            .and_then(|fm| fm.title.as_deref())
-            .unwrap_or(&config.title);
-        let page_html = templates.render_page_with_meta(
-            page_title,
-            &html_content,
-            "./",
-            config,
-            &summary,
-            Some("index.html"),
-            &toc_items,
-            front_matter.as_ref(),
-        )?;
-        // Apply SVG processing if configured
-        let page_html = apply_svg_processing(page_html, output, config)?;
-        fs::write(output.join("index.html"), page_html)?;
-        stats.pages += 1;
+        let page = hooks::PageContext { updated: None, ..book.page(NOT_FOUND_SOURCE, "404.html", front_matter.as_ref()) };
+        let (html_content, toc_items) = pipeline.render_content(parsed.content, &page, None, line_offset, &book)?;
+        let page_title = front_matter.as_ref().and_then(|fm| fm.title.as_deref()).unwrap_or(NOT_FOUND_TITLE);
+        let root = not_found_root(config, language);
+        let page_html = templates.render_page(&page, page_title, &html_content, &root, &toc_items)?;
+        let page_html = pipeline.render_page(page_html, output, &page)?;
+        let page_html = rebase_links(&page_html, "404.html", &root);
+        fs::write(output.join("404.html"), minify::page(page_html, config))?;
+        logging::set_page(None);
+    }
+
+    // Stubs at the old paths of pages (`redirect_from`)
+    if !page_redirects.is_empty() {
+        let written = redirects::write_redirects(output, config, language, &page_redirects, &mut stats.sources)?;
+        info!("  Wrote {} redirects", written);
     }
+    stats.add_phase("pages", phase_start.elapsed());
 
     // Generate search index (skip on hot reload for performance)
-    if !skip_search_index {
-        generate_search_index(source, output, &summary)?;
+    let fast_search_index = options.fast_mode && config.serve.fast_mode.contains(&ServePass::SearchIndex);
+    if !options.skip_search_index && !fast_search_index {
+        let phase_start = Instant::now();
+        SearchIndex::write(output, &search_entries)?;
+        stats.add_phase("search index", phase_start.elapsed());
+    }
+
+    // Algolia DocSearch: the crawler's config
+    if let Some(algolia_config) = &config.search.algolia {
+        algolia::write_crawler_config(output, config, algolia_config, language)?;
+    }
+
+    // Pushed to Algolia, Meilisearch or Typesense once the output is in place
+    if options.push_search && (config.search.algolia.is_some() || searchserver::server(&config.search)?.is_some()) {
+        stats.search_pushes.push(SearchPush { entries: search_entries, config: config.clone(), language: language.map(str::to_string) });
     }
 
     // Download remote images if enabled
     if config.fetch_remote_images {
-        println!("Downloading remote images...");
+        info!("Downloading remote images...");
+        let phase_start = Instant::now();
         let downloaded = process_remote_images(output)?;
+        stats.add_phase("remote images", phase_start.elapsed());
         if downloaded > 0 {
-            println!("  Downloaded {} remote images", downloaded);
+            info!("  Downloaded {} remote images", downloaded);
+        }
+    }
+
+    if config.fingerprint && !options.plain_asset_names {
+        let phase_start = Instant::now();
+        for (plain, fingerprinted) in fingerprint::fingerprint_assets(output)? {
+            if let Some(source) = stats.sources.remove(&plain) {
+                stats.sources.insert(fingerprinted, source);
+            }
         }
+        stats.add_phase("fingerprint", phase_start.elapsed());
+    }
+
+    if !options.exports.is_empty() {
+        let phase_start = Instant::now();
+        let export_book = export::ExportBook::new(source, config.clone())?;
+        export::write_exports(&export_book, output, &options.exports)?;
+        stats.add_phase("exports", phase_start.elapsed());
+    }
+
+    // Last, so the service worker precaches the files of the whole build
+    if let Some(pwa_config) = &config.pwa {
+        let precached = pwa::write_app(output, config, pwa_config)?;
+        info!("  Wrote {} and {} precaching {} files", pwa::WEB_MANIFEST_FILE, pwa::SERVICE_WORKER_FILE, precached);
     }
 
     Ok(stats)
@@ -184,25 +771,26 @@ fn build_single_book(source: &Path, output: &Path, config: &BookConfig, skip_sea
 fn write_static_assets(output: &Path, config: &BookConfig) -> Result<()> {
     let gitbook_dir = output.join("gitbook");
     fs::create_dir_all(&gitbook_dir)?;
+    let write = |name: &str, content: &str| fs::write(gitbook_dir.join(name), minify::asset(name, content, config));
 
     // Write CSS
-    fs::write(gitbook_dir.join("gitbook.css"), GITBOOK_CSS)?;
+    write("gitbook.css", GITBOOK_CSS)?;
 
     // Write JS
-    fs::write(gitbook_dir.join("gitbook.js"), GITBOOK_JS)?;
+    write("gitbook.js", GITBOOK_JS)?;
 
     // Write collapsible JS only if plugin is enabled
     if config.is_plugin_enabled("collapsible-chapters") {
-        fs::write(gitbook_dir.join("collapsible.js"), COLLAPSIBLE_JS)?;
+        write("collapsible.js", COLLAPSIBLE_JS)?;
     }
 
     // Write fontsettings JS only if plugin is enabled
     if config.is_plugin_enabled("fontsettings") {
-        fs::write(gitbook_dir.join("fontsettings.js"), FONTSETTINGS_JS)?;
+        write("fontsettings.js", FONTSETTINGS_JS)?;
     }
 
     // Write search JS
-    fs::write(gitbook_dir.join("search.js"), SEARCH_JS)?;
+    write("search.js", SEARCH_JS)?;
 
     Ok(())
 }
@@ -212,67 +800,231 @@ fn build_multi_lang_book(
     output: &Path,
     config: &BookConfig,
     languages: &[Language],
-    skip_search_index: bool,
+    options: &BuildOptions,
 ) -> Result<BuildStats> {
     let mut stats = BuildStats::default();
 
+    let dirs = language_dirs(source, languages, config)?;
+
     // Create output directory
     fs::create_dir_all(output)?;
 
-    // Generate language index page
-    generate_lang_index(output, languages, config)?;
+    // Generate language index page, unless a language is published at the root
+    if config.languages.default.is_empty() {
+        generate_lang_index(source, output, languages, &dirs, config)?;
+    }
 
     // Build each language
-    for lang in languages {
-        println!("\nBuilding {} ({})...", lang.title, lang.code);
+    for (lang, dir) in languages.iter().zip(&dirs) {
+        info!("\nBuilding {} ({})...", lang.title, lang.code);
         let lang_source = source.join(&lang.code);
-        let lang_output = output.join(&lang.code);
+        let lang_output = if dir.is_empty() { output.to_path_buf() } else { output.join(dir) };
+        let prefix = if dir.is_empty() { String::new() } else { format!("{}/", dir) };
 
         // Use language-specific config if exists, otherwise use root config
         let lang_config_path = lang_source.join("book.json");
-        let lang_config = if lang_config_path.exists() {
-            BookConfig::load(&lang_source)?
+        let mut lang_config = if lang_config_path.exists() {
+            let mut lang_config = BookConfig::load(&lang_source)?;
+            define_variables(&mut lang_config, &options.variables);
+            if options.skip_pwa {
+                lang_config.pwa = None;
+            }
+            // The preview server skips the same passes in every language
+            lang_config.serve = config.serve.clone();
+            if options.fast_mode {
+                skip_fast_mode_passes(&mut lang_config);
+            }
+            lang_config
         } else {
             config.clone()
         };
+        // Shared chapters link to the other languages where they are published
+        lang_config.languages = config.languages.clone();
+        // Templates format dates and numbers for the language
+        if lang_config.language.is_empty() {
+            lang_config.language = lang.code.clone();
+        }
+        // Languages are published in their directory under the book's base URL
+        lang_config.base_url =
+            if config.base_url.is_empty() { String::new() } else { format!("{}{}", config.root_path(0), prefix) };
 
-        let lang_stats = build_single_book(&lang_source, &lang_output, &lang_config, skip_search_index)?;
+        let lang_stats = build_single_book(&lang_source, &lang_output, &lang_config, Some(&lang.code), options)?;
         stats.pages += lang_stats.pages;
+        stats.reused += lang_stats.reused;
         stats.assets += lang_stats.assets;
+        stats.search_pushes.extend(lang_stats.search_pushes);
+        for (name, elapsed) in lang_stats.phases {
+            stats.add_phase(name, elapsed);
+        }
+        if dir.is_empty() && !dirs.contains(&lang.code) {
+            let redirects = write_language_redirects(output, config, &lang.code, lang_stats.sources.keys())?;
+            info!("  Wrote {} redirects from {}/ to the root", redirects, lang.code);
+        }
+        for (output_file, source_file) in lang_stats.sources {
+            stats.sources.insert(
+                format!("{}{}", prefix, output_file),
+                format!("{}/{}", lang.code, source_file),
+            );
+        }
+    }
+
+    // Hosts only look for a 404 page at the root; the first language's links
+    // are absolute, so it can be used there as it is
+    let root_not_found = output.join("404.html");
+    if let Some(dir) = dirs.first().filter(|dir| !dir.is_empty() && !root_not_found.exists()) {
+        let not_found = output.join(dir).join("404.html");
+        if not_found.exists() {
+            fs::copy(not_found, root_not_found)?;
+        }
     }
 
     // Copy root assets if they exist
     let assets_dir = source.join("assets");
     if assets_dir.exists() {
-        stats.assets += copy_dir_recursive_count(&assets_dir, &output.join("assets"))?;
+        stats.assets += copy_dir_recursive_count(&assets_dir, &output.join("assets"), !options.reproducible)?;
     }
 
     Ok(stats)
 }
 
+/// Output directory of each language (`languages` section of book.json),
+/// "" for the language published at the root
+fn language_dirs(source: &Path, languages: &[Language], config: &BookConfig) -> Result<Vec<String>> {
+    let default = &config.languages.default;
+    if !default.is_empty() && !languages.iter().any(|lang| &lang.code == default) {
+        anyhow::bail!("languages.default: {} is not a language of LANGS.md", default);
+    }
+    let dirs: Vec<String> = languages.iter().map(|lang| config.languages.output_dir(&lang.code)).collect();
+    for (i, dir) in dirs.iter().enumerate() {
+        if languages[i].code != *default && (dir.is_empty() || dir.split('/').any(|part| part == "." || part == "..")) {
+            anyhow::bail!("languages.paths: {:?} is not a directory for {}", dir, languages[i].code);
+        }
+        if dirs[..i].contains(dir) {
+            let other = &languages[dirs.iter().position(|d| d == dir).unwrap_or(i)];
+            anyhow::bail!("languages.paths: {} and {} are both published in {}/", other.code, languages[i].code, dir);
+        }
+    }
+    // The default language's files share the root with the other languages
+    if !default.is_empty() {
+        if let Some(dir) = dirs.iter().find(|dir| !dir.is_empty() && source.join(default).join(dir).exists()) {
+            anyhow::bail!("languages.default: {}/{} would be published where the {}/ language is", default, dir, dir);
+        }
+    }
+    Ok(dirs)
+}
+
+/// Redirect the pages of the language published at the root from the
+/// directory it used to be published in (`<code>/page.html` -> `page.html`)
+/// pages: output-relative paths of the language's files
+fn write_language_redirects<'a>(output: &Path, config: &BookConfig, code: &str, pages: impl Iterator<Item = &'a String>) -> Result<usize> {
+    let mut count = 0;
+    for page in pages.filter(|page| page.ends_with(".html")) {
+        let target = format!("{}{}", "../".repeat(paths::depth(page) + 1), paths::encode_href(page));
+        let html = redirects::stub_html(&target, redirects::canonical_url(config, page).as_deref());
+        let dest = output.join(code).join(page);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(dest, html)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// A book being rendered: what every page of it shares
+#[derive(Clone, Copy)]
+pub(crate) struct BookContext<'a> {
+    /// Book source directory (the language directory of multi-language books)
+    pub source: &'a Path,
+    pub config: &'a BookConfig,
+    pub summary: &'a Summary,
+    pub glossary: &'a Glossary,
+    pub defaults: &'a FrontMatterDefaults,
+    pub wiki_links: &'a WikiLinks,
+    /// Tera environment the pages are templated with
+    pub templates: &'a ContentTemplates,
+    /// Language code of a multi-language book's language directory
+    pub language: Option<&'a str>,
+    /// Commit times of the book's files, with `lastUpdated` in book.json
+    pub last_modified: Option<&'a LastModified>,
+    /// Renderer of the pages' preview images, with `socialCards` in book.json
+    pub social_cards: Option<&'a SocialCards>,
+}
+
+impl<'a> BookContext<'a> {
+    /// Context of the page published at `html_path` from `source_path`
+    fn page<'p>(&self, source_path: &'p str, html_path: &'p str, front_matter: Option<&'p parser::FrontMatter>) -> hooks::PageContext<'p>
+    where
+        'a: 'p,
+    {
+        hooks::PageContext {
+            book_dir: self.source,
+            source_path,
+            html_path,
+            config: self.config,
+            front_matter,
+            language: self.language,
+            position: self.summary.position(html_path),
+            updated: self.updated(source_path),
+        }
+    }
+
+    /// Last change of the source files of a page (Unix seconds), with `lastUpdated`
+    fn updated(&self, source_path: &str) -> Option<u64> {
+        self.last_modified?.latest(self.source, &self.summary.page_sources(source_path))
+    }
+}
+
+/// Chapters written so far by `build_chapters`
+struct BuiltPages<'a> {
+    /// Source paths already built (or skipped)
+    files: std::collections::HashSet<String>,
+    cache: Option<&'a mut PageCache>,
+    /// Old paths of the pages written (`redirect_from`)
+    redirects: Vec<redirects::Redirect>,
+}
+
+/// only: renders just the chapters whose source matches (`build --only`)
+/// cache: keeps the pages whose inputs are unchanged, records the others
+/// search: receives an entry per page written, with its rendered text
+/// redirects: receives the old paths of the pages written
 fn build_chapters(
-    source: &Path,
+    book: &BookContext,
     output: &Path,
-    items: &[SummaryItem],
-    config: &BookConfig,
     templates: &Templates,
-    summary: &Summary,
-    glossary: &Glossary,
+    only: Option<&Regex>,
+    cache: Option<&mut PageCache>,
+    search: &mut Vec<SearchEntry>,
+    redirects: &mut Vec<redirects::Redirect>,
 ) -> Result<usize> {
-    let mut built_files: std::collections::HashSet<String> = std::collections::HashSet::new();
-    build_chapters_inner(source, output, items, config, templates, summary, glossary, &mut built_files)
+    let mut built = BuiltPages { files: std::collections::HashSet::new(), cache, redirects: Vec::new() };
+    let mut count = 0;
+    let mut part_heading = None;
+    for item in &book.summary.items {
+        if let SummaryItem::PartTitle(heading) = item {
+            part_heading = Some(heading);
+        }
+        // Search results are grouped by the part their page is listed under
+        let first = search.len();
+        count += build_chapters_inner(book, output, templates, std::slice::from_ref(item), &mut built, only, search)?;
+        for entry in &mut search[first..] {
+            entry.part = part_heading.cloned();
+        }
+    }
+    redirects.append(&mut built.redirects);
+    Ok(count)
 }
 
 fn build_chapters_inner(
-    source: &Path,
+    book: &BookContext,
     output: &Path,
-    items: &[SummaryItem],
-    config: &BookConfig,
     templates: &Templates,
-    summary: &Summary,
-    glossary: &Glossary,
-    built_files: &mut std::collections::HashSet<String>,
+    items: &[SummaryItem],
+    built: &mut BuiltPages,
+    only: Option<&Regex>,
+    search: &mut Vec<SearchEntry>,
 ) -> Result<usize> {
+    let BookContext { source, config, summary, .. } = *book;
     let mut count = 0;
 
     for item in items {
@@ -280,109 +1032,81 @@ fn build_chapters_inner(
             if let Some(md_path) = path {
                 // Extract base file path (remove anchor #xxx if present)
                 // Also strip leading slash to handle absolute-style paths in SUMMARY.md
-                let base_path = if let Some(hash_pos) = md_path.find('#') {
-                    md_path[..hash_pos].trim_start_matches('/')
-                } else {
-                    md_path.trim_start_matches('/')
-                };
+                let base_path = paths::split_fragment(md_path).0.trim_start_matches('/');
 
                 // Skip if already built (avoid duplicate builds for anchor-only references)
-                if base_path.is_empty() || built_files.contains(base_path) {
+                if base_path.is_empty() || built.files.contains(base_path) {
                     // Still need to process children
                     if !children.is_empty() {
-                        count += build_chapters_inner(source, output, children, config, templates, summary, glossary, built_files)?;
+                        count += build_chapters_inner(book, output, templates, children, built, only, search)?;
                     }
                     continue;
                 }
 
-                let src_file = source.join(base_path);
-                if src_file.exists() {
+                let selected = only.is_none_or(|re| summary.page_sources(base_path).iter().any(|p| re.is_match(p)));
+                if !selected {
+                    built.files.insert(base_path.to_string());
+                } else if summary.has_source(source, base_path) {
                     // Mark as built before processing
-                    built_files.insert(base_path.to_string());
-
-                    // Read file content
-                    let raw_content = fs::read_to_string(&src_file)?;
-                    // Parse front matter
-                    let parsed = parse_front_matter(&raw_content);
-                    let front_matter = parsed.front_matter;
-
-                    // Check if this is an AsciiDoc file
-                    let is_asciidoc = is_asciidoc_file(&src_file);
-
-                    // Render content based on file type
-                    let (html_content, toc_items) = if is_asciidoc {
-                        // AsciiDoc rendering
-                        let html = render_asciidoc_with_path(&parsed.content, Some(base_path));
-                        let toc = extract_headings_from_asciidoc(&parsed.content);
-                        (html, toc)
-                    } else {
-                        // Markdown rendering
-                        // Process @import directives before template processing
-                        let imported_content = process_imports_for_file(&parsed.content, &src_file)?;
-                        // Process Nunjucks templates (conditionals, loops, filters, variables)
-                        let content = nunjucks::process_nunjucks_templates(&imported_content, config)
-                            .unwrap_or_else(|e| {
-                                eprintln!("  Warning: Template error in {}: {}", base_path, e);
-                                imported_content.clone()
-                            });
-                        let html = render_markdown_with_path(&content, Some(base_path), config.hardbreaks);
-                        let toc = extract_headings(&content);
-                        (html, toc)
-                    };
+                    built.files.insert(base_path.to_string());
+                    let page_start = Instant::now();
+                    logging::set_page(Some(base_path));
+
+                    // Chapters shared from another language are rendered there
+                    let shared = SharedChapter::new(source, base_path, config, book.language);
+                    let shared_book = shared.as_ref().map(|shared| BookContext { source: &shared.home, ..*book });
+                    let page_book = shared_book.as_ref().unwrap_or(book);
+                    let page_path = shared.as_ref().map_or(base_path, |shared| shared.path);
+
+                    // Shared chapters also depend on the other language's files
+                    let inputs = built.cache.as_ref().filter(|_| shared.is_none()).map(|_| cache::page_inputs(book, base_path));
+                    let kept = inputs.as_deref().and_then(|inputs| built.cache.as_mut()?.reuse(base_path, inputs, output));
+                    if let Some(entries) = kept {
+                        search.extend(entries);
+                        logging::set_page(None);
+                        verbose!("  {} unchanged", base_path);
+                        if !children.is_empty() {
+                            count += build_chapters_inner(book, output, templates, children, built, only, search)?;
+                        }
+                        continue;
+                    }
+                    let first = search.len();
 
-                    // Apply glossary terms
-                    let html_content = apply_glossary(&html_content, glossary);
+                    let RenderedPage { html: html_content, toc_items, front_matter } =
+                        render_summary_page(page_book, page_path)?;
 
                     // Generate output path (use base_path without anchor)
-                    // Handle .md, .adoc, and .asciidoc extensions
-                    let html_path = base_path
-                        .replace(".md", ".html")
-                        .replace(".adoc", ".html")
-                        .replace(".asciidoc", ".html");
-                    let dest_file = output.join(&html_path);
-
-                    // Calculate relative path to root
-                    let depth = html_path.matches('/').count();
-                    let root_path = if depth > 0 {
-                        "../".repeat(depth)
+                    // Parts with a URL prefix publish their pages elsewhere
+                    let html_path = summary.html_path(base_path);
+
+                    // Relative links were written against the source layout
+                    let html_content = if summary.remapped.is_empty() {
+                        html_content
                     } else {
-                        "./".to_string()
+                        rewrite_page_links(&html_content, &default_html_path(page_path), &html_path, &summary.remapped)
+                    };
+                    let html_content = match &shared {
+                        Some(shared) => shared.publish(&html_content, source, output, &html_path, &summary.published_pages())?,
+                        None => html_content,
                     };
 
-                    // Use front matter title if available, otherwise use summary title
-                    let page_title = front_matter.as_ref()
-                        .and_then(|fm| fm.title.as_deref())
-                        .unwrap_or(title);
-
-                    // Render with template
-                    let page_html = templates.render_page_with_meta(
-                        page_title,
-                        &html_content,
-                        &root_path,
-                        config,
-                        summary,
-                        Some(&html_path),
-                        &toc_items,
-                        front_matter.as_ref(),
-                    )?;
-
-                    // Apply SVG processing if configured
-                    let page_html = apply_svg_processing(page_html, output, config)?;
-
-                    // Write output
-                    if let Some(parent) = dest_file.parent() {
-                        fs::create_dir_all(parent)?;
+                    let target = PageTarget { source_path: page_path, html_path: &html_path, title, entry_path: &html_path };
+                    let rendered = RenderedPage { html: html_content, toc_items, front_matter };
+                    let outputs = write_page(page_book, output, templates, target, rendered, search, &mut built.redirects)?;
+                    count += outputs.len();
+                    if let (Some(cache), Some(inputs)) = (built.cache.as_mut(), inputs) {
+                        cache.record(base_path, inputs, outputs, search[first..].to_vec());
                     }
-                    fs::write(&dest_file, page_html)?;
-                    count += 1;
+                    logging::set_page(None);
+                    verbose!("  {} -> {} ({:.1} ms)", base_path, html_path, page_start.elapsed().as_secs_f64() * 1000.0);
                 } else {
-                    println!("  Warning: {} not found", base_path);
+                    warning!("{} not found", base_path);
                 }
             }
 
             // Build children recursively
             if !children.is_empty() {
-                count += build_chapters_inner(source, output, children, config, templates, summary, glossary, built_files)?;
+                count += build_chapters_inner(book, output, templates, children, built, only, search)?;
             }
         }
     }
@@ -390,51 +1114,241 @@ fn build_chapters_inner(
     Ok(count)
 }
 
-fn copy_assets(source: &Path, output: &Path) -> Result<usize> {
+/// Page content rendered from a source file, before it is wrapped in the page template
+struct RenderedPage {
+    html: String,
+    toc_items: Vec<TocItem>,
+    front_matter: Option<parser::FrontMatter>,
+}
+
+/// Where a rendered page is published
+struct PageTarget<'a> {
+    /// Book-relative source path (e.g. "guide/start.md")
+    source_path: &'a str,
+    /// Published path (e.g. "guide/start.html")
+    html_path: &'a str,
+    /// Title unless front matter sets one
+    title: &'a str,
+    /// Published path of its SUMMARY.md entry, for the sidebar and prev/next
+    /// (the README index takes its README.md entry's)
+    entry_path: &'a str,
+}
+
+/// Publish a rendered page: each section of a `split` page wrapped in the
+/// book layout, through the page stages, with media and attachments copied
+/// and links rebased; its search entries and redirects are added
+/// Returns the output paths written
+fn write_page(
+    book: &BookContext,
+    output: &Path,
+    templates: &Templates,
+    target: PageTarget,
+    page: RenderedPage,
+    search: &mut Vec<SearchEntry>,
+    redirects: &mut Vec<redirects::Redirect>,
+) -> Result<Vec<String>> {
+    let config = book.config;
+    let RenderedPage { html, toc_items, front_matter } = page;
+    let html_path = target.html_path;
+
+    // Path to the book root (the base URL when one is set)
+    let root_path = config.root_path(html_path.matches('/').count());
+
+    // Use front matter title if available, otherwise the target's
+    let page_title = front_matter.as_ref()
+        .and_then(|fm| fm.title.as_deref())
+        .unwrap_or(target.title);
+
+    // `split: h2` publishes each section as its own page
+    let split_level = front_matter.as_ref()
+        .and_then(|fm| fm.split.as_deref())
+        .and_then(|value| {
+            let level = split::split_level(value);
+            if level.is_none() {
+                warning!("{}: invalid split level {:?} (expected h1 to h6)", target.source_path, value);
+            }
+            level
+        });
+    let parts = match split_level {
+        Some(level) => split::split_page(&html, html_path, level, &toc_items),
+        None => vec![split::SplitPart { html_path: html_path.to_string(), title: None, html, toc_items }],
+    };
+
+    let position = book.summary.position(target.entry_path);
+    for (i, part) in parts.iter().enumerate() {
+        let part_title = match &part.title {
+            Some(section_title) if i > 0 => section_title.as_str(),
+            _ => page_title,
+        };
+
+        // Sections keep their chapter's position, so they are
+        // highlighted in the sidebar as their chapter
+        let page = hooks::PageContext {
+            book_dir: book.source,
+            source_path: target.source_path,
+            html_path: &part.html_path,
+            config,
+            front_matter: front_matter.as_ref(),
+            language: book.language,
+            position: position.clone(),
+            updated: book.updated(target.source_path),
+        };
+
+        // Render with template
+        let page_html = templates.render_page(&page, part_title, &part.html, &root_path, &part.toc_items)?;
+        let page_html = Pipeline::new(config)?.render_page(page_html, output, &page)?;
+        let page_html = media::publish_media(page_html, book.source, output, &part.html_path)?;
+        let page_html = attachments::publish_attachments(page_html, book.source, output, &part.html_path)?;
+        let page_html = rebase_links(&page_html, &part.html_path, &config.base_url);
+
+        // Write output
+        let dest_file = output.join(&part.html_path);
+        if let Some(parent) = dest_file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&dest_file, minify::page(page_html, config))?;
+
+        if let (Some(cards), Some(card)) = (book.social_cards, socialcards::card_path(&page)) {
+            if let Err(e) = cards.write(output, &card, part_title, &part.html, &part.html_path) {
+                warning!("{}: no social card: {:#}", part.html_path, e);
+            }
+        }
+
+        // Indexed as rendered, with @imports and templates applied
+        if !front_matter.as_ref().is_some_and(|fm| fm.is_noindex()) {
+            search.extend(page_search_entries(part_title, &part.html_path, &part.html));
+        }
+    }
+    if let Some(fm) = &front_matter {
+        redirects.extend(redirects::page_redirects(&fm.redirect_from, html_path, target.source_path));
+    }
+    Ok(parts.into_iter().map(|part| part.html_path).collect())
+}
+
+/// Render a chapter source file to HTML content: front matter, then the
+/// content stages of the pipeline (@import, templating, wiki links, Markdown or AsciiDoc, glossary)
+/// base_path: book-relative source path without anchor (e.g. "guide/start.md")
+fn render_source_page(book: &BookContext, base_path: &str) -> Result<RenderedPage> {
+    // Read file content (generated pages have no file)
+    let raw_content = book.summary.read_source(book.source, base_path)?;
+    // Parse front matter
+    let parsed = parse_front_matter_with_defaults(&raw_content, &book.defaults.for_page(base_path));
+    let line_offset = parsed.body_line_offset(&raw_content);
+    let front_matter = parsed.front_matter;
+
+    let html_path = book.summary.html_path(base_path);
+    let page = book.page(base_path, &html_path, front_matter.as_ref());
+    let (html_content, toc_items) =
+        Pipeline::new(book.config)?.render_content(parsed.content, &page, Some(base_path), line_offset, book)?;
+
+    Ok(RenderedPage {
+        html: html_content,
+        toc_items,
+        front_matter,
+    })
+}
+
+/// Markdown of a SUMMARY.md entry's source files after the content stages
+/// before Markdown rendering (@import, templating, wiki links), front matter
+/// removed: (book-relative source path, Markdown) for each file
+fn render_source_markdown(book: &BookContext, base_path: &str) -> Result<Vec<(String, String)>> {
+    let pipeline = Pipeline::new(book.config)?.source_stages();
+    let mut files = Vec::new();
+    for file in book.summary.page_sources(base_path) {
+        let raw_content = book.summary.read_source(book.source, &file)?;
+        let parsed = parse_front_matter_with_defaults(&raw_content, &book.defaults.for_page(&file));
+        let line_offset = parsed.body_line_offset(&raw_content);
+        let html_path = book.summary.html_path(&file);
+        let page = book.page(&file, &html_path, parsed.front_matter.as_ref());
+        let (markdown, _) = pipeline.render_content(parsed.content, &page, Some(&file), line_offset, book)?;
+        files.push((file, markdown));
+    }
+    Ok(files)
+}
+
+/// Directory of page templates selected with `template:` front matter
+const PAGE_TEMPLATES_DIR: &str = "_templates";
+
+/// Render a page body through its `_templates/<name>.md` page template
+fn apply_page_template(source: &Path, front_matter: &parser::FrontMatter, body: &str, templates: &ContentTemplates) -> Result<String> {
+    let name = front_matter.template.as_deref().unwrap_or_default();
+    let template_path = format!("{}/{}.md", PAGE_TEMPLATES_DIR, name);
+    let template = fs::read_to_string(source.join(&template_path))
+        .map_err(|_| anyhow::anyhow!("Page template {} not found", template_path))?;
+    templates.render_page_template(&template, &template_path, &front_matter.template_fields(), body)
+}
+
+/// Render the page of a SUMMARY.md entry: its source file, or every file of a
+/// merged entry in order, with one combined TOC and the first file's front matter
+fn render_summary_page(book: &BookContext, base_path: &str) -> Result<RenderedPage> {
+    let files = book.summary.page_sources(base_path);
+    let mut page = render_source_page(book, &files[0])?;
+
+    let page_at = default_html_path(&files[0]);
+    for file in &files[1..] {
+        let part = render_source_page(book, file)?;
+        // Relative links were written against each file's own location
+        page.html.push('\n');
+        page.html.push_str(&rewrite_page_links(&part.html, &default_html_path(file), &page_at, &HashMap::new()));
+        page.toc_items.extend(part.toc_items);
+    }
+    Ok(page)
+}
+
+fn copy_assets(source: &Path, output: &Path, link: bool) -> Result<usize> {
     let mut count = 0;
+    for (src_dir, relative) in asset_dirs(source)? {
+        count += copy_dir_recursive_count(&src_dir, &output.join(relative), link)?;
+    }
+    Ok(count)
+}
+
+/// Asset directories copied to the output: (source directory, output-relative path)
+/// Root-level and nested (e.g. chapter/image/) `assets`, `images`, `image` and `img`
+fn asset_dirs(source: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
     let asset_dir_names: &[&str] = &["assets", "images", "image", "img"];
+    let mut dirs = Vec::new();
 
-    // Copy root-level asset directories
+    // Root-level asset directories
     for dir_name in asset_dir_names {
         let src_dir = source.join(dir_name);
         if src_dir.exists() {
-            let dest_dir = output.join(dir_name);
-            count += copy_dir_recursive_count(&src_dir, &dest_dir)?;
+            dirs.push((src_dir, PathBuf::from(dir_name)));
         }
     }
 
-    // Also copy nested asset directories (e.g., chapter/image/)
-    for entry in walkdir::WalkDir::new(source)
+    // Also nested asset directories (e.g., chapter/image/)
+    for entry in paths::walk(source)
         .into_iter()
         .filter_entry(|e| {
-            // Skip root-level asset dirs (already copied) and output directories
+            // Skip root-level asset dirs (already listed), output directories
+            // and the staging/previous siblings of a build into the source
             let name = e.file_name().to_string_lossy();
-            !(e.depth() == 1 && asset_dir_names.contains(&name.as_ref()))
-                && name != "_book"
-                && name != "node_modules"
+            let root_asset_dir = e.depth() == 1 && asset_dir_names.contains(&name.as_ref());
+            !root_asset_dir && !is_build_sibling(&name) && name != "_book" && name != "node_modules"
         })
+        // Broken symlinks are warned about when the asset directories are copied
+        .filter_map(Result::ok)
     {
-        let entry = entry?;
         if entry.file_type().is_dir() {
             let name = entry.file_name().to_string_lossy();
             if asset_dir_names.contains(&name.as_ref()) {
                 // Found a nested asset directory
-                let relative = entry.path().strip_prefix(source)?;
-                let dest_dir = output.join(relative);
-                count += copy_dir_recursive_count(entry.path(), &dest_dir)?;
+                let relative = entry.path().strip_prefix(source)?.to_path_buf();
+                dirs.push((entry.path().to_path_buf(), relative));
             }
         }
     }
 
-    Ok(count)
+    Ok(dirs)
 }
 
-fn copy_dir_recursive_count(src: &Path, dest: &Path) -> Result<usize> {
+/// link: symlink files instead of copying them (Unix only)
+fn copy_dir_recursive_count(src: &Path, dest: &Path, link: bool) -> Result<usize> {
     fs::create_dir_all(dest)?;
     let mut count = 0;
 
-    for entry in walkdir::WalkDir::new(src) {
-        let entry = entry?;
+    for entry in paths::walk(src).into_iter().filter_map(paths::readable) {
         let relative = entry.path().strip_prefix(src)?;
         let dest_path = dest.join(relative);
 
@@ -445,16 +1359,17 @@ fn copy_dir_recursive_count(src: &Path, dest: &Path) -> Result<usize> {
                 fs::create_dir_all(parent)?;
             }
             // Use symlinks on Unix for faster builds (no actual file copy)
-            // Falls back to copy on Windows
+            // Falls back to copy on Windows and for reproducible builds
             #[cfg(unix)]
-            {
+            if link {
                 let abs_src = entry.path().canonicalize()?;
                 std::os::unix::fs::symlink(&abs_src, &dest_path)?;
+                count += 1;
+                continue;
             }
             #[cfg(not(unix))]
-            {
-                fs::copy(entry.path(), &dest_path)?;
-            }
+            let _ = link;
+            fs::copy(entry.path(), &dest_path)?;
             count += 1;
         }
     }
@@ -462,7 +1377,7 @@ fn copy_dir_recursive_count(src: &Path, dest: &Path) -> Result<usize> {
     Ok(count)
 }
 
-fn generate_lang_index(output: &Path, languages: &[Language], config: &BookConfig) -> Result<()> {
+fn generate_lang_index(source: &Path, output: &Path, languages: &[Language], dirs: &[String], config: &BookConfig) -> Result<()> {
     let title = if config.title.is_empty() {
         "Select Language"
     } else {
@@ -470,14 +1385,14 @@ fn generate_lang_index(output: &Path, languages: &[Language], config: &BookConfi
     };
 
     let mut lang_links = String::new();
-    for lang in languages {
+    for (lang, dir) in languages.iter().zip(dirs) {
         lang_links.push_str(&format!(
             r#"
             <li>
                 <a href="{}/">{}</a>
             </li>
         "#,
-            lang.code, lang.title
+            dir, lang.title
         ));
     }
 
@@ -495,8 +1410,7 @@ fn generate_lang_index(output: &Path, languages: &[Language], config: &BookConfi
         <meta name="viewport" content="width=device-width, initial-scale=1, user-scalable=no">
         <meta name="apple-mobile-web-app-capable" content="yes">
         <meta name="apple-mobile-web-app-status-bar-style" content="black">
-        <link rel="apple-touch-icon-precomposed" sizes="152x152" href="gitbook/images/apple-touch-icon-precomposed-152.png">
-        <link rel="shortcut icon" href="gitbook/images/favicon.ico" type="image/x-icon">
+        {}
     </head>
     <body>
 
@@ -512,13 +1426,16 @@ fn generate_lang_index(output: &Path, languages: &[Language], config: &BookConfi
 
     </body>
 </html>"#,
-        title, lang_links
+        title,
+        icons::links(config, ""),
+        lang_links
     );
 
-    fs::write(output.join("index.html"), html)?;
+    fs::write(output.join("index.html"), rebase_links(&html, "index.html", &config.base_url))?;
 
     // Copy gitbook static files to root for the language selector page
     copy_gitbook_static_to_root(output)?;
+    icons::write_icons(source, output, config)?;
 
     Ok(())
 }
@@ -545,77 +1462,95 @@ fn strip_html_tags(html: &str) -> String {
         .join(" ")
 }
 
-/// Collect search entries from summary items
-fn collect_search_entries(
-    source: &Path,
-    items: &[SummaryItem],
-    entries: &mut Vec<SearchEntry>,
-) -> Result<()> {
+/// Map published page paths to their source files
+fn collect_page_sources(source: &Path, items: &[SummaryItem], summary: &Summary, sources: &mut BTreeMap<String, String>) {
     for item in items {
-        if let SummaryItem::Link { title, path, children } = item {
-            if let Some(file_path) = path {
-                // Strip leading slash to handle absolute-style paths in SUMMARY.md
-                let file_path = file_path.trim_start_matches('/');
-                let src_file = source.join(file_path);
-                if src_file.exists() {
-                    let content = fs::read_to_string(&src_file)?;
-
-                    // Render based on file type
-                    let html_content = if is_asciidoc_file(&src_file) {
-                        render_asciidoc(&content)
-                    } else {
-                        render_markdown(&content)
-                    };
-
-                    let text_content = strip_html_tags(&html_content);
-
-                    // Generate HTML path for any supported extension
-                    let html_path = file_path
-                        .replace(".md", ".html")
-                        .replace(".adoc", ".html")
-                        .replace(".asciidoc", ".html");
-
-                    entries.push(SearchEntry {
-                        title: title.clone(),
-                        path: html_path,
-                        content: text_content,
-                    });
+        if let SummaryItem::Link { path, children, .. } = item {
+            if let Some(md_path) = path {
+                let base_path = paths::split_fragment(md_path).0.trim_start_matches('/');
+                if let Some(page) = summary.generated.get(base_path) {
+                    sources.insert(summary.html_path(base_path), page.data_file.clone());
+                } else if !base_path.is_empty() && source.join(base_path).is_file() {
+                    sources.insert(summary.html_path(base_path), base_path.to_string());
                 }
             }
-            if !children.is_empty() {
-                collect_search_entries(source, children, entries)?;
-            }
+            collect_page_sources(source, children, summary, sources);
         }
     }
-    Ok(())
 }
 
-/// Generate search index JSON file
-fn generate_search_index(source: &Path, output: &Path, summary: &Summary) -> Result<()> {
-    let mut entries = Vec::new();
+/// Pages of the sitemap: every page of `pages` (output path -> source) except
+/// `robots: noindex` ones, under the language's directory
+fn sitemap_pages(book: &BookContext, pages: &BTreeMap<String, String>) -> Vec<sitemap::SitemapPage> {
+    let dir = book.language.map(|code| book.config.languages.output_dir(code)).filter(|dir| !dir.is_empty());
+    pages
+        .iter()
+        .filter(|(_, source)| {
+            let content = book.summary.read_source(book.source, source).unwrap_or_default();
+            let front_matter = parse_front_matter_with_defaults(&content, &book.defaults.for_page(source)).front_matter;
+            !front_matter.is_some_and(|fm| fm.is_noindex())
+        })
+        .map(|(path, source)| sitemap::SitemapPage {
+            path: dir.as_ref().map_or_else(|| path.clone(), |dir| format!("{}/{}", dir, path)),
+            updated: book.updated(source),
+        })
+        .collect()
+}
 
-    // Collect from README.md
-    let readme_path = source.join("README.md");
-    if readme_path.exists() {
-        let content = fs::read_to_string(&readme_path)?;
-        let html_content = render_markdown(&content);
-        let text_content = strip_html_tags(&html_content);
+/// Fail when output pages differ only in case (`Setup.html`, `setup.html`):
+/// they would overwrite each other on case-insensitive file systems (macOS, Windows)
+/// pages: output path -> source
+fn check_case_collisions(pages: &BTreeMap<String, String>) -> Result<()> {
+    let mut folded: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (dest, source) in pages {
+        folded.entry(dest.to_lowercase()).or_default().push(format!("{} (from {})", dest, source));
+    }
+    let collisions: Vec<String> = folded.into_values().filter(|group| group.len() > 1).map(|group| group.join(", ")).collect();
+    if !collisions.is_empty() {
+        anyhow::bail!(
+            "Output paths differ only in case and would overwrite each other on case-insensitive file systems:\n  {}",
+            collisions.join("\n  ")
+        );
+    }
+    Ok(())
+}
 
+/// Search entries of a rendered page: the page itself (its content before
+/// the first heading, or the first heading's section when the page starts
+/// with one) and one per heading section, linking to the heading's anchor
+fn page_search_entries(title: &str, html_path: &str, html: &str) -> Vec<SearchEntry> {
+    let heading_re = Regex::new(r#"<h[1-6]\b[^>]*\bid="([^"]+)"[^>]*>"#).unwrap();
+    let href = paths::encode_href(html_path);
+    let starts: Vec<(usize, &str)> =
+        heading_re.captures_iter(html).map(|cap| (cap.get(0).unwrap().start(), cap.get(1).unwrap().as_str())).collect();
+
+    let intro_end = starts.first().map(|&(start, _)| start).unwrap_or(html.len());
+    let mut entries = vec![SearchEntry {
+        title: title.to_string(),
+        section: None,
+        path: href.clone(),
+        part: None,
+        content: strip_html_tags(&html[..intro_end]),
+        reading_minutes: Some(stats::html_reading_minutes(html)),
+    }];
+    for (i, &(start, id)) in starts.iter().enumerate() {
+        let section = &html[start..starts.get(i + 1).map(|&(s, _)| s).unwrap_or(html.len())];
+        let content = strip_html_tags(section);
+        if i == 0 && entries[0].content.is_empty() {
+            entries[0].content = content;
+            continue;
+        }
+        let heading = section.find("</h").map(|end| strip_html_tags(&section[..end])).unwrap_or_default();
         entries.push(SearchEntry {
-            title: "Home".to_string(),
-            path: "index.html".to_string(),
-            content: text_content,
+            title: title.to_string(),
+            section: Some(if heading.is_empty() { id.to_string() } else { heading }),
+            path: format!("{}#{}", href, id),
+            part: None,
+            content,
+            reading_minutes: None,
         });
     }
-
-    // Collect from all chapters
-    collect_search_entries(source, &summary.items, &mut entries)?;
-
-    // Write search index
-    let json = serde_json::to_string(&entries)?;
-    fs::write(output.join("search_index.json"), json)?;
-
-    Ok(())
+    entries
 }
 
 /// Process all HTML files in output directory to download remote images
@@ -643,7 +1578,7 @@ fn process_remote_images(output: &Path) -> Result<usize> {
                             }
                         }
                         Err(e) => {
-                            eprintln!("  Warning: Failed to process {}: {}", entry.path().display(), e);
+                            warning!("Failed to process {}: {}", entry.path().display(), e);
                         }
                     }
                 }
@@ -655,12 +1590,14 @@ fn process_remote_images(output: &Path) -> Result<usize> {
     Ok(downloaded)
 }
 
+/// Matches <!-- @import("path/to/file") --> with optional whitespace
+const IMPORT_PATTERN: &str = r#"<!--\s*@import\s*\(\s*"([^"]+)"\s*\)\s*-->"#;
+
 /// Process @import directives in Markdown content
 /// Replaces <!-- @import("path/to/file.md") --> with the contents of the referenced file
 /// Supports recursive imports with loop prevention
 fn process_imports(content: &str, base_path: &Path, visited: &mut HashSet<PathBuf>) -> Result<String> {
-    // Regex to match <!-- @import("path/to/file") --> with optional whitespace
-    let re = Regex::new(r#"<!--\s*@import\s*\(\s*"([^"]+)"\s*\)\s*-->"#).unwrap();
+    let re = Regex::new(IMPORT_PATTERN).unwrap();
 
     let mut result = content.to_string();
     let mut offset: i64 = 0;
@@ -670,19 +1607,19 @@ fn process_imports(content: &str, base_path: &Path, visited: &mut HashSet<PathBu
         let import_path = &caps[1];
 
         // Resolve the path relative to the base_path (directory containing the current file)
-        let resolved_path = base_path.join(import_path);
+        let resolved_path = base_path.join(paths::normalize(import_path));
         let canonical_path = match resolved_path.canonicalize() {
             Ok(p) => p,
             Err(_) => {
                 // File doesn't exist, leave the directive as-is and warn
-                eprintln!("  Warning: @import file not found: {}", resolved_path.display());
+                warning!("@import file not found: {}", resolved_path.display());
                 continue;
             }
         };
 
         // Check for circular imports
         if visited.contains(&canonical_path) {
-            eprintln!("  Warning: Circular @import detected, skipping: {}", canonical_path.display());
+            warning!("Circular @import detected, skipping: {}", canonical_path.display());
             continue;
         }
 
@@ -696,14 +1633,15 @@ fn process_imports(content: &str, base_path: &Path, visited: &mut HashSet<PathBu
                 c.strip_prefix('\u{FEFF}').unwrap_or(&c).to_string()
             },
             Err(e) => {
-                eprintln!("  Warning: Failed to read @import file {}: {}", canonical_path.display(), e);
+                warning!("Failed to read @import file {}: {}", canonical_path.display(), e);
                 continue;
             }
         };
 
         // Recursively process imports in the imported content
-        // Use the directory of the imported file as the new base path
-        let import_base_path = canonical_path.parent().unwrap_or(base_path);
+        // Use the directory of the imported file as the new base path (where
+        // it is in the book: a symlinked file's imports resolve from the link)
+        let import_base_path = resolved_path.parent().unwrap_or(base_path);
         let processed_content = process_imports(&imported_content, import_base_path, visited)?;
 
         // Calculate the adjusted positions accounting for previous replacements
@@ -894,10 +1832,6 @@ fn copy_gitbook_static_to_root(output: &Path) -> Result<()> {
 
     fs::write(gitbook_dir.join("style.css"), style_css)?;
 
-    // Create images directory with placeholder favicon
-    let images_dir = gitbook_dir.join("images");
-    fs::create_dir_all(&images_dir)?;
-
     Ok(())
 }
 
@@ -1120,4 +2054,879 @@ After code block: {{ book.version }}"#;
         assert!(!re.is_match(r#"@import("file.md")"#)); // No HTML comment
         assert!(!re.is_match(r#"<!-- @import('file.md') -->"#)); // Single quotes
     }
+
+    fn write_minimal_book(dir: &Path) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("README.md"), "# Book").unwrap();
+        fs::write(dir.join("SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n").unwrap();
+    }
+
+    #[test]
+    fn test_build_atomic_removes_stale_and_keeps_hidden_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        let output = source.join("_book");
+        write_minimal_book(&source);
+        fs::create_dir_all(output.join("old")).unwrap();
+        fs::create_dir_all(output.join(".git")).unwrap();
+        fs::write(output.join("old/renamed.html"), "stale").unwrap();
+        fs::write(output.join(".nojekyll"), "").unwrap();
+
+        build_atomic(&source, &output, &BuildOptions { clean: true, ..Default::default() }).unwrap();
+        assert!(!output.join("old").exists());
+        assert!(output.join("index.html").exists());
+        assert!(output.join(".git").is_dir());
+        assert!(output.join(".nojekyll").exists());
+        assert!(!source.join("._book.staging").exists());
+        assert!(!source.join("._book.previous").exists());
+    }
+
+    #[test]
+    fn test_build_atomic_copies_hidden_asset_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        let output = source.join("_book");
+        write_minimal_book(&source);
+        // GitBook keeps uploaded images in .gitbook/assets/
+        fs::create_dir_all(source.join(".gitbook/assets")).unwrap();
+        fs::write(source.join(".gitbook/assets/x.png"), "png").unwrap();
+        fs::create_dir_all(source.join("._book.previous/assets")).unwrap();
+
+        build_atomic(&source, &output, &BuildOptions { clean: true, ..Default::default() }).unwrap();
+        assert!(output.join(".gitbook/assets/x.png").is_file());
+        assert!(!output.join("._book.previous").exists());
+        assert!(!output.join("._book.staging").exists());
+    }
+
+    #[test]
+    fn test_build_applies_defaults_of_the_chapter_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        let output = dir.path().join("site");
+        write_minimal_book(&source);
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n* [Start](guide/start.md)\n").unwrap();
+        fs::create_dir_all(source.join("guide")).unwrap();
+        fs::write(source.join("guide/start.md"), "# Start").unwrap();
+        fs::write(source.join("guide/_defaults.yaml"), "description: From the guide defaults\n").unwrap();
+
+        build_atomic(&source, &output, &BuildOptions { clean: true, ..Default::default() }).unwrap();
+        let start = fs::read_to_string(output.join("guide/start.html")).unwrap();
+        assert!(start.contains(r#"<meta name="description" content="From the guide defaults">"#));
+        let intro = fs::read_to_string(output.join("index.html")).unwrap();
+        assert!(!intro.contains("From the guide defaults"));
+    }
+
+    #[test]
+    fn test_build_lists_precompressed_copies_in_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        let output = dir.path().join("site");
+        write_minimal_book(&source);
+        fs::write(source.join("book.json"), r#"{"precompress": true}"#).unwrap();
+
+        build_atomic(&source, &output, &BuildOptions { clean: true, ..Default::default() }).unwrap();
+        let manifest = fs::read_to_string(output.join(manifest::MANIFEST_FILE)).unwrap();
+        assert!(manifest.contains(r#""index.html.gz""#) && manifest.contains(r#""index.html.br""#));
+        assert!(!output.join("manifest.json.gz").exists());
+    }
+
+    #[test]
+    fn test_build_atomic_no_clean_keeps_previous_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        let output = dir.path().join("site");
+        write_minimal_book(&source);
+        fs::create_dir_all(output.join("gitbook")).unwrap();
+        fs::write(output.join("gitbook/extra.css"), "keep").unwrap();
+
+        build_atomic(&source, &output, &BuildOptions::default()).unwrap();
+        assert!(output.join("gitbook/extra.css").exists());
+        assert!(output.join("gitbook/gitbook.css").exists());
+    }
+
+    #[test]
+    fn test_build_atomic_failure_leaves_output_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        let output = dir.path().join("site");
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&output).unwrap();
+        fs::write(output.join("index.html"), "previous").unwrap();
+
+        // No SUMMARY.md: the build fails
+        assert!(build_atomic(&source, &output, &BuildOptions { clean: true, ..Default::default() }).is_err());
+        assert_eq!(fs::read_to_string(output.join("index.html")).unwrap(), "previous");
+        assert!(!dir.path().join(".site.staging").exists());
+    }
+
+    #[test]
+    fn test_build_atomic_refuses_to_clean_source_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        write_minimal_book(dir.path());
+
+        assert!(build_atomic(dir.path(), dir.path(), &BuildOptions { clean: true, ..Default::default() }).is_err());
+        assert!(dir.path().join("README.md").exists());
+    }
+
+    #[test]
+    fn test_incremental_build_keeps_unchanged_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        let output = dir.path().join("site");
+        fs::create_dir_all(source.join("guide")).unwrap();
+        fs::write(source.join("README.md"), "# Intro\n").unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [A](guide/a.md)\n* [B](guide/b.md)\n").unwrap();
+        fs::write(source.join("guide/a.md"), "# A\n\n<!-- @import(\"part.md\") -->\n").unwrap();
+        fs::write(source.join("guide/part.md"), "First\n").unwrap();
+        fs::write(source.join("guide/b.md"), "# B\n").unwrap();
+        let options = BuildOptions { clean: true, cache: Some(cache_path(&output)), ..Default::default() };
+        build_atomic(&source, &output, &options).unwrap();
+
+        // Marks pages that are written again
+        fs::write(output.join("guide/a.html"), "kept").unwrap();
+        fs::write(output.join("guide/b.html"), "kept").unwrap();
+        fs::write(source.join("guide/part.md"), "Second\n").unwrap();
+        build_atomic(&source, &output, &options).unwrap();
+        assert!(fs::read_to_string(output.join("guide/a.html")).unwrap().contains("Second"));
+        assert_eq!(fs::read_to_string(output.join("guide/b.html")).unwrap(), "kept");
+        let index = fs::read_to_string(output.join("search_index.json")).unwrap();
+        assert!(index.contains("\"title\":\"B\""), "{}", index);
+
+        // Anything else the pages share renders them all
+        fs::write(source.join("book.json"), r#"{"title": "Renamed"}"#).unwrap();
+        build_atomic(&source, &output, &options).unwrap();
+        assert!(fs::read_to_string(output.join("guide/b.html")).unwrap().contains("Renamed"));
+
+        // A build without the cache drops it
+        build_atomic(&source, &output, &BuildOptions { clean: true, ..Default::default() }).unwrap();
+        assert!(!cache_path(&output).exists());
+    }
+
+    #[test]
+    fn test_output_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("_book");
+        assert!(output_artifacts(&output).is_empty());
+
+        fs::create_dir_all(&output).unwrap();
+        fs::create_dir_all(dir.path().join("._book.staging")).unwrap();
+        assert_eq!(output_artifacts(&output), vec![output.clone(), dir.path().join("._book.staging")]);
+    }
+
+    #[test]
+    fn test_reproducible_builds_are_identical() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::create_dir_all(source.join("images")).unwrap();
+        fs::write(source.join("images/logo.png"), "png").unwrap();
+
+        let options = BuildOptions { reproducible: true, ..Default::default() };
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        build_with_options(&source, &first, &options).unwrap();
+        build_with_options(&source, &second, &options).unwrap();
+
+        let logo = fs::symlink_metadata(first.join("images/logo.png")).unwrap();
+        assert!(logo.file_type().is_file());
+        for entry in walkdir::WalkDir::new(&first) {
+            let entry = entry.unwrap();
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let other = second.join(entry.path().strip_prefix(&first).unwrap());
+            assert_eq!(fs::read(entry.path()).unwrap(), fs::read(&other).unwrap());
+            assert_eq!(
+                entry.metadata().unwrap().modified().unwrap(),
+                fs::metadata(&other).unwrap().modified().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_merged_summary_entry_builds_one_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n* [API](api/*.md \"merge\")\n").unwrap();
+        fs::create_dir_all(source.join("api/img")).unwrap();
+        fs::write(source.join("api/auth.md"), "# Auth\n\nSee [users](./users.md#list).\n").unwrap();
+        fs::write(source.join("api/users.md"), "# Users\n\n## List\n\n![diagram](img/users.png)\n").unwrap();
+        fs::write(source.join("README.md"), "# Book\n\n[Users](api/users.md)\n").unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+
+        assert!(!output.join("api/users.html").exists());
+        let page = fs::read_to_string(output.join("api/auth.html")).unwrap();
+        assert!(page.contains("<h1 id=\"auth\">Auth</h1>"));
+        assert!(page.contains("<h2 id=\"list\">List</h2>"));
+        assert!(page.contains("href=\"auth.html#list\""));
+        assert!(page.contains("src=\"img/users.png\""));
+        assert!(page.contains("href=\"#list\""), "combined TOC lists headings of all files");
+        let index = fs::read_to_string(output.join("index.html")).unwrap();
+        assert!(index.contains("href=\"api/auth.html\""));
+    }
+
+    #[test]
+    fn test_page_template_receives_front_matter() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Get user](get-user.md)\n").unwrap();
+        fs::create_dir_all(source.join("_templates")).unwrap();
+        fs::write(source.join("_templates/api-endpoint.md"), "# {{ page.title }}\n\n`{{ page.method }} {{ page.path }}`\n\n{{ content }}\n").unwrap();
+        fs::write(
+            source.join("get-user.md"),
+            "---\ntitle: Get user\ntemplate: api-endpoint\nmethod: GET\npath: /users/{id}\n---\nReturns one user.\n",
+        )
+        .unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        let page = fs::read_to_string(output.join("get-user.html")).unwrap();
+        assert!(page.contains("<h1 id=\"get-user\">Get user</h1>"), "{}", page);
+        assert!(page.contains("<code>GET /users/{id}</code>"));
+        assert!(page.contains("<p>Returns one user.</p>"));
+    }
+
+    #[test]
+    fn test_build_with_alternate_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::write(source.join("README.md"), "# {{ book.edition }}").unwrap();
+        fs::write(source.join("book.json"), r#"{"title": "Book", "variables": {"edition": "Production"}}"#).unwrap();
+        fs::write(source.join("book.staging.json"), r#"{"title": "Book", "variables": {"edition": "Staging"}}"#).unwrap();
+
+        let output = dir.path().join("out");
+        let options = BuildOptions { config_file: Some(source.join("book.staging.json")), ..Default::default() };
+        build_with_options(&source, &output, &options).unwrap();
+        let index = fs::read_to_string(output.join("index.html")).unwrap();
+        assert!(index.contains(">Staging</h1>"), "{}", index);
+    }
+
+    #[test]
+    fn test_build_defines_override_variables() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::write(source.join("README.md"), "# {{ book.edition }} at {{ book.api_host }}").unwrap();
+        fs::write(source.join("book.json"), r#"{"title": "Book", "variables": {"edition": "Production"}}"#).unwrap();
+
+        let output = dir.path().join("out");
+        let variables = vec![
+            ("edition".to_string(), "Staging".to_string()),
+            ("api_host".to_string(), "staging.example.com".to_string()),
+        ];
+        build_with_options(&source, &output, &BuildOptions { variables, ..Default::default() }).unwrap();
+        let index = fs::read_to_string(output.join("index.html")).unwrap();
+        assert!(index.contains(">Staging at staging.example.com</h1>"), "{}", index);
+    }
+
+    #[test]
+    fn test_build_base_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::create_dir_all(source.join("guide")).unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n* [Start](guide/start.md)\n").unwrap();
+        fs::write(source.join("guide/start.md"), "# Start\n\n[Home](/README.md) ![shot](shot.png)\n").unwrap();
+        fs::write(source.join("book.json"), r#"{"baseUrl": "/docs/book"}"#).unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        let page = fs::read_to_string(output.join("guide/start.html")).unwrap();
+        assert!(page.contains(r#"href="/docs/book/gitbook/gitbook.css""#), "{}", page);
+        assert!(page.contains(r#"src="/docs/book/gitbook/search.js""#), "{}", page);
+        assert!(page.contains(r#"data-root-path="/docs/book/""#), "{}", page);
+        assert!(page.contains(r#"<a href="/docs/book/README.html">Home</a>"#), "{}", page);
+        assert!(page.contains(r#"src="/docs/book/guide/shot.png""#), "{}", page);
+        assert!(!page.contains("\"../"), "{}", page);
+
+        // --base-url overrides book.json; empty keeps links relative
+        let options = BuildOptions { base_url: Some(String::new()), ..Default::default() };
+        build_with_options(&source, &output, &options).unwrap();
+        let page = fs::read_to_string(output.join("guide/start.html")).unwrap();
+        assert!(page.contains(r#"href="../gitbook/gitbook.css""#), "{}", page);
+    }
+
+    #[test]
+    fn test_build_default_language_at_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        for code in ["en", "jp"] {
+            fs::create_dir_all(source.join(code).join("guide")).unwrap();
+            fs::write(source.join(code).join("SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n* [Start](guide/start.md)\n").unwrap();
+            fs::write(source.join(code).join("README.md"), format!("# Intro {}\n", code)).unwrap();
+            fs::write(source.join(code).join("guide/start.md"), "# Start\n\n[Home](/README.md)\n").unwrap();
+        }
+        fs::write(source.join("LANGS.md"), "# Languages\n\n* [English](en/)\n* [日本語](jp/)\n").unwrap();
+        fs::write(source.join("book.json"), r#"{"languages": {"default": "en", "paths": {"jp": "ja"}}}"#).unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        assert!(fs::read_to_string(output.join("index.html")).unwrap().contains("Intro en"));
+        assert!(fs::read_to_string(output.join("ja/index.html")).unwrap().contains("Intro jp"));
+        assert!(!output.join("jp").exists());
+        assert!(output.join("search_index.json").is_file() && output.join("ja/search_index.json").is_file());
+        let page = fs::read_to_string(output.join("guide/start.html")).unwrap();
+        assert!(page.contains(r#"<a href="../README.html">Home</a>"#), "{}", page);
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output.join(manifest::MANIFEST_FILE)).unwrap()).unwrap();
+        assert_eq!(manifest["files"]["ja/guide/start.html"]["source"], "jp/guide/start.md");
+        assert_eq!(manifest["files"]["guide/start.html"]["source"], "en/guide/start.md");
+
+        // The language's old URLs redirect to the root
+        let redirect = fs::read_to_string(output.join("en/guide/start.html")).unwrap();
+        assert!(redirect.contains(r#"<meta http-equiv="refresh" content="0; url=../../guide/start.html">"#), "{}", redirect);
+
+        fs::write(source.join("book.json"), r#"{"languages": {"default": "fr"}}"#).unwrap();
+        let err = build_with_options(&source, &output, &BuildOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("fr is not a language"), "{}", err);
+    }
+
+    #[test]
+    fn test_build_shared_chapters() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        fs::create_dir_all(source.join("en/api/img")).unwrap();
+        fs::create_dir_all(source.join("ja")).unwrap();
+        fs::write(source.join("LANGS.md"), "# Languages\n\n* [English](en/)\n* [日本語](ja/)\n").unwrap();
+        fs::write(source.join("book.json"), r#"{"languages": {"default": "en"}}"#).unwrap();
+        fs::write(source.join("en/SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n* [API](api/ref.md)\n* [Errors](api/errors.md)\n").unwrap();
+        fs::write(source.join("en/README.md"), "# Intro\n").unwrap();
+        fs::write(source.join("en/api/ref.md"), "# API\n\n<!-- @import(\"part.md\") -->\n\n[Errors](/api/errors.md) [Intro](/README.md)\n").unwrap();
+        fs::write(source.join("en/api/part.md"), "![Flow](img/flow.png)").unwrap();
+        fs::write(source.join("en/api/img/flow.png"), "png").unwrap();
+        fs::write(source.join("en/api/errors.md"), "# Errors\n").unwrap();
+        fs::write(source.join("ja/SUMMARY.md"), "# Summary\n\n* [はじめに](README.md)\n* [API](../en/api/ref.md)\n").unwrap();
+        fs::write(source.join("ja/README.md"), "# はじめに\n").unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        let page = fs::read_to_string(output.join("ja/api/ref.html")).unwrap();
+        assert!(page.contains(r#"<img src="img/flow.png" alt="Flow" />"#), "{}", page);
+        assert!(page.contains(r#"<a href="../../api/errors.html">Errors</a>"#), "{}", page);
+        assert!(page.contains(r#"<a href="../README.html">Intro</a>"#), "{}", page);
+        assert!(output.join("ja/api/img/flow.png").is_file());
+        // The sidebar lists the chapter where it is published
+        let index = fs::read_to_string(output.join("ja/index.html")).unwrap();
+        assert!(index.contains(r#"<a href="./api/ref.html">API</a>"#), "{}", index);
+        assert!(output.join("api/ref.html").is_file());
+    }
+
+    #[test]
+    fn test_build_publishes_media() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::create_dir_all(source.join("guide/media")).unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n* [Start](guide/start.md)\n").unwrap();
+        fs::write(source.join("guide/start.md"), "# Start\n\n![Demo](media/demo.mp4)\n").unwrap();
+        fs::write(source.join("guide/media/demo.mp4"), "video").unwrap();
+        fs::write(source.join("guide/media/demo.jpg"), "poster").unwrap();
+        fs::write(source.join("book.json"), r#"{"baseUrl": "/docs"}"#).unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        let page = fs::read_to_string(output.join("guide/start.html")).unwrap();
+        assert!(page.contains(r#"src="/docs/guide/media/demo.mp4" aria-label="Demo" poster="/docs/guide/media/demo.jpg">"#), "{}", page);
+        assert!(output.join("guide/media/demo.mp4").is_file());
+        assert!(output.join("guide/media/demo.jpg").is_file());
+    }
+
+    #[test]
+    fn test_build_file_attachments() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::create_dir_all(source.join("guide")).unwrap();
+        fs::create_dir_all(source.join("downloads")).unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n* [Start](guide/start.md)\n").unwrap();
+        fs::write(source.join("guide/start.md"), "# Start\n\n{% file \"downloads/template.xlsx\" title=\"Report template\" %}\n").unwrap();
+        fs::write(source.join("downloads/template.xlsx"), "xlsx").unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        let page = fs::read_to_string(output.join("guide/start.html")).unwrap();
+        assert!(page.contains(r#"<a href="../downloads/template.xlsx" download data-attachment="../downloads/template.xlsx">"#), "{}", page);
+        assert!(page.contains(r#"<span class="file-card-title">Report template</span><span class="file-card-meta">template.xlsx · 4 B</span>"#), "{}", page);
+        assert_eq!(fs::read_to_string(output.join("downloads/template.xlsx")).unwrap(), "xlsx");
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output.join(manifest::MANIFEST_FILE)).unwrap()).unwrap();
+        assert_eq!(manifest["files"]["downloads/template.xlsx"]["attachedTo"], serde_json::json!(["guide/start.html"]));
+        assert_eq!(manifest["files"]["downloads/template.xlsx"]["source"], "downloads/template.xlsx");
+    }
+
+    #[test]
+    fn test_build_only_renders_matching_chapters() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::create_dir_all(source.join("guide")).unwrap();
+        fs::write(
+            source.join("SUMMARY.md"),
+            "# Summary\n\n* [Intro](README.md)\n* [Guide](guide/start.md)\n    * [Install](guide/install.md)\n* [FAQ](faq.md)\n",
+        )
+        .unwrap();
+        for file in ["guide/start.md", "guide/install.md", "faq.md"] {
+            fs::write(source.join(file), "# Page\n").unwrap();
+        }
+
+        let output = dir.path().join("out");
+        let options = BuildOptions { only: Some("./guide/*.md".to_string()), ..Default::default() };
+        build_with_options(&source, &output, &options).unwrap();
+        assert!(output.join("guide/start.html").is_file());
+        assert!(output.join("guide/install.html").is_file());
+        assert!(!output.join("faq.html").exists());
+        assert!(!output.join("index.html").exists());
+        // The sidebar still lists every chapter
+        let page = fs::read_to_string(output.join("guide/install.html")).unwrap();
+        assert!(page.contains("faq.html"), "{}", page);
+
+        let options = BuildOptions { only: Some("missing.md".to_string()), ..Default::default() };
+        let err = build_with_options(&source, &dir.path().join("out2"), &options).unwrap_err();
+        assert!(err.to_string().contains("matches no chapter"), "{}", err);
+    }
+
+    #[test]
+    fn test_windows_separators_in_book_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::create_dir_all(source.join("guide/setup")).unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n* [Install](.\\guide\\setup\\install.md)\n").unwrap();
+        fs::write(
+            source.join("guide/setup/install.md"),
+            "# Install\n\n<!-- @import(\"..\\parts\\note.md\") -->\n\n[Intro](README.md) [Notes](..\\parts\\note.md) ![logo](C:\\images\\logo.png)\n",
+        )
+        .unwrap();
+        fs::create_dir_all(source.join("guide/parts")).unwrap();
+        fs::write(source.join("guide/parts/note.md"), "Imported note.\n").unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        let page = fs::read_to_string(output.join("guide/setup/install.html")).unwrap();
+        assert!(page.contains("Imported note."), "{}", page);
+        assert!(page.contains("href=\"../../README.html\""), "{}", page);
+        assert!(page.contains("href=\"../parts/note.html\""), "{}", page);
+        // Drive-letter paths are not book paths
+        assert!(page.contains("src=\"C:/images/logo.png\""), "{}", page);
+    }
+
+    #[test]
+    fn test_exotic_file_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        let long_name = format!("{}.md", "長".repeat(84));
+        fs::create_dir_all(source.join("C#")).unwrap();
+        fs::write(
+            source.join("SUMMARY.md"),
+            format!(
+                "# Summary\n\n* [Intro](README.md)\n* [Notes](<my notes.md>)\n* [Sharp](C%23/intro.md#setup)\n* [Done](100%25.md)\n* [Long]({})\n",
+                long_name
+            ),
+        )
+        .unwrap();
+        fs::write(source.join("my notes.md"), "# Notes\n\n[Sharp](C%23/intro.md)\n").unwrap();
+        fs::write(source.join("C#/intro.md"), "# Sharp\n\n## Setup\n").unwrap();
+        fs::write(source.join("100%.md"), "# Done\n").unwrap();
+        fs::write(source.join(&long_name), "# Long\n").unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        let page = fs::read_to_string(output.join("my notes.html")).unwrap();
+        assert!(page.contains("href=\"C%23/intro.html\""), "{}", page);
+        assert!(page.contains("href=\"./C%23/intro.html#setup\""), "{}", page);
+        assert!(page.contains("href=\"./100%25.html\""), "{}", page);
+        assert!(page.contains("href=\"./my%20notes.html\""), "{}", page);
+        assert!(output.join("C#/intro.html").is_file());
+        assert!(output.join("100%.html").is_file());
+
+        // A name too long with .html is shortened the same way everywhere
+        let long_html = default_html_path(&long_name);
+        assert!(long_html.len() <= 255 && long_html.ends_with(".html"), "{}", long_html);
+        assert!(output.join(&long_html).is_file());
+        assert!(page.contains(&paths::encode_href(&long_html)), "{}", page);
+
+        let index = fs::read_to_string(output.join("search_index.json")).unwrap();
+        assert!(index.contains("\"path\":\"C%23/intro.html\""), "{}", index);
+    }
+
+    #[test]
+    fn test_search_index_uses_rendered_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::write(source.join("book.json"), r#"{"variables": {"product": "Widgetron"}}"#).unwrap();
+        fs::write(
+            source.join("SUMMARY.md"),
+            "# Summary\n\n* [Intro](README.md)\n* [Guide](guide.md)\n* [Guide again](guide.md#more)\n",
+        )
+        .unwrap();
+        fs::write(source.join("guide.md"), "# Guide\n\n<!-- @import(\"snippet.md\") -->\n\n{{ book.product }} setup\n").unwrap();
+        fs::write(source.join("snippet.md"), "Imported snippet text\n").unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        let index: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output.join("search_index.json")).unwrap()).unwrap();
+        let entries = index.as_array().unwrap();
+        assert_eq!(entries.len(), 3, "{}", index);
+        assert_eq!(entries[0]["path"], "index.html");
+        let guide = entries.iter().find(|e| e["path"] == "guide.html").unwrap();
+        let content = guide["content"].as_str().unwrap();
+        assert!(content.contains("Imported snippet text"), "{}", content);
+        assert!(content.contains("Widgetron setup"), "{}", content);
+    }
+
+    #[test]
+    fn test_search_entries_carry_their_part() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::write(
+            source.join("SUMMARY.md"),
+            "# Summary\n\n* [Intro](README.md)\n\n## Basics\n\n* [Setup](setup.md)\n    * [Install](install.md)\n\n## Reference\n\n* [API](api.md)\n",
+        )
+        .unwrap();
+        for page in ["setup", "install", "api"] {
+            fs::write(source.join(format!("{}.md", page)), format!("# {}\n", page)).unwrap();
+        }
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        let index: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(output.join("search_index.json")).unwrap()).unwrap();
+        let parts: Vec<(&str, Option<&str>)> =
+            index.as_array().unwrap().iter().map(|e| (e["path"].as_str().unwrap(), e["part"].as_str())).collect();
+        assert_eq!(
+            parts,
+            vec![
+                ("index.html", None),
+                ("README.html", None),
+                ("setup.html", Some("Basics")),
+                ("install.html", Some("Basics")),
+                ("api.html", Some("Reference")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_noindex_pages_and_robots_txt() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::write(source.join("book.json"), r#"{"robots": {"disallow": ["/drafts/"]}}"#).unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Draft](draft.md)\n* [Guide](guide.md)\n").unwrap();
+        fs::write(source.join("draft.md"), "---\nrobots: noindex, nofollow\n---\n\n# Draft\n").unwrap();
+        fs::write(source.join("guide.md"), "# Guide\n").unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        let draft = fs::read_to_string(output.join("draft.html")).unwrap();
+        assert!(draft.contains(r#"<meta name="robots" content="noindex, nofollow">"#), "{}", draft);
+        assert!(!fs::read_to_string(output.join("guide.html")).unwrap().contains(r#"name="robots""#));
+        let index = fs::read_to_string(output.join("search_index.json")).unwrap();
+        assert!(index.contains("guide.html") && !index.contains("draft.html"), "{}", index);
+        assert_eq!(fs::read_to_string(output.join("robots.txt")).unwrap(), "User-agent: *\nDisallow: /drafts/\n");
+    }
+
+    #[test]
+    fn test_last_updated_and_sitemap() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::write(source.join("book.json"), r#"{"lastUpdated": true, "deploy": {"siteUrl": "https://docs.example.com"}}"#).unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Draft](draft.md)\n* [Guide](guide.md)\n").unwrap();
+        fs::write(source.join("draft.md"), "---\nrobots: noindex\n---\n\n# Draft\n").unwrap();
+        fs::write(source.join("guide.md"), "# Guide\n").unwrap();
+        // Outside a git repository, modification times are used
+        let march = std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_546_400);
+        fs::File::options().write(true).open(source.join("guide.md")).unwrap().set_modified(march).unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        let guide = fs::read_to_string(output.join("guide.html")).unwrap();
+        assert!(guide.contains(r#"Last updated: <time datetime="2024-03-04">March 4, 2024</time>"#), "{}", guide);
+        let sitemap = fs::read_to_string(output.join("sitemap.xml")).unwrap();
+        assert!(
+            sitemap.contains("<loc>https://docs.example.com/guide.html</loc>\n    <lastmod>2024-03-04</lastmod>"),
+            "{}",
+            sitemap
+        );
+        assert!(sitemap.contains("<loc>https://docs.example.com/</loc>") && !sitemap.contains("draft"), "{}", sitemap);
+    }
+
+    #[test]
+    fn test_fast_mode_skips_serve_passes() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::write(source.join("book.json"), r#"{"externalize_svg": true, "serve": {"fastMode": ["svg"]}}"#).unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Chart](chart.md)\n").unwrap();
+        fs::write(source.join("chart.md"), "# Chart\n\n<svg width=\"10\" height=\"10\"><rect width=\"10\" height=\"10\"/></svg>\n").unwrap();
+
+        let output = dir.path().join("full");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        assert!(!fs::read_to_string(output.join("chart.html")).unwrap().contains("<rect"));
+
+        // Skipped in preview rebuilds only; the search index is written unless listed
+        let output = dir.path().join("fast");
+        build_with_options(&source, &output, &BuildOptions { fast_mode: true, ..Default::default() }).unwrap();
+        assert!(fs::read_to_string(output.join("chart.html")).unwrap().contains("<rect"));
+        assert!(output.join("search_index.json").exists());
+
+        // By default only the search index is skipped
+        fs::write(source.join("book.json"), r#"{"externalize_svg": true}"#).unwrap();
+        let output = dir.path().join("default");
+        build_with_options(&source, &output, &BuildOptions { fast_mode: true, ..Default::default() }).unwrap();
+        assert!(!fs::read_to_string(output.join("chart.html")).unwrap().contains("<rect"));
+        assert!(!output.join("search_index.json").exists());
+    }
+
+    #[test]
+    fn test_build_exports() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Guide](guide.md)\n").unwrap();
+        fs::write(source.join("guide.md"), "# Guide\n").unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions { exports: vec![ExportTarget::Epub], ..Default::default() }).unwrap();
+        assert!(output.join("guide.html").is_file());
+        let epub = fs::read(output.join("book.epub")).unwrap();
+        assert!(epub.starts_with(b"PK"));
+        let manifest = fs::read_to_string(output.join(manifest::MANIFEST_FILE)).unwrap();
+        assert!(manifest.contains("\"book.epub\""), "{}", manifest);
+    }
+
+    #[test]
+    fn test_not_found_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Guide](guide/start.md)\n").unwrap();
+        fs::create_dir_all(source.join("guide")).unwrap();
+        fs::write(source.join("guide/start.md"), "# Start\n").unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        let page = fs::read_to_string(output.join("404.html")).unwrap();
+        assert!(page.contains("<title>Page not found"), "{}", page);
+        assert!(page.contains(r#"href="/gitbook/gitbook.css""#), "{}", page);
+        assert!(page.contains(r#"data-root-path="/""#), "{}", page);
+        assert!(page.contains(r#"href="/guide/start.html""#), "{}", page);
+        assert!(!page.contains("\"./"), "{}", page);
+        let index = fs::read_to_string(output.join("search_index.json")).unwrap();
+        assert!(!index.contains("404.html"), "{}", index);
+
+        // From 404.md, under the path of the site URL
+        fs::write(source.join("book.json"), r#"{"deploy": {"siteUrl": "https://acme.github.io/docs"}}"#).unwrap();
+        fs::write(source.join("404.md"), "---\ntitle: Lost\n---\n\n# Lost?\n\n[Start here](guide/start.md)\n").unwrap();
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        let page = fs::read_to_string(output.join("404.html")).unwrap();
+        assert!(page.contains("<title>Lost") && page.contains("Lost?</h1>"), "{}", page);
+        assert!(page.contains(r#"<a href="/docs/guide/start.html">Start here</a>"#), "{}", page);
+        assert!(page.contains(r#"href="/docs/gitbook/gitbook.css""#), "{}", page);
+    }
+
+    #[test]
+    fn test_index_page_is_built_as_its_chapter() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Welcome](README.md)\n* [Guide](guide.md)\n").unwrap();
+        fs::write(source.join("README.md"), "---\nsplit: h2\n---\n\n# Welcome\n\nIntro\n\n## Setup\n\nSteps\n").unwrap();
+        fs::write(source.join("guide.md"), "# Guide\n").unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        let index = fs::read_to_string(output.join("index.html")).unwrap();
+        assert!(index.contains("<title>Welcome |"), "{}", index);
+        assert!(index.contains(r#"<li class="chapter active"><a href="./README.html">Welcome</a>"#), "{}", index);
+        assert!(index.contains(r#"class="page-nav next" href="./guide.html""#), "{}", index);
+        // Front matter options apply as they do to chapters
+        assert!(!index.contains("Steps"), "{}", index);
+        let section = fs::read_to_string(output.join("index-setup.html")).unwrap();
+        assert!(section.contains("Steps"), "{}", section);
+    }
+
+    #[test]
+    fn test_directory_readme_is_section_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::create_dir_all(source.join("guide")).unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Guide](guide/)\n    * [Start](guide/start.md)\n").unwrap();
+        fs::write(source.join("guide/README.md"), "# Guide\n\n[Start](guide/start.md)\n").unwrap();
+        fs::write(source.join("guide/start.md"), "# Start\n\n[Back](guide/README.md)\n").unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        assert!(!output.join("guide/README.html").exists());
+        let index = fs::read_to_string(output.join("guide/index.html")).unwrap();
+        assert!(index.contains(r#"<li class="chapter active expandable expanded"><a href="../guide/index.html">Guide</a>"#), "{}", index);
+        assert!(index.contains(r#"<a href="../guide/start.html">Start</a>"#), "{}", index);
+        let start = fs::read_to_string(output.join("guide/start.html")).unwrap();
+        assert!(start.contains(r#"<a href="index.html">Back</a>"#), "{}", start);
+    }
+
+    #[test]
+    fn test_redirect_from() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::create_dir_all(source.join("guide")).unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Start](guide/start.md)\n* [API](api.md)\n").unwrap();
+        fs::write(source.join("guide/start.md"), "---\nredirect_from:\n  - /setup.html\n  - old/install/\n  - api.md\n---\n\n# Start\n").unwrap();
+        fs::write(source.join("api.md"), "---\nredirect_from: ../outside.html\n---\n\n# API\n").unwrap();
+
+        let output = dir.path().join("out");
+        build_with_options(&source, &output, &BuildOptions::default()).unwrap();
+        let stub = fs::read_to_string(output.join("setup.html")).unwrap();
+        assert!(stub.contains(r#"<meta http-equiv="refresh" content="0; url=./guide/start.html">"#), "{}", stub);
+        assert!(stub.contains(r#"<link rel="canonical" href="./guide/start.html">"#), "{}", stub);
+        let stub = fs::read_to_string(output.join("old/install/index.html")).unwrap();
+        assert!(stub.contains(r#"url=../../guide/start.html""#), "{}", stub);
+        // Pages are never replaced
+        assert!(fs::read_to_string(output.join("api.html")).unwrap().contains("API</h1>"));
+        assert!(!dir.path().join("outside.html").exists());
+    }
+
+    #[test]
+    fn test_search_index_has_heading_sections() {
+        let html = "<h1 id=\"guide\">Guide</h1>\n<p>Intro text</p>\n<h2 id=\"set-up\">Set <em>up</em></h2>\n<p>Run it</p>\n";
+        let entries = page_search_entries("The Guide", "my guide.html", html);
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].path.as_str(), entries[0].section.as_deref()), ("my%20guide.html", None));
+        assert_eq!(entries[0].content, "Guide Intro text");
+        assert_eq!(entries[1].path, "my%20guide.html#set-up");
+        assert_eq!(entries[1].section.as_deref(), Some("Set up"));
+        assert_eq!(entries[1].content, "Set up Run it");
+
+        // Text before the first heading stays with the page
+        let entries = page_search_entries("Page", "p.html", "<p>Lead</p>\n<h2 id=\"a\">A</h2>\n<p>x</p>");
+        assert_eq!(entries.iter().map(|e| e.content.as_str()).collect::<Vec<_>>(), vec!["Lead", "A x"]);
+    }
+
+    #[test]
+    fn test_case_only_collisions_fail_the_build() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::create_dir_all(source.join("guide")).unwrap();
+        fs::write(source.join("guide/Setup.md"), "# Setup\n").unwrap();
+        fs::write(source.join("guide/setup.md"), "# setup\n").unwrap();
+        fs::write(source.join("Index.md"), "# Index\n").unwrap();
+        fs::write(
+            source.join("SUMMARY.md"),
+            "# Summary\n\n* [Intro](README.md)\n* [Setup](guide/Setup.md)\n* [setup](guide/setup.md)\n* [Index](Index.md)\n",
+        )
+        .unwrap();
+
+        let output = dir.path().join("out");
+        let error = build_with_options(&source, &output, &BuildOptions::default()).unwrap_err().to_string();
+        assert!(error.contains("guide/Setup.html (from guide/Setup.md), guide/setup.html (from guide/setup.md)"), "{}", error);
+        assert!(error.contains("Index.html (from Index.md), index.html (from README.md)"), "{}", error);
+        assert!(!output.join("guide").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_sources_are_followed() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let vault = dir.path().join("vault");
+        fs::create_dir_all(vault.join("notes")).unwrap();
+        fs::create_dir_all(vault.join("images")).unwrap();
+        fs::write(vault.join("notes/tips.md"), "# Tips\n\n[Guide](../guide.md)\n").unwrap();
+        fs::write(vault.join("shared.md"), "Shared <!-- @import(\"local.md\") -->\n").unwrap();
+        fs::write(vault.join("images/logo.png"), "png").unwrap();
+
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        fs::create_dir_all(source.join("parts")).unwrap();
+        symlink(vault.join("notes"), source.join("notes")).unwrap();
+        symlink(vault.join("images"), source.join("images")).unwrap();
+        symlink(vault.join("shared.md"), source.join("parts/shared.md")).unwrap();
+        // Nested imports resolve from the link, not from the vault
+        fs::write(source.join("parts/local.md"), "from the book").unwrap();
+        symlink(source.join("missing.png"), vault.join("images/broken.png")).unwrap();
+        symlink(&vault, vault.join("notes/loop")).unwrap();
+        fs::write(source.join("guide.md"), "# Guide\n\n<!-- @import(\"parts/shared.md\") -->\n").unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Guide](guide.md)\n* [Notes](notes/*.md)\n").unwrap();
+
+        let output = dir.path().join("out");
+        logging::reset_warnings();
+        let options = BuildOptions { reproducible: true, ..Default::default() };
+        build_with_options(&source, &output, &options).unwrap();
+        let guide = fs::read_to_string(output.join("guide.html")).unwrap();
+        assert!(guide.contains("Shared from the book"), "{}", guide);
+        let tips = fs::read_to_string(output.join("notes/tips.html")).unwrap();
+        assert!(tips.contains("href=\"../guide.html\""), "{}", tips);
+        assert!(output.join("images/logo.png").is_file());
+        assert!(!output.join("images/broken.png").exists());
+        let warnings: Vec<String> = logging::warnings().into_iter().map(|w| w.message).collect();
+        assert!(warnings.iter().any(|w| w.contains("broken.png")), "{:?}", warnings);
+        assert!(warnings.iter().any(|w| w.contains("loop")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn test_strict_build_fails_on_warnings() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        write_minimal_book(&source);
+        let output = dir.path().join("out");
+        let options = BuildOptions { strict: true, ..Default::default() };
+        build_atomic(&source, &output, &options).unwrap();
+
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n* [Gone](gone.md)\n* [Guide](guide.md)\n").unwrap();
+        fs::write(source.join("guide.md"), "# Guide\n\n<!-- @import(\"missing.md\") -->\n").unwrap();
+        let error = build_atomic(&source, &output, &options).unwrap_err().to_string();
+        assert!(error.starts_with("2 warnings in strict mode:"), "{}", error);
+        assert!(error.contains("\n  gone.md not found"), "{}", error);
+        assert!(error.contains("\n  guide.md: @import file not found"), "{}", error);
+        // The previous output is kept
+        assert!(output.join("index.html").is_file());
+        assert!(!output.join("guide.html").exists());
+
+        build_atomic(&source, &output, &BuildOptions::default()).unwrap();
+        assert!(output.join("guide.html").is_file());
+    }
+
+    #[test]
+    fn test_page_context_carries_language_and_position() {
+        // The registry is global: only touch this test's page while other tests build books
+        hooks::register_postprocessor("page-context-test", 0, |html: &str, page: &hooks::PageContext| {
+            let Some(position) = page.position.as_ref().filter(|_| page.source_path == "guide/context-test.md") else {
+                return html.to_string();
+            };
+            let parents: Vec<&str> = position.parents.iter().map(|p| p.title.as_str()).collect();
+            format!("{}<!-- {:?} {}/{} {} -->", html, page.language, position.index, position.total, parents.join(" > "))
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        fs::create_dir_all(source.join("en/guide")).unwrap();
+        fs::write(source.join("book.json"), "{}").unwrap();
+        fs::write(source.join("LANGS.md"), "# Languages\n\n* [English](en/)\n").unwrap();
+        fs::write(source.join("en/SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n* Guide\n    * [Context](guide/context-test.md)\n").unwrap();
+        fs::write(source.join("en/README.md"), "# Intro\n").unwrap();
+        fs::write(source.join("en/guide/context-test.md"), "# Context\n").unwrap();
+
+        let output = dir.path().join("out");
+        let result = build_with_options(&source, &output, &BuildOptions::default());
+        hooks::unregister_postprocessor("page-context-test");
+        result.unwrap();
+
+        let page = fs::read_to_string(output.join("en/guide/context-test.html")).unwrap();
+        assert!(page.ends_with("<!-- Some(\"en\") 1/2 Guide -->"), "{}", page);
+        assert!(page.contains("README.html\" title=\"Intro\""), "prev link to the first chapter");
+    }
 }
@@ -29,12 +29,31 @@
 //! {{ value | lower }}
 //! {{ value | default("fallback") }}
 //! ```
+//!
+//! Locale-aware `localdate`, `localnumber` and `localsort` filters follow the
+//! book's language (see the `locale` module).
+//!
+//! ### Raw blocks
+//! ```text
+//! {% raw %}
+//!   {{ this is output literally }}
+//! {% endraw %}
+//! ```
 
+use super::locale;
 use crate::parser::BookConfig;
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Result};
 use regex::Regex;
+use std::cell::RefCell;
+use std::error::Error as StdError;
 use tera::{Context as TeraContext, Tera};
 
+/// Name under which page content is registered with Tera
+const TEMPLATE_NAME: &str = "__content__";
+
+/// Number of lines shown before and after the failing line in error excerpts
+const EXCERPT_CONTEXT_LINES: usize = 2;
+
 /// Process Nunjucks templates in Markdown content
 ///
 /// This function replaces the simple `expand_variables()` approach with full Tera template
@@ -48,22 +67,9 @@ use tera::{Context as TeraContext, Tera};
 /// # Returns
 /// * `Ok(String)` - Processed content with templates rendered
 /// * `Err` - Template parsing or rendering error with location info
+#[allow(dead_code)]
 pub fn process_nunjucks_templates(content: &str, config: &BookConfig) -> Result<String> {
-    // Fast path: if no template syntax detected, return as-is
-    if !has_template_syntax(content) {
-        return Ok(content.to_string());
-    }
-
-    // Find protected regions (code blocks) to exclude from template processing
-    let protected_regions = find_protected_regions(content);
-
-    // If content has protected regions, we need to handle them specially
-    if !protected_regions.is_empty() {
-        return process_with_protected_regions(content, config, &protected_regions);
-    }
-
-    // No protected regions, process the entire content
-    render_template(content, config)
+    ContentTemplates::new(config).process(content, None, 0)
 }
 
 /// Check if content contains any Nunjucks template syntax
@@ -72,68 +78,182 @@ fn has_template_syntax(content: &str) -> bool {
     content.contains("{{") || content.contains("{%")
 }
 
-/// Find all protected regions in the content (fenced code blocks)
+/// A region of content that is copied through without template processing
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct ProtectedRegion {
+    pub(super) start: usize,
+    pub(super) end: usize,
+    /// Byte range of the region that is emitted (raw blocks drop their tags)
+    keep: (usize, usize),
+}
+
+/// Find all protected regions in the content (fenced code blocks and raw blocks)
 /// These regions should not have template processing applied
-fn find_protected_regions(content: &str) -> Vec<(usize, usize)> {
-    let mut regions = Vec::new();
+pub(super) fn find_protected_regions(content: &str) -> Vec<ProtectedRegion> {
+    ProtectedPatterns::new().find(content)
+}
 
-    // Find fenced code blocks (``` ... ```)
-    // Use a more robust approach that handles multi-line content
-    let fenced_re = Regex::new(r"(?m)^```[^\n]*\n[\s\S]*?^```").unwrap();
-    for m in fenced_re.find_iter(content) {
-        regions.push((m.start(), m.end()));
+/// Patterns of the protected regions, compiled once per book
+struct ProtectedPatterns {
+    /// Fenced code blocks (``` ... ```)
+    fenced: Regex,
+    /// {% raw %} ... {% endraw %} blocks
+    raw: Regex,
+}
+
+impl ProtectedPatterns {
+    fn new() -> Self {
+        ProtectedPatterns {
+            // Use a more robust approach that handles multi-line content
+            fenced: Regex::new(r"(?m)^```[^\n]*\n[\s\S]*?^```").unwrap(),
+            raw: Regex::new(r"(?s)\{%-?\s*raw\s*-?%\}(.*?)\{%-?\s*endraw\s*-?%\}").unwrap(),
+        }
     }
 
-    // Also handle indented code blocks (4 spaces or tab at start)
-    // These are less common but should be protected too
-    // Note: This is a simplified check; full markdown parsing would be more accurate
+    fn find(&self, content: &str) -> Vec<ProtectedRegion> {
+        let mut candidates = Vec::new();
 
-    regions
+        // Find fenced code blocks (``` ... ```)
+        for m in self.fenced.find_iter(content) {
+            candidates.push(ProtectedRegion {
+                start: m.start(),
+                end: m.end(),
+                keep: (m.start(), m.end()),
+            });
+        }
+
+        // Find {% raw %} ... {% endraw %} blocks; their body is emitted literally
+        for caps in self.raw.captures_iter(content) {
+            let whole = caps.get(0).unwrap();
+            let body = caps.get(1).unwrap();
+            candidates.push(ProtectedRegion {
+                start: whole.start(),
+                end: whole.end(),
+                keep: (body.start(), body.end()),
+            });
+        }
+
+        // Also handle indented code blocks (4 spaces or tab at start)
+        // These are less common but should be protected too
+        // Note: This is a simplified check; full markdown parsing would be more accurate
+
+        // Whichever region starts first wins: a raw tag inside a code fence stays literal,
+        // and a code fence inside a raw block is part of the raw body
+        candidates.sort_by_key(|r| r.start);
+        let mut regions: Vec<ProtectedRegion> = Vec::new();
+        for region in candidates {
+            if regions.last().map(|last| region.start >= last.end).unwrap_or(true) {
+                regions.push(region);
+            }
+        }
+
+        regions
+    }
 }
 
-/// Process content with protected regions
-/// Splits content into protected and unprotected segments, only processing unprotected ones
-fn process_with_protected_regions(
-    content: &str,
-    config: &BookConfig,
-    protected_regions: &[(usize, usize)],
-) -> Result<String> {
-    let mut result = String::new();
-    let mut last_end = 0;
-
-    for (start, end) in protected_regions {
-        // Process the unprotected segment before this code block
-        if *start > last_end {
-            let segment = &content[last_end..*start];
-            let processed = render_template(segment, config)
-                .with_context(|| format!("Template error in content before position {}", start))?;
+/// The Tera environment a book's pages are templated with
+///
+/// Setting up Tera (its built-in filters and the locale ones), converting the
+/// book.json variables and compiling the code block patterns costs more than
+/// rendering a typical page, so a build creates one per book and renders
+/// every page segment with it.
+pub struct ContentTemplates {
+    tera: RefCell<Tera>,
+    context: TeraContext,
+    protected: ProtectedPatterns,
+}
+
+impl ContentTemplates {
+    pub fn new(config: &BookConfig) -> Self {
+        let mut tera = Tera::default();
+        locale::register_filters(&mut tera, &config.language);
+        ContentTemplates { tera: RefCell::new(tera), context: book_context(config), protected: ProtectedPatterns::new() }
+    }
+
+    /// Process Nunjucks templates in Markdown content
+    /// source_path: the path of the source file, used to name the file in error messages
+    /// line_offset: number of source lines before `content` (e.g. stripped front matter)
+    pub fn process(&self, content: &str, source_path: Option<&str>, line_offset: usize) -> Result<String> {
+        // Fast path: if no template syntax detected, return as-is
+        if !has_template_syntax(content) {
+            return Ok(content.to_string());
+        }
+
+        // Find protected regions (code blocks) to exclude from template processing
+        let protected_regions = self.protected.find(content);
+
+        // If content has protected regions, we need to handle them specially
+        if !protected_regions.is_empty() {
+            return self.process_with_protected_regions(content, &protected_regions, source_path, line_offset);
+        }
+
+        // No protected regions, process the entire content
+        self.render_segment(content, 0, content.len(), (source_path, line_offset))
+    }
+
+    /// Process content with protected regions
+    /// Splits content into protected and unprotected segments, only processing unprotected ones
+    fn process_with_protected_regions(
+        &self,
+        content: &str,
+        protected_regions: &[ProtectedRegion],
+        source_path: Option<&str>,
+        line_offset: usize,
+    ) -> Result<String> {
+        let mut result = String::new();
+        let mut last_end = 0;
+
+        for region in protected_regions {
+            // Process the unprotected segment before this region
+            if region.start > last_end {
+                let processed = self.render_segment(content, last_end, region.start, (source_path, line_offset))?;
+                result.push_str(&processed);
+            }
+
+            // Add the protected region (code block or raw body) as-is
+            result.push_str(&content[region.keep.0..region.keep.1]);
+            last_end = region.end;
+        }
+
+        // Process any remaining content after the last protected region
+        if last_end < content.len() {
+            let processed = self.render_segment(content, last_end, content.len(), (source_path, line_offset))?;
             result.push_str(&processed);
         }
 
-        // Add the protected region (code block) as-is
-        result.push_str(&content[*start..*end]);
-        last_end = *end;
+        Ok(result)
     }
 
-    // Process any remaining content after the last protected region
-    if last_end < content.len() {
-        let segment = &content[last_end..];
-        let processed = render_template(segment, config)
-            .with_context(|| "Template error in content after last code block")?;
-        result.push_str(&processed);
+    /// Render the `start..end` byte range of `content`
+    /// Errors are reported with line numbers relative to the whole content
+    fn render_segment(&self, content: &str, start: usize, end: usize, source: (Option<&str>, usize)) -> Result<String> {
+        let segment = &content[start..end];
+        self.render(segment, &self.context)
+            .map_err(|e| anyhow!(format_template_error(content, start, segment, &e, source)))
     }
 
-    Ok(result)
-}
+    /// Render a page template (`template:` front matter) with the page's front
+    /// matter fields as `page` and its Markdown body as `content`
+    /// template_path: book-relative path of the template, used in error messages
+    pub fn render_page_template(&self, template: &str, template_path: &str, page: &serde_json::Value, content: &str) -> Result<String> {
+        let mut context = self.context.clone();
+        context.insert("page", &json_to_tera_value(page));
+        context.insert("content", content);
 
-/// Render a template string using Tera
-fn render_template(content: &str, config: &BookConfig) -> Result<String> {
-    let mut tera = Tera::default();
+        self.render(template, &context)
+            .map_err(|e| anyhow!(format_template_error(template, 0, template, &e, (Some(template_path), 0))))
+    }
 
-    // Add custom template with a unique name
-    tera.add_raw_template("__content__", content)
-        .with_context(|| format_template_error(content, "Failed to parse template"))?;
+    /// Render a template string; it replaces the previous one in the environment
+    fn render(&self, template: &str, context: &TeraContext) -> tera::Result<String> {
+        let mut tera = self.tera.borrow_mut();
+        tera.add_raw_template(TEMPLATE_NAME, template)?;
+        tera.render(TEMPLATE_NAME, context)
+    }
+}
 
+/// Template context holding the book.json variables
+fn book_context(config: &BookConfig) -> TeraContext {
     // Build context from book config
     let mut context = TeraContext::new();
 
@@ -146,16 +266,14 @@ fn render_template(content: &str, config: &BookConfig) -> Result<String> {
 
     // Add a "book" object for {{ book.xxx }} compatibility
     // This maintains backward compatibility with the existing syntax
-    let book_map: std::collections::HashMap<String, tera::Value> = config
+    // Sorted so templates iterating over `book` render deterministically
+    let book_map: std::collections::BTreeMap<String, tera::Value> = config
         .variables
         .iter()
         .map(|(k, v)| (k.clone(), json_to_tera_value(v)))
         .collect();
     context.insert("book", &book_map);
-
-    // Render the template
-    tera.render("__content__", &context)
-        .with_context(|| format_template_error(content, "Failed to render template"))
+    context
 }
 
 /// Convert serde_json::Value to tera::Value
@@ -167,7 +285,7 @@ fn json_to_tera_value(json: &serde_json::Value) -> tera::Value {
             if let Some(i) = n.as_i64() {
                 tera::Value::Number(i.into())
             } else if let Some(f) = n.as_f64() {
-                tera::Value::Number(serde_json::Number::from_f64(f).unwrap_or_else(|| 0.into()).into())
+                tera::Value::Number(serde_json::Number::from_f64(f).unwrap_or_else(|| 0.into()))
             } else {
                 tera::Value::String(n.to_string())
             }
@@ -187,18 +305,177 @@ fn json_to_tera_value(json: &serde_json::Value) -> tera::Value {
     }
 }
 
-/// Format template error with helpful context
-fn format_template_error(content: &str, message: &str) -> String {
-    // Try to find the problematic line
+/// Location of a template error within a rendered segment (1-based)
+#[derive(Debug, PartialEq)]
+struct ErrorLocation {
+    line: usize,
+    column: usize,
+    width: usize,
+}
+
+/// Format template error with a caret-annotated excerpt around the failing location
+///
+/// Produces output like:
+/// ```text
+/// Template error in intro.md:3:12: Variable `book.missing` not found in context
+///    |
+///  2 |
+///  3 | Version is {{ book.missing }}
+///    |               ^^^^^^^^^^^^
+///  4 | More text
+/// ```
+fn format_template_error(
+    content: &str,
+    segment_start: usize,
+    segment: &str,
+    error: &tera::Error,
+    (source_path, line_offset): (Option<&str>, usize),
+) -> String {
+    let messages = error_chain_messages(error);
+    let summary = summarize_error(&messages);
+    let location = locate_error(segment, &messages)
+        .map(|loc| to_content_location(content, segment_start, loc));
+
+    let file = source_path.unwrap_or("template");
+    let mut output = match &location {
+        Some(loc) => format!(
+            "Template error in {}:{}:{}: {}",
+            file,
+            loc.line + line_offset,
+            loc.column,
+            summary
+        ),
+        None => format!("Template error in {}: {}", file, summary),
+    };
+
+    match location {
+        Some(loc) => output.push_str(&format_excerpt(content, &loc, line_offset)),
+        None => {
+            // No location found: fall back to a preview of the segment
+            let lines: Vec<&str> = segment.lines().collect();
+            let preview_lines = lines.iter().take(5).cloned().collect::<Vec<_>>().join("\n");
+            output.push_str(&format!(
+                "\n\nContent preview:\n{}{}",
+                preview_lines,
+                if lines.len() > 5 { "\n..." } else { "" }
+            ));
+        }
+    }
+
+    output
+}
+
+/// Collect the messages of a Tera error and all of its sources (outermost first)
+fn error_chain_messages(error: &tera::Error) -> Vec<String> {
+    let mut messages = vec![error.to_string()];
+    let mut source = StdError::source(error);
+    while let Some(err) = source {
+        messages.push(err.to_string());
+        source = err.source();
+    }
+    messages
+}
+
+/// Reduce the error chain to a single-line description of the actual problem
+fn summarize_error(messages: &[String]) -> String {
+    let innermost = messages.last().map(String::as_str).unwrap_or("Unknown template error");
+
+    // Parse errors carry a pest report; its "= expected ..." line is the useful part
+    if let Some(expected) = innermost.lines().find_map(|l| l.trim().strip_prefix("= ")) {
+        return format!("Failed to parse template: {}", expected);
+    }
+
+    innermost
+        .replace(&format!(" while rendering '{}'", TEMPLATE_NAME), "")
+        .replace(&format!("'{}'", TEMPLATE_NAME), "template")
+}
+
+/// Find where in the segment the error occurred
+/// Parse errors report their position directly; render errors only name the
+/// failing expression, so search the segment for it
+fn locate_error(segment: &str, messages: &[String]) -> Option<ErrorLocation> {
+    let position_re = Regex::new(r"-->\s*(\d+):(\d+)").unwrap();
+    for message in messages {
+        if let Some(caps) = position_re.captures(message) {
+            let line = caps[1].parse().ok()?;
+            let column = caps[2].parse().ok()?;
+            return Some(ErrorLocation { line, column, width: 1 });
+        }
+    }
+
+    let expression_re = Regex::new(r"`([^`]+)`|'([^']+)'").unwrap();
+    for message in messages.iter().rev() {
+        for caps in expression_re.captures_iter(message) {
+            let expression = caps.get(1).or_else(|| caps.get(2)).unwrap().as_str();
+            if expression == TEMPLATE_NAME {
+                continue;
+            }
+            if let Some(loc) = find_in_template_tags(segment, expression) {
+                return Some(loc);
+            }
+        }
+    }
+
+    None
+}
+
+/// Find the first occurrence of `expression` inside a `{{ }}` or `{% %}` tag
+fn find_in_template_tags(segment: &str, expression: &str) -> Option<ErrorLocation> {
+    let tag_re = Regex::new(r"(?s)\{\{.*?\}\}|\{%.*?%\}").unwrap();
+    for tag in tag_re.find_iter(segment) {
+        if let Some(offset) = tag.as_str().find(expression) {
+            let pos = tag.start() + offset;
+            let line_start = segment[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            return Some(ErrorLocation {
+                line: segment[..pos].matches('\n').count() + 1,
+                column: segment[line_start..pos].chars().count() + 1,
+                width: expression.chars().count(),
+            });
+        }
+    }
+    None
+}
+
+/// Translate a location within a segment into a location within the whole content
+fn to_content_location(content: &str, segment_start: usize, loc: ErrorLocation) -> ErrorLocation {
+    let before = &content[..segment_start];
+    let line_offset = before.matches('\n').count();
+    // A segment may start mid-line (right after a code fence), which shifts its first line
+    let column = if loc.line == 1 {
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        loc.column + before[line_start..].chars().count()
+    } else {
+        loc.column
+    };
+    ErrorLocation {
+        line: loc.line + line_offset,
+        column,
+        width: loc.width,
+    }
+}
+
+/// Render the lines around an error location with a caret marker
+fn format_excerpt(content: &str, loc: &ErrorLocation, line_offset: usize) -> String {
     let lines: Vec<&str> = content.lines().collect();
-    let preview_lines = lines.iter().take(5).cloned().collect::<Vec<_>>().join("\n");
-
-    format!(
-        "{}\n\nContent preview:\n{}{}",
-        message,
-        preview_lines,
-        if lines.len() > 5 { "\n..." } else { "" }
-    )
+    let first = loc.line.saturating_sub(EXCERPT_CONTEXT_LINES).max(1);
+    let last = (loc.line + EXCERPT_CONTEXT_LINES).min(lines.len()).max(loc.line);
+    let gutter = (last + line_offset).to_string().len();
+
+    let mut excerpt = format!("\n{} |", " ".repeat(gutter));
+    for number in first..=last {
+        let line = lines.get(number - 1).copied().unwrap_or("");
+        excerpt.push_str(&format!("\n{:>width$} | {}", number + line_offset, line, width = gutter));
+        if number == loc.line {
+            excerpt.push_str(&format!(
+                "\n{} | {}{}",
+                " ".repeat(gutter),
+                " ".repeat(loc.column.saturating_sub(1)),
+                "^".repeat(loc.width.max(1))
+            ));
+        }
+    }
+
+    excerpt
 }
 
 #[cfg(test)]
@@ -579,4 +856,123 @@ This is advanced content.
         assert!(result.contains("## Advanced Usage"));
         assert!(result.contains("This is advanced content."));
     }
+
+    // === Error Reporting Tests ===
+
+    #[test]
+    fn test_render_error_names_file_and_line() {
+        let config = create_test_config(HashMap::new());
+        let content = "# Title\n\nVersion: {{ book.version | no_such_filter }}\n";
+        let err = ContentTemplates::new(&config).process(content, Some("guide/intro.md"), 0)
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.starts_with("Template error in guide/intro.md:3:"), "{}", err);
+        assert!(err.contains("no_such_filter"), "{}", err);
+        assert!(err.contains("3 | Version: {{ book.version | no_such_filter }}"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_error_has_caret_excerpt() {
+        let config = create_test_config(HashMap::new());
+        let content = "line one\nline two {{ book.name | }}\nline three";
+        let err = process_nunjucks_templates(content, &config).unwrap_err().to_string();
+
+        assert!(err.contains("Failed to parse template"), "{}", err);
+        assert!(err.contains("2 | line two {{ book.name | }}"), "{}", err);
+        assert!(err.contains("^"), "{}", err);
+        assert!(!err.contains("__content__"), "{}", err);
+    }
+
+    #[test]
+    fn test_error_line_accounts_for_code_blocks() {
+        let config = create_test_config(HashMap::new());
+        let content = "intro\n\n```\n{{ ignored }}\n```\n\nafter {{ missing_var }}\n";
+        let err = ContentTemplates::new(&config).process(content, Some("page.md"), 3)
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.starts_with("Template error in page.md:10:10:"), "{}", err);
+        assert!(err.contains(" 8 | ```"), "{}", err);
+        assert!(err.contains("Variable `missing_var` not found in context"), "{}", err);
+    }
+
+    // === Raw Block Tests ===
+
+    #[test]
+    fn test_raw_block_outputs_literal_braces() {
+        let mut vars = HashMap::new();
+        vars.insert("name".to_string(), serde_json::json!("Test"));
+
+        let config = create_test_config(vars);
+        let content = "{{ book.name }}: {% raw %}{{ .Values.image }} {% if x %}{% endraw %} done";
+        let result = process_nunjucks_templates(content, &config).unwrap();
+
+        assert_eq!(result, "Test: {{ .Values.image }} {% if x %} done");
+    }
+
+    #[test]
+    fn test_raw_block_spanning_code_fence() {
+        let mut vars = HashMap::new();
+        vars.insert("name".to_string(), serde_json::json!("Test"));
+
+        let config = create_test_config(vars);
+        let content = "{% raw %}\nValues: {{ .Values }}\n\n```\ncode\n```\n\n{{ after }}\n{% endraw %}\n{{ book.name }}";
+        let result = process_nunjucks_templates(content, &config).unwrap();
+
+        assert!(result.contains("Values: {{ .Values }}"));
+        assert!(result.contains("{{ after }}"));
+        assert!(result.ends_with("\nTest"));
+        assert!(!result.contains("raw %}"));
+    }
+
+    #[test]
+    fn test_raw_tag_inside_code_fence_is_untouched() {
+        let config = create_test_config(HashMap::new());
+        let content = "```jinja\n{% raw %}{{ x }}{% endraw %}\n```\n";
+        let result = process_nunjucks_templates(content, &config).unwrap();
+
+        assert_eq!(result, content);
+    }
+
+    // === Page Templates ===
+
+    #[test]
+    fn test_render_page_template() {
+        let mut vars = HashMap::new();
+        vars.insert("api_base".to_string(), serde_json::json!("https://api.example.com"));
+        let config = create_test_config(vars);
+        let template = "# {{ page.method }} {{ page.path }}\n\n`{{ book.api_base }}{{ page.path }}`\n\n{{ content }}\n{% for p in page.params %}- {{ p }}\n{% endfor %}";
+        let page = serde_json::json!({"method": "GET", "path": "/users", "params": ["limit", "offset"]});
+
+        let result = ContentTemplates::new(&config).render_page_template(template, "_templates/api.md", &page, "Lists users.").unwrap();
+        assert_eq!(
+            result,
+            "# GET /users\n\n`https://api.example.com/users`\n\nLists users.\n- limit\n- offset\n"
+        );
+    }
+
+    #[test]
+    fn test_render_page_template_error_names_template() {
+        let config = create_test_config(HashMap::new());
+        let err = ContentTemplates::new(&config)
+            .render_page_template("{% if %}", "_templates/api.md", &serde_json::json!({}), "")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("_templates/api.md"), "{}", err);
+    }
+
+    #[test]
+    fn test_templates_reused_across_pages() {
+        let mut vars = HashMap::new();
+        vars.insert("product".to_string(), serde_json::json!("Acme"));
+        let templates = ContentTemplates::new(&create_test_config(vars));
+
+        assert_eq!(templates.process("# {{ book.product }}", Some("a.md"), 0).unwrap(), "# Acme");
+        assert!(templates.process("{% if %}", Some("b.md"), 0).is_err());
+        let page = serde_json::json!({"title": "Users"});
+        assert_eq!(templates.render_page_template("{{ page.title }}: {{ content }}", "_templates/api.md", &page, "Lists users.").unwrap(), "Users: Lists users.");
+        // A page's fields don't leak into the next page
+        assert_eq!(templates.process("{{ page.title | default(value=\"none\") }} {{ product }}", Some("c.md"), 0).unwrap(), "none Acme");
+    }
 }
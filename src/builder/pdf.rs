@@ -0,0 +1,351 @@
+//! PDF export
+//!
+//! Renders every chapter in SUMMARY.md order into a single print document
+//! (cover, table of contents, chapters) and prints it to PDF with a locally
+//! installed Chrome, Chromium or Edge in headless mode.
+//!
+//! Links between chapters become in-document anchors. Element ids are
+//! namespaced per chapter (`ch3--install`) so headings with the same title in
+//! different chapters stay unique, and images are referenced by absolute
+//! `file://` URLs so the document can be printed from anywhere.
+
+use super::export::{classify_url, Chapter, ExportBook, UrlTarget};
+use super::renderer::html_escape;
+use crate::parser::{default_html_path, BookConfig, SummaryItem};
+use crate::{info, paths};
+use anyhow::{bail, Context, Result};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const PDF_CSS: &str = include_str!("../../templates/pdf.css");
+
+/// Characters escaped in file:// URLs
+const PATH_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?');
+
+/// Environment variable pointing at the browser used for printing
+const BROWSER_ENV: &str = "GUIDEBOOK_CHROME";
+
+/// Browser executables searched for in PATH, in order
+const BROWSER_NAMES: &[&str] = &[
+    "google-chrome",
+    "google-chrome-stable",
+    "chromium",
+    "chromium-browser",
+    "chrome",
+    "msedge",
+    "microsoft-edge",
+];
+
+/// Well-known install locations outside PATH
+const BROWSER_PATHS: &[&str] = &[
+    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+    "/Applications/Chromium.app/Contents/MacOS/Chromium",
+    "/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+    r"C:\Program Files\Google\Chrome\Application\chrome.exe",
+    r"C:\Program Files (x86)\Google\Chrome\Application\chrome.exe",
+    r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe",
+];
+
+/// Export the book as a single PDF file
+/// html_output: also keep the print document at this path
+pub fn export_pdf(source: &Path, output: &Path, html_output: Option<&Path>) -> Result<()> {
+    info!("Loading book configuration...");
+    let book = ExportBook::load(source)?;
+    write_pdf(&book, output, html_output)
+}
+
+/// Print a loaded book to a PDF file
+pub(crate) fn write_pdf(book: &ExportBook, output: &Path, html_output: Option<&Path>) -> Result<()> {
+    info!("Rendering chapters...");
+    let document = render_print_document(book)?;
+
+    let html_path = match html_output {
+        Some(path) => {
+            fs::write(path, &document)?;
+            info!("  Wrote print HTML to {:?}", path);
+            path.to_path_buf()
+        }
+        None => {
+            let path = std::env::temp_dir().join(format!("guidebook-pdf-{}.html", std::process::id()));
+            fs::write(&path, &document)?;
+            path
+        }
+    };
+
+    let result = print_to_pdf(&html_path, output);
+    if html_output.is_none() {
+        let _ = fs::remove_file(&html_path);
+    }
+    result?;
+
+    info!();
+    info!(">> PDF written to {:?}", output);
+    Ok(())
+}
+
+/// Render cover, table of contents and all chapters into one HTML document
+fn render_print_document(book: &ExportBook) -> Result<String> {
+    let config = &book.config;
+    let chapters = book.chapters();
+    let chapter_ids: HashMap<String, String> = chapters
+        .iter()
+        .map(|c| (default_html_path(&c.source_path), c.id.clone()))
+        .collect();
+
+    let mut body = String::new();
+    for chapter in &chapters {
+        let html = book.render_chapter(chapter)?;
+        let html = namespace_chapter(&html, chapter, &chapter_ids, &book.source);
+        body.push_str(&format!(
+            "<section class=\"chapter\" id=\"{}\">\n{}\n</section>\n",
+            chapter.id, html
+        ));
+    }
+
+    let title = if config.title.is_empty() { "Book" } else { &config.title };
+    let lang = if config.language.is_empty() { "en" } else { &config.language };
+
+    Ok(format!(
+        "<!DOCTYPE html>\n<html lang=\"{lang}\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n{css}</style>\n</head>\n<body>\n{cover}{toc}{body}</body>\n</html>\n",
+        lang = html_escape(lang),
+        title = html_escape(title),
+        css = PDF_CSS,
+        cover = render_cover(&book.source, config),
+        toc = render_toc(&book.summary.items, &chapter_ids),
+        body = body,
+    ))
+}
+
+/// Cover page: optional cover.jpg/cover.png, title, description and author
+fn render_cover(source: &Path, config: &BookConfig) -> String {
+    let mut html = String::from("<section class=\"cover\">\n");
+    if let Some(cover) = ["cover.jpg", "cover.png"].iter().map(|name| source.join(name)).find(|p| p.is_file()) {
+        html.push_str(&format!("<img src=\"{}\" alt=\"\">\n", file_url(&cover)));
+    }
+    html.push_str(&format!("<h1>{}</h1>\n", html_escape(&config.title)));
+    if !config.description.is_empty() {
+        html.push_str(&format!("<p class=\"description\">{}</p>\n", html_escape(&config.description)));
+    }
+    if !config.author.is_empty() {
+        html.push_str(&format!("<p class=\"author\">{}</p>\n", html_escape(&config.author)));
+    }
+    html.push_str("</section>\n");
+    html
+}
+
+/// Table of contents linking to chapter anchors
+fn render_toc(items: &[SummaryItem], chapter_ids: &HashMap<String, String>) -> String {
+    fn render_items(items: &[SummaryItem], chapter_ids: &HashMap<String, String>) -> String {
+        let mut html = String::from("<ul>\n");
+        for item in items {
+            match item {
+                SummaryItem::Link { title, path, children } => {
+                    let target = path.as_deref().and_then(|p| {
+                        let (base, fragment) = paths::split_fragment(p);
+                        let id = chapter_ids.get(&default_html_path(base))?;
+                        Some(match fragment.strip_prefix('#') {
+                            Some(fragment) => format!("{}--{}", id, fragment),
+                            None => id.clone(),
+                        })
+                    });
+                    html.push_str("<li>");
+                    match target {
+                        Some(id) => html.push_str(&format!("<a href=\"#{}\">{}</a>", id, html_escape(title))),
+                        None => html.push_str(&html_escape(title)),
+                    }
+                    if !children.is_empty() {
+                        html.push_str(&render_items(children, chapter_ids));
+                    }
+                    html.push_str("</li>\n");
+                }
+                SummaryItem::PartTitle(title) => {
+                    html.push_str(&format!("<li class=\"part\">{}</li>\n", html_escape(title)));
+                }
+                SummaryItem::Separator => {}
+            }
+        }
+        html.push_str("</ul>\n");
+        html
+    }
+
+    format!("<nav class=\"toc\">\n<h1>Contents</h1>\n{}</nav>\n", render_items(items, chapter_ids))
+}
+
+/// Prefix element ids with the chapter id and point links at in-document anchors
+fn namespace_chapter(
+    html: &str,
+    chapter: &Chapter,
+    chapter_ids: &HashMap<String, String>,
+    source: &Path,
+) -> String {
+    let id_re = Regex::new(r#"\bid="([^"]+)""#).unwrap();
+    let html = id_re.replace_all(html, |caps: &Captures| format!("id=\"{}--{}\"", chapter.id, &caps[1]));
+
+    let page_path = default_html_path(&chapter.source_path);
+    let url_re = Regex::new(r#"\b(href|src)="([^"]*)""#).unwrap();
+    url_re
+        .replace_all(&html, |caps: &Captures| {
+            let url = &caps[2];
+            match rewrite_url(url, &chapter.id, &page_path, chapter_ids, source) {
+                Some(rewritten) => format!("{}=\"{}\"", &caps[1], rewritten),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Rewrite one href/src value of a chapter; None keeps it unchanged
+fn rewrite_url(
+    url: &str,
+    chapter_id: &str,
+    page_path: &str,
+    chapter_ids: &HashMap<String, String>,
+    source: &Path,
+) -> Option<String> {
+    match classify_url(url, page_path) {
+        UrlTarget::Fragment(fragment) => Some(format!("#{}--{}", chapter_id, fragment)),
+        UrlTarget::Book { path, fragment } => {
+            if let Some(id) = chapter_ids.get(&path) {
+                return Some(if fragment.is_empty() {
+                    format!("#{}", id)
+                } else {
+                    format!("#{}--{}", id, fragment)
+                });
+            }
+            let file = source.join(&path);
+            file.is_file().then(|| file_url(&file))
+        }
+        UrlTarget::Other => None,
+    }
+}
+
+/// Absolute file:// URL for a local file
+fn file_url(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let path = path.trim_start_matches("//?/");
+    let encoded = utf8_percent_encode(path, PATH_ENCODE_SET).to_string();
+    if encoded.starts_with('/') {
+        format!("file://{}", encoded)
+    } else {
+        format!("file:///{}", encoded)
+    }
+}
+
+/// Locate a Chromium-based browser: $GUIDEBOOK_CHROME, PATH, then well-known locations
+fn find_browser() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(BROWSER_ENV) {
+        return Some(PathBuf::from(path));
+    }
+
+    let exe_suffix = std::env::consts::EXE_SUFFIX;
+    if let Some(paths) = std::env::var_os("PATH") {
+        for dir in std::env::split_paths(&paths) {
+            for name in BROWSER_NAMES {
+                let candidate = dir.join(format!("{}{}", name, exe_suffix));
+                if candidate.is_file() {
+                    return Some(candidate);
+                }
+            }
+        }
+    }
+
+    BROWSER_PATHS.iter().map(PathBuf::from).find(|p| p.is_file())
+}
+
+/// Print an HTML file to PDF with a headless browser
+fn print_to_pdf(html_path: &Path, output: &Path) -> Result<()> {
+    let browser = find_browser().with_context(|| {
+        format!(
+            "No Chrome, Chromium or Edge found for PDF printing. Install one or set {} to its path \
+             (use --html to export the print document instead)",
+            BROWSER_ENV
+        )
+    })?;
+    info!("Printing PDF with {:?}...", browser);
+
+    let output_abs = if output.is_absolute() {
+        output.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(output)
+    };
+    let html_abs = html_path.canonicalize()?;
+
+    let result = Command::new(&browser)
+        .arg("--headless")
+        .arg("--disable-gpu")
+        .arg("--no-pdf-header-footer")
+        .arg(format!("--print-to-pdf={}", output_abs.display()))
+        .arg(file_url(&html_abs))
+        .output()
+        .with_context(|| format!("Failed to run {:?}", browser))?;
+
+    if !result.status.success() || !output_abs.is_file() {
+        bail!(
+            "PDF printing failed ({}): {}",
+            result.status,
+            String::from_utf8_lossy(&result.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter_ids() -> HashMap<String, String> {
+        let mut ids = HashMap::new();
+        ids.insert("README.html".to_string(), "ch1".to_string());
+        ids.insert("guide/start.html".to_string(), "ch2".to_string());
+        ids
+    }
+
+    #[test]
+    fn test_namespace_chapter_links_and_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("guide/img")).unwrap();
+        fs::write(dir.path().join("guide/img/a b.png"), "png").unwrap();
+
+        let chapter = Chapter {
+            id: "ch2".to_string(),
+            title: "Start".to_string(),
+            source_path: "guide/start.md".to_string(),
+        };
+        let html = r##"<h2 id="setup">Setup</h2><a href="#setup">s</a> <a href="../README.html#intro">r</a> <a href="start.html">self</a> <img src="img/a%20b.png"> <a href="https://example.com">e</a> <a href="missing.html">m</a>"##;
+        let output = namespace_chapter(html, &chapter, &chapter_ids(), dir.path());
+
+        assert!(output.contains(r#"id="ch2--setup""#), "{}", output);
+        assert!(output.contains(r##"href="#ch2--setup""##), "{}", output);
+        assert!(output.contains(r##"href="#ch1--intro""##), "{}", output);
+        assert!(output.contains(r##"href="#ch2""##), "{}", output);
+        assert!(output.contains("src=\"file://"), "{}", output);
+        assert!(output.contains("a%20b.png\""), "{}", output);
+        assert!(output.contains(r#"href="https://example.com""#), "{}", output);
+        assert!(output.contains(r#"href="missing.html""#), "{}", output);
+    }
+
+    #[test]
+    fn test_render_toc() {
+        let summary = crate::parser::summary::parse_summary(
+            "# Summary\n\n* [Intro](README.md)\n\n## Guide\n\n* [Start](guide/start.md#top)\n    * Draft\n",
+        )
+        .unwrap();
+        let toc = render_toc(&summary.items, &chapter_ids());
+
+        assert!(toc.contains(r##"<a href="#ch1">Intro</a>"##), "{}", toc);
+        assert!(toc.contains(r#"<li class="part">Guide</li>"#), "{}", toc);
+        assert!(toc.contains(r##"<a href="#ch2--top">Start</a>"##), "{}", toc);
+        assert!(toc.contains("<li>Draft</li>"), "{}", toc);
+    }
+}
@@ -0,0 +1,277 @@
+//! Page rendering stages
+//!
+//! A page is rendered by named stages in two groups: content stages turn the
+//! source (after front matter) into HTML content, which is wrapped in the page
+//! template, then page stages run on the full page HTML. The `pipeline`
+//! section of book.json can reorder stages within their group or skip them:
+//!
+//! ```json
+//! { "pipeline": { "disable": ["glossary"] } }
+//! ```
+//!
+//! Time spent in each stage is summed over a build and shown with `--verbose`.
+
+use super::attachments;
+use super::links;
+use super::hooks::{self, PageContext};
+use super::thumbnails;
+use super::{apply_page_template, apply_svg_processing, process_imports_for_file, BookContext};
+use super::{extract_headings, extract_headings_from_asciidoc, render_asciidoc_with_path, render_markdown_with_path, TocItem};
+use crate::parser::{apply_glossary, BookConfig, PipelineStage};
+use crate::warning;
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Stages of the full page HTML, after the page template
+const PAGE_STAGES: [PipelineStage; 3] = [PipelineStage::Svg, PipelineStage::Thumbnails, PipelineStage::Postprocessors];
+
+static TIMINGS: Mutex<Option<HashMap<PipelineStage, Duration>>> = Mutex::new(None);
+
+/// The stages a book renders its pages with
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pipeline {
+    /// Stages of the page source, producing the HTML content
+    pub content: Vec<PipelineStage>,
+    /// Stages of the full page HTML
+    pub page: Vec<PipelineStage>,
+}
+
+impl Pipeline {
+    /// Stages from the `pipeline` section of book.json
+    pub fn new(config: &BookConfig) -> Result<Pipeline> {
+        let order = if config.pipeline.stages.is_empty() { PipelineStage::ALL.to_vec() } else { config.pipeline.stages.clone() };
+        let mut seen = Vec::new();
+        for stage in &order {
+            if seen.contains(stage) {
+                bail!("pipeline: stage `{}` is listed twice", stage.name());
+            }
+            seen.push(*stage);
+        }
+        let enabled: Vec<PipelineStage> = order.into_iter().filter(|s| !config.pipeline.disable.contains(s)).collect();
+        if !enabled.contains(&PipelineStage::Markdown) {
+            bail!("pipeline: the `markdown` stage can't be left out or disabled");
+        }
+
+        let (page, content) = enabled.into_iter().partition(|s| PAGE_STAGES.contains(s));
+        Ok(Pipeline { content, page })
+    }
+
+    /// The content stages before Markdown rendering, which turn a page's
+    /// source into the Markdown that is rendered
+    pub fn source_stages(&self) -> Pipeline {
+        let markdown = self.content.iter().position(|s| *s == PipelineStage::Markdown).unwrap_or(self.content.len());
+        Pipeline { content: self.content[..markdown].to_vec(), page: Vec::new() }
+    }
+
+    /// Run the content stages on a page's source (after front matter)
+    /// link_base: source path relative links are resolved against (None keeps them as written)
+    pub(crate) fn render_content(
+        &self,
+        content: String,
+        page: &PageContext,
+        link_base: Option<&str>,
+        line_offset: usize,
+        book: &BookContext,
+    ) -> Result<(String, Vec<TocItem>)> {
+        let src_file = page.book_dir.join(page.source_path);
+        let asciidoc = super::is_asciidoc_file(&src_file);
+        let mut content = content;
+        let mut toc_items = Vec::new();
+
+        for &stage in &self.content {
+            let start = Instant::now();
+            content = match stage {
+                // Directives and templates are Markdown features
+                PipelineStage::Imports
+                | PipelineStage::Preprocessors
+                | PipelineStage::Nunjucks
+                | PipelineStage::PageTemplate
+                | PipelineStage::WikiLinks
+                    if asciidoc =>
+                {
+                    continue
+                }
+                PipelineStage::Imports => process_imports_for_file(&content, &src_file)?,
+                PipelineStage::Preprocessors => hooks::run_preprocessors(content, page),
+                // Pages opt out with `templating: false`
+                PipelineStage::Nunjucks if !page.front_matter.map(|fm| fm.templating_enabled()).unwrap_or(true) => continue,
+                PipelineStage::Nunjucks => {
+                    let content = attachments::expand_file_tags(&content, page.book_dir, page.source_path);
+                    book.templates.process(&content, Some(page.source_path), line_offset)
+                        .unwrap_or_else(|e| {
+                            warning!("{}", e);
+                            content
+                        })
+                }
+                PipelineStage::PageTemplate => match page.front_matter.filter(|fm| fm.template.is_some()) {
+                    Some(fm) => apply_page_template(page.book_dir, fm, &content, book.templates).unwrap_or_else(|e| {
+                        warning!("{}", e);
+                        content
+                    }),
+                    None => continue,
+                },
+                PipelineStage::WikiLinks => book.wiki_links.apply(&content, page.source_path),
+                PipelineStage::Markdown if asciidoc => {
+                    toc_items = extract_headings_from_asciidoc(&content);
+                    render_asciidoc_with_path(&content, link_base, &page.config.autolink)
+                }
+                PipelineStage::Markdown => {
+                    toc_items = extract_headings(&content);
+                    let hardbreaks = page.front_matter.and_then(|fm| fm.hardbreaks).unwrap_or(page.config.hardbreaks);
+                    render_markdown_with_path(&content, link_base, hardbreaks, &page.config.autolink)
+                }
+                PipelineStage::Glossary => apply_glossary(&content, book.glossary),
+                PipelineStage::ExternalLinks => {
+                    links::decorate_external_links(&content, &page.config.external_links, &page.config.deploy.site_url)
+                }
+                PipelineStage::Svg | PipelineStage::Thumbnails | PipelineStage::Postprocessors => content,
+            };
+            record(stage, start.elapsed());
+        }
+        Ok((content, toc_items))
+    }
+
+    /// Run the page stages on a page's full HTML
+    /// output: build output directory (externalized SVGs and thumbnails are written there)
+    pub(crate) fn render_page(&self, html: String, output: &Path, page: &PageContext) -> Result<String> {
+        let mut html = html;
+        for &stage in &self.page {
+            let start = Instant::now();
+            html = match stage {
+                PipelineStage::Svg => apply_svg_processing(html, output, page.config)?,
+                PipelineStage::Thumbnails => thumbnails::apply_thumbnails(html, output, page.html_path, &page.config.thumbnails),
+                PipelineStage::Postprocessors => hooks::run_postprocessors(html, page),
+                _ => html,
+            };
+            record(stage, start.elapsed());
+        }
+        Ok(html)
+    }
+}
+
+fn record(stage: PipelineStage, elapsed: Duration) {
+    let mut timings = TIMINGS.lock().unwrap_or_else(|e| e.into_inner());
+    *timings.get_or_insert_with(HashMap::new).entry(stage).or_default() += elapsed;
+}
+
+/// Clear the stage timings, at the start of a build
+pub fn reset_timings() {
+    *TIMINGS.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Time spent in each stage that ran since the last [`reset_timings`], in default stage order
+pub fn timings() -> Vec<(PipelineStage, Duration)> {
+    let timings = TIMINGS.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(timings) = timings.as_ref() else {
+        return Vec::new();
+    };
+    PipelineStage::ALL
+        .iter()
+        .filter_map(|stage| timings.get(stage).map(|elapsed| (*stage, *elapsed)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::nunjucks::ContentTemplates;
+    use crate::builder::wikilinks::WikiLinks;
+    use crate::parser::summary::parse_summary;
+    use crate::parser::{FrontMatter, FrontMatterDefaults, Glossary};
+
+    fn config(json: &str) -> BookConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_pipeline_from_config() {
+        let pipeline = Pipeline::new(&BookConfig::default()).unwrap();
+        assert_eq!(pipeline.content.len(), 8);
+        assert_eq!(pipeline.page, vec![PipelineStage::Svg, PipelineStage::Thumbnails, PipelineStage::Postprocessors]);
+
+        let pipeline = Pipeline::new(&config(
+            r#"{"pipeline": {"stages": ["postprocessors", "nunjucks", "imports", "markdown", "svg"], "disable": ["svg"]}}"#,
+        ))
+        .unwrap();
+        assert_eq!(pipeline.content, vec![PipelineStage::Nunjucks, PipelineStage::Imports, PipelineStage::Markdown]);
+        assert_eq!(pipeline.page, vec![PipelineStage::Postprocessors]);
+        assert_eq!(pipeline.source_stages().content, vec![PipelineStage::Nunjucks, PipelineStage::Imports]);
+        assert!(pipeline.source_stages().page.is_empty());
+
+        assert!(Pipeline::new(&config(r#"{"pipeline": {"disable": ["markdown"]}}"#)).is_err());
+        assert!(Pipeline::new(&config(r#"{"pipeline": {"stages": ["markdown", "markdown"]}}"#)).is_err());
+        assert!(serde_json::from_str::<BookConfig>(r#"{"pipeline": {"disable": ["minify"]}}"#).is_err());
+    }
+
+    #[test]
+    fn test_render_content_skips_disabled_stages() {
+        let config = config(r#"{"variables": {"name": "Acme"}, "pipeline": {"disable": ["nunjucks", "glossary"]}}"#);
+        let page = PageContext {
+            book_dir: Path::new("."),
+            source_path: "pipeline-test.md",
+            html_path: "pipeline-test.html",
+            config: &config,
+            front_matter: None,
+            language: None,
+            position: None,
+            updated: None,
+        };
+        let summary = parse_summary("# Summary\n").unwrap();
+        let glossary = Glossary::parse("## Acme\nA company\n").unwrap();
+        let book = BookContext {
+            source: Path::new("."),
+            config: &config,
+            summary: &summary,
+            glossary: &glossary,
+            defaults: &FrontMatterDefaults::default(),
+            wiki_links: &WikiLinks::default(),
+            templates: &ContentTemplates::new(&config),
+            language: None,
+            last_modified: None,
+            social_cards: None,
+        };
+        let (html, toc) = Pipeline::new(&config)
+            .unwrap()
+            .render_content("## Hi\n\n{{ book.name }} Acme\n".to_string(), &page, Some("pipeline-test.md"), 0, &book)
+            .unwrap();
+        assert!(html.contains("{{ book.name }} Acme</p>"), "{}", html);
+        assert_eq!(toc.len(), 1);
+    }
+
+    #[test]
+    fn test_front_matter_overrides_hardbreaks() {
+        let config = config(r#"{"hardbreaks": true}"#);
+        let summary = parse_summary("# Summary\n").unwrap();
+        let book = BookContext {
+            source: Path::new("."),
+            config: &config,
+            summary: &summary,
+            glossary: &Glossary::default(),
+            defaults: &FrontMatterDefaults::default(),
+            wiki_links: &WikiLinks::default(),
+            templates: &ContentTemplates::new(&config),
+            language: None,
+            last_modified: None,
+            social_cards: None,
+        };
+        let render = |front_matter: Option<&FrontMatter>| {
+            let page = PageContext {
+                book_dir: Path::new("."),
+                source_path: "page.md",
+                html_path: "page.html",
+                config: &config,
+                front_matter,
+                language: None,
+                position: None,
+                updated: None,
+            };
+            Pipeline::new(&config).unwrap().render_content("one\ntwo\n".to_string(), &page, None, 0, &book).unwrap().0
+        };
+        assert!(render(None).contains("one<br />"));
+        let fm = FrontMatter { hardbreaks: Some(false), ..Default::default() };
+        assert_eq!(render(Some(&fm)), "<p>one\ntwo</p>\n");
+    }
+}
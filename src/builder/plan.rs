@@ -0,0 +1,249 @@
+//! What a build would write, for `guidebook build --dry-run`
+//!
+//! Walks SUMMARY.md and the asset directories the same way the build does,
+//! without rendering pages or touching the output directory.
+
+use super::{asset_dirs, generate, icons, language_dirs};
+use crate::parser::{self, BookConfig, Summary, SummaryItem};
+use crate::paths;
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// A file the build would produce
+#[derive(Debug, PartialEq)]
+pub struct PlannedFile {
+    /// Book-relative source (several files for merged entries, the data file for generated pages)
+    pub source: String,
+    /// Output-relative destination
+    pub dest: String,
+    /// How the page is produced, when not from a single source file
+    pub note: Option<&'static str>,
+}
+
+/// Pages and assets of a build, in build order
+#[derive(Debug, Default)]
+pub struct BuildPlan {
+    pub pages: Vec<PlannedFile>,
+    pub assets: Vec<PlannedFile>,
+    /// SUMMARY.md entries whose source file does not exist
+    pub missing: Vec<String>,
+}
+
+/// List the pages and assets a build of `source` would write
+/// config_file: used instead of book.json, like [`super::BuildOptions::config_file`]
+pub fn plan_build(source: &Path, config_file: Option<&Path>) -> Result<BuildPlan> {
+    let source = source.canonicalize().context("Source directory not found")?;
+    let config = match config_file {
+        Some(config_file) => BookConfig::load_file(config_file)?,
+        None => load_config(&source)?,
+    };
+    let languages = parser::langs::parse_langs(&source)?;
+    if languages.is_empty() {
+        return plan_single_book(&source, &config, "", "");
+    }
+
+    let dirs = language_dirs(&source, &languages, &config)?;
+    let mut plan = BuildPlan::default();
+    if config.languages.default.is_empty() {
+        plan.pages.push(PlannedFile { source: "LANGS.md".to_string(), dest: "index.html".to_string(), note: None });
+    }
+    for (lang, dir) in languages.iter().zip(&dirs) {
+        let lang_source = source.join(&lang.code);
+        let lang_config = if lang_source.join("book.json").exists() { load_config(&lang_source)? } else { config.clone() };
+        let dest_prefix = if dir.is_empty() { String::new() } else { format!("{}/", dir) };
+        let lang_plan = plan_single_book(&lang_source, &lang_config, &format!("{}/", lang.code), &dest_prefix)?;
+        if dir.is_empty() && !dirs.contains(&lang.code) {
+            let redirects: Vec<PlannedFile> = lang_plan
+                .pages
+                .iter()
+                .map(|page| PlannedFile {
+                    source: page.source.clone(),
+                    dest: format!("{}/{}", lang.code, page.dest),
+                    note: Some("redirect to the root"),
+                })
+                .collect();
+            plan.pages.extend(lang_plan.pages);
+            plan.pages.extend(redirects);
+        } else {
+            plan.pages.extend(lang_plan.pages);
+        }
+        plan.assets.extend(lang_plan.assets);
+        plan.missing.extend(lang_plan.missing);
+    }
+    let assets_dir = source.join("assets");
+    if assets_dir.exists() {
+        plan_dir_files(&source, &assets_dir, "assets", &mut plan.assets);
+    }
+    Ok(plan)
+}
+
+/// book.json, without creating a default one like [`BookConfig::load`] does
+fn load_config(book_dir: &Path) -> Result<BookConfig> {
+    if book_dir.join("book.json").exists() {
+        BookConfig::load(book_dir)
+    } else {
+        Ok(BookConfig::default())
+    }
+}
+
+/// prefix: language directory of multi-language books ("en/"), prepended to source paths
+/// dest_prefix: output directory of the language ("en/", "" at the root), prepended to destinations
+fn plan_single_book(source: &Path, config: &BookConfig, prefix: &str, dest_prefix: &str) -> Result<BuildPlan> {
+    let summary = generate::load_summary(source, config)?;
+    let mut plan = BuildPlan::default();
+    // Asset sources of multi-language books are listed from the book root
+    let book_root = if prefix.is_empty() { source } else { source.parent().unwrap_or(source) };
+
+    let mut built = HashSet::new();
+    plan_pages(source, &summary.items, &summary, prefix, dest_prefix, &mut built, &mut plan);
+    if source.join("README.md").exists() {
+        plan.pages.push(PlannedFile {
+            source: format!("{}README.md", prefix),
+            dest: format!("{}index.html", dest_prefix),
+            note: None,
+        });
+    }
+
+    for (src_dir, relative) in asset_dirs(source)? {
+        let relative = format!("{}{}", dest_prefix, paths::to_slash(&relative));
+        plan_dir_files(book_root, &src_dir, &relative, &mut plan.assets);
+    }
+    if let Some(style_path) = config.get_website_style() {
+        if source.join(style_path).exists() {
+            plan.assets.push(PlannedFile {
+                source: format!("{}{}", prefix, style_path.trim_start_matches("./")),
+                dest: format!("{}gitbook/style.css", dest_prefix),
+                note: None,
+            });
+        }
+    }
+    for icon in [Some(icons::favicon(config)), icons::touch_icon(config)].into_iter().flatten() {
+        if let Some(file) = icon.source.filter(|file| source.join(file).is_file()) {
+            plan.assets.push(PlannedFile { source: format!("{}{}", prefix, file), dest: format!("{}{}", dest_prefix, icon.path), note: None });
+        }
+    }
+    Ok(plan)
+}
+
+/// Pages of SUMMARY.md entries, skipping repeated and missing sources like the build
+fn plan_pages(
+    source: &Path,
+    items: &[SummaryItem],
+    summary: &Summary,
+    prefix: &str,
+    dest_prefix: &str,
+    built: &mut HashSet<String>,
+    plan: &mut BuildPlan,
+) {
+    for item in items {
+        let SummaryItem::Link { path, children, .. } = item else {
+            continue;
+        };
+        let base_path = paths::split_fragment(path.as_deref().unwrap_or("")).0.trim_start_matches('/');
+        if !base_path.is_empty() && built.insert(base_path.to_string()) {
+            if !summary.has_source(source, base_path) {
+                plan.missing.push(format!("{}{}", prefix, base_path));
+                plan_pages(source, children, summary, prefix, dest_prefix, built, plan);
+                continue;
+            }
+            let files = summary.page_sources(base_path);
+            let (page_source, note) = match summary.generated.get(base_path) {
+                Some(page) => (page.data_file.clone(), Some("generated")),
+                None if is_split(source, base_path) => (base_path.to_string(), Some("split into one page per section")),
+                None if files.len() > 1 => (files.join(", "), Some("merged")),
+                None => (base_path.to_string(), None),
+            };
+            // Chapters shared from another language are listed from the book root
+            let page_source = match parser::shared_source(&page_source) {
+                Some((dir, path)) if !prefix.is_empty() => format!("{}/{}", dir, path),
+                _ => format!("{}{}", prefix, page_source),
+            };
+            plan.pages.push(PlannedFile {
+                source: page_source,
+                dest: format!("{}{}", dest_prefix, summary.html_path(base_path)),
+                note,
+            });
+        }
+        plan_pages(source, children, summary, prefix, dest_prefix, built, plan);
+    }
+}
+
+/// Whether a page asks to be split with `split:` front matter
+fn is_split(source: &Path, base_path: &str) -> bool {
+    let content = fs::read_to_string(source.join(base_path)).unwrap_or_default();
+    parser::frontmatter::parse_front_matter(&content)
+        .front_matter
+        .is_some_and(|fm| fm.split.is_some())
+}
+
+/// Files under `dir`, copied to `dest_dir` (output-relative)
+/// Sources are listed relative to `root`
+fn plan_dir_files(root: &Path, dir: &Path, dest_dir: &str, assets: &mut Vec<PlannedFile>) {
+    for entry in paths::walk(dir).sort_by_file_name().into_iter().filter_map(|e| e.ok()) {
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        let (Some(relative), Some(source)) = (paths::relative(dir, entry.path()), paths::relative(root, entry.path())) else {
+            continue;
+        };
+        assets.push(PlannedFile {
+            source,
+            dest: format!("{}/{}", dest_dir, relative),
+            note: None,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_build_lists_pages_and_assets() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("book.json"), r#"{"styles": {"website": "styles/site.css"}}"#).unwrap();
+        fs::write(
+            root.join("SUMMARY.md"),
+            "# Summary\n\n* [Intro](README.md)\n* [Guide](guide/start.md)\n    * [Setup](guide/start.md#setup)\n* [Long](long.md)\n* [Both](<a.md, b.md>)\n* [Gone](gone.md)\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("guide/img")).unwrap();
+        fs::create_dir_all(root.join("images")).unwrap();
+        fs::create_dir_all(root.join("styles")).unwrap();
+        for file in ["README.md", "guide/start.md", "a.md", "b.md"] {
+            fs::write(root.join(file), "# Page\n").unwrap();
+        }
+        fs::write(root.join("long.md"), "---\nsplit: h2\n---\n# Long\n").unwrap();
+        fs::write(root.join("guide/img/shot.png"), "png").unwrap();
+        fs::write(root.join("images/logo.png"), "png").unwrap();
+        fs::write(root.join("styles/site.css"), "body {}").unwrap();
+
+        let plan = plan_build(root, None).unwrap();
+        let pages: Vec<(&str, &str, Option<&str>)> =
+            plan.pages.iter().map(|p| (p.source.as_str(), p.dest.as_str(), p.note)).collect();
+        assert_eq!(
+            pages,
+            vec![
+                ("README.md", "README.html", None),
+                ("guide/start.md", "guide/start.html", None),
+                ("long.md", "long.html", Some("split into one page per section")),
+                ("a.md, b.md", "a.html", Some("merged")),
+                ("README.md", "index.html", None),
+            ]
+        );
+        let assets: Vec<(&str, &str)> = plan.assets.iter().map(|a| (a.source.as_str(), a.dest.as_str())).collect();
+        assert_eq!(
+            assets,
+            vec![
+                ("images/logo.png", "images/logo.png"),
+                ("guide/img/shot.png", "guide/img/shot.png"),
+                ("styles/site.css", "gitbook/style.css"),
+            ]
+        );
+        assert_eq!(plan.missing, vec!["gone.md"]);
+        assert!(!root.join("_book").exists());
+    }
+}
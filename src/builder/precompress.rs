@@ -0,0 +1,140 @@
+//! Precompressed output (`"precompress": true` in book.json)
+//!
+//! After a build, every HTML, CSS, JavaScript and JSON file of the output gets
+//! a gzip (`.gz`) and a brotli (`.br`) copy next to it, for static hosts and
+//! CDNs that serve precompressed files (nginx `gzip_static`, Caddy
+//! `precompressed`, ...). A copy that would not be smaller than the file is
+//! not written. Copies left from previous builds whose file is gone or
+//! no longer compresses are removed. Hidden entries of the output (.git, ...)
+//! are skipped, and so is manifest.json: the copies are written before it, so
+//! that it lists them.
+
+use super::manifest::MANIFEST_FILE;
+use crate::paths;
+use anyhow::Result;
+use flate2::write::GzEncoder;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Extensions of the files that are compressed
+const EXTENSIONS: [&str; 4] = ["html", "css", "js", "json"];
+
+/// Extensions of the compressed copies
+const COMPRESSED: [&str; 2] = ["gz", "br"];
+
+/// Write the compressed copies of the files of `output`; returns the number
+/// of copies written
+pub fn precompress_output(output: &Path) -> Result<usize> {
+    let mut written = 0;
+    let entries: Vec<_> = paths::walk(output)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(paths::readable)
+        .filter(|e| e.file_type().is_file())
+        .collect();
+
+    for entry in entries {
+        let path = entry.path();
+        let Some(extension) = path.extension().and_then(|e| e.to_str()) else { continue };
+        if COMPRESSED.contains(&extension) {
+            // A copy whose file is gone
+            let original = path.with_extension("");
+            if !original.is_file() || !is_compressible(output, &original) {
+                fs::remove_file(path)?;
+            }
+            continue;
+        }
+        if !is_compressible(output, path) {
+            continue;
+        }
+        let content = fs::read(path)?;
+        for (suffix, compressed) in [("gz", gzip(&content)?), ("br", brotli(&content)?)] {
+            let copy = compressed_path(path, suffix);
+            if compressed.len() < content.len() {
+                fs::write(&copy, compressed)?;
+                written += 1;
+            } else if copy.exists() {
+                fs::remove_file(&copy)?;
+            }
+        }
+    }
+    Ok(written)
+}
+
+fn is_compressible(output: &Path, path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()).is_some_and(|e| EXTENSIONS.contains(&e)) && path != output.join(MANIFEST_FILE)
+}
+
+/// `page.html` -> `page.html.gz`
+fn compressed_path(path: &Path, suffix: &str) -> std::path::PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    name.into()
+}
+
+/// Gzip at the best compression; the header carries no timestamp, so the
+/// same content always gives the same bytes
+fn gzip(content: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(content)?;
+    Ok(encoder.finish()?)
+}
+
+/// Brotli at the best quality, with the text-oriented mode
+fn brotli(content: &[u8]) -> Result<Vec<u8>> {
+    let mut compressed = Vec::new();
+    let params = brotli::enc::BrotliEncoderParams {
+        quality: 11,
+        mode: brotli::enc::backward_references::BrotliEncoderMode::BROTLI_MODE_TEXT,
+        ..Default::default()
+    };
+    brotli::BrotliCompress(&mut &content[..], &mut compressed, &params)?;
+    Ok(compressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_precompress_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path();
+        let page = "<p>guidebook</p>\n".repeat(200);
+        fs::create_dir_all(output.join("gitbook")).unwrap();
+        fs::create_dir_all(output.join(".git")).unwrap();
+        fs::write(output.join("index.html"), &page).unwrap();
+        fs::write(output.join("gitbook/gitbook.css"), "body { color: red; }\n".repeat(50)).unwrap();
+        fs::write(output.join("tiny.json"), "[]").unwrap();
+        fs::write(output.join("image.png"), "x".repeat(1000)).unwrap();
+        fs::write(output.join(".git/config.json"), "x".repeat(1000)).unwrap();
+        fs::write(output.join(MANIFEST_FILE), "{}".repeat(500)).unwrap();
+        // Left from a previous build
+        fs::write(output.join("old.html.gz"), "stale").unwrap();
+        fs::write(output.join("manifest.json.gz"), "stale").unwrap();
+        fs::write(output.join("tiny.json.br"), "stale").unwrap();
+
+        assert_eq!(precompress_output(output).unwrap(), 4);
+        let mut decoded = String::new();
+        GzDecoder::new(&fs::read(output.join("index.html.gz")).unwrap()[..]).read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, page);
+        let mut decoded = Vec::new();
+        brotli::BrotliDecompress(&mut &fs::read(output.join("index.html.br")).unwrap()[..], &mut decoded).unwrap();
+        assert_eq!(decoded, page.as_bytes());
+        assert!(output.join("gitbook/gitbook.css.gz").is_file() && output.join("gitbook/gitbook.css.br").is_file());
+        // Not smaller, not compressible, hidden, stale
+        assert!(!output.join("tiny.json.gz").exists() && !output.join("tiny.json.br").exists());
+        assert!(!output.join("image.png.gz").exists() && !output.join(".git/config.json.gz").exists());
+        assert!(!output.join("old.html.gz").exists() && !output.join("manifest.json.gz").exists());
+
+        // Same content, same bytes
+        let gz = fs::read(output.join("index.html.gz")).unwrap();
+        precompress_output(output).unwrap();
+        assert_eq!(fs::read(output.join("index.html.gz")).unwrap(), gz);
+    }
+}
@@ -0,0 +1,161 @@
+//! Publishing to a git branch (GitHub Pages)
+//!
+//! `guidebook publish` builds the book and commits the output directory to a
+//! branch of the book's git repository (`gh-pages` by default), then pushes
+//! it. The commit is made with git plumbing and a temporary index, so the
+//! working tree, the index and the checked-out branch of the repository are
+//! left alone. Each publish adds one commit on top of the remote branch; the
+//! branch is created when the remote doesn't have it yet.
+//!
+//! A `.nojekyll` file is added to the output so GitHub Pages serves files and
+//! directories starting with `_` (like `_assets`) as they are.
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Branch published to when none is given
+pub const DEFAULT_BRANCH: &str = "gh-pages";
+
+/// Remote pushed to when none is given
+pub const DEFAULT_REMOTE: &str = "origin";
+
+/// Where and how to publish
+pub struct PublishOptions {
+    /// Remote name or URL
+    pub remote: String,
+    /// Branch to commit the output to
+    pub branch: String,
+    /// Commit message (defaults to one naming the source commit)
+    pub message: Option<String>,
+}
+
+impl Default for PublishOptions {
+    fn default() -> Self {
+        PublishOptions { remote: DEFAULT_REMOTE.to_string(), branch: DEFAULT_BRANCH.to_string(), message: None }
+    }
+}
+
+/// Outcome of a publish
+#[derive(Debug, PartialEq)]
+pub enum Published {
+    /// The output was pushed in this commit
+    Commit(String),
+    /// The branch already holds exactly this output
+    Unchanged,
+}
+
+/// Commit `output` to the publish branch and push it
+/// source: directory inside the git repository to publish from
+pub fn publish(source: &Path, output: &Path, options: &PublishOptions) -> Result<Published> {
+    let output = output.canonicalize().with_context(|| format!("Output directory {:?} not found", output))?;
+    git(source, &["rev-parse", "--git-dir"], None)
+        .with_context(|| format!("{:?} is not in a git repository", source))?;
+    fs::write(output.join(".nojekyll"), "")?;
+
+    let branch_ref = format!("refs/heads/{}", options.branch);
+    let remote = options.remote.as_str();
+    let parent = if git(source, &["ls-remote", "--heads", remote, &branch_ref], None)?.is_empty() {
+        None
+    } else {
+        git(source, &["fetch", "--quiet", remote, &branch_ref], None)?;
+        Some(git(source, &["rev-parse", "FETCH_HEAD"], None)?)
+    };
+
+    let tree = write_tree(source, &output)?;
+    if let Some(parent) = &parent {
+        if git(source, &["rev-parse", &format!("{}^{{tree}}", parent)], None)? == tree {
+            return Ok(Published::Unchanged);
+        }
+    }
+
+    let message = match &options.message {
+        Some(message) => message.clone(),
+        None => match git(source, &["rev-parse", "--short", "HEAD"], None) {
+            Ok(head) => format!("Publish book from {}", head),
+            // A repository without commits yet
+            Err(_) => "Publish book".to_string(),
+        },
+    };
+    let mut args = vec!["commit-tree", tree.as_str(), "-m", message.as_str()];
+    if let Some(parent) = &parent {
+        args.extend(["-p", parent.as_str()]);
+    }
+    let commit = git(source, &args, None)?;
+
+    git(source, &["push", "--quiet", remote, &format!("{}:{}", commit, branch_ref)], None)
+        .with_context(|| format!("Failed to push to {} {}", remote, options.branch))?;
+    Ok(Published::Commit(commit))
+}
+
+/// Tree object of every file in `output`, written with a temporary index
+fn write_tree(source: &Path, output: &Path) -> Result<String> {
+    let index = std::env::temp_dir().join(format!("guidebook-publish-{}.index", std::process::id()));
+    let _ = fs::remove_file(&index);
+    let work_tree = output.to_string_lossy();
+    // -f: publish files the repository ignores (like the output directory itself)
+    let result = git(source, &["--work-tree", &work_tree, "add", "--all", "--force", "."], Some(&index))
+        .and_then(|_| git(source, &["write-tree"], Some(&index)));
+    let _ = fs::remove_file(&index);
+    result
+}
+
+/// Run git in `dir` and return its trimmed stdout
+/// index: index file to use instead of the repository's
+pub(super) fn git(dir: &Path, args: &[&str], index: Option<&PathBuf>) -> Result<String> {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(dir).args(args);
+    if let Some(index) = index {
+        command.env("GIT_INDEX_FILE", index);
+    }
+    let result = command.output().context("Failed to run git (is it installed?)")?;
+    if !result.status.success() {
+        bail!("git {} failed: {}", args[0], String::from_utf8_lossy(&result.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&result.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(dir: &Path, args: &[&str]) -> String {
+        git(dir, args, None).unwrap()
+    }
+
+    #[test]
+    fn test_publish_commits_output_to_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let (remote, book, output) = (dir.path().join("remote.git"), dir.path().join("book"), dir.path().join("book/_book"));
+        fs::create_dir_all(&remote).unwrap();
+        fs::create_dir_all(output.join("_assets")).unwrap();
+        run(&remote, &["init", "--quiet", "--bare"]);
+        run(&book, &["init", "--quiet"]);
+        run(&book, &["config", "user.name", "Test"]);
+        run(&book, &["config", "user.email", "test@example.com"]);
+        run(&book, &["remote", "add", "origin", &remote.to_string_lossy()]);
+        fs::write(book.join(".gitignore"), "_book\n").unwrap();
+        fs::write(output.join("index.html"), "v1").unwrap();
+        fs::write(output.join("_assets/style.css"), "body {}").unwrap();
+
+        let first = publish(&book, &output, &PublishOptions::default()).unwrap();
+        let Published::Commit(first) = first else { panic!("nothing published") };
+        assert_eq!(run(&remote, &["rev-parse", "gh-pages"]), first);
+        assert_eq!(
+            run(&remote, &["ls-tree", "-r", "--name-only", "gh-pages"]),
+            ".nojekyll\n_assets/style.css\nindex.html"
+        );
+        // The repository itself is left alone
+        assert_eq!(run(&book, &["status", "--porcelain"]), "?? .gitignore");
+
+        assert_eq!(publish(&book, &output, &PublishOptions::default()).unwrap(), Published::Unchanged);
+
+        fs::write(output.join("index.html"), "v2").unwrap();
+        let options = PublishOptions { message: Some("Docs v2".to_string()), ..Default::default() };
+        let Published::Commit(second) = publish(&book, &output, &options).unwrap() else { panic!("nothing published") };
+        assert_eq!(run(&remote, &["rev-parse", "gh-pages^"]), first);
+        assert_eq!(run(&remote, &["log", "-1", "--format=%s", &second]), "Docs v2");
+        assert_eq!(run(&remote, &["show", "gh-pages:index.html"]), "v2");
+    }
+}
@@ -0,0 +1,190 @@
+//! Installable, offline-readable books (`pwa` section of book.json)
+//!
+//! After a book is built, a web app manifest (`manifest.webmanifest`) and a
+//! service worker (`sw.js`) are written to its output root, and every page
+//! links the manifest and registers the worker. The worker precaches the
+//! files of the build (pages, assets, search_index.json), so a book opened
+//! once can be read offline; other files of the site are cached when fetched.
+//! Its cache is named after a hash of the precached files, so a new build
+//! replaces the copies of the previous one. With `fetchRemoteImages`, remote
+//! images are part of the build too.
+//!
+//! Files larger than `MAX_PRECACHE_SIZE` (videos, large downloads) are only
+//! cached once fetched. The books of other languages, in subdirectories, have
+//! their own manifest and worker.
+
+use crate::parser::{BookConfig, PwaConfig};
+use crate::{paths, warning};
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+/// File name of the web app manifest (manifest.json is the build manifest)
+pub const WEB_MANIFEST_FILE: &str = "manifest.webmanifest";
+
+/// File name of the service worker
+pub const SERVICE_WORKER_FILE: &str = "sw.js";
+
+/// Files larger than this are not precached (bytes)
+const MAX_PRECACHE_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Hex digits of the build hash in the cache name
+const VERSION_LENGTH: usize = 12;
+
+const SERVICE_WORKER_JS: &str = include_str!("../../templates/sw.js");
+
+/// Write the web app manifest and the service worker of the book built in
+/// `output`; returns the number of files the worker precaches
+pub fn write_app(output: &Path, config: &BookConfig, pwa: &PwaConfig) -> Result<usize> {
+    fs::write(output.join(WEB_MANIFEST_FILE), web_manifest(output, config, pwa)?)?;
+
+    let files = precache_files(output)?;
+    let mut hasher = Sha256::new();
+    for (path, content) in &files {
+        hasher.update(path.as_bytes());
+        hasher.update([0]);
+        hasher.update(content);
+    }
+    let version = format!("{:x}", hasher.finalize());
+    let urls: Vec<String> = files.iter().map(|(path, _)| paths::encode_href(path)).collect();
+    let worker = SERVICE_WORKER_JS
+        .replace("'__VERSION__'", &format!("'{}'", &version[..VERSION_LENGTH]))
+        .replace("[/* __PRECACHE__ */]", &serde_json::to_string(&urls)?);
+    fs::write(output.join(SERVICE_WORKER_FILE), super::minify::asset(SERVICE_WORKER_FILE, &worker, config))?;
+    Ok(files.len())
+}
+
+/// JSON of `manifest.webmanifest`; icons are files of the output
+fn web_manifest(output: &Path, config: &BookConfig, pwa: &PwaConfig) -> Result<String> {
+    let name = pwa.name.as_deref().unwrap_or(&config.title);
+    let mut manifest = serde_json::json!({
+        "name": name,
+        "short_name": pwa.short_name.as_deref().unwrap_or(name),
+        "start_url": "./",
+        "scope": "./",
+        "display": "standalone",
+    });
+    if !config.description.is_empty() {
+        manifest["description"] = config.description.clone().into();
+    }
+    if !config.language.is_empty() {
+        manifest["lang"] = config.language.clone().into();
+    }
+    if let Some(color) = &pwa.theme_color {
+        manifest["theme_color"] = color.clone().into();
+    }
+    if let Some(color) = &pwa.background_color {
+        manifest["background_color"] = color.clone().into();
+    }
+    let icons: Vec<serde_json::Value> = pwa.icons.iter().filter_map(|icon| icon_entry(output, icon)).collect();
+    if !icons.is_empty() {
+        manifest["icons"] = icons.into();
+    }
+    Ok(serde_json::to_string_pretty(&manifest)?)
+}
+
+/// Manifest entry of an icon, with its size read from the file
+fn icon_entry(output: &Path, icon: &str) -> Option<serde_json::Value> {
+    let path = paths::normalize(icon.trim_start_matches("./"));
+    let file = output.join(&path);
+    if !file.is_file() {
+        warning!("pwa.icons: {} is not a file of the book", icon);
+        return None;
+    }
+    let extension = path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+    let (mime, sizes) = match extension.as_str() {
+        "svg" => ("image/svg+xml".to_string(), "any".to_string()),
+        "png" | "jpg" | "jpeg" | "webp" => {
+            let Ok((width, height)) = image::image_dimensions(&file) else {
+                warning!("pwa.icons: {} is not a readable image", icon);
+                return None;
+            };
+            let mime = if extension == "jpg" { "jpeg" } else { extension.as_str() };
+            (format!("image/{}", mime), format!("{}x{}", width, height))
+        }
+        _ => {
+            warning!("pwa.icons: {} is not a PNG, JPEG, WebP or SVG image", icon);
+            return None;
+        }
+    };
+    Some(serde_json::json!({ "src": paths::encode_href(&path), "sizes": sizes, "type": mime }))
+}
+
+/// Output-relative paths and contents of the files to precache
+/// ("index.html" also as "./", which browsers ask for)
+fn precache_files(output: &Path) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut files = Vec::new();
+    let entries = paths::walk(output)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| {
+            e.depth() == 0
+                || !(e.file_name().to_string_lossy().starts_with('.') || e.file_type().is_dir() && e.path().join("gitbook").is_dir())
+        })
+        .filter_map(paths::readable)
+        .filter(|e| e.file_type().is_file());
+    for entry in entries {
+        let relative = paths::to_slash(entry.path().strip_prefix(output)?);
+        let skipped = relative == SERVICE_WORKER_FILE
+            || relative == super::manifest::MANIFEST_FILE
+            || relative.ends_with(".gz")
+            || relative.ends_with(".br");
+        if skipped || entry.metadata().map_or(true, |m| m.len() > MAX_PRECACHE_SIZE) {
+            continue;
+        }
+        let content = fs::read(entry.path())?;
+        if relative == "index.html" {
+            files.push(("./".to_string(), content.clone()));
+        }
+        files.push((relative, content));
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_app() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path();
+        fs::create_dir_all(output.join("gitbook")).unwrap();
+        fs::create_dir_all(output.join("guide")).unwrap();
+        fs::create_dir_all(output.join("ja/gitbook")).unwrap();
+        fs::write(output.join("index.html"), "<p>Home</p>").unwrap();
+        fs::write(output.join("guide/set up.html"), "<p>Set up</p>").unwrap();
+        fs::write(output.join("gitbook/gitbook.css"), "body {}").unwrap();
+        fs::write(output.join("gitbook/gitbook.css.gz"), "gz").unwrap();
+        fs::write(output.join("manifest.json"), "{}").unwrap();
+        fs::write(output.join("ja/index.html"), "<p>ホーム</p>").unwrap();
+        image::RgbaImage::new(48, 32).save(output.join("icon.png")).unwrap();
+
+        let config: BookConfig = serde_json::from_str(
+            r##"{"title": "Handbook", "language": "en", "pwa": {"shortName": "HB", "themeColor": "#336699", "icons": ["icon.png", "missing.png"]}}"##,
+        )
+        .unwrap();
+        assert_eq!(write_app(output, &config, config.pwa.as_ref().unwrap()).unwrap(), 6);
+
+        let manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(output.join(WEB_MANIFEST_FILE)).unwrap()).unwrap();
+        assert_eq!(manifest["name"], "Handbook");
+        assert_eq!(manifest["short_name"], "HB");
+        assert_eq!(manifest["theme_color"], "#336699");
+        assert_eq!(manifest["lang"], "en");
+        assert_eq!(manifest["icons"], serde_json::json!([{"src": "icon.png", "sizes": "48x32", "type": "image/png"}]));
+
+        let worker = fs::read_to_string(output.join(SERVICE_WORKER_FILE)).unwrap();
+        assert!(
+            worker.contains(r#"["gitbook/gitbook.css","guide/set%20up.html","icon.png","./","index.html","manifest.webmanifest"]"#),
+            "{}",
+            worker
+        );
+        assert!(!worker.contains("__VERSION__") && !worker.contains("ja/"), "{}", worker);
+
+        // A changed page gives a new cache
+        fs::write(output.join("guide/set up.html"), "<p>Setting up</p>").unwrap();
+        write_app(output, &config, config.pwa.as_ref().unwrap()).unwrap();
+        assert_ne!(fs::read_to_string(output.join(SERVICE_WORKER_FILE)).unwrap(), worker);
+    }
+}
@@ -0,0 +1,157 @@
+//! Redirects from old URLs (`redirect_from` in front matter)
+//!
+//! A page lists the paths it used to be published at:
+//!
+//! ```yaml
+//! redirect_from:
+//!   - /setup.html
+//!   - guide/install/
+//! ```
+//!
+//! Each gets a stub page with a meta refresh and a canonical link to the
+//! page (its absolute URL under `deploy.siteUrl` when set), so bookmarks
+//! survive a restructured book. `.md` paths are their
+//! published `.html`, directories their `index.html`, and paths without an
+//! extension get `.html` (hosts serve `old.html` for `/old`). Stubs never
+//! replace a page of the book.
+
+use super::renderer::html_escape;
+use crate::parser::BookConfig;
+use crate::{paths, warning};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// An old path of a page
+#[derive(Debug)]
+pub struct Redirect {
+    /// Output path of the stub (e.g. "setup.html")
+    pub from: String,
+    /// Published path of the page (e.g. "guide/start.html")
+    pub to: String,
+    /// Book-relative source path of the page, for warnings
+    pub source: String,
+}
+
+/// The redirects a page declares; invalid paths are warned about and skipped
+pub fn page_redirects(redirect_from: &[String], to: &str, source: &str) -> Vec<Redirect> {
+    redirect_from
+        .iter()
+        .filter_map(|from| match redirect_path(from) {
+            Some(path) => Some(Redirect { from: path, to: to.to_string(), source: source.to_string() }),
+            None => {
+                warning!("{}: redirect_from {:?} is not a path in the book", source, from);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Output path of the stub for an old URL path
+fn redirect_path(from: &str) -> Option<String> {
+    let path = from.trim().split(['?', '#']).next().unwrap_or("");
+    if path.contains(':') || path.split(['/', '\\']).any(|segment| segment == "..") {
+        return None;
+    }
+    let directory = path.is_empty() || path.ends_with('/');
+    let path = paths::normalize(path);
+    let path = path.trim_start_matches('/');
+    Some(if path.is_empty() {
+        "index.html".to_string()
+    } else if directory {
+        format!("{}/index.html", path)
+    } else if let Some(stem) = path.strip_suffix(".md") {
+        format!("{}.html", stem)
+    } else if path.ends_with(".html") || path.ends_with(".htm") {
+        path.to_string()
+    } else {
+        format!("{}.html", path)
+    })
+}
+
+/// Write the stub pages of `redirects`, except at the paths of `pages` (output
+/// path -> source); the stubs are added to `pages`. Returns the number written
+/// language: language code when building a language directory of a multi-language book
+pub fn write_redirects(
+    output: &Path,
+    config: &BookConfig,
+    language: Option<&str>,
+    redirects: &[Redirect],
+    pages: &mut BTreeMap<String, String>,
+) -> Result<usize> {
+    let dir = language.map(|code| config.languages.output_dir(code)).unwrap_or_default();
+    let mut count = 0;
+    for redirect in redirects {
+        if let Some(page) = pages.get(&redirect.from) {
+            warning!("{}: redirect_from {} is already published from {}", redirect.source, redirect.from, page);
+            continue;
+        }
+        let target = format!("{}{}", config.root_path(paths::depth(&redirect.from)), paths::encode_href(&redirect.to));
+        let site_path = if dir.is_empty() { redirect.to.clone() } else { format!("{}/{}", dir, redirect.to) };
+        let canonical = canonical_url(config, &site_path);
+        let dest = output.join(&redirect.from);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(dest, stub_html(&target, canonical.as_deref()))?;
+        pages.insert(redirect.from.clone(), redirect.source.clone());
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Absolute URL of the site path of a page under `deploy.siteUrl`; None
+/// without a site URL
+pub fn canonical_url(config: &BookConfig, site_path: &str) -> Option<String> {
+    let site_url = config.deploy.site_url.trim_end_matches('/');
+    (!site_url.is_empty()).then(|| format!("{}/{}", site_url, paths::encode_href(site_path)))
+}
+
+/// Page that sends browsers and crawlers on to `target`; the canonical link
+/// is `canonical` when given (crawlers expect an absolute URL), else `target`
+pub fn stub_html(target: &str, canonical: Option<&str>) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"UTF-8\">\n<meta http-equiv=\"refresh\" content=\"0; url={0}\">\n<link rel=\"canonical\" href=\"{1}\">\n</head>\n<body><a href=\"{0}\">{0}</a></body>\n</html>\n",
+        html_escape(target),
+        html_escape(canonical.unwrap_or(target))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redirect_path() {
+        assert_eq!(redirect_path("/setup.html").as_deref(), Some("setup.html"));
+        assert_eq!(redirect_path("guide/install/").as_deref(), Some("guide/install/index.html"));
+        assert_eq!(redirect_path("/").as_deref(), Some("index.html"));
+        assert_eq!(redirect_path("old/start.md").as_deref(), Some("old/start.html"));
+        assert_eq!(redirect_path("/old/start?x=1").as_deref(), Some("old/start.html"));
+        assert_eq!(redirect_path("../outside.html"), None);
+        assert_eq!(redirect_path("https://example.com/a.html"), None);
+    }
+
+    #[test]
+    fn test_write_redirects() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path();
+        let redirects = page_redirects(&["/setup.html".to_string(), "old/install/".to_string()], "guide/setup.html", "guide/setup.md");
+        let mut pages = BTreeMap::new();
+
+        let config = BookConfig::default();
+        assert_eq!(write_redirects(output, &config, None, &redirects, &mut pages).unwrap(), 2);
+        let stub = fs::read_to_string(output.join("old/install/index.html")).unwrap();
+        assert!(stub.contains(r#"<meta http-equiv="refresh" content="0; url=../../guide/setup.html">"#), "{}", stub);
+        assert!(stub.contains(r#"<link rel="canonical" href="../../guide/setup.html">"#), "{}", stub);
+
+        // Absolute under deploy.siteUrl, in the language's directory
+        let config: BookConfig = serde_json::from_str(r#"{"deploy": {"siteUrl": "https://docs.example.com/book/"}}"#).unwrap();
+        pages.clear();
+        write_redirects(output, &config, Some("fr"), &redirects, &mut pages).unwrap();
+        let stub = fs::read_to_string(output.join("setup.html")).unwrap();
+        assert!(stub.contains(r#"<meta http-equiv="refresh" content="0; url=./guide/setup.html">"#), "{}", stub);
+        assert!(stub.contains(r#"<link rel="canonical" href="https://docs.example.com/book/fr/guide/setup.html">"#), "{}", stub);
+    }
+}
@@ -1,5 +1,7 @@
+use super::media::{media_kind, player_html, MediaKind};
+use crate::parser::AutolinkConfig;
+use crate::{paths, warning};
 use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd, CodeBlockKind, HeadingLevel};
-use std::path::Path;
 
 /// Table of Contents item
 #[derive(Debug, Clone)]
@@ -38,7 +40,7 @@ pub fn extract_headings(content: &str) -> Vec<TocItem> {
             Event::End(TagEnd::Heading(level)) if in_heading.is_some() => {
                 let level_num = heading_level_to_num(*level);
                 // Only include h2, h3, h4 in TOC (skip h1 which is page title)
-                if level_num >= 2 && level_num <= 4 {
+                if (2..=4).contains(&level_num) {
                     let id = slugify(&heading_text);
                     headings.push(TocItem {
                         level: level_num,
@@ -58,10 +60,11 @@ pub fn extract_headings(content: &str) -> Vec<TocItem> {
 /// Render markdown content to HTML with Mermaid support
 /// current_path: the path of the current markdown file (e.g., "Customer/AssetStatus/PortfolioTop.md")
 /// hardbreaks: when true, treat single newlines as hard breaks (<br>)
-pub fn render_markdown_with_path(content: &str, current_path: Option<&str>, hardbreaks: bool) -> String {
+/// autolink: how bare URLs are turned into links (`autolink` in book.json)
+pub fn render_markdown_with_path(content: &str, current_path: Option<&str>, hardbreaks: bool, autolink: &AutolinkConfig) -> String {
     // Normalize CRLF/CR to LF for consistent line handling
     let content = content.replace("\r\n", "\n").replace("\r", "\n");
-    let html = render_markdown_internal(&content, hardbreaks);
+    let html = render_markdown_internal(&content, hardbreaks, &Autolinker::new(autolink));
 
     // If we have a current path, convert relative links to absolute
     if let Some(path) = current_path {
@@ -75,17 +78,17 @@ pub fn render_markdown_with_path(content: &str, current_path: Option<&str>, hard
 pub fn render_markdown(content: &str) -> String {
     // Normalize CRLF/CR to LF for consistent line handling
     let content = content.replace("\r\n", "\n").replace("\r", "\n");
-    render_markdown_internal(&content, false)
+    render_markdown_internal(&content, false, &Autolinker::default())
 }
 
 /// Render markdown content to HTML with hardbreaks option
 pub fn render_markdown_with_hardbreaks(content: &str, hardbreaks: bool) -> String {
     // Normalize CRLF/CR to LF for consistent line handling
     let content = content.replace("\r\n", "\n").replace("\r", "\n");
-    render_markdown_internal(&content, hardbreaks)
+    render_markdown_internal(&content, hardbreaks, &Autolinker::default())
 }
 
-fn render_markdown_internal(content: &str, hardbreaks: bool) -> String {
+fn render_markdown_internal(content: &str, hardbreaks: bool, autolinker: &Autolinker) -> String {
     // Strip all UTF-8 BOM characters (fixes reference link parsing issues)
     // BOM can appear at start of file or in concatenated content from @import
     let content = content.replace('\u{FEFF}', "");
@@ -97,9 +100,13 @@ fn render_markdown_internal(content: &str, hardbreaks: bool) -> String {
     let content = fix_multiline_footnotes(&content);
     // Preprocess: fix malformed table separator rows
     let content = fix_table_separator_columns(&content);
+    // Preprocess: turn `> [!type]` callouts into admonition blocks
+    let content = super::admonitions::convert_callouts(&content);
+    // Preprocess: turn blockquotes ending with `> — Author` into figures
+    let content = super::admonitions::convert_attributions(&content);
 
     // Convert footnote definitions to inline format (preserve original position)
-    let content = convert_footnote_definitions_inline(&content, hardbreaks);
+    let content = convert_footnote_definitions_inline(&content, hardbreaks, autolinker);
 
     // Convert footnote references [^n] to placeholders BEFORE markdown parsing
     // This prevents [A][^1] from being interpreted as a markdown link reference
@@ -113,6 +120,10 @@ fn render_markdown_internal(content: &str, hardbreaks: bool) -> String {
     options.insert(Options::ENABLE_TASKLISTS);
     options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
 
+    // Take table captions and column widths out, to add after each `<table>`
+    let (content, table_extras) = super::tables::extract_table_extras(&content, options);
+    let mut table_extras = table_extras.into_iter();
+
     let parser = Parser::new_ext(&content, options);
 
     // Process events to handle mermaid code blocks and heading IDs
@@ -121,10 +132,30 @@ fn render_markdown_internal(content: &str, hardbreaks: bool) -> String {
     let mut in_heading: Option<HeadingLevel> = None;
     let mut heading_text = String::new();
     let mut custom_heading_id: Option<String> = None;  // Store custom ID from {#id} syntax
+    // Image syntax for a video or audio file: (kind, url, alt text)
+    let mut media: Option<(MediaKind, String, String)> = None;
     let mut events: Vec<Event> = Vec::new();
 
-    for event in parser {
+    for event in convert_remaining_markdown_images(parser) {
         match &event {
+            // Video and audio files in image syntax become players
+            Event::Start(Tag::Image { dest_url, .. }) if media_kind(dest_url).is_some() => {
+                media = media_kind(dest_url).map(|kind| (kind, dest_url.to_string(), String::new()));
+                continue;
+            }
+            Event::Text(text) | Event::Code(text) if media.is_some() => {
+                if let Some((_, _, alt)) = media.as_mut() {
+                    alt.push_str(text);
+                }
+                continue;
+            }
+            Event::End(TagEnd::Image) if media.is_some() => {
+                if let Some((kind, url, alt)) = media.take() {
+                    events.push(Event::Html(player_html(kind, &url, &alt).into()));
+                }
+                continue;
+            }
+            _ if media.is_some() => continue,
             Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) => {
                 let lang_str = lang.as_ref();
                 if lang_str == "mermaid" || lang_str.starts_with("mermaid") {
@@ -186,6 +217,13 @@ fn render_markdown_internal(content: &str, hardbreaks: bool) -> String {
                 in_heading = None;
                 continue;
             }
+            Event::Start(Tag::Table(_)) => {
+                events.push(event.clone());
+                if let Some(html) = table_extras.next().map(|t| t.html()).filter(|h| !h.is_empty()) {
+                    events.push(Event::Html(html.into()));
+                }
+                continue;
+            }
             // Convert soft breaks to hard breaks when hardbreaks option is enabled
             Event::SoftBreak if hardbreaks => {
                 events.push(Event::HardBreak);
@@ -196,6 +234,9 @@ fn render_markdown_internal(content: &str, hardbreaks: bool) -> String {
         events.push(event);
     }
 
+    // Auto-link URLs that are not already linked
+    let events = autolink_urls(events, autolinker);
+
     let mut html_output = String::new();
     html::push_html(&mut html_output, events.into_iter());
 
@@ -205,15 +246,6 @@ fn render_markdown_internal(content: &str, hardbreaks: bool) -> String {
     // Remove leading slashes from internal links
     html_output = remove_leading_slash_from_links(&html_output);
 
-    // Auto-link URLs that are not already linked
-    html_output = autolink_urls(&html_output);
-
-    // Add target="_blank" to external links (Markdown-style links like [text](https://...))
-    html_output = add_target_blank_to_external_links(&html_output);
-
-    // Convert any remaining markdown images inside HTML blocks to <img> tags
-    html_output = convert_remaining_markdown_images(&html_output);
-
     // Convert footnote placeholders to HTML
     html_output = convert_footnote_placeholders_to_html(&html_output);
 
@@ -232,7 +264,7 @@ fn heading_level_to_num(level: HeadingLevel) -> u8 {
 }
 
 /// Generate a URL-safe slug from text (matching github-slugger / HonKit behavior)
-fn slugify(text: &str) -> String {
+pub(crate) fn slugify(text: &str) -> String {
     text.to_lowercase()
         .chars()
         .filter_map(|c| {
@@ -255,7 +287,7 @@ fn slugify(text: &str) -> String {
         .join("-")
 }
 
-fn html_escape(s: &str) -> String {
+pub fn html_escape(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
         .replace('>', "&gt;")
@@ -301,9 +333,8 @@ fn resolve_reference_links(text: &str, reference_links: &std::collections::HashM
                 let after_bracket = &rest[end_byte + 1..];
 
                 // Check for full reference link [text][ref]
-                if after_bracket.starts_with('[') {
+                if let Some(after_second_open) = after_bracket.strip_prefix('[') {
                     // Find the second closing bracket
-                    let after_second_open = &after_bracket[1..];
                     if let Some(second_end_byte) = after_second_open.find(']') {
                         let ref_label = &after_second_open[..second_end_byte];
                         // Look up the reference (use ref_label, or first_label if ref is empty)
@@ -350,7 +381,7 @@ fn resolve_reference_links(text: &str, reference_links: &std::collections::HashM
 }
 
 /// Convert footnote definitions in-place to HTML (preserve original position)
-fn convert_footnote_definitions_inline(content: &str, hardbreaks: bool) -> String {
+fn convert_footnote_definitions_inline(content: &str, hardbreaks: bool, autolinker: &Autolinker) -> String {
     // Collect reference link definitions for resolving within footnotes
     let reference_links = collect_reference_links(content);
 
@@ -366,8 +397,17 @@ fn convert_footnote_definitions_inline(content: &str, hardbreaks: bool) -> Strin
             // Trim trailing whitespace from first line (for hardbreaks consistency)
             let first_line_content = first_line_content.trim_end();
 
+            // The first line ends up in raw HTML, so link bare URLs here
+            let first_line_linked: String = split_bare_urls(first_line_content)
+                .into_iter()
+                .map(|run| match run {
+                    TextRun::Plain(plain) => plain.to_string(),
+                    TextRun::Url(url) => autolinker.link(url).unwrap_or_else(|| url.to_string()),
+                })
+                .collect();
+
             // Resolve reference links in the first line content
-            let first_line_resolved = resolve_reference_links(first_line_content, &reference_links);
+            let first_line_resolved = resolve_reference_links(&first_line_linked, &reference_links);
 
             let mut continuation_lines: Vec<String> = Vec::new();
 
@@ -413,7 +453,7 @@ fn convert_footnote_definitions_inline(content: &str, hardbreaks: bool) -> Strin
                 // Multi-line footnote: first line in blockquote, continuation outside
                 // This matches HonKit behavior: blockquote has border, continuation doesn't
                 let continuation_content = continuation_lines.join("\n");
-                let continuation_html = render_footnote_continuation(&continuation_content, hardbreaks);
+                let continuation_html = render_footnote_continuation(&continuation_content, hardbreaks, autolinker);
                 result_lines.push(format!(
                     "<blockquote id=\"fn_{}\"><sup>{}</sup>. {}{}</blockquote>\n{}",
                     number, number, first_line_resolved, return_link, continuation_html
@@ -501,7 +541,7 @@ fn parse_footnote_def_start(line: &str) -> Option<(&str, &str)> {
 
 
 /// Render footnote continuation content (lists, paragraphs after first line)
-fn render_footnote_continuation(content: &str, hardbreaks: bool) -> String {
+fn render_footnote_continuation(content: &str, hardbreaks: bool, autolinker: &Autolinker) -> String {
     // Find minimum indentation (excluding empty lines) to preserve relative indentation
     let min_indent = content
         .lines()
@@ -542,7 +582,7 @@ fn render_footnote_continuation(content: &str, hardbreaks: bool) -> String {
     }).collect();
 
     let mut html = String::new();
-    html::push_html(&mut html, events.into_iter());
+    html::push_html(&mut html, autolink_urls(events, autolinker).into_iter());
 
     html.trim().to_string()
 }
@@ -656,11 +696,7 @@ fn count_table_columns(line: &str) -> usize {
     let pipe_count = trimmed.chars().filter(|&c| c == '|').count();
 
     // Number of columns = pipes - 1 (for |col1|col2|col3| format)
-    if pipe_count > 1 {
-        pipe_count - 1
-    } else {
-        0
-    }
+    pipe_count.saturating_sub(1)
 }
 
 /// Check if a line is a table separator row (contains only |, -, :, and whitespace)
@@ -747,76 +783,58 @@ fn fix_fullwidth_heading_spaces(content: &str) -> String {
 
 /// Fix image paths that contain spaces by wrapping them in angle brackets
 /// Converts ![alt](path with space.png) to ![alt](<path with space.png>)
+/// Code spans and code blocks are left as written, and an image never spans
+/// lines, so an unclosed bracket can't swallow the rest of the page
 fn fix_image_paths_with_spaces(content: &str) -> String {
-    let mut result = String::new();
-    let mut chars = content.chars().peekable();
-
-    while let Some(c) = chars.next() {
-        if c == '!' {
-            // Check for image syntax: ![...](...)
-            if chars.peek() == Some(&'[') {
-                // Collect the entire potential image syntax
-                let mut img_str = String::from("!");
-                img_str.push(chars.next().unwrap()); // '['
-
-                // Read alt text until ']'
-                let mut bracket_depth = 1;
-                while let Some(&ch) = chars.peek() {
-                    img_str.push(chars.next().unwrap());
-                    if ch == '[' {
-                        bracket_depth += 1;
-                    } else if ch == ']' {
-                        bracket_depth -= 1;
-                        if bracket_depth == 0 {
-                            break;
-                        }
-                    }
-                }
-
-                // Check for '(' after ']'
-                if chars.peek() == Some(&'(') {
-                    img_str.push(chars.next().unwrap()); // '('
-
-                    // Read URL until ')'
-                    let mut url = String::new();
-                    let mut paren_depth = 1;
-                    while let Some(&ch) = chars.peek() {
-                        if ch == '(' {
-                            paren_depth += 1;
-                            url.push(chars.next().unwrap());
-                        } else if ch == ')' {
-                            paren_depth -= 1;
-                            if paren_depth == 0 {
-                                chars.next(); // consume ')'
-                                break;
-                            }
-                            url.push(chars.next().unwrap());
-                        } else {
-                            url.push(chars.next().unwrap());
-                        }
-                    }
+    let re = regex::Regex::new(r"!\[([^\[\]\n]*(?:\[[^\[\]\n]*\][^\[\]\n]*)*)\]\(([^()<>\n]*)\)").unwrap();
+    let wrap = |text: &str| {
+        re.replace_all(text, |caps: &regex::Captures| {
+            let url = caps[2].trim();
+            if url.contains(' ') {
+                format!("![{}](<{}>)", &caps[1], url)
+            } else {
+                caps[0].to_string()
+            }
+        })
+        .into_owned()
+    };
+
+    let mut result = String::with_capacity(content.len());
+    let mut last = 0;
+    for range in code_ranges(content) {
+        result.push_str(&wrap(&content[last..range.start]));
+        result.push_str(&content[range.clone()]);
+        last = range.end;
+    }
+    result.push_str(&wrap(&content[last..]));
+    result
+}
 
-                    // Check if URL contains spaces and doesn't already use angle brackets
-                    if url.contains(' ') && !url.starts_with('<') {
-                        img_str.push('<');
-                        img_str.push_str(&url);
-                        img_str.push('>');
-                    } else {
-                        img_str.push_str(&url);
-                    }
-                    img_str.push(')');
+/// Byte ranges of code spans and code blocks in markdown, in document order
+pub(crate) fn code_ranges(content: &str) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut block_start: Option<usize> = None;
+    for (event, range) in Parser::new_ext(content, Options::ENABLE_TABLES).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => block_start = Some(range.start),
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some(start) = block_start.take() {
+                    ranges.push(start..range.end);
                 }
-
-                result.push_str(&img_str);
-            } else {
-                result.push(c);
             }
-        } else {
-            result.push(c);
+            Event::Code(_) if block_start.is_none() => ranges.push(range),
+            _ => {}
         }
     }
-
-    result
+    // Nested containers can report overlapping ranges; keep them ordered and disjoint
+    let mut disjoint: Vec<std::ops::Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match disjoint.last_mut() {
+            Some(last) if range.start < last.end => last.end = last.end.max(range.end),
+            _ => disjoint.push(range),
+        }
+    }
+    disjoint
 }
 
 fn fix_relative_links(html: &str) -> String {
@@ -855,14 +873,14 @@ fn remove_leading_slash_from_links(html: &str) -> String {
         // Check for href=" or src="
         if c == '"' || c == '\'' {
             let quote_char = c;
-            // Check if this is after href= or src= (check last 6 ASCII chars)
-            let suffix: String = result.chars().rev().take(6).collect::<Vec<_>>().into_iter().rev().collect();
+            // Check if this is after href= or src= (check the 5 ASCII chars before the quote)
+            let suffix: String = result.chars().rev().skip(1).take(5).collect::<Vec<_>>().into_iter().rev().collect();
             let is_href_or_src = suffix.to_lowercase().ends_with("href=") || suffix.to_lowercase().ends_with("src=");
 
             if is_href_or_src {
                 // Collect the URL
                 let mut url = String::new();
-                while let Some((_, ch)) = chars.next() {
+                for (_, ch) in chars.by_ref() {
                     if ch == quote_char {
                         // Check if URL starts with single / (not //)
                         let processed_url = if url.starts_with('/') && !url.starts_with("//") {
@@ -891,273 +909,206 @@ fn remove_leading_slash_from_links(html: &str) -> String {
 }
 
 /// Convert backslashes to forward slashes in href and src attributes
-/// Handles Windows-style paths like href="path\to\file" → href="path/to/file"
+/// Handles Windows-style paths like href="path\to\file" → href="path/to/file",
+/// also when Markdown rendering percent-encoded them (href="path%5Cto%5Cfile")
 fn normalize_path_separators(html: &str) -> String {
-    let mut result = String::new();
-    let mut chars = html.char_indices().peekable();
-
-    while let Some((_, c)) = chars.next() {
-        result.push(c);
-
-        // Check for href=" or src="
-        if c == '"' || c == '\'' {
-            let quote_char = c;
-            // Check if this is after href= or src= (check last 6 ASCII chars)
-            let suffix: String = result.chars().rev().take(6).collect::<Vec<_>>().into_iter().rev().collect();
-            let is_href_or_src = suffix.to_lowercase().ends_with("href=") || suffix.to_lowercase().ends_with("src=");
-
-            if is_href_or_src {
-                // Collect the URL and normalize backslashes
-                let mut url = String::new();
-                while let Some((_, ch)) = chars.next() {
-                    if ch == quote_char {
-                        // Normalize backslashes to forward slashes
-                        let normalized_url = url.replace('\\', "/");
-                        result.push_str(&normalized_url);
-                        result.push(quote_char);
-                        break;
-                    }
-                    url.push(ch);
-                }
-            }
-        }
-    }
-
-    result
+    let re = regex::Regex::new(r#"(?i)\b((?:href|src)=)("[^"]*"|'[^']*')"#).unwrap();
+    re.replace_all(html, |caps: &regex::Captures| {
+        let url = caps[2].replace('\\', "/");
+        // Percent-encoded by Markdown rendering; left alone in external URLs
+        let url = if url.contains("://") { url } else { url.replace("%5C", "/").replace("%5c", "/") };
+        format!("{}{}", &caps[1], url)
+    })
+    .into_owned()
 }
 
-/// Add target="_blank" rel="noopener noreferrer" to external links that don't have target attribute
-/// This handles Markdown-style links [text](https://...) that were converted to <a href="...">
-fn add_target_blank_to_external_links(html: &str) -> String {
-    let mut result = String::new();
-    let mut chars = html.char_indices().peekable();
-
-    while let Some((i, c)) = chars.next() {
-        if c == '<' && html[i..].starts_with("<a ") {
-            // Found an anchor tag start
-            let mut tag_content = String::from("<a ");
-            // Skip past "<a "
-            chars.next(); // 'a'
-            chars.next(); // ' '
-
-            // Collect the entire tag until '>'
-            while let Some((_, ch)) = chars.next() {
-                tag_content.push(ch);
-                if ch == '>' {
-                    break;
-                }
-            }
+/// A run of text split by [`split_bare_urls`]
+enum TextRun<'a> {
+    Plain(&'a str),
+    Url(&'a str),
+}
 
-            // Check if this is an external link without target attribute
-            let tag_lower = tag_content.to_lowercase();
-            let has_target = tag_lower.contains("target=");
-            let is_external = tag_lower.contains("href=\"http://") || tag_lower.contains("href=\"https://")
-                || tag_lower.contains("href='http://") || tag_lower.contains("href='https://");
-
-            if is_external && !has_target {
-                // Insert target="_blank" rel="noopener noreferrer" before the closing >
-                let without_close = tag_content.trim_end_matches('>');
-                result.push_str(without_close);
-                result.push_str(" target=\"_blank\" rel=\"noopener noreferrer\">");
-            } else {
-                result.push_str(&tag_content);
-            }
+/// Split text into plain runs and bare http(s) URLs
+/// A URL ends at whitespace, <, >, " or ', without trailing punctuation
+fn split_bare_urls(text: &str) -> Vec<TextRun<'_>> {
+    let mut runs = Vec::new();
+    let mut rest = text;
+    while let Some(start) = [rest.find("http://"), rest.find("https://")].into_iter().flatten().min() {
+        let len = rest[start..]
+            .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '\''))
+            .unwrap_or(rest.len() - start);
+        let url = rest[start..start + len]
+            .trim_end_matches(['.', ',', ';', ':', ')', '!', '?']);
+        // A bare scheme is not a link
+        let end = if url.ends_with("//") { start + len } else { start };
+        if end > 0 {
+            runs.push(TextRun::Plain(&rest[..end]));
+        }
+        if end == start {
+            runs.push(TextRun::Url(url));
+            rest = &rest[start + url.len()..];
         } else {
-            result.push(c);
+            rest = &rest[end..];
         }
     }
-
-    result
+    if !rest.is_empty() {
+        runs.push(TextRun::Plain(rest));
+    }
+    runs
 }
 
-/// Auto-link URLs that are not already inside anchor tags or code blocks
-/// Converts bare URLs like https://example.com to <a href="..." target="_blank">...</a>
-fn autolink_urls(html: &str) -> String {
-    let mut result = String::new();
-    let mut chars = html.char_indices().peekable();
-    let mut in_code = false;  // Track if we're inside <code> or <pre>
+/// How bare URLs are turned into links, from the `autolink` section of book.json
+struct Autolinker {
+    enabled: bool,
+    /// Attributes after href, with their leading space
+    attributes: String,
+    exclude: Option<regex::Regex>,
+}
 
-    while let Some((i, c)) = chars.next() {
-        // Check if we're inside an HTML tag
-        if c == '<' {
-            result.push(c);
-
-            // Collect the tag
-            let mut tag_content = String::new();
-            while let Some((_, ch)) = chars.next() {
-                result.push(ch);
-                if ch == '>' {
-                    break;
-                }
-                tag_content.push(ch);
-            }
+impl Default for Autolinker {
+    fn default() -> Self {
+        Autolinker::new(&AutolinkConfig::default())
+    }
+}
 
-            // Check for code/pre tags
-            let tag_lower = tag_content.to_lowercase();
-            if tag_lower.starts_with("code") || tag_lower.starts_with("pre") {
-                in_code = true;
-            } else if tag_lower.starts_with("/code") || tag_lower.starts_with("/pre") {
-                in_code = false;
-            }
-            continue;
+impl Autolinker {
+    fn new(config: &AutolinkConfig) -> Self {
+        // Always set, or the external link pass would add target="_blank"
+        let target = match config.target.as_deref() {
+            None => "_blank",
+            Some("") => "_self",
+            Some(target) => target,
+        };
+        let mut attributes = format!(r#" target="{}""#, html_escape(target));
+        if let Some(rel) = config.rel.as_deref().filter(|rel| !rel.is_empty()) {
+            attributes.push_str(&format!(r#" rel="{}""#, html_escape(rel)));
         }
+        Autolinker { enabled: config.is_enabled(), attributes, exclude: config.exclude_regex() }
+    }
 
-        // Skip auto-linking if inside code block
-        if in_code {
-            result.push(c);
-            continue;
+    /// The link for a bare URL; None when autolinking is disabled or the URL is excluded
+    fn link(&self, url: &str) -> Option<String> {
+        if !self.enabled || self.exclude.as_ref().is_some_and(|re| re.is_match(url)) {
+            return None;
         }
+        let url = html_escape(url);
+        Some(format!(r#"<a href="{}"{}>{}</a>"#, url, self.attributes, url))
+    }
+}
 
-        // Check for http:// or https://
-        if c == 'h' && html[i..].starts_with("http://") || html[i..].starts_with("https://") {
-            // Check if this URL is already inside an href=""
-            if result.ends_with("href=\"") || result.ends_with("src=\"") {
-                // Already in an href, just copy normally
-                result.push(c);
+/// Auto-link bare URLs in text outside links, images and code blocks
+/// Converts https://example.com to <a href="..." target="_blank">...</a>
+/// Raw HTML is never rewritten, so URLs in attributes stay as written
+fn autolink_urls<'a>(events: Vec<Event<'a>>, autolinker: &Autolinker) -> Vec<Event<'a>> {
+    let mut result = Vec::with_capacity(events.len());
+    // The parser splits runs of text at delimiter characters; join them back up
+    let mut text = String::new();
+    // Images can nest inside links
+    let mut link_depth = 0usize;
+    let mut in_code_block = false;
+
+    for event in events {
+        match &event {
+            Event::Text(t) if link_depth == 0 && !in_code_block => {
+                text.push_str(t);
                 continue;
             }
-
-            // Extract the URL
-            let url_start = i;
-            let mut url_end = i + 1;
-
-            // Continue consuming URL characters
-            while let Some(&(next_i, next_c)) = chars.peek() {
-                // URL ends at whitespace, <, >, ", '
-                if next_c.is_whitespace() || next_c == '<' || next_c == '>'
-                    || next_c == '"' || next_c == '\'' {
-                    break;
-                }
-                url_end = next_i + next_c.len_utf8();
-                chars.next();
-            }
-
-            let mut url = &html[url_start..url_end];
-
-            // Remove trailing punctuation that's likely not part of URL
-            while url.ends_with('.') || url.ends_with(',') || url.ends_with(';')
-                || url.ends_with(':') || url.ends_with(')') || url.ends_with('!') || url.ends_with('?') {
-                url = &url[..url.len() - 1];
-            }
-
-            // Create the link with target="_blank"
-            result.push_str(&format!(
-                r#"<a href="{}" target="_blank">{}</a>"#,
-                url, url
-            ));
-
-            // If we trimmed trailing punctuation, add it back
-            let trimmed_len = url_end - url_start - url.len();
-            if trimmed_len > 0 {
-                result.push_str(&html[url_start + url.len()..url_end]);
-            }
-        } else {
-            result.push(c);
+            Event::Start(Tag::Link { .. }) | Event::Start(Tag::Image { .. }) => link_depth += 1,
+            Event::End(TagEnd::Link) | Event::End(TagEnd::Image) => link_depth = link_depth.saturating_sub(1),
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            _ => {}
         }
+        push_autolinked_text(&mut result, &std::mem::take(&mut text), autolinker);
+        result.push(event);
     }
-
+    push_autolinked_text(&mut result, &text, autolinker);
     result
 }
 
-/// Convert remaining markdown image syntax ![alt](url) to <img> tags
-/// This handles images inside raw HTML blocks that pulldown-cmark doesn't parse
-/// Skips content inside <code> and <pre> tags
-fn convert_remaining_markdown_images(html: &str) -> String {
-    let mut result = String::new();
-    let mut chars = html.char_indices().peekable();
-    let mut in_code = false;  // Track if we're inside <code> or <pre>
-
-    while let Some((_, c)) = chars.next() {
-        // Check if we're inside an HTML tag
-        if c == '<' {
-            result.push(c);
-
-            // Collect the tag
-            let mut tag_content = String::new();
-            while let Some((_, ch)) = chars.next() {
-                result.push(ch);
-                if ch == '>' {
-                    break;
-                }
-                tag_content.push(ch);
-            }
+fn push_autolinked_text(events: &mut Vec<Event<'_>>, text: &str, autolinker: &Autolinker) {
+    for run in split_bare_urls(text) {
+        events.push(match run {
+            TextRun::Url(url) => match autolinker.link(url) {
+                Some(link) => Event::Html(link.into()),
+                None => Event::Text(url.to_string().into()),
+            },
+            TextRun::Plain(plain) => Event::Text(plain.to_string().into()),
+        });
+    }
+}
 
-            // Check for code/pre tags
-            let tag_lower = tag_content.to_lowercase();
-            if tag_lower.starts_with("code") || tag_lower.starts_with("pre") {
-                in_code = true;
-            } else if tag_lower.starts_with("/code") || tag_lower.starts_with("/pre") {
-                in_code = false;
+/// Auto-link bare URLs in rendered HTML that has no event stream (AsciiDoc)
+/// Only text between tags is linked, outside <a>, <code> and <pre> elements
+fn autolink_rendered_html(html: &str, autolinker: &Autolinker) -> String {
+    let tag = regex::Regex::new(r"<(/?)([A-Za-z][A-Za-z0-9]*)?[^>]*>").unwrap();
+    let mut result = String::with_capacity(html.len());
+    let mut skip_depth = 0usize;
+    let mut last = 0;
+    for caps in tag.captures_iter(html) {
+        let m = caps.get(0).unwrap();
+        for run in split_bare_urls(&html[last..m.start()]) {
+            // The text is already escaped
+            let link = match run {
+                TextRun::Url(url) if skip_depth == 0 => autolinker.link(&unescape_html(url)),
+                _ => None,
+            };
+            match (link, run) {
+                (Some(link), _) => result.push_str(&link),
+                (None, TextRun::Url(text) | TextRun::Plain(text)) => result.push_str(text),
             }
-            continue;
-        }
-
-        // Skip image conversion if inside code block
-        if in_code {
-            result.push(c);
-            continue;
         }
-
-        if c == '!' && chars.peek().map(|(_, ch)| *ch) == Some('[') {
-            chars.next(); // consume '['
-
-            // Collect alt text until ']'
-            let mut alt = String::new();
-            let mut bracket_depth = 1;
-            while let Some((_, ch)) = chars.next() {
-                if ch == '[' {
-                    bracket_depth += 1;
-                    alt.push(ch);
-                } else if ch == ']' {
-                    bracket_depth -= 1;
-                    if bracket_depth == 0 {
-                        break;
-                    }
-                    alt.push(ch);
-                } else {
-                    alt.push(ch);
-                }
-            }
-
-            // Check for '(' after ']'
-            if chars.peek().map(|(_, ch)| *ch) == Some('(') {
-                chars.next(); // consume '('
-
-                // Collect URL until ')'
-                let mut url = String::new();
-                let mut paren_depth = 1;
-                while let Some((_, ch)) = chars.next() {
-                    if ch == '(' {
-                        paren_depth += 1;
-                        url.push(ch);
-                    } else if ch == ')' {
-                        paren_depth -= 1;
-                        if paren_depth == 0 {
-                            break;
-                        }
-                        url.push(ch);
-                    } else {
-                        url.push(ch);
-                    }
-                }
-
-                // Output as <img> tag
-                result.push_str(&format!(r#"<img src="{}" alt="{}">"#, url, alt));
+        let name = caps.get(2).map(|n| n.as_str().to_lowercase()).unwrap_or_default();
+        if matches!(name.as_str(), "a" | "code" | "pre") && !m.as_str().ends_with("/>") {
+            if caps[1].is_empty() {
+                skip_depth += 1;
             } else {
-                // Not an image, output as-is
-                result.push('!');
-                result.push('[');
-                result.push_str(&alt);
-                result.push(']');
+                skip_depth = skip_depth.saturating_sub(1);
             }
-        } else {
-            result.push(c);
         }
+        result.push_str(m.as_str());
+        last = m.end();
     }
-
+    result.push_str(&html[last..]);
     result
 }
 
+/// Undo [`html_escape`]
+pub(crate) fn unescape_html(s: &str) -> String {
+    s.replace("&quot;", "\"").replace("&gt;", ">").replace("&lt;", "<").replace("&amp;", "&")
+}
+
+/// Convert markdown image syntax ![alt](url) in raw HTML to <img> tags
+/// pulldown-cmark doesn't parse markdown inside HTML blocks; images inside
+/// <code> and <pre> elements are left as written
+fn convert_remaining_markdown_images<'a>(events: impl Iterator<Item = Event<'a>>) -> impl Iterator<Item = Event<'a>> {
+    let re = regex::Regex::new(r"(?i)<(/?)(?:code|pre)\b[^>]*>|!\[([^\[\]\n]*)\]\(([^()\n]*)\)").unwrap();
+    // An HTML block arrives as several events, so <pre> state carries across them
+    let mut in_code = false;
+    let mut convert = move |html: &str| {
+        re.replace_all(html, |caps: &regex::Captures| {
+            if caps.get(2).is_none() {
+                in_code = caps[1].is_empty();
+                return caps[0].to_string();
+            }
+            if in_code {
+                return caps[0].to_string();
+            }
+            format!(
+                r#"<img src="{}" alt="{}">"#,
+                caps[3].trim().replace('"', "&quot;"),
+                caps[2].replace('"', "&quot;")
+            )
+        })
+        .into_owned()
+    };
+    events.map(move |event| match event {
+        Event::Html(html) => Event::Html(convert(&html).into()),
+        Event::InlineHtml(html) => Event::InlineHtml(convert(&html).into()),
+        event => event,
+    })
+}
+
 /// Convert internal links to proper relative paths from current file
 /// Links like "Customer/AssetStatus/PortfolioStock.html" (relative from book root)
 /// need to be converted to "../../Customer/AssetStatus/PortfolioStock.html"
@@ -1168,17 +1119,7 @@ fn convert_relative_links_to_absolute(html: &str, current_path: &str) -> String
 
     // Calculate the depth (number of directories from root)
     // e.g., "Customer/AssetStatus/PortfolioTop.md" -> depth 2
-    let depth = Path::new(current_path)
-        .parent()
-        .map(|p| {
-            let dir = p.to_string_lossy();
-            if dir.is_empty() {
-                0
-            } else {
-                dir.matches('/').count() + 1
-            }
-        })
-        .unwrap_or(0);
+    let depth = paths::depth(current_path);
 
     // Create the prefix to go back to root (e.g., "../../" for depth 2)
     let root_prefix: String = "../".repeat(depth);
@@ -1212,6 +1153,7 @@ fn convert_relative_links_to_absolute(html: &str, current_path: &str) -> String
                 && !url.starts_with("mailto:")
                 && !url.starts_with("javascript:")
                 && !url.starts_with("data:")
+                && !paths::has_drive_letter(url)
                 && depth > 0;
 
             if needs_conversion {
@@ -1235,19 +1177,178 @@ fn convert_relative_links_to_absolute(html: &str, current_path: &str) -> String
     new_result
 }
 
+/// Rewrite relative links of a page whose published location differs from its
+/// source layout (part URL prefixes in SUMMARY.md)
+/// rendered_at: the path links were written against (e.g. "reference/auth.html")
+/// output_at: the path the page is published at (e.g. "api/auth.html")
+/// remapped: default html path -> published html path for moved pages
+pub fn rewrite_page_links(
+    html: &str,
+    rendered_at: &str,
+    output_at: &str,
+    remapped: &std::collections::HashMap<String, String>,
+) -> String {
+    let rendered_dir = parent_segments(rendered_at);
+    let output_dir = parent_segments(output_at);
+    let page_moved = rendered_dir != output_dir;
+
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(pos) = find_link_attr(rest) {
+        let (attr_len, url_start) = pos;
+        let url_start_abs = url_start + attr_len;
+        let Some(url_len) = rest[url_start_abs..].find('"') else {
+            break;
+        };
+        let url = &rest[url_start_abs..url_start_abs + url_len];
+        result.push_str(&rest[..url_start_abs]);
+
+        let is_relative = !url.is_empty()
+            && !url.contains("://")
+            && !url.starts_with('#')
+            && !url.starts_with('/')
+            && !url.starts_with("mailto:")
+            && !url.starts_with("javascript:")
+            && !url.starts_with("data:")
+            && !paths::has_drive_letter(url);
+
+        let rewritten = if is_relative {
+            let split = url.find(['?', '#']).unwrap_or(url.len());
+            let (path, suffix) = url.split_at(split);
+            // Remapped paths are book paths: decode the href to look them up
+            resolve_segments(&rendered_dir, &paths::decode(path)).and_then(|target| {
+                let remapped_target = remapped.get(&target);
+                if !page_moved && remapped_target.is_none() {
+                    return None;
+                }
+                let target = remapped_target.cloned().unwrap_or(target);
+                Some(format!("{}{}", paths::encode_href(&relative_path(&output_dir, &target)), suffix))
+            })
+        } else {
+            None
+        };
+
+        result.push_str(rewritten.as_deref().unwrap_or(url));
+        rest = &rest[url_start_abs + url_len..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Point the relative links of a published page at the book's base URL
+/// ("../images/a.png" on "guide/start.html" -> "/docs/images/a.png")
+/// Links leaving the book root are kept as written
+pub fn rebase_links(html: &str, page_path: &str, base_url: &str) -> String {
+    if base_url.is_empty() {
+        return html.to_string();
+    }
+    let base = format!("{}/", base_url.trim_end_matches('/'));
+    let page_dir = parent_segments(page_path);
+
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some((attr_len, url_start)) = find_link_attr(rest) {
+        let url_start_abs = url_start + attr_len;
+        let Some(url_len) = rest[url_start_abs..].find('"') else {
+            break;
+        };
+        let url = &rest[url_start_abs..url_start_abs + url_len];
+        result.push_str(&rest[..url_start_abs]);
+
+        let is_relative = !url.is_empty()
+            && !url.starts_with('#')
+            && !url.starts_with('/')
+            && !url.split('/').next().unwrap_or("").contains(':')
+            && !paths::has_drive_letter(url);
+        let rebased = is_relative
+            .then(|| {
+                let split = url.find(['?', '#']).unwrap_or(url.len());
+                let (path, suffix) = url.split_at(split);
+                // Directory links ("./", "..") keep pointing at a directory
+                let directory = path.ends_with('/') || path.rsplit('/').next().is_some_and(|last| last == "." || last == "..");
+                resolve_segments(&page_dir, path).map(|target| {
+                    let slash = if directory && !target.is_empty() { "/" } else { "" };
+                    format!("{}{}{}{}", base, target, slash, suffix)
+                })
+            })
+            .flatten();
+
+        result.push_str(rebased.as_deref().unwrap_or(url));
+        rest = &rest[url_start_abs + url_len..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Resolve a relative URL path found in a page to a book-relative path
+/// e.g. ("guide/start.html", "../api/auth.html") -> "api/auth.html"
+/// Returns None if the path escapes the book root
+pub fn resolve_book_path(page_path: &str, url_path: &str) -> Option<String> {
+    resolve_segments(&parent_segments(page_path), url_path)
+}
+
+/// Find the next `href="`, `src="` or `poster="` attribute: (attribute length, start offset)
+fn find_link_attr(html: &str) -> Option<(usize, usize)> {
+    ["href=\"", "src=\"", "poster=\""]
+        .iter()
+        .filter_map(|attr| html.find(attr).map(|p| (attr.len(), p)))
+        .min_by_key(|&(_, p)| p)
+}
+
+/// Directory segments of a book-relative file path
+fn parent_segments(path: &str) -> Vec<String> {
+    let mut segments: Vec<String> = path.split('/').map(String::from).collect();
+    segments.pop();
+    segments.retain(|s| !s.is_empty() && s != ".");
+    segments
+}
+
+/// Resolve a relative path against a directory; None if it escapes the book root
+fn resolve_segments(dir: &[String], path: &str) -> Option<String> {
+    let mut segments = dir.to_vec();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            other => segments.push(other.to_string()),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+/// Relative path from a directory to a book-relative target
+fn relative_path(from_dir: &[String], target: &str) -> String {
+    let target_segments: Vec<&str> = target.split('/').collect();
+    let common = from_dir
+        .iter()
+        .zip(target_segments.iter())
+        .take_while(|(a, b)| a.as_str() == **b)
+        .count()
+        .min(target_segments.len().saturating_sub(1));
+    let mut relative = "../".repeat(from_dir.len() - common);
+    relative.push_str(&target_segments[common..].join("/"));
+    relative
+}
+
 // =============================================================================
 // AsciiDoc Rendering
 // =============================================================================
 
 /// Render AsciiDoc content to HTML
-/// Applies the same post-processing as markdown (target="_blank", link normalization, etc.)
+/// Applies the same post-processing as markdown (autolinks, link normalization, etc.)
 pub fn render_asciidoc(content: &str) -> String {
-    render_asciidoc_internal(content)
+    render_asciidoc_internal(content, &Autolinker::default())
 }
 
 /// Render AsciiDoc content to HTML with path for relative link conversion
-pub fn render_asciidoc_with_path(content: &str, current_path: Option<&str>) -> String {
-    let html = render_asciidoc_internal(content);
+/// autolink: how bare URLs are turned into links (`autolink` in book.json)
+pub fn render_asciidoc_with_path(content: &str, current_path: Option<&str>, autolink: &AutolinkConfig) -> String {
+    let html = render_asciidoc_internal(content, &Autolinker::new(autolink));
 
     // If we have a current path, convert relative links to absolute
     if let Some(path) = current_path {
@@ -1271,12 +1372,12 @@ pub fn extract_headings_from_asciidoc(content: &str) -> Vec<TocItem> {
 
             // Level mapping: == is h2, === is h3, ==== is h4
             // (= is h1 which is typically the document title)
-            if eq_count >= 2 && eq_count <= 5 {
+            if (2..=5).contains(&eq_count) {
                 let level = eq_count as u8;  // 2 = h2, 3 = h3, etc.
                 let text = trimmed[eq_count..].trim().to_string();
 
                 // Only include h2, h3, h4 in TOC (skip h1 which is page title)
-                if level >= 2 && level <= 4 && !text.is_empty() {
+                if (2..=4).contains(&level) && !text.is_empty() {
                     let id = slugify(&text);
                     headings.push(TocItem {
                         level,
@@ -1291,7 +1392,7 @@ pub fn extract_headings_from_asciidoc(content: &str) -> Vec<TocItem> {
     headings
 }
 
-fn render_asciidoc_internal(content: &str) -> String {
+fn render_asciidoc_internal(content: &str, autolinker: &Autolinker) -> String {
     // Normalize CRLF/CR to LF for consistent line handling
     let content = content.replace("\r\n", "\n").replace("\r", "\n");
 
@@ -1316,19 +1417,16 @@ fn render_asciidoc_internal(content: &str) -> String {
                     // Apply the same post-processing as markdown
                     let html = fix_asciidoc_relative_links(&html);
                     let html = remove_leading_slash_from_links(&html);
-                    let html = autolink_urls(&html);
-                    let html = add_target_blank_to_external_links(&html);
-
-                    html
+                    autolink_rendered_html(&html, autolinker)
                 }
                 Err(e) => {
-                    eprintln!("  Warning: AsciiDoc conversion error: {:?}", e);
+                    warning!("AsciiDoc conversion error: {:?}", e);
                     format!("<p>{}</p>", html_escape(&content))
                 }
             }
         }
         Err(e) => {
-            eprintln!("  Warning: AsciiDoc parsing error: {:?}", e);
+            warning!("AsciiDoc parsing error: {:?}", e);
             // Return the content wrapped in a simple paragraph as fallback
             format!("<p>{}</p>", html_escape(&content))
         }
@@ -1425,6 +1523,15 @@ sequenceDiagram
         assert!(fixed.contains(r#"href="chapter1.html""#));
     }
 
+    #[test]
+    fn test_normalize_path_separators() {
+        let html = r#"<a href="guide\start.html">a</a> <img src='img%5Cx.png'> <a href="https://x.test/?q=%5C">b</a> a\b"#;
+        assert_eq!(
+            normalize_path_separators(html),
+            r#"<a href="guide/start.html">a</a> <img src='img/x.png'> <a href="https://x.test/?q=%5C">b</a> a\b"#
+        );
+    }
+
     #[test]
     fn test_image_in_table() {
         let md = r#"
@@ -1603,7 +1710,7 @@ fn test_reference_link_with_bom() {
 #[test]
 fn test_footnote_with_list() {
     let content = "- データソース項目の値\n- 上記以外の場合";
-    let html = render_footnote_continuation(content, false);
+    let html = render_footnote_continuation(content, false, &Autolinker::default());
     println!("Footnote continuation HTML: {}", html);
     assert!(html.contains("<li>") && html.contains("<ul>"), "Should contain list: {}", html);
 }
@@ -1640,3 +1747,83 @@ fn test_resolve_reference_links_full_style() {
     assert!(output.contains("の場合"),
         "Text after link should be preserved: {}", output);
 }
+
+#[test]
+fn test_rewrite_page_links_for_moved_page() {
+    let mut remapped = std::collections::HashMap::new();
+    remapped.insert("reference/users.html".to_string(), "api/users.html".to_string());
+
+    let html = r##"<a href="users.html#list">u</a> <img src="../images/a.png"> <a href="../guide/start.html?x=1">s</a> <a href="https://example.com">e</a> <a href="#top">t</a>"##;
+    let output = rewrite_page_links(html, "reference/auth.html", "api/v1/auth.html", &remapped);
+    assert!(output.contains(r##"href="../users.html#list""##), "{}", output);
+    assert!(output.contains(r#"src="../../images/a.png""#), "{}", output);
+    assert!(output.contains(r#"href="../../guide/start.html?x=1""#), "{}", output);
+    assert!(output.contains(r#"href="https://example.com""#), "{}", output);
+    assert!(output.contains(r##"href="#top""##), "{}", output);
+}
+
+#[test]
+fn test_rewrite_page_links_to_moved_target() {
+    let mut remapped = std::collections::HashMap::new();
+    remapped.insert("reference/auth.html".to_string(), "api/auth.html".to_string());
+
+    let html = r#"<a href="../reference/auth.html">a</a> <a href="other.html">o</a>"#;
+    let output = rewrite_page_links(html, "guide/start.html", "guide/start.html", &remapped);
+    assert_eq!(output, r#"<a href="../api/auth.html">a</a> <a href="other.html">o</a>"#);
+}
+
+#[test]
+fn test_rebase_links() {
+    let html = r##"<a href="../api/auth.html#login">a</a> <img src="img/a%20b.png"> <a href="./">r</a> <a href="https://example.com">e</a> <a href="mailto:x@example.com">m</a> <a href="#top">t</a> <a href="/abs.html">s</a> <a href="../../out.html">o</a>"##;
+    let output = rebase_links(html, "guide/start.html", "/docs/mybook");
+    assert_eq!(
+        output,
+        r##"<a href="/docs/mybook/api/auth.html#login">a</a> <img src="/docs/mybook/guide/img/a%20b.png"> <a href="/docs/mybook/guide/">r</a> <a href="https://example.com">e</a> <a href="mailto:x@example.com">m</a> <a href="#top">t</a> <a href="/abs.html">s</a> <a href="../../out.html">o</a>"##
+    );
+    assert_eq!(rebase_links(html, "guide/start.html", ""), html);
+}
+
+#[test]
+fn test_image_scanners_leave_code_alone() {
+    let md = "`![a](b c.png)`\n\n```\n![a](b c.png)\n```\n\n<pre>![a](b.png)</pre>\n\n<div>\n![a](b.png)\n</div>";
+    let html = render_markdown(md);
+    assert_eq!(html.matches("![a](b c.png)").count(), 2, "{}", html);
+    assert!(html.contains("<pre>![a](b.png)</pre>"), "{}", html);
+    assert!(html.contains(r#"<img src="b.png" alt="a">"#), "{}", html);
+}
+
+#[test]
+fn test_unclosed_image_does_not_swallow_page() {
+    let md = "![a](b c\n\nnext https://example.com paragraph";
+    let html = render_markdown(md);
+    assert!(html.contains("<p>![a](b c</p>"), "{}", html);
+    assert!(html.contains(r#"<a href="https://example.com" target="_blank">"#), "{}", html);
+}
+
+#[test]
+fn test_autolink_skips_raw_html_and_code() {
+    let md = "<img src='https://example.com/a.png'>\n\n`https://example.com` and https://example.com/?a=1&b=2.";
+    let html = render_markdown(md);
+    assert!(html.contains("<img src='https://example.com/a.png'>"), "{}", html);
+    assert!(html.contains("<code>https://example.com</code>"), "{}", html);
+    assert!(html.contains(r#"<a href="https://example.com/?a=1&amp;b=2" target="_blank">https://example.com/?a=1&amp;b=2</a>."#), "{}", html);
+}
+
+#[test]
+fn test_autolink_config() {
+    let md = "See https://example.com and http://localhost:3000/api.\n\n[^1]: https://example.com/note\n";
+    let render = |config: &AutolinkConfig| render_markdown_with_path(md, None, false, config);
+
+    let html = render(&AutolinkConfig {
+        target: Some(String::new()),
+        rel: Some("nofollow".to_string()),
+        exclude: vec!["http://localhost*".to_string()],
+        ..Default::default()
+    });
+    assert!(html.contains(r#"<a href="https://example.com" target="_self" rel="nofollow">https://example.com</a>"#), "{}", html);
+    assert!(html.contains(r#"<a href="https://example.com/note" target="_self" rel="nofollow">"#), "{}", html);
+    assert!(html.contains("and http://localhost:3000/api.</p>"), "{}", html);
+
+    let html = render(&AutolinkConfig { enabled: Some(false), ..Default::default() });
+    assert!(!html.contains("<a href=\"http"), "{}", html);
+}
@@ -0,0 +1,106 @@
+//! JSON build report for `guidebook build --report`
+//!
+//! Written after every build, including failed ones, so CI can keep the
+//! warnings and timings of a build next to its logs.
+
+use super::{pipeline, BuildStats};
+use crate::logging::{self, Warning};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Debug, Serialize)]
+pub struct BuildReport {
+    pub success: bool,
+    /// Why the build failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: f64,
+    pub pages: usize,
+    pub assets: usize,
+    pub warnings: Vec<Warning>,
+    /// Build phases (config, load, assets, pages, search index, ...), in order
+    pub phases: Vec<Timing>,
+    /// Rendering stages, summed over all pages
+    pub stages: Vec<Timing>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Timing {
+    pub name: String,
+    pub duration_ms: f64,
+}
+
+impl Timing {
+    fn new(name: &str, elapsed: Duration) -> Self {
+        // Microsecond precision keeps the report readable
+        let duration_ms = (elapsed.as_secs_f64() * 1_000_000.0).round() / 1000.0;
+        Timing { name: name.to_string(), duration_ms }
+    }
+}
+
+impl BuildReport {
+    /// Report of the build that just ran, with the warnings and stage timings
+    /// collected since it started
+    pub(super) fn new(result: &Result<BuildStats>, elapsed: Duration) -> Self {
+        let (stats, error) = match result {
+            Ok(stats) => (Some(stats), None),
+            Err(e) => (None, Some(format!("{:#}", e))),
+        };
+        BuildReport {
+            success: error.is_none(),
+            error,
+            duration_ms: Timing::new("", elapsed).duration_ms,
+            pages: stats.map(|s| s.pages).unwrap_or(0),
+            assets: stats.map(|s| s.assets).unwrap_or(0),
+            warnings: logging::warnings(),
+            phases: stats
+                .map(|s| s.phases.iter().map(|(name, elapsed)| Timing::new(name, *elapsed)).collect())
+                .unwrap_or_default(),
+            stages: pipeline::timings().into_iter().map(|(stage, elapsed)| Timing::new(stage.name(), elapsed)).collect(),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json + "\n").with_context(|| format!("Failed to write build report {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{build_with_options, BuildOptions};
+
+    #[test]
+    fn test_build_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("book.json"), "{}").unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n* [Gone](gone.md)\n").unwrap();
+        fs::write(source.join("README.md"), "# Intro\n").unwrap();
+        let report_path = dir.path().join("reports/build.json");
+        let options = BuildOptions { report: Some(report_path.clone()), ..Default::default() };
+
+        build_with_options(&source, &dir.path().join("out"), &options).unwrap();
+        let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(&report_path).unwrap()).unwrap();
+        assert_eq!(report["success"], true);
+        assert_eq!(report["pages"], 2);
+        assert!(report.get("error").is_none());
+        assert_eq!(report["warnings"], serde_json::json!([{"page": null, "message": "gone.md not found"}]));
+        let phases: Vec<&str> = report["phases"].as_array().unwrap().iter().map(|p| p["name"].as_str().unwrap()).collect();
+        assert_eq!(phases, vec!["config", "load", "assets", "pages", "search index", "finish"]);
+
+        fs::write(source.join("book.json"), r#"{"pipeline": {"disable": ["markdown"]}}"#).unwrap();
+        assert!(build_with_options(&source, &dir.path().join("out"), &options).is_err());
+        let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(&report_path).unwrap()).unwrap();
+        assert_eq!(report["success"], false);
+        assert!(report["error"].as_str().unwrap().contains("markdown"));
+    }
+}
@@ -0,0 +1,49 @@
+//! robots.txt (`robots` section of book.json)
+//!
+//! With the section present, the build writes a robots.txt to the output
+//! root with one group of rules for `userAgent`. Pages are kept out of search
+//! results with `robots: noindex` front matter instead, which crawlers can
+//! only see on pages robots.txt lets them fetch.
+
+use crate::parser::RobotsConfig;
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+/// Write `robots.txt` to the output root
+pub fn write_robots_txt(output: &Path, config: &RobotsConfig) -> Result<()> {
+    fs::write(output.join("robots.txt"), robots_txt(config))?;
+    Ok(())
+}
+
+fn robots_txt(config: &RobotsConfig) -> String {
+    let mut text = format!("User-agent: {}\n", config.user_agent.as_deref().unwrap_or("*"));
+    for path in &config.allow {
+        text.push_str(&format!("Allow: {}\n", path));
+    }
+    for path in &config.disallow {
+        text.push_str(&format!("Disallow: {}\n", path));
+    }
+    // An empty Disallow allows everything
+    if config.disallow.is_empty() {
+        text.push_str("Disallow:\n");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_robots_txt() {
+        assert_eq!(robots_txt(&RobotsConfig::default()), "User-agent: *\nDisallow:\n");
+
+        let config: RobotsConfig =
+            serde_json::from_str(r#"{"userAgent": "Googlebot", "allow": ["/drafts/public/"], "disallow": ["/drafts/", "/tmp/"]}"#).unwrap();
+        assert_eq!(
+            robots_txt(&config),
+            "User-agent: Googlebot\nAllow: /drafts/public/\nDisallow: /drafts/\nDisallow: /tmp/\n"
+        );
+    }
+}
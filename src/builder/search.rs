@@ -0,0 +1,177 @@
+//! Search index queries
+//!
+//! The build writes `search_index.json`, one entry per page and heading
+//! section, which search.js normally downloads and searches in the browser.
+//! `guidebook serve` also loads it once and answers `/__search?q=` itself,
+//! so large books are searchable without the browser loading the whole index.
+//! Matching and scoring are the same as in search.js: a case-insensitive
+//! substring of the heading scores 10, of the content 1.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Results returned for a query
+pub const MAX_RESULTS: usize = 10;
+
+/// Characters of content shown on each side of the match
+const SNIPPET_CONTEXT: usize = 50;
+
+/// Search index entry: a page, or a heading section of a page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchEntry {
+    /// Page title
+    pub title: String,
+    /// Heading of the section (None for the page itself)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    /// Percent-encoded href of the page, with the heading anchor for sections
+    pub path: String,
+    /// SUMMARY.md part the page is listed under, results are grouped by it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub part: Option<String>,
+    pub content: String,
+    /// Estimated reading time of the page in minutes (pages, not sections)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reading_minutes: Option<u64>,
+}
+
+/// A matching entry, in the form search.js renders
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SearchResult {
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub part: Option<String>,
+    pub path: String,
+    /// Content around the first match (empty when only the heading matches)
+    pub snippet: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reading_minutes: Option<u64>,
+}
+
+/// search_index.json of a built book
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    pub entries: Vec<SearchEntry>,
+}
+
+impl SearchIndex {
+    /// Load search_index.json from a build output directory
+    pub fn load(output: &Path) -> Result<Self> {
+        let path = output.join("search_index.json");
+        let json = fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        let entries = serde_json::from_str(&json).with_context(|| format!("Invalid search index {:?}", path))?;
+        Ok(SearchIndex { entries })
+    }
+
+    /// Write search_index.json to a build output directory
+    pub fn write(output: &Path, entries: &[SearchEntry]) -> Result<()> {
+        let json = serde_json::to_string(entries)?;
+        fs::write(output.join("search_index.json"), json)?;
+        Ok(())
+    }
+
+    /// Best matches for `query`, heading matches first, then in index order
+    pub fn query(&self, query: &str) -> Vec<SearchResult> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let re = Regex::new(&format!("(?i){}", regex::escape(query))).unwrap();
+
+        let mut scored: Vec<(usize, SearchResult)> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let heading = entry.section.as_deref().unwrap_or(&entry.title);
+                let content_match = re.find(&entry.content);
+                let score = if re.is_match(heading) { 10 } else { 0 } + if content_match.is_some() { 1 } else { 0 };
+                if score == 0 {
+                    return None;
+                }
+                let snippet = content_match.map(|m| snippet(&entry.content, m.start(), m.end())).unwrap_or_default();
+                Some((
+                    score,
+                    SearchResult {
+                        title: entry.title.clone(),
+                        section: entry.section.clone(),
+                        part: entry.part.clone(),
+                        path: entry.path.clone(),
+                        snippet,
+                        reading_minutes: entry.reading_minutes,
+                    },
+                ))
+            })
+            .collect();
+        // Stable: equal scores keep index order, like search.js
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().take(MAX_RESULTS).map(|(_, result)| result).collect()
+    }
+}
+
+/// `content[start..end]` with up to SNIPPET_CONTEXT characters on each side,
+/// "..." marking cut text
+fn snippet(content: &str, start: usize, end: usize) -> String {
+    let from = content[..start].char_indices().rev().nth(SNIPPET_CONTEXT - 1).map(|(i, _)| i).unwrap_or(0);
+    let to = content[end..].char_indices().nth(SNIPPET_CONTEXT).map(|(i, _)| end + i).unwrap_or(content.len());
+    format!(
+        "{}{}{}",
+        if from > 0 { "..." } else { "" },
+        &content[from..to],
+        if to < content.len() { "..." } else { "" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, section: Option<&str>, path: &str, content: &str) -> SearchEntry {
+        SearchEntry {
+            title: title.to_string(),
+            section: section.map(str::to_string),
+            path: path.to_string(),
+            part: None,
+            content: content.to_string(),
+            reading_minutes: None,
+        }
+    }
+
+    #[test]
+    fn test_query_scores_headings_first() {
+        let index = SearchIndex {
+            entries: vec![
+                entry("Intro", None, "index.html", "Install the tool first"),
+                entry("Guide", Some("Install"), "guide.html#install", "Run the installer"),
+                entry("Other", None, "other.html", "Nothing here"),
+            ],
+        };
+        let results = index.query("INSTALL");
+        assert_eq!(results.iter().map(|r| r.path.as_str()).collect::<Vec<_>>(), vec!["guide.html#install", "index.html"]);
+        assert_eq!(results[0].section.as_deref(), Some("Install"));
+        assert_eq!(results[1].snippet, "Install the tool first");
+        assert!(index.query("  ").is_empty());
+        assert!(index.query("a+b").is_empty());
+    }
+
+    #[test]
+    fn test_snippet_cuts_around_the_match() {
+        let content = format!("{}needle{}", "é".repeat(60), "x".repeat(60));
+        let start = content.find("needle").unwrap();
+        let snippet = snippet(&content, start, start + 6);
+        assert_eq!(snippet, format!("...{}needle{}...", "é".repeat(50), "x".repeat(50)));
+    }
+
+    #[test]
+    fn test_load_reads_written_index() {
+        let dir = tempfile::tempdir().unwrap();
+        SearchIndex::write(dir.path(), &[entry("Guide", None, "guide.html", "Text")]).unwrap();
+        let index = SearchIndex::load(dir.path()).unwrap();
+        assert_eq!(index.query("text")[0].title, "Guide");
+        assert!(SearchIndex::load(&dir.path().join("missing")).is_err());
+    }
+}
@@ -0,0 +1,268 @@
+//! Self-hosted search (`search.meilisearch` or `search.typesense` in book.json)
+//!
+//! Books too large for an index loaded by the browser search an instance of
+//! Meilisearch or Typesense: search.js sends the queries there with the
+//! search-only key, and falls back to search_index.json when the instance
+//! doesn't answer. `build --push-search` fills the index with a document per
+//! page and per heading section (title, heading, body, url and language),
+//! using the admin API key read from the environment. Documents go to a new
+//! index that then replaces the searched one (a swap in Meilisearch, the
+//! collection alias in Typesense), so searches never see a half-written index.
+
+use super::search::SearchEntry;
+use crate::parser::{BookConfig, SearchConfig, SearchServerConfig};
+use anyhow::{anyhow, bail, Result};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde_json::{json, Value};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Documents per request
+const BATCH_SIZE: usize = 1000;
+
+/// Longest wait for Meilisearch to process the pushed documents
+const TASK_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Search engine of the instance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Engine {
+    Meilisearch,
+    Typesense,
+}
+
+impl Engine {
+    pub fn name(self) -> &'static str {
+        match self {
+            Engine::Meilisearch => "Meilisearch",
+            Engine::Typesense => "Typesense",
+        }
+    }
+
+    /// Environment variable of the admin API key unless `adminKeyEnv` is set
+    fn default_admin_key_env(self) -> &'static str {
+        match self {
+            Engine::Meilisearch => "MEILISEARCH_ADMIN_KEY",
+            Engine::Typesense => "TYPESENSE_ADMIN_KEY",
+        }
+    }
+}
+
+/// The configured instance; an error when book.json sets both
+pub fn server(search: &SearchConfig) -> Result<Option<(Engine, &SearchServerConfig)>> {
+    match (&search.meilisearch, &search.typesense) {
+        (Some(_), Some(_)) => bail!("Set either search.meilisearch or search.typesense in book.json, not both"),
+        (Some(server), None) => Ok(Some((Engine::Meilisearch, server))),
+        (None, Some(server)) => Ok(Some((Engine::Typesense, server))),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Settings search.js queries the instance with (`data-search-server`)
+pub fn client_options(engine: Engine, server: &SearchServerConfig, language: Option<&str>) -> String {
+    let engine = engine.name().to_ascii_lowercase();
+    json!({ "engine": engine, "url": server.url.trim_end_matches('/'), "apiKey": server.api_key, "index": server.index(language) }).to_string()
+}
+
+/// Documents of the search index entries: pages, and their heading sections
+/// with `heading`; `url` is relative to the book root
+fn documents(entries: &[SearchEntry], lang: &str) -> Vec<Value> {
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let mut document = json!({
+                "id": i.to_string(),
+                "title": entry.title,
+                "heading": entry.section,
+                "content": entry.content,
+                "url": entry.path,
+                "lang": lang,
+                "part": entry.part,
+                "reading_minutes": entry.reading_minutes,
+            });
+            // Typesense rejects null for optional fields
+            if let Value::Object(fields) = &mut document {
+                fields.retain(|_, value| !value.is_null());
+            }
+            document
+        })
+        .collect()
+}
+
+/// Replace the documents of the index with the book's search index entries;
+/// returns the number of documents sent
+pub fn push(entries: &[SearchEntry], config: &BookConfig, engine: Engine, server: &SearchServerConfig, language: Option<&str>) -> Result<usize> {
+    let key_env = server.admin_key_env.as_deref().unwrap_or(engine.default_admin_key_env());
+    let admin_key = std::env::var(key_env)
+        .map_err(|_| anyhow!("Set {} to the {} admin API key to push search documents", key_env, engine.name()))?;
+    let lang = language.unwrap_or(&config.language);
+    let documents = documents(entries, lang);
+    let instance = Instance { engine, url: server.url.trim_end_matches('/'), admin_key: &admin_key };
+    let index = server.index(language);
+    match engine {
+        Engine::Meilisearch => push_meilisearch(&instance, &index, &documents)?,
+        Engine::Typesense => push_typesense(&instance, &index, &documents)?,
+    }
+    Ok(documents.len())
+}
+
+/// An instance, with the admin key
+struct Instance<'a> {
+    engine: Engine,
+    url: &'a str,
+    admin_key: &'a str,
+}
+
+impl Instance<'_> {
+    /// Send a request; None for a 404 when `missing_ok`
+    fn request(&self, method: &str, path: &str, body: Option<String>, missing_ok: bool) -> Result<Option<String>> {
+        let request = ureq::request(method, &format!("{}/{}", self.url, path)).timeout(Duration::from_secs(60));
+        let request = match self.engine {
+            Engine::Meilisearch => request.set("Authorization", &format!("Bearer {}", self.admin_key)),
+            Engine::Typesense => request.set("X-TYPESENSE-API-KEY", self.admin_key),
+        };
+        let response = match body {
+            Some(body) => request.set("Content-Type", "application/json").send_string(&body),
+            None => request.call(),
+        };
+        match response {
+            Ok(response) => Ok(Some(response.into_string()?)),
+            Err(ureq::Error::Status(404, _)) if missing_ok => Ok(None),
+            Err(e) => Err(anyhow!("{} request failed: {}", self.engine.name(), e)),
+        }
+    }
+
+    fn json(&self, method: &str, path: &str, body: &Value) -> Result<Value> {
+        let response = self.request(method, path, Some(body.to_string()), false)?.unwrap_or_default();
+        Ok(serde_json::from_str(&response)?)
+    }
+}
+
+fn encode(name: &str) -> String {
+    utf8_percent_encode(name, NON_ALPHANUMERIC).to_string()
+}
+
+/// Fill `<index>_tmp`, then swap it with the index
+fn push_meilisearch(instance: &Instance, index: &str, documents: &[Value]) -> Result<()> {
+    let temporary = format!("{}_tmp", index);
+    // Left over by a failed push
+    instance.request("DELETE", &format!("indexes/{}", encode(&temporary)), None, true)?;
+    let mut tasks = vec![instance.json("POST", "indexes", &json!({ "uid": temporary, "primaryKey": "id" }))?];
+    let settings = json!({ "searchableAttributes": ["title", "heading", "content"], "filterableAttributes": ["lang"] });
+    tasks.push(instance.json("PATCH", &format!("indexes/{}/settings", encode(&temporary)), &settings)?);
+    for chunk in documents.chunks(BATCH_SIZE) {
+        tasks.push(instance.json("POST", &format!("indexes/{}/documents", encode(&temporary)), &json!(chunk))?);
+    }
+    // The first push swaps with an empty index (creating it fails when it exists)
+    instance.json("POST", "indexes", &json!({ "uid": index, "primaryKey": "id" }))?;
+    for task in &tasks {
+        wait_task(instance, task)?;
+    }
+    wait_task(instance, &instance.json("POST", "swap-indexes", &json!([{ "indexes": [index, temporary] }]))?)?;
+    instance.request("DELETE", &format!("indexes/{}", encode(&temporary)), None, true)?;
+    Ok(())
+}
+
+/// Wait for Meilisearch to process an enqueued task
+fn wait_task(instance: &Instance, task: &Value) -> Result<()> {
+    let Some(uid) = task["taskUid"].as_u64() else {
+        bail!("Meilisearch returned no task: {}", task);
+    };
+    let start = Instant::now();
+    loop {
+        let response = instance.request("GET", &format!("tasks/{}", uid), None, false)?.unwrap_or_default();
+        let status: Value = serde_json::from_str(&response)?;
+        match status["status"].as_str() {
+            Some("succeeded") => return Ok(()),
+            Some("failed") | Some("canceled") => {
+                bail!("Meilisearch task {} ({}) failed: {}", uid, status["type"], status["error"]["message"])
+            }
+            _ if start.elapsed() > TASK_TIMEOUT => bail!("Meilisearch task {} is still not processed", uid),
+            _ => thread::sleep(Duration::from_millis(500)),
+        }
+    }
+}
+
+/// Import into a new collection, point the alias at it, and drop the
+/// collection it pointed to
+fn push_typesense(instance: &Instance, alias: &str, documents: &[Value]) -> Result<()> {
+    let created = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    let collection = format!("{}_{}", alias, created);
+    let schema = json!({
+        "name": collection,
+        "fields": [
+            { "name": "title", "type": "string" },
+            { "name": "heading", "type": "string", "optional": true },
+            { "name": "content", "type": "string" },
+            { "name": "lang", "type": "string", "facet": true },
+            { "name": "part", "type": "string", "optional": true },
+        ],
+    });
+    instance.json("POST", "collections", &schema)?;
+    for chunk in documents.chunks(BATCH_SIZE) {
+        let lines: Vec<String> = chunk.iter().map(Value::to_string).collect();
+        let path = format!("collections/{}/documents/import?action=create", encode(&collection));
+        let results = instance.request("POST", &path, Some(lines.join("\n")), false)?.unwrap_or_default();
+        let failed: Vec<Value> = results
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+            .filter(|result| result["success"] != true)
+            .collect();
+        if let Some(first) = failed.first() {
+            bail!("Typesense rejected {} documents: {}", failed.len(), first["error"]);
+        }
+    }
+
+    let previous = instance.request("GET", &format!("aliases/{}", encode(alias)), None, true)?;
+    let previous = previous.and_then(|response| serde_json::from_str::<Value>(&response).ok());
+    instance.json("PUT", &format!("aliases/{}", encode(alias)), &json!({ "collection_name": collection }))?;
+    if let Some(old) = previous.as_ref().and_then(|alias| alias["collection_name"].as_str()) {
+        instance.request("DELETE", &format!("collections/{}", encode(old)), None, true)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_documents_and_client_options() {
+        let config: BookConfig = serde_json::from_str(
+            r#"{"search": {"typesense": {"url": "https://search.example.com/", "apiKey": "search-key", "indexName": "handbook"}}}"#,
+        )
+        .unwrap();
+        let (engine, server) = server(&config.search).unwrap().unwrap();
+        assert_eq!(engine, Engine::Typesense);
+        assert_eq!(
+            client_options(engine, server, Some("ja")),
+            r#"{"engine":"typesense","url":"https://search.example.com","apiKey":"search-key","index":"handbook_ja"}"#
+        );
+
+        let entries = vec![
+            SearchEntry { title: "Start".to_string(), section: None, path: "guide/start.html".to_string(), part: None, content: "Begin here.".to_string(), reading_minutes: Some(1) },
+            SearchEntry {
+                title: "Start".to_string(),
+                section: Some("Install".to_string()),
+                path: "guide/start.html#install".to_string(),
+                part: Some("Guide".to_string()),
+                content: "Run the installer.".to_string(),
+                reading_minutes: None,
+            },
+        ];
+        assert_eq!(
+            documents(&entries, "ja"),
+            vec![
+                json!({"id": "0", "title": "Start", "content": "Begin here.", "url": "guide/start.html", "lang": "ja", "reading_minutes": 1}),
+                json!({"id": "1", "title": "Start", "heading": "Install", "content": "Run the installer.", "url": "guide/start.html#install", "lang": "ja", "part": "Guide"}),
+            ]
+        );
+
+        let both: BookConfig = serde_json::from_str(concat!(
+            r#"{"search": {"meilisearch": {"url": "https://a.example.com", "apiKey": "a", "indexName": "docs"},"#,
+            r#" "typesense": {"url": "https://b.example.com", "apiKey": "b", "indexName": "docs"}}}"#
+        ))
+        .unwrap();
+        assert!(super::server(&both.search).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! Chapters shared across languages
+//!
+//! A language's SUMMARY.md can list a chapter of another language directory
+//! instead of a copy of it, e.g. an API reference that isn't translated:
+//!
+//! ```markdown
+//! * [API reference](../en/api/reference.md)
+//! ```
+//!
+//! The chapter is published in the language at its path in the other one
+//! (`api/reference.html`) and rendered from the other language's directory,
+//! so its @imports, images and downloads are found there. The files it uses
+//! that this language doesn't have are copied from there with the page, and
+//! its links to pages this language doesn't publish go to the other
+//! language's pages.
+
+use super::media::copy_file;
+use super::renderer::resolve_book_path;
+use crate::parser::{shared_source, BookConfig};
+use crate::paths;
+use anyhow::Result;
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Where a chapter shared from another language comes from
+pub struct SharedChapter<'a> {
+    /// Source directory of the language the chapter is shared from
+    pub home: PathBuf,
+    /// Source path of the chapter there ("api/reference.md")
+    pub path: &'a str,
+    /// Output root of that language, relative to this language's output
+    /// root ("../en/"); None when it isn't published with this book
+    home_root: Option<String>,
+}
+
+impl<'a> SharedChapter<'a> {
+    /// The shared chapter of a SUMMARY.md source path ("../en/api/reference.md"),
+    /// None for the book's own chapters
+    /// language: code of the language being built, None for single-language books
+    pub fn new(source: &Path, source_path: &'a str, config: &BookConfig, language: Option<&str>) -> Option<Self> {
+        let (dir, path) = shared_source(source_path)?;
+        let home_root = language.map(|language| {
+            let from = config.languages.output_dir(language);
+            let to = config.languages.output_dir(dir);
+            let up = if from.is_empty() { 0 } else { from.split('/').count() };
+            let to = if to.is_empty() { to } else { format!("{}/", to) };
+            format!("{}{}", "../".repeat(up), to)
+        });
+        Some(SharedChapter { home: source.parent().unwrap_or(source).join(dir), path, home_root })
+    }
+
+    /// Copy the files a published shared chapter uses from its language when
+    /// this language has none at that path, and point its links to pages this
+    /// language doesn't publish at the other language's pages
+    /// html_path: output path of the page, links are relative to it
+    /// published: HTML paths of this language's pages
+    pub fn publish(&self, html: &str, source: &Path, output: &Path, html_path: &str, published: &HashSet<String>) -> Result<String> {
+        let link_re = Regex::new(r#"\b(href|src)="([^"]*)""#).unwrap();
+        let root = "../".repeat(paths::depth(html_path));
+
+        let mut result = String::with_capacity(html.len());
+        let mut last = 0;
+        for link in link_re.captures_iter(html) {
+            let url = link.get(2).unwrap();
+            if url.as_str().is_empty() || url.as_str().contains(':') || url.as_str().starts_with(['/', '#']) {
+                continue;
+            }
+            let split = url.as_str().find(['?', '#']).unwrap_or(url.as_str().len());
+            let (path, suffix) = url.as_str().split_at(split);
+            let Some(target) = resolve_book_path(html_path, &paths::decode(path)) else { continue };
+
+            if !target.ends_with(".html") {
+                // Missing files are reported by `guidebook check`
+                if !source.join(&target).exists() {
+                    copy_file(&self.home, output, &target)?;
+                }
+                continue;
+            }
+            let Some(home_root) = &self.home_root else { continue };
+            if published.contains(&target) || target == "index.html" {
+                continue;
+            }
+            result.push_str(&html[last..url.start()]);
+            result.push_str(&format!("{}{}{}{}", root, home_root, paths::encode_href(&target), suffix));
+            last = url.end();
+        }
+        result.push_str(&html[last..]);
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_shared_chapter_links_and_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let (source, output) = (dir.path().join("book/ja"), dir.path().join("out/ja"));
+        fs::create_dir_all(dir.path().join("book/en/api/img")).unwrap();
+        fs::create_dir_all(source.join("images")).unwrap();
+        fs::write(dir.path().join("book/en/api/img/flow.png"), "en").unwrap();
+        fs::write(source.join("images/logo.png"), "ja").unwrap();
+
+        let config: BookConfig = serde_json::from_str(r#"{"languages": {"paths": {"ja": "docs/ja"}}}"#).unwrap();
+        let chapter = SharedChapter::new(&source, "../en/api/ref.md", &config, Some("ja")).unwrap();
+        assert_eq!(chapter.path, "api/ref.md");
+        assert_eq!(chapter.home_root.as_deref(), Some("../../en/"));
+        assert!(SharedChapter::new(&source, "api/ref.md", &config, Some("ja")).is_none());
+
+        let published: HashSet<String> = ["api/ref.html".to_string(), "guide.html".to_string()].into();
+        let html = concat!(
+            r##"<a href="../guide.html#setup">Guide</a> <a href="other%20page.html?x">Other</a> <a href="#top">Top</a> "##,
+            r#"<img src="img/flow.png" /><img src="../images/logo.png" /><a href="https://example.com/a.html">Site</a>"#
+        );
+        let result = chapter.publish(html, &source, &output, "api/ref.html", &published).unwrap();
+        assert_eq!(
+            result,
+            concat!(
+                r##"<a href="../guide.html#setup">Guide</a> <a href="../../../en/api/other%20page.html?x">Other</a> <a href="#top">Top</a> "##,
+                r#"<img src="img/flow.png" /><img src="../images/logo.png" /><a href="https://example.com/a.html">Site</a>"#
+            )
+        );
+        assert_eq!(fs::read_to_string(output.join("api/img/flow.png")).unwrap(), "en");
+        // This language's own files are left to the asset copy
+        assert!(!output.join("images/logo.png").exists());
+    }
+}
@@ -0,0 +1,71 @@
+//! sitemap.xml (with `deploy.siteUrl` in book.json)
+//!
+//! Lists the pages of the book at their public URL, so search engines find
+//! them without following links. Pages with `robots: noindex` front matter
+//! are left out. With `lastUpdated`, each page has the date it last changed
+//! as `<lastmod>`. The books of other languages, in subdirectories, have their
+//! own sitemap.
+
+use super::indexnow;
+use super::renderer::html_escape;
+use super::translate::format_day;
+use crate::paths;
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+/// File name of the sitemap
+pub const SITEMAP_FILE: &str = "sitemap.xml";
+
+/// A page listed in the sitemap
+#[derive(Debug)]
+pub struct SitemapPage {
+    /// Site-relative published path (e.g. "ja/guide/start.html")
+    pub path: String,
+    /// Last change (Unix seconds)
+    pub updated: Option<u64>,
+}
+
+/// Write `sitemap.xml` to the output root
+pub fn write_sitemap(output: &Path, site_url: &str, pages: &[SitemapPage]) -> Result<()> {
+    fs::write(output.join(SITEMAP_FILE), sitemap_xml(site_url, pages))?;
+    Ok(())
+}
+
+fn sitemap_xml(site_url: &str, pages: &[SitemapPage]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for page in pages {
+        let Some(url) = indexnow::page_urls(site_url, &[paths::encode_href(&page.path)]).pop() else { continue };
+        xml.push_str(&format!("  <url>\n    <loc>{}</loc>\n", html_escape(&url)));
+        if let Some(updated) = page.updated {
+            xml.push_str(&format!("    <lastmod>{}</lastmod>\n", format_day(updated)));
+        }
+        xml.push_str("  </url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sitemap_xml() {
+        let pages = [
+            SitemapPage { path: "index.html".to_string(), updated: Some(1_709_546_400) },
+            SitemapPage { path: "guide/set up.html".to_string(), updated: None },
+            SitemapPage { path: "guide/index.html".to_string(), updated: None },
+        ];
+        assert_eq!(
+            sitemap_xml("https://docs.example.com/", &pages),
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+                "  <url>\n    <loc>https://docs.example.com/</loc>\n    <lastmod>2024-03-04</lastmod>\n  </url>\n",
+                "  <url>\n    <loc>https://docs.example.com/guide/set%20up.html</loc>\n  </url>\n",
+                "  <url>\n    <loc>https://docs.example.com/guide/</loc>\n  </url>\n",
+                "</urlset>\n"
+            )
+        );
+    }
+}
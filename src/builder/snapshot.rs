@@ -0,0 +1,183 @@
+//! Snapshot tests of fixture books (`guidebook test`)
+//!
+//! Every directory under `tests/books/` is a small book exercising renderer
+//! and theme features. It is built the way `build --reproducible` builds it,
+//! and its output is compared with the snapshot of the same name under
+//! `tests/snapshots/`, a committed copy of the expected output. Changed files
+//! are shown as unified diffs; `guidebook test --update` writes the output as
+//! the new snapshot, so a change to the renderer or templates shows up in
+//! review as a diff of the pages it affects.
+//!
+//! Files the build copies unchanged (the embedded gitbook.css and scripts, the
+//! book's images and downloads) are left out of snapshots.
+
+use super::{build_with_options, BuildOptions, COLLAPSIBLE_JS, FONTSETTINGS_JS, GITBOOK_CSS, GITBOOK_JS, SEARCH_JS};
+use crate::paths;
+use anyhow::{Context, Result};
+use similar::TextDiff;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Embedded assets by output path, left out when written unchanged
+const EMBEDDED_ASSETS: [(&str, &str); 5] = [
+    ("gitbook/gitbook.css", GITBOOK_CSS),
+    ("gitbook/gitbook.js", GITBOOK_JS),
+    ("gitbook/collapsible.js", COLLAPSIBLE_JS),
+    ("gitbook/fontsettings.js", FONTSETTINGS_JS),
+    ("gitbook/search.js", SEARCH_JS),
+];
+
+/// A difference between the output of a fixture book and its snapshot
+#[derive(Debug, PartialEq)]
+pub enum SnapshotChange {
+    /// Output file missing from the snapshot
+    Added(String),
+    /// Snapshot file the build no longer writes
+    Removed(String),
+    /// Output file that differs from the snapshot; the unified diff of a text file
+    Changed { path: String, diff: Option<String> },
+}
+
+impl fmt::Display for SnapshotChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotChange::Added(path) => write!(f, "  added: {}", path),
+            SnapshotChange::Removed(path) => write!(f, "  removed: {}", path),
+            SnapshotChange::Changed { path, diff: None } => write!(f, "  changed: {} (binary)", path),
+            SnapshotChange::Changed { path, diff: Some(diff) } => write!(f, "  changed: {}\n{}", path, diff.trim_end()),
+        }
+    }
+}
+
+/// Names of the fixture books in `books`, sorted
+pub fn fixture_books(books: &Path) -> Result<Vec<String>> {
+    let mut names: Vec<String> = fs::read_dir(books)
+        .with_context(|| format!("No fixture books in {}", books.display()))?
+        .filter_map(Result::ok)
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.'))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Build the book at `source` and compare its output with the snapshot in
+/// `snapshot`; update: write the output as the snapshot instead
+/// Returns the differences (the ones written with update), empty when the
+/// output matches
+pub fn test_book(source: &Path, snapshot: &Path, update: bool) -> Result<Vec<SnapshotChange>> {
+    let name = source.file_name().unwrap_or_default().to_string_lossy();
+    let output = std::env::temp_dir().join(format!("guidebook-test-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&output);
+    let options = BuildOptions { reproducible: true, skip_manifest: true, ..Default::default() };
+    let built = build_with_options(source, &output, &options)
+        .with_context(|| format!("Failed to build {}", source.display()))
+        .and_then(|()| output_files(source, &output));
+    let _ = fs::remove_dir_all(&output);
+    let built = built?;
+
+    let expected = if snapshot.is_dir() { read_files(snapshot)? } else { BTreeMap::new() };
+    let changes = compare(&expected, &built);
+    if update && !changes.is_empty() {
+        let _ = fs::remove_dir_all(snapshot);
+        for (path, content) in &built {
+            let dest = snapshot.join(path);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(dest, content)?;
+        }
+    }
+    Ok(changes)
+}
+
+/// Files of the build output that go into its snapshot
+fn output_files(source: &Path, output: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut files = read_files(output)?;
+    files.retain(|path, content| {
+        let embedded = EMBEDDED_ASSETS.iter().any(|(asset, embedded)| asset == path && embedded.as_bytes() == content.as_slice());
+        !embedded && fs::read(source.join(path)).ok().as_deref() != Some(content.as_slice())
+    });
+    Ok(files)
+}
+
+/// Files under `dir` by relative path
+fn read_files(dir: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    for entry in paths::walk(dir).into_iter().filter_map(paths::readable).filter(|e| e.file_type().is_file()) {
+        if let Some(path) = paths::relative(dir, entry.path()) {
+            files.insert(path, fs::read(entry.path())?);
+        }
+    }
+    Ok(files)
+}
+
+/// Differences of `actual` from `expected`, by path
+fn compare(expected: &BTreeMap<String, Vec<u8>>, actual: &BTreeMap<String, Vec<u8>>) -> Vec<SnapshotChange> {
+    let paths: BTreeSet<&String> = expected.keys().chain(actual.keys()).collect();
+    let mut changes = Vec::new();
+    for path in paths {
+        match (expected.get(path), actual.get(path)) {
+            (None, Some(_)) => changes.push(SnapshotChange::Added(path.clone())),
+            (Some(_), None) => changes.push(SnapshotChange::Removed(path.clone())),
+            (Some(old), Some(new)) if old != new => {
+                let diff = match (std::str::from_utf8(old), std::str::from_utf8(new)) {
+                    (Ok(old), Ok(new)) => Some(
+                        TextDiff::from_lines(old, new)
+                            .unified_diff()
+                            .header(&format!("snapshot/{}", path), &format!("output/{}", path))
+                            .to_string(),
+                    ),
+                    _ => None,
+                };
+                changes.push(SnapshotChange::Changed { path: path.clone(), diff });
+            }
+            _ => {}
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("books/basic");
+        let snapshot = dir.path().join("snapshots/basic");
+        fs::create_dir_all(source.join("images")).unwrap();
+        fs::write(source.join("README.md"), "# Intro\n\nFirst line\n").unwrap();
+        fs::write(source.join("SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n").unwrap();
+        fs::write(source.join("images/logo.png"), [0x89, 0x50]).unwrap();
+        assert_eq!(fixture_books(&dir.path().join("books")).unwrap(), vec!["basic"]);
+
+        // No snapshot yet: every file is new
+        let changes = test_book(&source, &snapshot, true).unwrap();
+        assert!(changes.contains(&SnapshotChange::Added("index.html".to_string())), "{:?}", changes);
+        assert!(snapshot.join("index.html").is_file() && snapshot.join("search_index.json").is_file());
+        assert!(!snapshot.join("gitbook/gitbook.css").exists() && !snapshot.join("images/logo.png").exists());
+        assert_eq!(test_book(&source, &snapshot, false).unwrap(), vec![]);
+
+        fs::write(source.join("README.md"), "# Intro\n\nSecond line\n").unwrap();
+        fs::write(snapshot.join("old.html"), "gone").unwrap();
+        let changes = test_book(&source, &snapshot, false).unwrap();
+        let diff = changes
+            .iter()
+            .find_map(|change| match change {
+                SnapshotChange::Changed { path, diff } if path == "index.html" => diff.clone(),
+                _ => None,
+            })
+            .unwrap();
+        assert!(diff.contains("--- snapshot/index.html") && diff.contains("-<p>First line</p>") && diff.contains("+<p>Second line</p>"), "{}", diff);
+        assert!(changes.contains(&SnapshotChange::Removed("old.html".to_string())), "{:?}", changes);
+
+        test_book(&source, &snapshot, true).unwrap();
+        assert!(!snapshot.join("old.html").exists());
+        assert_eq!(test_book(&source, &snapshot, false).unwrap(), vec![]);
+    }
+}
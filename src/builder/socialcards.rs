@@ -0,0 +1,391 @@
+//! Social card images (`socialCards` in book.json)
+//!
+//! Links to a chapter shared on social networks and in chat apps show the
+//! image of the page's `og:image` tag. With a `socialCards` section, the build
+//! renders one for each page: the page title over the book's background color
+//! (or `image`), under the book title, and with `diagram` the page's first
+//! diagram beside it:
+//!
+//! ```json
+//! { "socialCards": { "background": "#1f2d3d", "color": "#ffffff", "diagram": true } }
+//! ```
+//!
+//! Cards are 1200x630 PNG files in `_social/`, named after the page. Their
+//! URL must be absolute, so cards need `deploy.siteUrl`. Pages with `image:`
+//! front matter use that image instead. Diagrams are SVG images and inline
+//! SVG; Mermaid diagrams, drawn by the browser, are rendered with mermaid-cli
+//! (`mmdc`) when it is installed.
+
+use super::hooks::PageContext;
+use super::renderer::{html_escape, resolve_book_path, unescape_html};
+use crate::parser::BookConfig;
+use crate::{paths, warning};
+use anyhow::{anyhow, bail, Context, Result};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use regex::Regex;
+use resvg::{tiny_skia, usvg};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Output directory of the cards
+pub const CARDS_DIR: &str = "_social";
+
+/// Card size, the one Facebook, LinkedIn and X show at full width
+const WIDTH: u32 = 1200;
+const HEIGHT: u32 = 630;
+
+/// Families tried, in order, for the text without `font`
+const SANS_SERIF: &[&str] = &["Inter", "Helvetica Neue", "Helvetica", "Arial", "Noto Sans", "DejaVu Sans", "Liberation Sans"];
+
+/// Page title lines, and the width of a line in characters of Latin text
+const TITLE_LINES: usize = 3;
+const TITLE_LINE_CHARS: usize = 26;
+const TITLE_LINE_CHARS_DIAGRAM: usize = 14;
+
+/// Temporary directories of mermaid-cli runs
+static MERMAID_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+/// Output path of a page's card; none without `socialCards` or
+/// `deploy.siteUrl`, for pages with `image:` front matter or `robots:
+/// noindex`, and for 404.html
+pub fn card_path(page: &PageContext) -> Option<String> {
+    let config = page.config;
+    if config.social_cards.is_none() || config.deploy.site_url.is_empty() || page.html_path == "404.html" {
+        return None;
+    }
+    if page.front_matter.is_some_and(|fm| fm.image.is_some() || fm.is_noindex()) {
+        return None;
+    }
+    let stem = page.html_path.strip_suffix(".html").unwrap_or(page.html_path);
+    Some(format!("{}/{}.png", CARDS_DIR, stem))
+}
+
+/// Renders the cards of a book, with the fonts loaded once
+pub struct SocialCards {
+    options: usvg::Options<'static>,
+    /// Font family of the text
+    family: String,
+    book_title: String,
+    background: String,
+    color: String,
+    /// Book path of the background image
+    image: Option<String>,
+    diagram: bool,
+    /// Whether mermaid-cli is installed
+    mermaid: bool,
+    /// Patterns of the diagrams of a page, compiled once per book
+    diagram_patterns: DiagramPatterns,
+}
+
+struct DiagramPatterns {
+    /// `<img>` of an SVG file
+    image: Regex,
+    /// Inline `<svg>`
+    inline: Regex,
+    /// Mermaid source of the mermaid-md-adoc plugin
+    mermaid: Regex,
+}
+
+impl SocialCards {
+    /// None without `socialCards` in book.json, or without `deploy.siteUrl`
+    pub fn new(source: &Path, config: &BookConfig) -> Option<Self> {
+        let cards = config.social_cards.as_ref()?;
+        if config.deploy.site_url.is_empty() {
+            warning!("socialCards: set deploy.siteUrl in book.json, og:image needs an absolute URL");
+            return None;
+        }
+
+        let mut options = usvg::Options { resources_dir: Some(source.to_path_buf()), ..Default::default() };
+        let fonts = options.fontdb_mut();
+        fonts.load_system_fonts();
+        let custom = cards.font.as_ref().and_then(|font| match fonts.load_font_file(source.join(font)) {
+            Ok(()) => fonts.faces().last().and_then(|face| face.families.first()).map(|(family, _)| family.clone()),
+            Err(e) => {
+                warning!("socialCards.font: {} is not a readable font: {}", font, e);
+                None
+            }
+        });
+        let family = custom.unwrap_or_else(|| {
+            let installed = |name: &&&str| fonts.faces().any(|face| face.families.iter().any(|(family, _)| family == **name));
+            SANS_SERIF.iter().find(installed).unwrap_or(&"sans-serif").to_string()
+        });
+        fonts.set_sans_serif_family(family.clone());
+
+        let mermaid = cards.diagram && Command::new("mmdc").arg("--version").output().is_ok_and(|output| output.status.success());
+        Some(Self {
+            options,
+            family,
+            book_title: config.title.clone(),
+            background: cards.background.clone().unwrap_or_else(|| "#1f2d3d".to_string()),
+            color: cards.color.clone().unwrap_or_else(|| "#ffffff".to_string()),
+            image: cards.image.as_ref().map(|image| paths::normalize(image.trim_start_matches("./"))),
+            diagram: cards.diagram,
+            mermaid,
+            diagram_patterns: DiagramPatterns {
+                image: Regex::new(r#"(?i)<img\b[^>]*\bsrc="([^":#?]+\.svg)""#).unwrap(),
+                inline: Regex::new(r"(?s)<svg\b.*?</svg>").unwrap(),
+                mermaid: Regex::new(r#"(?s)<div class="mermaid">(.*?)</div>"#).unwrap(),
+            },
+        })
+    }
+
+    /// Render the card of a page to `card_path` in the output directory
+    /// html: content of the page, html_path: its output path
+    pub fn write(&self, output: &Path, card_path: &str, title: &str, html: &str, html_path: &str) -> Result<()> {
+        let diagram = if self.diagram { self.first_diagram(html, html_path, output) } else { None };
+        let svg = self.card_svg(title, diagram.as_deref());
+        let tree = usvg::Tree::from_str(&svg, &self.options)?;
+        let mut pixmap = tiny_skia::Pixmap::new(WIDTH, HEIGHT).ok_or_else(|| anyhow!("cannot allocate the card"))?;
+        resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+        let dest = output.join(card_path);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(dest, pixmap.encode_png()?)?;
+        Ok(())
+    }
+
+    /// SVG document of a card; diagram: `href` of the diagram image
+    fn card_svg(&self, title: &str, diagram: Option<&str>) -> String {
+        let font = html_escape(&self.family);
+        let color = html_escape(&self.color);
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}"><rect width="100%" height="100%" fill="{}"/>"#,
+            WIDTH,
+            HEIGHT,
+            html_escape(&self.background)
+        );
+        if let Some(image) = &self.image {
+            svg.push_str(&format!(
+                r#"<image href="{}" width="{}" height="{}" preserveAspectRatio="xMidYMid slice"/>"#,
+                html_escape(image),
+                WIDTH,
+                HEIGHT
+            ));
+        }
+
+        let line_chars = if diagram.is_some() { TITLE_LINE_CHARS_DIAGRAM } else { TITLE_LINE_CHARS };
+        let book_title = wrap(&self.book_title, line_chars * 2, 1);
+        svg.push_str(&format!(
+            r#"<text x="80" y="110" font-family="{}" font-size="32" fill="{}" fill-opacity="0.75">{}</text>"#,
+            font,
+            color,
+            html_escape(book_title.first().map_or("", String::as_str))
+        ));
+        let lines = wrap(title, line_chars, TITLE_LINES);
+        // Centered between the book title and the rule
+        let mut y = 345 - 38 * lines.len() as i32 + 56;
+        svg.push_str(&format!(r#"<text font-family="{}" font-size="64" font-weight="bold" fill="{}">"#, font, color));
+        for line in &lines {
+            svg.push_str(&format!(r#"<tspan x="80" y="{}">{}</tspan>"#, y, html_escape(line)));
+            y += 76;
+        }
+        svg.push_str(&format!(r#"</text><rect x="80" y="530" width="120" height="8" fill="{}"/>"#, color));
+
+        if let Some(diagram) = diagram {
+            svg.push_str(&format!(
+                r##"<rect x="680" y="95" width="440" height="440" rx="16" fill="#ffffff"/><image href="{}" x="700" y="115" width="400" height="400"/>"##,
+                html_escape(diagram)
+            ));
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+
+    /// `href` of the first diagram of a page's HTML: a local SVG image, an
+    /// inline SVG or a Mermaid diagram
+    fn first_diagram(&self, html: &str, html_path: &str, output: &Path) -> Option<String> {
+        let patterns = &self.diagram_patterns;
+        let mut diagrams: Vec<(usize, Diagram)> = Vec::new();
+        diagrams.extend(patterns.image.captures_iter(html).map(|caps| (caps.get(0).unwrap().start(), Diagram::Image(caps[1].to_string()))));
+        diagrams.extend(patterns.inline.find_iter(html).map(|m| (m.start(), Diagram::Inline(m.as_str().to_string()))));
+        if self.mermaid {
+            diagrams.extend(patterns.mermaid.captures_iter(html).map(|caps| (caps.get(0).unwrap().start(), Diagram::Mermaid(unescape_html(&caps[1])))));
+        }
+        diagrams.sort_by_key(|(start, _)| *start);
+
+        diagrams.into_iter().find_map(|(_, diagram)| {
+            let svg = match diagram {
+                Diagram::Image(src) => {
+                    let path = resolve_book_path(html_path, &paths::decode(&src))?;
+                    fs::read_to_string(output.join(path)).ok()?
+                }
+                // Icons aren't diagrams
+                Diagram::Inline(svg) if svg.contains("currentColor") => return None,
+                Diagram::Inline(svg) if !svg.contains("xmlns=") => svg.replacen("<svg", r#"<svg xmlns="http://www.w3.org/2000/svg""#, 1),
+                Diagram::Inline(svg) => svg,
+                Diagram::Mermaid(source) => match render_mermaid(&source) {
+                    Ok(svg) => svg,
+                    Err(e) => {
+                        warning!("{}: Mermaid diagram left out of the social card: {:#}", html_path, e);
+                        return None;
+                    }
+                },
+            };
+            Some(format!("data:image/svg+xml,{}", utf8_percent_encode(&svg, NON_ALPHANUMERIC)))
+        })
+    }
+}
+
+enum Diagram {
+    /// `src` of an SVG image
+    Image(String),
+    Inline(String),
+    /// Source of a Mermaid diagram
+    Mermaid(String),
+}
+
+/// SVG of a Mermaid diagram, from mermaid-cli; labels are SVG text, which
+/// the renderer draws (unlike the HTML labels of the browser)
+fn render_mermaid(source: &str) -> Result<String> {
+    let run = MERMAID_RUNS.fetch_add(1, Ordering::Relaxed);
+    let dir = std::env::temp_dir().join(format!("guidebook-mermaid-{}-{}", std::process::id(), run));
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("diagram.mmd"), source)?;
+    fs::write(dir.join("config.json"), r#"{"htmlLabels": false, "flowchart": {"htmlLabels": false}}"#)?;
+    let result = Command::new("mmdc")
+        .args(["--quiet", "-i", "diagram.mmd", "-o", "diagram.svg", "-c", "config.json", "-b", "transparent"])
+        .current_dir(&dir)
+        .output()
+        .context("cannot run mmdc")
+        .and_then(|output| {
+            if !output.status.success() {
+                bail!("mmdc failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+            }
+            Ok(fs::read_to_string(dir.join("diagram.svg"))?)
+        });
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+/// Width of a character in columns: CJK characters and the like take two
+fn char_columns(c: char) -> usize {
+    if c >= '\u{2e80}' {
+        2
+    } else {
+        1
+    }
+}
+
+/// Lines of at most `columns` columns, breaking between words and between
+/// wide characters; the last of `max_lines` ends with "…" when cut short
+fn wrap(text: &str, columns: usize, max_lines: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    let mut width = 0;
+    let mut truncated = false;
+    // Words, and wide characters on their own, each with whether a space precedes it
+    let mut tokens: Vec<(bool, String)> = Vec::new();
+    let mut space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            space = true;
+            continue;
+        }
+        match tokens.last_mut() {
+            Some((_, word)) if !space && char_columns(c) == 1 && word.chars().last().is_some_and(|last| char_columns(last) == 1) => word.push(c),
+            _ => tokens.push((space, c.to_string())),
+        }
+        space = false;
+    }
+
+    for (spaced, token) in tokens {
+        let token_width: usize = token.chars().map(char_columns).sum();
+        let gap = usize::from(spaced && !line.is_empty());
+        if width + gap + token_width > columns && !line.is_empty() {
+            if lines.len() + 1 == max_lines {
+                truncated = true;
+                break;
+            }
+            lines.push(std::mem::take(&mut line));
+            width = 0;
+        } else if gap == 1 {
+            line.push(' ');
+            width += 1;
+        }
+        // Words longer than a line are cut
+        for c in token.chars() {
+            if width + char_columns(c) > columns {
+                if lines.len() + 1 == max_lines {
+                    truncated = true;
+                    break;
+                }
+                lines.push(std::mem::take(&mut line));
+                width = 0;
+            }
+            line.push(c);
+            width += char_columns(c);
+        }
+        if truncated {
+            break;
+        }
+    }
+    if truncated {
+        while line.chars().map(char_columns).sum::<usize>() + 1 > columns {
+            line.pop();
+        }
+        line = format!("{}…", line.trim_end());
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("Getting started", 30, 3), vec!["Getting started"]);
+        assert_eq!(wrap("Install the command line tools", 12, 3), vec!["Install the", "command line", "tools"]);
+        assert_eq!(wrap("Install the command line tools on Linux", 12, 2), vec!["Install the", "command lin…"]);
+        assert_eq!(wrap("はじめにお読みください", 8, 3), vec!["はじめに", "お読みく", "ださい"]);
+    }
+
+    #[test]
+    fn test_write_card() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("book");
+        let output = dir.path().join("_book");
+        fs::create_dir_all(output.join("guide/img")).unwrap();
+        fs::write(output.join("guide/img/flow.svg"), r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10"><rect width="10" height="10" fill="red"/></svg>"#).unwrap();
+
+        let config: BookConfig = serde_json::from_str(
+            r##"{"title": "Handbook", "deploy": {"siteUrl": "https://docs.example.com/"}, "socialCards": {"background": "#000000", "diagram": true}}"##,
+        )
+        .unwrap();
+        let page = PageContext {
+            book_dir: &source,
+            source_path: "guide/start.md",
+            html_path: "guide/start.html",
+            config: &config,
+            front_matter: None,
+            language: None,
+            position: None,
+            updated: None,
+        };
+        let card = card_path(&page).unwrap();
+        assert_eq!(card, "_social/guide/start.png");
+        let cards = SocialCards::new(&source, &config).unwrap();
+        let html = r#"<h1>Start</h1><p><img src="img/flow.svg" alt="Flow"></p>"#;
+        cards.write(&output, &card, "Start", html, "guide/start.html").unwrap();
+
+        let image = image::open(output.join(&card)).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (WIDTH, HEIGHT));
+        assert_eq!(image.get_pixel(20, 20).0, [0, 0, 0]);
+        // The diagram, on its white panel
+        assert_eq!(image.get_pixel(900, 315).0, [255, 0, 0]);
+        assert_eq!(image.get_pixel(690, 105).0, [255, 255, 255]);
+
+        // Front matter images are used instead; no card without a site URL
+        let front_matter = crate::parser::FrontMatter { image: Some("cover.png".to_string()), ..Default::default() };
+        assert_eq!(card_path(&PageContext { front_matter: Some(&front_matter), position: None, ..page }), None);
+        let config: BookConfig = serde_json::from_str(r#"{"socialCards": {}}"#).unwrap();
+        assert_eq!(card_path(&PageContext { config: &config, position: None, ..page }), None);
+        assert!(SocialCards::new(&source, &config).is_none());
+    }
+}
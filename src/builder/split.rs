@@ -0,0 +1,219 @@
+//! Split long pages into one output page per section
+//!
+//! A page with `split: h2` in its front matter is published as an intro page
+//! (the content before the first `## heading`, at the usual path) followed by
+//! one page per section (`<page>-<heading-id>.html`), with navigation
+//! between them. The source file stays in one piece.
+
+use super::renderer::{html_escape, TocItem};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// One output page of a split chapter
+#[derive(Debug)]
+pub struct SplitPart {
+    /// Published path (e.g. "guide/api-auth.html")
+    pub html_path: String,
+    /// Section heading text; None for the intro page
+    pub title: Option<String>,
+    pub html: String,
+    pub toc_items: Vec<TocItem>,
+}
+
+/// Heading level of a `split` front matter value ("h1" to "h6")
+pub fn split_level(value: &str) -> Option<u8> {
+    let level = value.trim().to_ascii_lowercase().strip_prefix('h')?.parse().ok()?;
+    (1..=6).contains(&level).then_some(level)
+}
+
+/// Split rendered page content at headings of `level`
+/// Returns a single part (the page unchanged) when there is nothing to split
+pub fn split_page(html: &str, html_path: &str, level: u8, toc_items: &[TocItem]) -> Vec<SplitPart> {
+    let heading_re = Regex::new(&format!(r#"<h{}\b[^>]*\bid="([^"]+)"[^>]*>"#, level)).unwrap();
+    let starts: Vec<(usize, &str)> = heading_re
+        .captures_iter(html)
+        .map(|cap| (cap.get(0).unwrap().start(), cap.get(1).unwrap().as_str()))
+        .collect();
+    if starts.is_empty() {
+        return vec![unsplit(html, html_path, toc_items)];
+    }
+
+    let stem = html_path.strip_suffix(".html").unwrap_or(html_path);
+    let mut parts = Vec::new();
+    let intro = &html[..starts[0].0];
+    if !intro.trim().is_empty() {
+        parts.push(SplitPart {
+            html_path: html_path.to_string(),
+            title: None,
+            html: intro.to_string(),
+            toc_items: Vec::new(),
+        });
+    }
+    for (i, &(start, id)) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).map(|&(s, _)| s).unwrap_or(html.len());
+        // A page starting with a section heading keeps its path for that section
+        let path = if parts.is_empty() {
+            html_path.to_string()
+        } else {
+            format!("{}-{}.html", stem, section_slug(id, i + 1))
+        };
+        let title = toc_items
+            .iter()
+            .find(|t| t.id == id)
+            .map(|t| t.text.clone())
+            .unwrap_or_else(|| id.to_string());
+        parts.push(SplitPart {
+            html_path: path,
+            title: Some(title),
+            html: html[start..end].to_string(),
+            toc_items: Vec::new(),
+        });
+    }
+
+    // Each part lists the headings it contains and links to anchors moved elsewhere
+    let id_re = Regex::new(r#"\bid="([^"]+)""#).unwrap();
+    let mut anchor_pages: HashMap<String, String> = HashMap::new();
+    for part in &parts {
+        let file_name = part.html_path.rsplit('/').next().unwrap_or(&part.html_path).to_string();
+        for cap in id_re.captures_iter(&part.html) {
+            anchor_pages.entry(cap[1].to_string()).or_insert_with(|| file_name.clone());
+        }
+    }
+    for part in &mut parts {
+        part.toc_items = toc_items
+            .iter()
+            .filter(|t| t.level > level && part.html.contains(&format!("id=\"{}\"", t.id)))
+            .cloned()
+            .collect();
+        let file_name = part.html_path.rsplit('/').next().unwrap_or(&part.html_path).to_string();
+        part.html = rewrite_fragment_links(&part.html, &file_name, &anchor_pages);
+    }
+
+    let nav: Vec<(String, String)> = parts
+        .iter()
+        .map(|p| {
+            let file_name = p.html_path.rsplit('/').next().unwrap_or(&p.html_path).to_string();
+            (file_name, p.title.clone().unwrap_or_default())
+        })
+        .collect();
+    let has_intro = parts[0].title.is_none();
+    for (i, part) in parts.iter_mut().enumerate() {
+        if i == 0 && has_intro {
+            part.html.push_str(&section_list(&nav[1..]));
+        }
+        part.html.push_str(&split_nav(i.checked_sub(1).map(|p| &nav[p]), nav.get(i + 1)));
+    }
+    parts
+}
+
+fn unsplit(html: &str, html_path: &str, toc_items: &[TocItem]) -> SplitPart {
+    SplitPart {
+        html_path: html_path.to_string(),
+        title: None,
+        html: html.to_string(),
+        toc_items: toc_items.to_vec(),
+    }
+}
+
+/// File name suffix for a section: the heading id when it is URL-safe,
+/// otherwise its position ("section-3")
+fn section_slug(id: &str, position: usize) -> String {
+    if id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        id.to_string()
+    } else {
+        format!("section-{}", position)
+    }
+}
+
+/// Point `href="#id"` links at the part that now holds the anchor
+fn rewrite_fragment_links(html: &str, file_name: &str, anchor_pages: &HashMap<String, String>) -> String {
+    let href_re = Regex::new(r##"href="#([^"]+)""##).unwrap();
+    href_re
+        .replace_all(html, |cap: &regex::Captures| match anchor_pages.get(&cap[1]) {
+            Some(page) if page != file_name => format!("href=\"{}#{}\"", page, &cap[1]),
+            _ => cap[0].to_string(),
+        })
+        .to_string()
+}
+
+fn section_list(sections: &[(String, String)]) -> String {
+    let mut html = String::from("\n<ul class=\"split-sections\">\n");
+    for (href, title) in sections {
+        html.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n", href, html_escape(title)));
+    }
+    html.push_str("</ul>\n");
+    html
+}
+
+fn split_nav(prev: Option<&(String, String)>, next: Option<&(String, String)>) -> String {
+    let mut html = String::from("\n<nav class=\"split-nav\">\n");
+    if let Some((href, title)) = prev {
+        let title = if title.is_empty() { "Overview" } else { title };
+        html.push_str(&format!("<a class=\"split-prev\" href=\"{}\">&larr; {}</a>\n", href, html_escape(title)));
+    }
+    if let Some((href, title)) = next {
+        html.push_str(&format!("<a class=\"split-next\" href=\"{}\">{} &rarr;</a>\n", href, html_escape(title)));
+    }
+    html.push_str("</nav>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toc(level: u8, text: &str, id: &str) -> TocItem {
+        TocItem { level, text: text.to_string(), id: id.to_string() }
+    }
+
+    #[test]
+    fn test_split_level() {
+        assert_eq!(split_level("h2"), Some(2));
+        assert_eq!(split_level("H3"), Some(3));
+        assert_eq!(split_level("h7"), None);
+        assert_eq!(split_level("2"), None);
+    }
+
+    #[test]
+    fn test_split_page_sections() {
+        let html = concat!(
+            "<h1 id=\"api\">API</h1>\n<p>See <a href=\"#token\">tokens</a>.</p>\n",
+            "<h2 id=\"auth\">Auth</h2>\n<h3 id=\"token\">Token</h3>\n",
+            "<h2 id=\"über\">Über</h2>\n<p><a href=\"#auth\">back</a></p>\n",
+        );
+        let toc_items = vec![
+            toc(1, "API", "api"),
+            toc(2, "Auth", "auth"),
+            toc(3, "Token", "token"),
+            toc(2, "Über", "über"),
+        ];
+
+        let parts = split_page(html, "guide/api.html", 2, &toc_items);
+        let paths: Vec<&str> = parts.iter().map(|p| p.html_path.as_str()).collect();
+        assert_eq!(paths, vec!["guide/api.html", "guide/api-auth.html", "guide/api-section-2.html"]);
+        assert_eq!(parts[0].title, None);
+        assert_eq!(parts[2].title.as_deref(), Some("Über"));
+
+        assert!(parts[0].html.contains("href=\"api-auth.html#token\""));
+        assert!(parts[0].html.contains("<ul class=\"split-sections\">"));
+        assert!(parts[2].html.contains("href=\"api-auth.html#auth\""));
+        assert!(parts[1].html.contains("href=\"api.html\">&larr; Overview</a>"));
+        assert!(parts[1].html.contains("href=\"api-section-2.html\">Über &rarr;</a>"));
+
+        let toc_ids: Vec<&str> = parts[1].toc_items.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(toc_ids, vec!["token"]);
+    }
+
+    #[test]
+    fn test_split_page_without_intro_or_headings() {
+        let html = "<h2 id=\"a\">A</h2>\n<p>a</p>\n<h2 id=\"b\">B</h2>\n";
+        let parts = split_page(html, "page.html", 2, &[]);
+        let paths: Vec<&str> = parts.iter().map(|p| p.html_path.as_str()).collect();
+        assert_eq!(paths, vec!["page.html", "page-b.html"]);
+
+        let parts = split_page("<p>short</p>", "page.html", 2, &[toc(3, "X", "x")]);
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].html, "<p>short</p>");
+        assert_eq!(parts[0].toc_items.len(), 1);
+    }
+}
@@ -0,0 +1,178 @@
+//! Content statistics for `guidebook stats`
+//!
+//! Counts are taken from the rendered chapters in SUMMARY.md order, so
+//! imports, templates and merged entries are included. Each CJK character
+//! counts as a word; reading time assumes 200 words or 500 CJK characters
+//! per minute. Builds use the same estimate for the reading time of pages.
+
+use super::export::ExportBook;
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::path::Path;
+
+const WORDS_PER_MINUTE: f64 = 200.0;
+const CJK_CHARS_PER_MINUTE: f64 = 500.0;
+
+/// Tags and entities, replaced by spaces so adjacent elements don't run words together
+const TAG_PATTERN: &str = r"<[^>]*>|&#?\w+;";
+
+/// Counts for one chapter, or for the whole book
+#[derive(Debug, Default, Clone, Serialize, PartialEq)]
+pub struct Counts {
+    pub words: usize,
+    /// CJK characters among `words`
+    pub cjk_characters: usize,
+    pub images: usize,
+    pub headings: usize,
+    /// Estimated reading time in minutes (rounded up)
+    pub reading_minutes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChapterStats {
+    pub title: String,
+    /// Book-relative source path
+    pub path: String,
+    #[serde(flatten)]
+    pub counts: Counts,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BookStats {
+    pub chapters: Vec<ChapterStats>,
+    pub total: Counts,
+}
+
+/// Statistics for every chapter of the book at `source`
+pub fn book_stats(source: &Path) -> Result<BookStats> {
+    let book = ExportBook::load(source)?;
+    let image_re = Regex::new(r"<img\b").unwrap();
+    let heading_re = Regex::new(r"<h[1-6]\b").unwrap();
+    let tag_re = Regex::new(TAG_PATTERN).unwrap();
+
+    let mut chapters = Vec::new();
+    for chapter in book.chapters() {
+        let html = book.render_chapter(&chapter)?;
+        let (words, cjk_characters) = count_words(&tag_re.replace_all(&html, " "));
+        chapters.push(ChapterStats {
+            title: chapter.title,
+            path: chapter.source_path,
+            counts: Counts {
+                words,
+                cjk_characters,
+                images: image_re.find_iter(&html).count(),
+                headings: heading_re.find_iter(&html).count(),
+                reading_minutes: reading_minutes(words, cjk_characters),
+            },
+        });
+    }
+
+    let mut total = Counts::default();
+    for chapter in &chapters {
+        total.words += chapter.counts.words;
+        total.cjk_characters += chapter.counts.cjk_characters;
+        total.images += chapter.counts.images;
+        total.headings += chapter.counts.headings;
+    }
+    total.reading_minutes = reading_minutes(total.words, total.cjk_characters);
+
+    Ok(BookStats { chapters, total })
+}
+
+/// Count words in plain text as (words, CJK characters)
+/// Each CJK character counts as one word
+pub fn count_words(text: &str) -> (usize, usize) {
+    let mut words = 0;
+    let mut cjk = 0;
+    let mut in_word = false;
+    for c in text.chars() {
+        if is_cjk(c) {
+            cjk += 1;
+            in_word = false;
+        } else if c.is_alphanumeric() {
+            if !in_word {
+                words += 1;
+                in_word = true;
+            }
+        } else if c.is_whitespace() || !matches!(c, '\'' | '-' | '_' | '.') {
+            in_word = false;
+        }
+    }
+    (words + cjk, cjk)
+}
+
+/// Terminal columns taken by `text` (CJK and full-width characters take two)
+pub fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if is_cjk(c) || ('\u{FF01}'..='\u{FF60}').contains(&c) || c == '\u{3000}' { 2 } else { 1 })
+        .sum()
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}' // Hiragana, Katakana
+        | '\u{3400}'..='\u{4DBF}' // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+        | '\u{AC00}'..='\u{D7AF}' // Hangul
+        | '\u{F900}'..='\u{FAFF}' // CJK Compatibility Ideographs
+    )
+}
+
+/// Estimated reading time of rendered HTML in minutes (rounded up)
+pub fn html_reading_minutes(html: &str) -> u64 {
+    let tag_re = Regex::new(TAG_PATTERN).unwrap();
+    let (words, cjk_characters) = count_words(&tag_re.replace_all(html, " "));
+    reading_minutes(words, cjk_characters)
+}
+
+fn reading_minutes(words: usize, cjk_characters: usize) -> u64 {
+    let other = (words - cjk_characters) as f64;
+    (other / WORDS_PER_MINUTE + cjk_characters as f64 / CJK_CHARS_PER_MINUTE).ceil() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_count_words() {
+        assert_eq!(count_words("Hello, world! It's a well-known v1.2 API"), (7, 0));
+        assert_eq!(count_words("日本語のテキスト and English"), (10, 8));
+        assert_eq!(count_words("   "), (0, 0));
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("Intro"), 5);
+        assert_eq!(display_width("はじめに（概要）"), 16);
+    }
+
+    #[test]
+    fn test_reading_minutes() {
+        assert_eq!(reading_minutes(0, 0), 0);
+        assert_eq!(reading_minutes(200, 0), 1);
+        assert_eq!(reading_minutes(201, 0), 2);
+        assert_eq!(reading_minutes(600, 500), 2);
+        assert_eq!(html_reading_minutes(&format!("<p>{}</p><p>{}</p>", "word ".repeat(150), "語".repeat(250))), 2);
+    }
+
+    #[test]
+    fn test_book_stats() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("book.json"), "{}").unwrap();
+        fs::write(dir.path().join("SUMMARY.md"), "# Summary\n\n* [Intro](README.md)\n* [Guide](guide.md)\n").unwrap();
+        fs::write(dir.path().join("README.md"), "# Intro\n\nOne two three.\n").unwrap();
+        fs::write(dir.path().join("guide.md"), "# Guide\n\n## Setup\n\n![logo](logo.png)\n\nFour five.\n").unwrap();
+
+        let stats = book_stats(dir.path()).unwrap();
+        assert_eq!(stats.chapters.len(), 2);
+        assert_eq!(stats.chapters[1].path, "guide.md");
+        assert_eq!(stats.chapters[1].counts.images, 1);
+        assert_eq!(stats.chapters[1].counts.headings, 2);
+        assert_eq!(stats.total.words, 8);
+        assert_eq!(stats.total.headings, 3);
+        assert_eq!(stats.total.reading_minutes, 1);
+    }
+}
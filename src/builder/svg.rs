@@ -46,6 +46,8 @@ pub fn externalize_inline_svg(html: &str, output_dir: &Path) -> Result<String> {
     // Regex to match inline SVG elements
     // Using (?s) flag for dotall mode to match across newlines
     let svg_regex = Regex::new(r"(?s)<svg([^>]*)>(.*?)</svg>")?;
+    let width_regex = Regex::new(r#"width\s*=\s*["']([^"']+)["']"#)?;
+    let height_regex = Regex::new(r#"height\s*=\s*["']([^"']+)["']"#)?;
 
     let mut result = html.to_string();
     let mut svg_index = 0;
@@ -77,9 +79,6 @@ pub fn externalize_inline_svg(html: &str, output_dir: &Path) -> Result<String> {
         fs::write(&svg_file_path, &svg_content)?;
 
         // Extract width and height from SVG attributes if present
-        let width_regex = Regex::new(r#"width\s*=\s*["']([^"']+)["']"#)?;
-        let height_regex = Regex::new(r#"height\s*=\s*["']([^"']+)["']"#)?;
-
         let width = width_regex.captures(svg_attrs)
             .map(|c| c[1].to_string());
         let height = height_regex.captures(svg_attrs)
@@ -124,6 +123,8 @@ pub fn externalize_inline_svg(html: &str, output_dir: &Path) -> Result<String> {
 pub fn inline_svg_files(html: &str, base_dir: &Path) -> Result<String> {
     // Regex to match img tags with SVG sources
     let img_regex = Regex::new(r#"<img([^>]+)src\s*=\s*["']([^"']+\.svg)["']([^>]*)>"#)?;
+    let width_regex = Regex::new(r#"width\s*=\s*["']([^"']+)["']"#)?;
+    let height_regex = Regex::new(r#"height\s*=\s*["']([^"']+)["']"#)?;
 
     let mut result = html.to_string();
     let mut offset: i64 = 0;
@@ -152,9 +153,6 @@ pub fn inline_svg_files(html: &str, base_dir: &Path) -> Result<String> {
         }
 
         // Extract width and height from img tag attributes
-        let width_regex = Regex::new(r#"width\s*=\s*["']([^"']+)["']"#)?;
-        let height_regex = Regex::new(r#"height\s*=\s*["']([^"']+)["']"#)?;
-
         let attrs = format!("{}{}", before_src, after_src);
         let width = width_regex.captures(&attrs)
             .map(|c| c[1].to_string());
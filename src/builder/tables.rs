@@ -0,0 +1,173 @@
+//! Table captions and column widths
+//!
+//! A `Table: ...` line right after a table (or after the blank line that ends
+//! it) becomes the table's `<caption>`:
+//!
+//! ```markdown
+//! | Test | Result |
+//! |:-- 60% --|-- 40% --:|
+//! | boot | pass   |
+//!
+//! Table: Results summary
+//! ```
+//!
+//! Cells of the delimiter row can carry a width between their dashes (`%`,
+//! `px`, `em`, `rem` or `ch`), written as the `<col>` widths of a `<colgroup>`;
+//! columns without one are sized by the browser. Printed output otherwise
+//! sizes columns by their content.
+
+use super::renderer::{code_ranges, html_escape};
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// A cell of a delimiter row, with an optional width among its dashes
+const DELIMITER_CELL_PATTERN: &str = r"^\s*(:?)-*\s*(\d+(?:\.\d+)?(?:%|px|em|rem|ch))?\s*-*(:?)\s*$";
+
+/// Caption and column widths of a table
+#[derive(Debug)]
+pub struct TableExtras {
+    pub caption: Option<String>,
+    pub widths: Vec<Option<String>>,
+}
+
+impl TableExtras {
+    /// HTML that goes right after `<table>`; empty when the table has neither
+    pub fn html(&self) -> String {
+        let mut html = String::new();
+        if let Some(caption) = &self.caption {
+            html.push_str(&format!("<caption>{}</caption>", html_escape(caption)));
+        }
+        if self.widths.iter().any(Option::is_some) {
+            html.push_str("<colgroup>");
+            for width in &self.widths {
+                match width {
+                    Some(width) => html.push_str(&format!(r#"<col style="width: {}">"#, width)),
+                    None => html.push_str("<col>"),
+                }
+            }
+            html.push_str("</colgroup>");
+        }
+        html
+    }
+}
+
+/// Take captions and width hints out of the tables of `content`
+/// Returns the Markdown to parse (with `options`) and the extras of each of
+/// its tables, in document order
+pub fn extract_table_extras(content: &str, options: Options) -> (String, Vec<TableExtras>) {
+    if !content.contains('|') {
+        return (content.to_string(), Vec::new());
+    }
+
+    // Width hints keep the parser from seeing a table, so they go first
+    let cell_re = Regex::new(DELIMITER_CELL_PATTERN).unwrap();
+    let code = code_ranges(content);
+    let mut widths: HashMap<usize, Vec<Option<String>>> = HashMap::new();
+    let mut offset = 0;
+    let mut lines: Vec<String> = Vec::new();
+    for (index, line) in content.split('\n').enumerate() {
+        let in_code = code.iter().any(|r| r.contains(&offset));
+        offset += line.len() + 1;
+        match delimiter_hints(line, &cell_re).filter(|_| !in_code) {
+            Some((stripped, hints)) => {
+                widths.insert(index, hints);
+                lines.push(stripped);
+            }
+            None => lines.push(line.to_string()),
+        }
+    }
+    let content = lines.join("\n");
+
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let line_of = |offset: usize| line_starts.partition_point(|&start| start <= offset) - 1;
+    let is_caption = |index: usize| lines.get(index).is_some_and(|l| l.trim_start().starts_with("Table:"));
+
+    let mut tables = Vec::new();
+    let mut caption_lines = Vec::new();
+    for (event, range) in Parser::new_ext(&content, options).into_offset_iter() {
+        let Event::Start(Tag::Table(_)) = event else { continue };
+        let first = line_of(range.start);
+        let last = line_of(range.end.saturating_sub(1).max(range.start));
+        // Without a blank line, the caption is parsed as the last row
+        let caption_line = if last > first + 1 && is_caption(last) {
+            Some(last)
+        } else if is_caption(last + 1) {
+            Some(last + 1)
+        } else if lines.get(last + 1).is_some_and(|l| l.trim().is_empty()) && is_caption(last + 2) {
+            Some(last + 2)
+        } else {
+            None
+        };
+        let caption = caption_line.map(|index| {
+            caption_lines.push(index);
+            lines[index].trim_start()["Table:".len()..].trim().to_string()
+        });
+        tables.push(TableExtras { caption, widths: widths.remove(&(first + 1)).unwrap_or_default() });
+    }
+
+    if caption_lines.is_empty() {
+        return (content, tables);
+    }
+    let content = lines
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !caption_lines.contains(index))
+        .map(|(_, line)| line.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    (content, tables)
+}
+
+/// A delimiter row with at least one width hint: the row without its hints,
+/// and the width of each column
+fn delimiter_hints(line: &str, cell_re: &Regex) -> Option<(String, Vec<Option<String>>)> {
+    let trimmed = line.trim();
+    if !trimmed.contains('-') || !trimmed.contains('|') || !trimmed.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let inner = trimmed.strip_prefix('|').unwrap_or(trimmed);
+    let inner = inner.strip_suffix('|').unwrap_or(inner);
+
+    let mut cells = Vec::new();
+    let mut hints = Vec::new();
+    for cell in inner.split('|') {
+        let caps = cell_re.captures(cell).filter(|_| cell.contains('-'))?;
+        cells.push(format!("{}---{}", &caps[1], &caps[3]));
+        hints.push(caps.get(2).map(|m| m.as_str().to_string()));
+    }
+    if hints.iter().all(Option::is_none) {
+        return None;
+    }
+    let indent = &line[..line.len() - line.trim_start().len()];
+    Some((format!("{}| {} |", indent, cells.join(" | ")), hints))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::renderer::render_markdown;
+
+    #[test]
+    fn test_table_caption_and_widths() {
+        let md = "| Test | Result |\n|:-- 60% --|-- 40% --:|\n| boot | pass |\n\nTable: Results *summary*\n\nAfter.\n";
+        let html = render_markdown(md);
+        assert!(
+            html.contains(r#"<table><caption>Results *summary*</caption><colgroup><col style="width: 60%"><col style="width: 40%"></colgroup><thead>"#),
+            "{}",
+            html
+        );
+        assert!(html.contains(r#"<td style="text-align: right">pass</td>"#), "{}", html);
+        assert!(!html.contains("Table:") && html.contains("<p>After.</p>"), "{}", html);
+
+        // Caption directly after the rows, a width on one column only
+        let html = render_markdown("| a | b |\n|---|--- 12em|\n| 1 | 2 |\nTable: Sizes\n");
+        assert!(html.contains(r#"<caption>Sizes</caption><colgroup><col><col style="width: 12em"></colgroup>"#), "{}", html);
+        assert_eq!(html.matches("<tr>").count(), 2, "{}", html);
+
+        // Plain tables and code blocks are untouched
+        let html = render_markdown("| a |\n|---|\n| 1 |\n\nText\n\n```\n| a |\n|-- 5% --|\n```\n");
+        assert!(html.contains("<table><thead>") && html.contains("|-- 5% --|"), "{}", html);
+    }
+}
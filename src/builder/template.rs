@@ -1,36 +1,106 @@
-use crate::parser::{BookConfig, FrontMatter, Summary, SummaryItem};
+use crate::parser::{BookConfig, Summary, SummaryItem, SummaryPosition};
+use crate::paths;
+use crate::builder::hooks::PageContext;
+use crate::builder::{icons, indexnow, locale, minify, searchserver, socialcards, stats};
+use crate::builder::translate::format_day;
 use crate::builder::TocItem;
 use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
 use tera::{Context, Tera};
 
+/// Renders the navigation of `gitbook/nav.js` into the sidebar
+const NAV_JS: &str = include_str!("../../templates/nav.js");
+
+/// The page being rendered, available to the page template as `page`
+#[derive(Serialize)]
+struct PageData<'a> {
+    /// Book-relative source path
+    source_path: &'a str,
+    /// Published path of the page
+    html_path: &'a str,
+    language: Option<&'a str>,
+    /// Front matter custom fields, title and description
+    front_matter: serde_json::Value,
+    /// SUMMARY.md entry, index, prev/next and parent entries
+    position: Option<&'a SummaryPosition>,
+}
+
 pub struct Templates {
     tera: Tera,
+    sidebar: Sidebar,
+    /// `gitbook/nav.js` when the sidebar is external (`sidebar.external`)
+    nav: Option<String>,
 }
 
 impl Templates {
-    pub fn new(_config: &BookConfig) -> Result<Self> {
+    pub fn new(config: &BookConfig, summary: &Summary) -> Result<Self> {
         let mut tera = Tera::default();
 
         // Register the main page template
         tera.add_raw_template("page.html", PAGE_TEMPLATE)?;
+        // The "Last updated" date is in the book's language
+        locale::register_filters(&mut tera, &config.language);
 
-        Ok(Self { tera })
+        let collapsible = config.is_plugin_enabled("collapsible-chapters");
+        let sidebar = Sidebar::new(summary, collapsible);
+        let nav = config.sidebar.external.then(|| {
+            let tree = serde_json::json!({ "collapsible": collapsible, "items": nav_items(&summary.items, summary) });
+            format!("window.guidebookNav = {};\n\n{}", tree, NAV_JS)
+        });
+        Ok(Self { tera, sidebar, nav })
     }
 
-    /// Render a page with front matter metadata support
-    pub fn render_page_with_meta(
+    /// Write `gitbook/nav.js` and the `gitbook/nav.html` fallback for browsers
+    /// without JavaScript, when the sidebar is external
+    pub fn write_nav(&self, output: &Path, config: &BookConfig) -> Result<()> {
+        let Some(nav) = &self.nav else {
+            return Ok(());
+        };
+        let gitbook_dir = output.join("gitbook");
+        fs::create_dir_all(&gitbook_dir)?;
+        fs::write(gitbook_dir.join("nav.js"), minify::asset("nav.js", nav, config))?;
+
+        let root_path = config.root_path(1);
+        let html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"UTF-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n<title>{} | {}</title>\n<link rel=\"stylesheet\" href=\"{}gitbook/gitbook.css\">\n</head>\n<body class=\"book\">\n<div class=\"book-summary\">\n<nav role=\"navigation\">\n<ul class=\"summary\">{}</ul>\n</nav>\n</div>\n</body>\n</html>\n",
+            NAV_FALLBACK_TITLE,
+            html_escape(&config.title),
+            root_path,
+            self.sidebar.render(None, &root_path)
+        );
+        fs::write(gitbook_dir.join("nav.html"), minify::page(html, config))?;
+        Ok(())
+    }
+
+    /// Render a page: its content wrapped in the book layout
+    pub fn render_page(
         &self,
+        page: &PageContext,
         title: &str,
         content: &str,
         root_path: &str,
-        config: &BookConfig,
-        summary: &Summary,
-        current_path: Option<&str>,
         toc_items: &[TocItem],
-        front_matter: Option<&FrontMatter>,
     ) -> Result<String> {
+        let config = page.config;
+        let front_matter = page.front_matter;
+        // Highlighted in the sidebar
+        let current_path = page.position.as_ref().and_then(|p| p.entry.path.as_deref());
         let mut context = Context::new();
 
+        context.insert(
+            "page",
+            &PageData {
+                source_path: page.source_path,
+                html_path: page.html_path,
+                language: page.language,
+                front_matter: front_matter.map(|fm| fm.template_fields()).unwrap_or_else(|| serde_json::json!({})),
+                position: page.position.as_ref(),
+            },
+        );
+
         context.insert("title", title);
         context.insert("book_title", &config.title);
         context.insert("content", content);
@@ -40,22 +110,61 @@ impl Templates {
         let collapsible = config.is_plugin_enabled("collapsible-chapters");
         context.insert("collapsible", &collapsible);
 
-        // Generate sidebar HTML - links need root_path prefix
-        let sidebar = generate_sidebar(&summary.items, current_path, root_path, collapsible);
-        context.insert("sidebar", &sidebar);
+        // Sidebar HTML - links need root_path prefix
+        // An external sidebar is rendered by gitbook/nav.js, which marks the entry of `nav_active`
+        if self.nav.is_some() {
+            context.insert("sidebar", "");
+            context.insert("nav_active", &current_path.map(paths::encode_href).unwrap_or_default());
+        } else {
+            context.insert("sidebar", &self.sidebar.render(current_path, root_path));
+        }
+        context.insert("external_sidebar", &self.nav.is_some());
+        context.insert("nav_fallback_title", NAV_FALLBACK_TITLE);
 
         // Generate prev/next navigation
-        let (prev_page, next_page) = get_prev_next_pages(&summary.items, current_path);
-        context.insert("prev_url", &prev_page.as_ref().map(|(url, _)| url.clone()));
-        context.insert("prev_title", &prev_page.map(|(_, title)| title));
-        context.insert("next_url", &next_page.as_ref().map(|(url, _)| url.clone()));
-        context.insert("next_title", &next_page.map(|(_, title)| title));
+        let prev_page = page.position.as_ref().and_then(|p| p.prev.as_ref());
+        let next_page = page.position.as_ref().and_then(|p| p.next.as_ref());
+        context.insert("prev_url", &prev_page.and_then(|link| link.path.as_deref()).map(paths::encode_href));
+        context.insert("prev_title", &prev_page.map(|link| &link.title));
+        context.insert("next_url", &next_page.and_then(|link| link.path.as_deref()).map(paths::encode_href));
+        context.insert("next_title", &next_page.map(|link| &link.title));
+
+        // Breadcrumbs: the part, then the enclosing entries (linked when they have a page)
+        let breadcrumbs: Vec<serde_json::Value> = page
+            .position
+            .as_ref()
+            .map(|p| {
+                let part = p.part.iter().map(|title| serde_json::json!({ "title": title, "url": null }));
+                let parents = p.parents.iter().map(|link| serde_json::json!({ "title": link.title, "url": link.path.as_deref().map(paths::encode_href) }));
+                part.chain(parents).collect()
+            })
+            .unwrap_or_default();
+        context.insert("breadcrumbs", &breadcrumbs);
+
+        // Estimated reading time in minutes, shown with `readingTime`
+        context.insert("reading_minutes", &stats::html_reading_minutes(content));
+        context.insert("reading_time", &config.reading_time);
+
+        // Last change of the source, with `lastUpdated` (YYYY-MM-DD)
+        context.insert("last_updated", &page.updated.map(format_day).unwrap_or_default());
 
         // Check plugin features
         context.insert("back_to_top", &config.is_plugin_enabled("back-to-top-button"));
         context.insert("mermaid", &config.is_plugin_enabled("mermaid-md-adoc"));
         context.insert("fontsettings", &config.is_plugin_enabled("fontsettings"));
         context.insert("math", &config.math);
+        context.insert("search_shortcut", config.search.shortcut.as_deref().unwrap_or("/"));
+        context.insert("search_history", &config.search.history.unwrap_or(5));
+
+        // Algolia DocSearch replaces the built-in search
+        let docsearch = config.search.algolia.as_ref().map(|algolia| {
+            let options = serde_json::json!({ "appId": algolia.app_id, "apiKey": algolia.api_key, "indexName": algolia.index(page.language) });
+            options.to_string().replace("</", "<\\/")
+        });
+        context.insert("docsearch", &docsearch.unwrap_or_default());
+        // Meilisearch or Typesense answers the queries of search.js
+        let search_server = searchserver::server(&config.search)?.map(|(engine, server)| searchserver::client_options(engine, server, page.language));
+        context.insert("search_server", &search_server.unwrap_or_default());
 
         // Generate TOC HTML
         let toc_html = generate_toc_html(toc_items);
@@ -67,8 +176,10 @@ impl Templates {
         context.insert("has_custom_style", &has_custom_style);
 
         // Add book variables to context (accessible as {{ book.xxx }} in templates)
+        // Sorted so iterating over `book` renders deterministically
         if !config.variables.is_empty() {
-            context.insert("book", &config.variables);
+            let variables: std::collections::BTreeMap<_, _> = config.variables.iter().collect();
+            context.insert("book", &variables);
         }
 
         // Add front matter metadata
@@ -85,124 +196,198 @@ impl Templates {
             context.insert("has_description", &false);
         }
 
+        context.insert("robots", &front_matter.and_then(|fm| fm.robots.as_deref()).unwrap_or_default());
+
+        // Open Graph, with the public URL of the page when `deploy.siteUrl` is set
+        context.insert("canonical_url", &html_escape(&canonical_url(page).unwrap_or_default()));
+        context.insert("og_type", if page.html_path == "index.html" { "website" } else { "article" });
+        // The page's image: from front matter, or its social card
+        let page_image = front_matter
+            .and_then(|fm| fm.image.as_deref())
+            .and_then(|image| absolute_url(config, image))
+            .or_else(|| socialcards::card_path(page).and_then(|card| public_url(page, &card)));
+        context.insert("og_image", &html_escape(page_image.as_deref().unwrap_or_default()));
+
+        // Twitter card, when book.json has a `twitter` section
+        if let Some(twitter) = &config.twitter {
+            let image = page_image.or_else(|| twitter.image.as_deref().and_then(|image| absolute_url(config, image)));
+            let card = match (&twitter.card, &image) {
+                (Some(card), _) => card.as_str(),
+                (None, Some(_)) => "summary_large_image",
+                (None, None) => "summary",
+            };
+            context.insert("twitter_card", card);
+            context.insert("twitter_site", twitter.site.as_deref().unwrap_or_default());
+            context.insert("twitter_image", &html_escape(&image.unwrap_or_default()));
+        } else {
+            context.insert("twitter_card", "");
+        }
+
+        // Installable book (`pwa` section), with its service worker
+        context.insert("icon_links", &icons::links(config, root_path));
+        context.insert("pwa", &config.pwa.is_some());
+        context.insert("theme_color", &config.pwa.as_ref().and_then(|pwa| pwa.theme_color.as_deref()).unwrap_or_default());
+
         let html = self.tera.render("page.html", &context)?;
         Ok(html)
     }
 }
 
-/// Get the previous and next pages based on the summary order
-fn get_prev_next_pages(
-    items: &[SummaryItem],
-    current_path: Option<&str>,
-) -> (Option<(String, String)>, Option<(String, String)>) {
-    // Flatten all pages into a list
-    let pages = flatten_pages(items);
-
-    if let Some(current) = current_path {
-        // Find current page index
-        let current_idx = pages.iter().position(|(path, _)| path == current);
-
-        if let Some(idx) = current_idx {
-            let prev = if idx > 0 {
-                pages.get(idx - 1).cloned()
-            } else {
-                None
-            };
-            let next = pages.get(idx + 1).cloned();
-            return (prev, next);
-        }
+/// Public URL of a page under `deploy.siteUrl` ("guide/index.html" is "guide/")
+fn canonical_url(page: &PageContext) -> Option<String> {
+    let site_url = &page.config.deploy.site_url;
+    if site_url.is_empty() {
+        return None;
     }
+    indexnow::page_urls(site_url, &[paths::encode_href(&site_path(page, page.html_path))]).pop()
+}
 
-    (None, None)
+/// Public URL of a file of the page's book under `deploy.siteUrl`
+/// path: output-relative path of the file
+fn public_url(page: &PageContext, path: &str) -> Option<String> {
+    let site_url = page.config.deploy.site_url.trim_end_matches('/');
+    (!site_url.is_empty()).then(|| format!("{}/{}", site_url, paths::encode_href(&site_path(page, path))))
 }
 
-/// Flatten summary items into a list of (html_path, title)
-fn flatten_pages(items: &[SummaryItem]) -> Vec<(String, String)> {
-    let mut pages = Vec::new();
+/// Site-relative path of a file of the page's book: languages are
+/// published in their directory
+fn site_path(page: &PageContext, path: &str) -> String {
+    match page.language.map(|code| page.config.languages.output_dir(code)).filter(|dir| !dir.is_empty()) {
+        Some(dir) => format!("{}/{}", dir, path),
+        None => path.to_string(),
+    }
+}
 
-    for item in items {
-        if let SummaryItem::Link { title, path, children } = item {
-            if let Some(md_path) = path {
-                // Remove leading slash and convert extension to .html
-                let html_path = md_path
-                    .trim_start_matches('/')
-                    .replace(".md", ".html")
-                    .replace(".adoc", ".html")
-                    .replace(".asciidoc", ".html");
-                pages.push((html_path, title.clone()));
-            }
-            // Recursively add children
-            pages.extend(flatten_pages(children));
-        }
+/// URL of an image: as it is when absolute, else a book-relative path under
+/// `deploy.siteUrl`; None without a site URL
+fn absolute_url(config: &BookConfig, path: &str) -> Option<String> {
+    if path.starts_with("http://") || path.starts_with("https://") {
+        return Some(path.to_string());
     }
+    let site_url = config.deploy.site_url.trim_end_matches('/');
+    (!site_url.is_empty()).then(|| format!("{}/{}", site_url, paths::encode_href(paths::normalize(path).trim_start_matches('/'))))
+}
 
-    pages
+/// The book's sidebar, rendered once per build
+///
+/// Pages only differ in the path to the book root in front of every link and
+/// the `active` class of their own entry, which are inserted at offsets
+/// recorded while rendering.
+pub struct Sidebar {
+    html: String,
+    /// Offsets of the link hrefs, where the root path goes
+    links: Vec<usize>,
+    /// Offsets after `chapter` in each entry's class list, by published path
+    entries: HashMap<String, Vec<usize>>,
 }
 
-fn generate_sidebar(items: &[SummaryItem], current_path: Option<&str>, prefix: &str, collapsible: bool) -> String {
-    let mut html = String::new();
+impl Sidebar {
+    pub fn new(summary: &Summary, collapsible: bool) -> Self {
+        let mut sidebar = Sidebar { html: String::new(), links: Vec::new(), entries: HashMap::new() };
+        sidebar.push_items(&summary.items, summary, collapsible);
+        sidebar
+    }
 
-    for item in items {
-        match item {
-            SummaryItem::Link { title, path, children } => {
-                // Remove leading slash and convert extension to .html
-                let html_path = path.as_ref().map(|p| {
-                    p.trim_start_matches('/')
-                        .replace(".md", ".html")
-                        .replace(".adoc", ".html")
-                        .replace(".asciidoc", ".html")
-                });
-                let is_active = current_path.map(|cp| {
-                    html_path.as_ref().map(|hp| cp == hp).unwrap_or(false)
-                }).unwrap_or(false);
-
-                let has_children = !children.is_empty();
-                // Always expand by default. User can collapse via JS, state saved in localStorage
-                let should_expand = has_children;
-
-                let active_class = if is_active { " active" } else { "" };
-                // Only add expandable class if collapsible plugin is enabled
-                let expandable_class = if has_children && collapsible { " expandable" } else { "" };
-                let expanded_class = if has_children && should_expand { " expanded" } else { "" };
-
-                html.push_str(&format!(
-                    r#"<li class="chapter{}{}{}">"#,
-                    active_class, expandable_class, expanded_class
-                ));
-
-                if let Some(ref hp) = html_path {
-                    html.push_str(&format!(
-                        r#"<a href="{}{}">{}</a>"#,
-                        prefix, hp, html_escape(title)
-                    ));
-                } else {
-                    html.push_str(&format!(
-                        r#"<span class="chapter-title">{}</span>"#,
-                        html_escape(title)
-                    ));
+    fn push_items(&mut self, items: &[SummaryItem], summary: &Summary, collapsible: bool) {
+        for item in items {
+            match item {
+                SummaryItem::Link { title, path, children } => {
+                    // Published .html path (honors part URL prefixes)
+                    let html_path = path.as_ref().map(|p| summary.html_path(p));
+
+                    let has_children = !children.is_empty();
+                    // Always expand by default. User can collapse via JS, state saved in localStorage
+                    let should_expand = has_children;
+
+                    // Only add expandable class if collapsible plugin is enabled
+                    let expandable_class = if has_children && collapsible { " expandable" } else { "" };
+                    let expanded_class = if has_children && should_expand { " expanded" } else { "" };
+
+                    self.html.push_str(r#"<li class="chapter"#);
+                    if let Some(ref hp) = html_path {
+                        self.entries.entry(hp.clone()).or_default().push(self.html.len());
+                    }
+                    self.html.push_str(&format!(r#"{}{}">"#, expandable_class, expanded_class));
+
+                    if let Some(ref hp) = html_path {
+                        self.html.push_str(r#"<a href=""#);
+                        self.links.push(self.html.len());
+                        self.html.push_str(&format!(r#"{}">{}</a>"#, paths::encode_href(hp), html_escape(title)));
+                    } else {
+                        self.html.push_str(&format!(
+                            r#"<span class="chapter-title">{}</span>"#,
+                            html_escape(title)
+                        ));
+                    }
+
+                    if has_children {
+                        self.html.push_str("<ul class=\"articles\">");
+                        self.push_items(children, summary, collapsible);
+                        self.html.push_str("</ul>");
+                    }
+
+                    self.html.push_str("</li>");
                 }
-
-                if has_children {
-                    html.push_str("<ul class=\"articles\">");
-                    html.push_str(&generate_sidebar(children, current_path, prefix, collapsible));
-                    html.push_str("</ul>");
+                SummaryItem::Separator => {
+                    self.html.push_str(r#"<li class="divider"></li>"#);
+                }
+                SummaryItem::PartTitle(part_title) => {
+                    self.html.push_str(&format!(
+                        r#"<li class="part-title"><span>{}</span></li>"#,
+                        html_escape(part_title)
+                    ));
                 }
-
-                html.push_str("</li>");
-            }
-            SummaryItem::Separator => {
-                html.push_str(r#"<li class="divider"></li>"#);
-            }
-            SummaryItem::PartTitle(part_title) => {
-                html.push_str(&format!(
-                    r#"<li class="part-title"><span>{}</span></li>"#,
-                    html_escape(part_title)
-                ));
             }
         }
     }
 
-    html
+    /// The sidebar of a page, with `current_path` highlighted
+    /// root_path: path from the page to the book root, prefixed to every link
+    pub fn render(&self, current_path: Option<&str>, root_path: &str) -> String {
+        let active = current_path.and_then(|path| self.entries.get(path)).map(Vec::as_slice).unwrap_or_default();
+        let mut html = String::with_capacity(self.html.len() + self.links.len() * root_path.len() + 64);
+        let mut last = 0;
+        let (mut links, mut active) = (self.links.iter().peekable(), active.iter().peekable());
+        loop {
+            // Entries and links never share an offset: an entry's link follows its class list
+            let (offset, insert) = match (links.peek(), active.peek()) {
+                (Some(&&link), Some(&&entry)) if entry < link => (*active.next().unwrap(), " active"),
+                (Some(_), _) => (*links.next().unwrap(), root_path),
+                (None, Some(_)) => (*active.next().unwrap(), " active"),
+                (None, None) => break,
+            };
+            html.push_str(&self.html[last..offset]);
+            html.push_str(insert);
+            last = offset;
+        }
+        html.push_str(&self.html[last..]);
+        html
+    }
+}
+
+/// Title of `gitbook/nav.html`, linked from pages without JavaScript
+const NAV_FALLBACK_TITLE: &str = "Table of Contents";
+
+/// Navigation tree of `gitbook/nav.js`: entries with a title, the href of
+/// their page (from the book root) and children, dividers and part titles
+fn nav_items(items: &[SummaryItem], summary: &Summary) -> Vec<serde_json::Value> {
+    items
+        .iter()
+        .map(|item| match item {
+            SummaryItem::Link { title, path, children } => {
+                let mut entry = serde_json::json!({ "title": title });
+                if let Some(path) = path {
+                    entry["href"] = paths::encode_href(&summary.html_path(path)).into();
+                }
+                if !children.is_empty() {
+                    entry["children"] = nav_items(children, summary).into();
+                }
+                entry
+            }
+            SummaryItem::Separator => serde_json::json!({ "divider": true }),
+            SummaryItem::PartTitle(title) => serde_json::json!({ "part": title }),
+        })
+        .collect()
 }
 
 fn html_escape(s: &str) -> String {
@@ -248,9 +433,51 @@ const PAGE_TEMPLATE: &str = r##"<!DOCTYPE html>
     {% if has_description %}
     <meta name="description" content="{{ description }}">
     {% endif %}
-    <link rel="stylesheet" href="{{ root_path }}gitbook/gitbook.css">
+    {%- if robots %}
+    <meta name="robots" content="{{ robots }}">
+    {%- endif %}
+    {%- if canonical_url %}
+    <link rel="canonical" href="{{ canonical_url | safe }}">
+    <meta property="og:url" content="{{ canonical_url | safe }}">
+    {%- endif %}
+    <meta property="og:type" content="{{ og_type }}">
+    <meta property="og:title" content="{{ title }}">
+    <meta property="og:site_name" content="{{ book_title }}">
+    {%- if og_image %}
+    <meta property="og:image" content="{{ og_image | safe }}">
+    {%- endif %}
+    {%- if has_description %}
+    <meta property="og:description" content="{{ description }}">
+    {%- endif %}
+    {%- if twitter_card %}
+    <meta name="twitter:card" content="{{ twitter_card }}">
+    {%- if twitter_site %}
+    <meta name="twitter:site" content="{{ twitter_site }}">
+    {%- endif %}
+    <meta name="twitter:title" content="{{ title }}">
+    {%- if has_description %}
+    <meta name="twitter:description" content="{{ description }}">
+    {%- endif %}
+    {%- if twitter_image %}
+    <meta name="twitter:image" content="{{ twitter_image | safe }}">
+    {%- endif %}
+    {%- endif %}
+    {%- if prev_url %}
+    <link rel="prev" href="{{ root_path | safe }}{{ prev_url | safe }}">
+    {%- endif %}
+    {%- if next_url %}
+    <link rel="next" href="{{ root_path | safe }}{{ next_url | safe }}">
+    {%- endif %}
+    {{ icon_links | safe }}
+    {%- if pwa %}
+    <link rel="manifest" href="{{ root_path | safe }}manifest.webmanifest">
+    {%- if theme_color %}
+    <meta name="theme-color" content="{{ theme_color }}">
+    {%- endif %}
+    {%- endif %}
+    <link rel="stylesheet" href="{{ root_path | safe }}gitbook/gitbook.css">
     {% if has_custom_style %}
-    <link rel="stylesheet" href="{{ root_path }}gitbook/style.css">
+    <link rel="stylesheet" href="{{ root_path | safe }}gitbook/style.css">
     {% endif %}
     <link rel="stylesheet" href="https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0/styles/github.min.css">
     <script src="https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0/highlight.min.js"></script>
@@ -269,17 +496,32 @@ const PAGE_TEMPLATE: &str = r##"<!DOCTYPE html>
                 ]
             });"></script>
     {% endif %}
+    {%- if docsearch %}
+    <link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/@docsearch/css@3">
+    {%- endif %}
 </head>
-<body class="book font-family-1" data-root-path="{{ root_path }}">
+<body class="book font-family-1" data-root-path="{{ root_path | safe }}">
     <div class="book-summary">
-        <div class="search-wrapper">
+        {%- if docsearch %}
+        <div class="search-wrapper docsearch-wrapper">
+            <div id="docsearch"></div>
+        </div>
+        {%- else %}
+        <div class="search-wrapper" data-shortcut="{{ search_shortcut }}" data-history="{{ search_history }}"{% if search_server %} data-search-server="{{ search_server }}"{% endif %}>
             <input type="text" class="search-input" placeholder="Search..." aria-label="Search">
             <div class="search-results"></div>
         </div>
+        {%- endif %}
         <nav role="navigation">
+            {% if external_sidebar %}
+            <noscript><a class="nav-fallback" href="{{ root_path | safe }}gitbook/nav.html">{{ nav_fallback_title }}</a></noscript>
+            <ul class="summary" data-active="{{ nav_active }}"></ul>
+            <script src="{{ root_path | safe }}gitbook/nav.js"></script>
+            {% else %}
             <ul class="summary">
                 {{ sidebar | safe }}
             </ul>
+            {% endif %}
         </nav>
     </div>
 
@@ -317,14 +559,14 @@ const PAGE_TEMPLATE: &str = r##"<!DOCTYPE html>
         {% endif %}
         <div class="body-inner">
             {% if prev_url %}
-            <a class="page-nav prev" href="{{ root_path }}{{ prev_url | safe }}" title="{{ prev_title }}">
+            <a class="page-nav prev" href="{{ root_path | safe }}{{ prev_url | safe }}" title="{{ prev_title }}">
                 <svg xmlns="http://www.w3.org/2000/svg" width="24" height="24" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
                     <polyline points="15 18 9 12 15 6"></polyline>
                 </svg>
             </a>
             {% endif %}
             {% if next_url %}
-            <a class="page-nav next" href="{{ root_path }}{{ next_url | safe }}" title="{{ next_title }}">
+            <a class="page-nav next" href="{{ root_path | safe }}{{ next_url | safe }}" title="{{ next_title }}">
                 <svg xmlns="http://www.w3.org/2000/svg" width="24" height="24" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
                     <polyline points="9 18 15 12 9 6"></polyline>
                 </svg>
@@ -332,9 +574,41 @@ const PAGE_TEMPLATE: &str = r##"<!DOCTYPE html>
             {% endif %}
             <div class="page-wrapper">
                 <div class="page-inner">
+                    {%- if breadcrumbs %}
+                    <nav class="breadcrumbs" aria-label="Breadcrumbs">
+                        <ol>
+                            {%- for crumb in breadcrumbs %}
+                            <li>{% if crumb.url %}<a href="{{ root_path | safe }}{{ crumb.url | safe }}">{{ crumb.title }}</a>{% else %}<span>{{ crumb.title }}</span>{% endif %}</li>
+                            {%- endfor %}
+                            <li aria-current="page">{{ title }}</li>
+                        </ol>
+                    </nav>
+                    {%- endif %}
+                    {%- if reading_time and reading_minutes > 0 %}
+                    <p class="page-reading-time">{{ reading_minutes }} min read</p>
+                    {%- endif %}
                     <section class="markdown-section">
                         {{ content | safe }}
                     </section>
+                    {%- if last_updated %}
+                    <p class="page-last-updated">Last updated: <time datetime="{{ last_updated }}">{{ last_updated | localdate }}</time></p>
+                    {%- endif %}
+                    {%- if prev_url or next_url %}
+                    <nav class="page-footer-nav" aria-label="Chapters">
+                        {%- if prev_url %}
+                        <a class="prev" rel="prev" href="{{ root_path | safe }}{{ prev_url | safe }}">
+                            <span class="page-footer-nav-label">Previous</span>
+                            <span class="page-footer-nav-title">{{ prev_title }}</span>
+                        </a>
+                        {%- endif %}
+                        {%- if next_url %}
+                        <a class="next" rel="next" href="{{ root_path | safe }}{{ next_url | safe }}">
+                            <span class="page-footer-nav-label">Next</span>
+                            <span class="page-footer-nav-title">{{ next_title }}</span>
+                        </a>
+                        {%- endif %}
+                    </nav>
+                    {%- endif %}
                 </div>
             </div>
         </div>
@@ -348,14 +622,252 @@ const PAGE_TEMPLATE: &str = r##"<!DOCTYPE html>
     </a>
     {% endif %}
 
-    <script src="{{ root_path }}gitbook/gitbook.js"></script>
+    <script src="{{ root_path | safe }}gitbook/gitbook.js"></script>
     {% if collapsible %}
-    <script src="{{ root_path }}gitbook/collapsible.js"></script>
+    <script src="{{ root_path | safe }}gitbook/collapsible.js"></script>
     {% endif %}
     {% if fontsettings %}
-    <script src="{{ root_path }}gitbook/fontsettings.js"></script>
+    <script src="{{ root_path | safe }}gitbook/fontsettings.js"></script>
     {% endif %}
-    <script src="{{ root_path }}gitbook/search.js"></script>
+    {%- if docsearch %}
+    <script src="https://cdn.jsdelivr.net/npm/@docsearch/js@3"></script>
+    <script>docsearch(Object.assign({ container: '#docsearch' }, {{ docsearch | safe }}));</script>
+    {%- else %}
+    <script src="{{ root_path | safe }}gitbook/search.js"></script>
+    {%- endif %}
+    {%- if pwa %}
+    <script>if ('serviceWorker' in navigator) navigator.serviceWorker.register('{{ root_path | safe }}sw.js');</script>
+    {%- endif %}
 </body>
 </html>
 "##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::summary::parse_summary;
+
+    #[test]
+    fn test_sidebar_marks_active_entry() {
+        let summary = parse_summary("# Summary\n\n* [Intro](README.md)\n* [Guide](guide/start.md)\n    * [Setup](guide/setup.md)\n\n## API\n\n* [Ref](api.md)\n").unwrap();
+        let sidebar = Sidebar::new(&summary, true);
+        assert_eq!(
+            sidebar.render(Some("guide/setup.html"), "../"),
+            concat!(
+                r#"<li class="chapter"><a href="../README.html">Intro</a></li>"#,
+                r#"<li class="chapter expandable expanded"><a href="../guide/start.html">Guide</a><ul class="articles">"#,
+                r#"<li class="chapter active"><a href="../guide/setup.html">Setup</a></li></ul></li>"#,
+                r#"<li class="part-title"><span>API</span></li>"#,
+                r#"<li class="chapter"><a href="../api.html">Ref</a></li>"#,
+            )
+        );
+        assert!(sidebar.render(Some("api.html"), "./").ends_with(r#"<li class="chapter active"><a href="./api.html">Ref</a></li>"#));
+        assert!(!sidebar.render(None, "").contains("active"));
+    }
+
+    #[test]
+    fn test_external_sidebar_writes_nav() {
+        let summary = parse_summary("# Summary\n\n* [Guide](guide/start.md)\n    * [Setup](<guide/set up.md>)\n\n---\n\n## API\n").unwrap();
+        let config: BookConfig = serde_json::from_str(r#"{"title": "Book", "sidebar": {"external": true}}"#).unwrap();
+        let templates = Templates::new(&config, &summary).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        templates.write_nav(dir.path(), &config).unwrap();
+
+        let nav = fs::read_to_string(dir.path().join("gitbook/nav.js")).unwrap();
+        assert!(nav.starts_with(concat!(
+            r#"window.guidebookNav = {"collapsible":true,"items":[{"title":"Guide","href":"guide/start.html","children":"#,
+            r#"[{"title":"Setup","href":"guide/set%20up.html"}]},{"divider":true},{"part":"API"}]};"#
+        )), "{}", nav);
+        let fallback = fs::read_to_string(dir.path().join("gitbook/nav.html")).unwrap();
+        assert!(fallback.contains(r#"<a href="../guide/set%20up.html">Setup</a>"#), "{}", fallback);
+
+        // Pages don't embed it
+        assert!(Templates::new(&BookConfig::default(), &summary).unwrap().nav.is_none());
+    }
+
+    #[test]
+    fn test_open_graph_and_canonical_url() {
+        let summary = parse_summary("# Summary\n\n* [Set up](guide/set up.md)\n").unwrap();
+        let config: BookConfig = serde_json::from_str(
+            r#"{"title": "Book", "deploy": {"siteUrl": "https://docs.acme.test/book/"}, "languages": {"paths": {"ja": "japanese"}}}"#,
+        )
+        .unwrap();
+        let templates = Templates::new(&config, &summary).unwrap();
+        let front_matter = crate::parser::FrontMatter { description: Some("How to <set up>".to_string()), ..Default::default() };
+        let page = PageContext {
+            book_dir: Path::new("."),
+            source_path: "guide/set up.md",
+            html_path: "guide/set up.html",
+            config: &config,
+            front_matter: Some(&front_matter),
+            language: Some("ja"),
+            position: None,
+            updated: None,
+        };
+        let html = templates.render_page(&page, "Set up", "", "../", &[]).unwrap();
+        assert!(html.contains(r#"<link rel="canonical" href="https://docs.acme.test/book/japanese/guide/set%20up.html">"#), "{}", html);
+        assert!(html.contains(r#"<meta property="og:url" content="https://docs.acme.test/book/japanese/guide/set%20up.html">"#), "{}", html);
+        assert!(html.contains(r#"<meta property="og:type" content="article">"#), "{}", html);
+        assert!(html.contains(r#"<meta property="og:title" content="Set up">"#), "{}", html);
+        assert!(html.contains(r#"<meta property="og:description" content="How to &lt;set up&gt;">"#), "{}", html);
+
+        // No public URL without a site URL
+        let config = BookConfig::default();
+        let html = templates.render_page(&PageContext { config: &config, html_path: "index.html", language: None, ..page }, "Home", "", "./", &[]).unwrap();
+        assert!(!html.contains("canonical") && !html.contains("og:url"), "{}", html);
+        assert!(html.contains(r#"<meta property="og:type" content="website">"#), "{}", html);
+        assert!(!html.contains("twitter:"), "{}", html);
+    }
+
+    #[test]
+    fn test_prev_next_links() {
+        let summary = parse_summary("# Summary\n\n* [Intro](README.md)\n* [Set up & run](guide/setup.md)\n* [API](api.md)\n").unwrap();
+        let config = BookConfig::default();
+        let templates = Templates::new(&config, &summary).unwrap();
+        let page = PageContext {
+            book_dir: Path::new("."),
+            source_path: "guide/setup.md",
+            html_path: "guide/setup.html",
+            config: &config,
+            front_matter: None,
+            language: None,
+            position: summary.position("guide/setup.html"),
+            updated: None,
+        };
+        let html = templates.render_page(&page, "Set up & run", "", "../", &[]).unwrap();
+        assert!(html.contains(r#"<link rel="prev" href="../README.html">"#), "{}", html);
+        assert!(html.contains(r#"<link rel="next" href="../api.html">"#), "{}", html);
+        assert!(html.contains(r#"<a class="prev" rel="prev" href="../README.html">"#), "{}", html);
+        assert!(html.contains(r#"<span class="page-footer-nav-title">API</span>"#), "{}", html);
+
+        // The first page has no previous link
+        let first = PageContext { position: summary.position("README.html"), ..page };
+        let html = templates.render_page(&first, "Intro", "", "./", &[]).unwrap();
+        assert!(!html.contains(r#"rel="prev""#), "{}", html);
+        assert!(html.contains(r#"<span class="page-footer-nav-title">Set up &amp; run</span>"#), "{}", html);
+    }
+
+    #[test]
+    fn test_breadcrumbs() {
+        let summary = parse_summary(
+            "# Summary\n\n* [Intro](README.md)\n\n## Reference\n\n* [API](api/index.md)\n    * Auth\n        * [Tokens](api/tokens.md)\n",
+        )
+        .unwrap();
+        let config = BookConfig::default();
+        let templates = Templates::new(&config, &summary).unwrap();
+        let page = PageContext {
+            book_dir: Path::new("."),
+            source_path: "api/tokens.md",
+            html_path: "api/tokens.html",
+            config: &config,
+            front_matter: None,
+            language: None,
+            position: summary.position("api/tokens.html"),
+            updated: None,
+        };
+        let html = templates.render_page(&page, "Tokens", "", "../", &[]).unwrap();
+        assert!(html.contains(r#"<li><span>Reference</span></li>"#), "{}", html);
+        assert!(html.contains(r#"<li><a href="../api/index.html">API</a></li>"#), "{}", html);
+        assert!(html.contains(r#"<li><span>Auth</span></li>"#), "{}", html);
+        assert!(html.contains(r#"<li aria-current="page">Tokens</li>"#), "{}", html);
+
+        // Top-level pages outside parts have none
+        let intro = PageContext { position: summary.position("README.html"), ..page };
+        assert!(!templates.render_page(&intro, "Intro", "", "./", &[]).unwrap().contains("breadcrumbs"));
+    }
+
+    #[test]
+    fn test_twitter_card() {
+        let summary = parse_summary("# Summary\n\n* [Start](start.md)\n").unwrap();
+        let config: BookConfig = serde_json::from_str(
+            r#"{"title": "Book", "twitter": {"site": "@acme", "image": "assets/card.png"}, "deploy": {"siteUrl": "https://docs.acme.test/"}}"#,
+        )
+        .unwrap();
+        let templates = Templates::new(&config, &summary).unwrap();
+        let front_matter = crate::parser::FrontMatter { description: Some("First steps".to_string()), ..Default::default() };
+        let page = PageContext {
+            book_dir: Path::new("."),
+            source_path: "start.md",
+            html_path: "start.html",
+            config: &config,
+            front_matter: Some(&front_matter),
+            language: None,
+            position: None,
+            updated: None,
+        };
+        let html = templates.render_page(&page, "Start", "", "./", &[]).unwrap();
+        assert!(html.contains(r#"<meta name="twitter:card" content="summary_large_image">"#), "{}", html);
+        assert!(html.contains(r#"<meta name="twitter:site" content="@acme">"#), "{}", html);
+        assert!(html.contains(r#"<meta name="twitter:title" content="Start">"#), "{}", html);
+        assert!(html.contains(r#"<meta name="twitter:description" content="First steps">"#), "{}", html);
+        assert!(html.contains(r#"<meta name="twitter:image" content="https://docs.acme.test/assets/card.png">"#), "{}", html);
+
+        // Front matter image wins; a relative image needs a site URL
+        let front_matter = crate::parser::FrontMatter { image: Some("https://cdn.test/start.png".to_string()), ..Default::default() };
+        let html = templates.render_page(&PageContext { front_matter: Some(&front_matter), position: None, ..page }, "Start", "", "./", &[]).unwrap();
+        assert!(html.contains(r#"<meta name="twitter:image" content="https://cdn.test/start.png">"#), "{}", html);
+        assert!(html.contains(r#"<meta property="og:image" content="https://cdn.test/start.png">"#), "{}", html);
+        // Then the page's social card
+        let config: BookConfig = serde_json::from_str(
+            r#"{"twitter": {"image": "assets/card.png"}, "socialCards": {}, "deploy": {"siteUrl": "https://docs.acme.test/"}, "languages": {"paths": {"ja": "jp"}}}"#,
+        )
+        .unwrap();
+        let html = templates.render_page(&PageContext { config: &config, front_matter: None, language: Some("ja"), position: None, ..page }, "Start", "", "./", &[]).unwrap();
+        assert!(html.contains(r#"<meta name="twitter:image" content="https://docs.acme.test/jp/_social/start.png">"#), "{}", html);
+        let config: BookConfig = serde_json::from_str(r#"{"twitter": {"image": "assets/card.png"}}"#).unwrap();
+        let html = templates.render_page(&PageContext { config: &config, front_matter: None, ..page }, "Start", "", "./", &[]).unwrap();
+        assert!(html.contains(r#"<meta name="twitter:card" content="summary">"#), "{}", html);
+        assert!(!html.contains("twitter:image") && !html.contains("twitter:site"), "{}", html);
+    }
+
+    #[test]
+    fn test_reading_time() {
+        let summary = parse_summary("# Summary\n\n* [Start](start.md)\n").unwrap();
+        let config: BookConfig = serde_json::from_str(r#"{"readingTime": true}"#).unwrap();
+        let templates = Templates::new(&config, &summary).unwrap();
+        let page = PageContext {
+            book_dir: Path::new("."),
+            source_path: "start.md",
+            html_path: "start.html",
+            config: &config,
+            front_matter: None,
+            language: None,
+            position: None,
+            updated: None,
+        };
+        let content = format!("<p>{}</p>", "word ".repeat(450));
+        let html = templates.render_page(&page, "Start", &content, "./", &[]).unwrap();
+        assert!(html.contains(r#"<p class="page-reading-time">3 min read</p>"#), "{}", html);
+
+        // Off by default
+        let config = BookConfig::default();
+        let html = templates.render_page(&PageContext { config: &config, position: None, ..page }, "Start", &content, "./", &[]).unwrap();
+        assert!(!html.contains("page-reading-time"), "{}", html);
+    }
+
+    #[test]
+    fn test_docsearch_replaces_search() {
+        let summary = parse_summary("# Summary\n\n* [Start](start.md)\n").unwrap();
+        let config: BookConfig =
+            serde_json::from_str(r#"{"search": {"algolia": {"appId": "APP", "apiKey": "search-key", "indexName": "docs"}}}"#).unwrap();
+        let templates = Templates::new(&config, &summary).unwrap();
+        let page = PageContext {
+            book_dir: Path::new("."),
+            source_path: "start.md",
+            html_path: "start.html",
+            config: &config,
+            front_matter: None,
+            language: Some("ja"),
+            position: None,
+            updated: None,
+        };
+        let html = templates.render_page(&page, "Start", "", "./", &[]).unwrap();
+        assert!(html.contains(r#"<div id="docsearch"></div>"#), "{}", html);
+        assert!(
+            html.contains(r#"docsearch(Object.assign({ container: '#docsearch' }, {"appId":"APP","apiKey":"search-key","indexName":"docs_ja"}));"#),
+            "{}",
+            html
+        );
+        assert!(!html.contains("search-input") && !html.contains("gitbook/search.js"), "{}", html);
+    }
+}
@@ -0,0 +1,196 @@
+//! Thumbnails for large images in tables
+//!
+//! A screenshot in a table cell is shown at its full size and stretches the
+//! table far beyond the page. With `thumbnails.width` set in book.json, local
+//! images in tables wider than that are replaced by a thumbnail of that width,
+//! linked to the full-size image:
+//!
+//! ```json
+//! { "thumbnails": { "width": 240 } }
+//! ```
+//!
+//! Thumbnails are written to `_thumbnails/` in the output directory, named
+//! after the image's output path, and reused while they are newer than it.
+
+use super::renderer::resolve_book_path;
+use crate::parser::ThumbnailsConfig;
+use crate::{paths, warning};
+use anyhow::{Context, Result};
+use crc32fast::Hasher;
+use image::ImageFormat;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+/// Output directory of the thumbnails
+pub const THUMBNAILS_DIR: &str = "_thumbnails";
+
+/// Replace large images in the tables of a page's HTML with linked thumbnails
+/// html_path: output path of the page, image sources are relative to it
+pub fn apply_thumbnails(html: String, output: &Path, html_path: &str, config: &ThumbnailsConfig) -> String {
+    if config.width == 0 || !html.contains("<table") {
+        return html;
+    }
+    let table_re = Regex::new(r"(?s)<table\b.*?</table>").unwrap();
+    let img_re = Regex::new(r"<img\b[^>]*>").unwrap();
+    let src_re = Regex::new(r#"\bsrc="([^"]*)""#).unwrap();
+    let root = "../".repeat(paths::depth(html_path));
+
+    table_re
+        .replace_all(&html, |table: &regex::Captures| {
+            let table = &table[0];
+            let mut result = String::with_capacity(table.len());
+            let mut last = 0;
+            for img in img_re.find_iter(table) {
+                let Some(src) = src_re.captures(img.as_str()).map(|c| c.get(1).unwrap()) else { continue };
+                let href = src.as_str();
+                if href.contains(':') || href.starts_with('/') || href.starts_with('#') {
+                    // Remote, data: and site-absolute images aren't in the output directory
+                    continue;
+                }
+                let Some(image_path) = resolve_book_path(html_path, &paths::decode(paths::split_fragment(href).0)) else { continue };
+                let thumbnail = match thumbnail(output, &image_path, config.width) {
+                    Ok(Some(thumbnail)) => thumbnail,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        warning!("{}: no thumbnail for {}: {:#}", html_path, href, e);
+                        continue;
+                    }
+                };
+
+                let tag = img.as_str();
+                let (start, end) = (src.start(), src.end());
+                let thumb_tag = format!(
+                    "{}{}{}",
+                    &tag[..start],
+                    paths::encode_href(&format!("{}{}", root, thumbnail.path)),
+                    &tag[end..]
+                );
+                let thumb_tag = thumb_tag.replacen("<img", &format!("<img width=\"{}\" height=\"{}\"", thumbnail.width, thumbnail.height), 1);
+                result.push_str(&table[last..img.start()]);
+                if inside_link(&table[..img.start()]) {
+                    result.push_str(&thumb_tag);
+                } else {
+                    result.push_str(&format!("<a class=\"thumbnail\" href=\"{}\">{}</a>", href, thumb_tag));
+                }
+                last = img.end();
+            }
+            result.push_str(&table[last..]);
+            result
+        })
+        .into_owned()
+}
+
+/// A thumbnail written to the output directory
+struct Thumbnail {
+    /// Output-relative path
+    path: String,
+    width: u32,
+    height: u32,
+}
+
+/// Thumbnail of an output image `width` pixels wide; None when the image is
+/// not wider or not a raster format
+fn thumbnail(output: &Path, image_path: &str, width: u32) -> Result<Option<Thumbnail>> {
+    let source = output.join(image_path);
+    // Broken images are reported by `guidebook check`
+    if !source.is_file() {
+        return Ok(None);
+    }
+    let Ok(format) = ImageFormat::from_path(&source) else { return Ok(None) };
+    if !matches!(format, ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP) {
+        return Ok(None);
+    }
+    let (image_width, image_height) = image::image_dimensions(&source).with_context(|| format!("Failed to read {:?}", source))?;
+    if image_width <= width {
+        return Ok(None);
+    }
+    let height = ((image_height as u64 * width as u64) / image_width as u64).max(1) as u32;
+
+    // GIFs become PNGs: the first frame is all a thumbnail shows
+    let (extension, thumb_format) = match format {
+        ImageFormat::Jpeg => ("jpg", ImageFormat::Jpeg),
+        ImageFormat::WebP => ("webp", ImageFormat::WebP),
+        _ => ("png", ImageFormat::Png),
+    };
+    let mut hasher = Hasher::new();
+    hasher.update(image_path.as_bytes());
+    let stem = Path::new(image_path).file_stem().and_then(|s| s.to_str()).unwrap_or("image");
+    let path = format!("{}/{}-{:08x}-{}.{}", THUMBNAILS_DIR, stem, hasher.finalize(), width, extension);
+    let dest = output.join(&path);
+
+    let up_to_date = match (fs::metadata(&dest).and_then(|m| m.modified()), fs::metadata(&source).and_then(|m| m.modified())) {
+        (Ok(thumb), Ok(image)) => thumb >= image,
+        _ => false,
+    };
+    if !up_to_date {
+        let image = image::open(&source).with_context(|| format!("Failed to decode {:?}", source))?;
+        fs::create_dir_all(output.join(THUMBNAILS_DIR))?;
+        image.thumbnail_exact(width, height).save_with_format(&dest, thumb_format)?;
+    }
+    Ok(Some(Thumbnail { path, width, height }))
+}
+
+/// Whether HTML ends inside an `<a>` element
+fn inside_link(html: &str) -> bool {
+    match (html.rfind("<a "), html.rfind("</a>")) {
+        (Some(open), Some(close)) => open > close,
+        (open, _) => open.is_some(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn write_image(path: &Path, width: u32, height: u32) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        RgbImage::from_pixel(width, height, Rgb([200, 10, 10])).save(path).unwrap();
+    }
+
+    #[test]
+    fn test_large_table_images_get_linked_thumbnails() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path();
+        write_image(&output.join("guide/img/big shot.png"), 800, 400);
+        write_image(&output.join("guide/img/small.png"), 100, 50);
+        let config = ThumbnailsConfig { width: 200 };
+
+        let html = concat!(
+            "<p><img src=\"img/big%20shot.png\" alt=\"outside\" /></p>\n",
+            "<table><tr><td><img src=\"img/big%20shot.png\" alt=\"big\" /></td>",
+            "<td><img src=\"img/small.png\" alt=\"small\" /></td>",
+            "<td><a href=\"x.html\"><img src=\"img/big%20shot.png\" alt=\"linked\" /></a></td></tr></table>"
+        );
+        let result = apply_thumbnails(html.to_string(), output, "guide/page.html", &config);
+
+        let thumbs: Vec<_> = fs::read_dir(output.join(THUMBNAILS_DIR)).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(thumbs.len(), 1);
+        let thumb = thumbs[0].to_string_lossy().to_string();
+        assert!(thumb.starts_with("big shot-") && thumb.ends_with("-200.png"), "{}", thumb);
+        assert_eq!(image::image_dimensions(output.join(THUMBNAILS_DIR).join(&thumb)).unwrap(), (200, 100));
+
+        let thumb_src = format!("../_thumbnails/{}", thumb.replace(' ', "%20"));
+        assert!(result.starts_with("<p><img src=\"img/big%20shot.png\" alt=\"outside\" /></p>"), "{}", result);
+        assert!(
+            result.contains(&format!(
+                "<td><a class=\"thumbnail\" href=\"img/big%20shot.png\"><img width=\"200\" height=\"100\" src=\"{}\" alt=\"big\" /></a></td>",
+                thumb_src
+            )),
+            "{}",
+            result
+        );
+        assert!(result.contains("<td><img src=\"img/small.png\" alt=\"small\" /></td>"), "{}", result);
+        // Already linked images only get the thumbnail
+        assert!(result.contains(&format!("<a href=\"x.html\"><img width=\"200\" height=\"100\" src=\"{}\" alt=\"linked\" /></a>", thumb_src)), "{}", result);
+    }
+
+    #[test]
+    fn test_thumbnails_disabled_or_unreadable() {
+        let dir = tempfile::tempdir().unwrap();
+        let html = "<table><tr><td><img src=\"missing.png\" /><img src=\"https://example.com/a.png\" /></td></tr></table>";
+        assert_eq!(apply_thumbnails(html.to_string(), dir.path(), "page.html", &ThumbnailsConfig::default()), html);
+        assert_eq!(apply_thumbnails(html.to_string(), dir.path(), "page.html", &ThumbnailsConfig { width: 100 }), html);
+    }
+}
@@ -0,0 +1,256 @@
+//! Translation coverage for `guidebook translate-status`
+//!
+//! Compares the chapters of a multi-language book's reference language (the
+//! `languages.default` of book.json, or the first language of LANGS.md) with
+//! every other language. A chapter is missing when the language has no file
+//! at the same path, and stale when the reference file changed after the
+//! translation: by last git commit when the book is in a git repository
+//! (modification time for files without commits), or by modification time
+//! only with `--mtime`.
+
+use super::generate;
+use super::publish::git;
+use crate::parser::{self, shared_source, BookConfig, SummaryItem};
+use crate::paths;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageState {
+    /// Translated after the reference last changed
+    Current,
+    /// The reference changed after the translation
+    Stale,
+    /// No file at the reference chapter's path
+    Missing,
+    /// Listed from the reference language (`../en/page.md` in SUMMARY.md)
+    Shared,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PageStatus {
+    /// Source path in both languages
+    pub path: String,
+    pub state: PageState,
+    /// Last change of the reference file (Unix seconds)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference_updated: Option<u64>,
+    /// Last change of the translation (Unix seconds)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LanguageStatus {
+    pub code: String,
+    pub title: String,
+    pub pages: Vec<PageStatus>,
+}
+
+impl LanguageStatus {
+    /// Number of pages in a state
+    pub fn count(&self, state: PageState) -> usize {
+        self.pages.iter().filter(|page| page.state == state).count()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TranslationStatus {
+    /// Code of the reference language
+    pub reference: String,
+    pub languages: Vec<LanguageStatus>,
+}
+
+/// Compare every language of the book at `source` with its reference language
+/// mtime: compare modification times even in a git repository
+pub fn translation_status(source: &Path, mtime: bool) -> Result<TranslationStatus> {
+    let source = source.canonicalize().context("Source directory not found")?;
+    let languages = parser::langs::parse_langs(&source)?;
+    if languages.len() < 2 {
+        bail!("translate-status needs a multi-language book (LANGS.md with two languages or more)");
+    }
+    let config = if source.join("book.json").exists() { BookConfig::load(&source)? } else { BookConfig::default() };
+    let reference = languages
+        .iter()
+        .find(|lang| lang.code == config.languages.default)
+        .unwrap_or(&languages[0]);
+    let use_git = !mtime && git(&source, &["rev-parse", "--git-dir"], None).is_ok();
+    let updated = |file: &Path| last_change(file, use_git);
+
+    let reference_dir = source.join(&reference.code);
+    let chapters = chapter_paths(&reference_dir, &config)?;
+    let mut status = TranslationStatus { reference: reference.code.clone(), languages: Vec::new() };
+    for lang in languages.iter().filter(|lang| lang.code != reference.code) {
+        let lang_dir = source.join(&lang.code);
+        let shared = shared_chapters(&lang_dir, &config, &reference.code);
+        let pages = chapters
+            .iter()
+            .map(|path| {
+                let reference_updated = updated(&reference_dir.join(path));
+                let translation = lang_dir.join(path);
+                let (state, updated) = if shared.contains(path) {
+                    (PageState::Shared, None)
+                } else if !translation.is_file() {
+                    (PageState::Missing, None)
+                } else {
+                    let updated = updated(&translation);
+                    let stale = matches!((reference_updated, updated), (Some(reference), Some(translated)) if reference > translated);
+                    (if stale { PageState::Stale } else { PageState::Current }, updated)
+                };
+                PageStatus { path: path.clone(), state, reference_updated, updated }
+            })
+            .collect();
+        status.languages.push(LanguageStatus { code: lang.code.clone(), title: lang.title.clone(), pages });
+    }
+    Ok(status)
+}
+
+/// Chapter source files of a language in SUMMARY.md order (README.md first)
+fn chapter_paths(lang_dir: &Path, config: &BookConfig) -> Result<Vec<String>> {
+    let config = language_config(lang_dir, config)?;
+    let summary = generate::load_summary(lang_dir, &config)?;
+    let mut pages = Vec::new();
+    if lang_dir.join("README.md").is_file() {
+        pages.push("README.md".to_string());
+    }
+    collect_files(&summary.items, &mut pages);
+    // Generated pages have no file to translate; shared ones are another language's
+    pages.retain(|page| shared_source(page).is_none() && lang_dir.join(page).is_file());
+    for files in summary.merged.values() {
+        for file in files {
+            if !pages.contains(file) {
+                pages.push(file.clone());
+            }
+        }
+    }
+    Ok(pages)
+}
+
+/// Chapters a language lists from the reference language
+fn shared_chapters(lang_dir: &Path, config: &BookConfig, reference: &str) -> Vec<String> {
+    let Ok(summary) = language_config(lang_dir, config).and_then(|config| generate::load_summary(lang_dir, &config)) else {
+        return Vec::new();
+    };
+    let mut pages = Vec::new();
+    collect_files(&summary.items, &mut pages);
+    pages
+        .iter()
+        .filter_map(|page| shared_source(page).filter(|(dir, _)| *dir == reference).map(|(_, path)| path.to_string()))
+        .collect()
+}
+
+/// The language's book.json, or the book's
+fn language_config(lang_dir: &Path, config: &BookConfig) -> Result<BookConfig> {
+    if lang_dir.join("book.json").exists() {
+        BookConfig::load(lang_dir)
+    } else {
+        Ok(config.clone())
+    }
+}
+
+fn collect_files(items: &[SummaryItem], pages: &mut Vec<String>) {
+    for item in items {
+        if let SummaryItem::Link { path, children, .. } = item {
+            if let Some(path) = path {
+                let base = paths::split_fragment(path).0.trim_start_matches('/');
+                if !base.is_empty() && !pages.iter().any(|page| page == base) {
+                    pages.push(base.to_string());
+                }
+            }
+            collect_files(children, pages);
+        }
+    }
+}
+
+/// Last change of a file (Unix seconds): its last commit, or its modification
+/// time when it has none or git isn't used
+fn last_change(file: &Path, use_git: bool) -> Option<u64> {
+    if use_git {
+        let (dir, name) = (file.parent()?, file.file_name()?.to_str()?);
+        let committed = git(dir, &["log", "-1", "--format=%ct", "--", name], None).ok().and_then(|time| time.parse().ok());
+        if committed.is_some() {
+            return committed;
+        }
+    }
+    let modified = fs::metadata(file).and_then(|m| m.modified()).ok()?;
+    modified.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+/// "YYYY-MM-DD" of a Unix time (UTC)
+pub fn format_day(seconds: u64) -> String {
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn set_mtime(path: &Path, seconds: u64) {
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)).unwrap();
+    }
+
+    #[test]
+    fn test_translation_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for code in ["en", "ja", "vi"] {
+            fs::create_dir_all(root.join(code).join("guide")).unwrap();
+            fs::write(root.join(code).join("README.md"), "# Intro\n").unwrap();
+        }
+        fs::write(root.join("LANGS.md"), "# Languages\n\n* [English](en/)\n* [日本語](ja/)\n* [Tiếng Việt](vi/)\n").unwrap();
+        fs::write(root.join("en/SUMMARY.md"), "# Summary\n\n* [Start](guide/start.md)\n    * [Setup](guide/start.md#setup)\n* [API](api.md)\n").unwrap();
+        fs::write(root.join("en/guide/start.md"), "# Start\n").unwrap();
+        fs::write(root.join("en/api.md"), "# API\n").unwrap();
+        fs::write(root.join("ja/SUMMARY.md"), "# Summary\n\n* [Start](guide/start.md)\n* [API](../en/api.md)\n").unwrap();
+        fs::write(root.join("ja/guide/start.md"), "# はじめに\n").unwrap();
+        fs::write(root.join("vi/SUMMARY.md"), "# Summary\n\n* [Start](guide/start.md)\n").unwrap();
+        fs::write(root.join("vi/guide/start.md"), "# Bắt đầu\n").unwrap();
+        for (file, time) in [("en/README.md", 100), ("ja/README.md", 200), ("vi/README.md", 50), ("en/guide/start.md", 300), ("ja/guide/start.md", 100), ("vi/guide/start.md", 400)] {
+            set_mtime(&root.join(file), time);
+        }
+
+        let status = translation_status(root, true).unwrap();
+        assert_eq!(status.reference, "en");
+        let states = |lang: &LanguageStatus| -> Vec<(String, PageState)> { lang.pages.iter().map(|p| (p.path.clone(), p.state)).collect() };
+        assert_eq!(
+            states(&status.languages[0]),
+            vec![
+                ("README.md".to_string(), PageState::Current),
+                ("guide/start.md".to_string(), PageState::Stale),
+                ("api.md".to_string(), PageState::Shared),
+            ]
+        );
+        assert_eq!(
+            states(&status.languages[1]),
+            vec![
+                ("README.md".to_string(), PageState::Stale),
+                ("guide/start.md".to_string(), PageState::Current),
+                ("api.md".to_string(), PageState::Missing),
+            ]
+        );
+        assert_eq!(status.languages[0].pages[1].reference_updated, Some(300));
+        assert_eq!(status.languages[1].count(PageState::Missing), 1);
+    }
+
+    #[test]
+    fn test_format_day() {
+        assert_eq!(format_day(0), "1970-01-01");
+        assert_eq!(format_day(1_792_108_800), "2026-10-16");
+    }
+}
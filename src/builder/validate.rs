@@ -0,0 +1,185 @@
+//! HTML validation for `build --validate-html`
+//!
+//! Every page the build writes is parsed with an HTML5 parser (html5ever),
+//! and the markup it had to repair is reported as a warning with its line in
+//! the output file: stray end tags, elements closed out of order, unclosed
+//! elements, block elements in paragraphs, ... Raw HTML in Markdown chapters
+//! is the usual source; the browser repairs it silently and the page looks
+//! broken in ways that are hard to trace back.
+
+use crate::{logging, warning};
+use html5ever::interface::{ElementFlags, NodeOrText, QuirksMode, TreeSink};
+use html5ever::tendril::{StrTendril, TendrilSink};
+use html5ever::{local_name, namespace_url, ns, parse_document, Attribute, ExpandedName, ParseOpts, QualName};
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Problems reported per page; a page with more gets a count of the rest
+const MAX_PROBLEMS_PER_PAGE: usize = 10;
+
+/// Markup of a page the parser had to repair
+#[derive(Debug, PartialEq)]
+pub struct HtmlProblem {
+    /// 1-based line in the page
+    pub line: u64,
+    pub message: String,
+}
+
+/// Warn about the problems of the pages a build wrote
+/// sources: output path -> source path of the files written (see the build manifest)
+/// Returns the number of pages with problems
+pub fn check_pages(output: &Path, sources: &BTreeMap<String, String>) -> usize {
+    let mut invalid = 0;
+    for (html_path, source_path) in sources.iter().filter(|(path, _)| path.ends_with(".html")) {
+        let Ok(html) = fs::read_to_string(output.join(html_path)) else { continue };
+        let problems = validate_html(&html);
+        if problems.is_empty() {
+            continue;
+        }
+        invalid += 1;
+        logging::set_page(Some(source_path));
+        for problem in problems.iter().take(MAX_PROBLEMS_PER_PAGE) {
+            warning!("{} line {}: {}", html_path, problem.line, problem.message);
+        }
+        if problems.len() > MAX_PROBLEMS_PER_PAGE {
+            warning!("{}: {} more HTML problems", html_path, problems.len() - MAX_PROBLEMS_PER_PAGE);
+        }
+        logging::set_page(None);
+    }
+    invalid
+}
+
+/// Problems of a page's HTML, in document order
+pub fn validate_html(html: &str) -> Vec<HtmlProblem> {
+    let mut opts = ParseOpts::default();
+    opts.tree_builder.exact_errors = true;
+    let sink = ProblemSink { names: vec![QualName::new(None, ns!(html), local_name!(""))], line: 1, problems: Vec::new() };
+    let problems = parse_document(sink, opts).one(html);
+
+    let tag_re = Regex::new(r"Tag \{ kind: (StartTag|EndTag), name: Atom\(\\?'([^'\\]+)\\?'").unwrap();
+    let unclosed_re = Regex::new(r"Unexpected open tag \{[^}]*\}:(\S+) at end of body").unwrap();
+    let closing_re = Regex::new(r"Unexpected open element while closing Atom\(\\?'([^'\\]+)").unwrap();
+    let mut described: Vec<HtmlProblem> = Vec::with_capacity(problems.len());
+    for HtmlProblem { line, message } in problems {
+        let message = if let Some(caps) = tag_re.captures(&message) {
+            format!("unexpected <{}{}>", if &caps[1] == "EndTag" { "/" } else { "" }, &caps[2])
+        } else if let Some(caps) = unclosed_re.captures(&message) {
+            format!("<{}> is not closed", &caps[1])
+        } else if let Some(caps) = closing_re.captures(&message) {
+            format!("</{}> closes an element left open inside it", &caps[1])
+        } else {
+            match message.as_str() {
+                "Found special tag while closing generic tag" | "No matching tag to close" => "end tag that matches no open element".to_string(),
+                "Formatting element not open" | "Formatting element not in scope" => "end tag of an inline element that isn't open".to_string(),
+                "Formatting element not current node" => "inline elements closed out of order".to_string(),
+                "No <p> tag to close" => "</p> without an open <p>".to_string(),
+                "Unacknowledged self-closing tag" => "self-closing tag (<tag/>) on a non-void element".to_string(),
+                _ => message,
+            }
+        };
+        // The same repair is often reported by the tokenizer and the tree builder
+        let problem = HtmlProblem { line, message };
+        if !described.contains(&problem) {
+            described.push(problem);
+        }
+    }
+    described
+}
+
+/// Tree sink that builds no tree: the parser keeps its own stack of open
+/// elements and only asks the sink for their names
+struct ProblemSink {
+    /// Element names by handle (0 is the document)
+    names: Vec<QualName>,
+    line: u64,
+    problems: Vec<HtmlProblem>,
+}
+
+impl TreeSink for ProblemSink {
+    type Handle = usize;
+    type Output = Vec<HtmlProblem>;
+
+    fn finish(self) -> Self::Output {
+        self.problems
+    }
+
+    fn parse_error(&mut self, msg: Cow<'static, str>) {
+        self.problems.push(HtmlProblem { line: self.line, message: msg.into_owned() });
+    }
+
+    fn set_current_line(&mut self, line_number: u64) {
+        self.line = line_number;
+    }
+
+    fn get_document(&mut self) -> usize {
+        0
+    }
+
+    fn elem_name<'a>(&'a self, target: &'a usize) -> ExpandedName<'a> {
+        self.names[*target].expanded()
+    }
+
+    fn create_element(&mut self, name: QualName, _attrs: Vec<Attribute>, _flags: ElementFlags) -> usize {
+        self.names.push(name);
+        self.names.len() - 1
+    }
+
+    fn create_comment(&mut self, _text: StrTendril) -> usize {
+        0
+    }
+
+    fn create_pi(&mut self, _target: StrTendril, _data: StrTendril) -> usize {
+        0
+    }
+
+    fn append(&mut self, _parent: &usize, _child: NodeOrText<usize>) {}
+
+    fn append_based_on_parent_node(&mut self, _element: &usize, _prev_element: &usize, _child: NodeOrText<usize>) {}
+
+    fn append_doctype_to_document(&mut self, _name: StrTendril, _public_id: StrTendril, _system_id: StrTendril) {}
+
+    fn get_template_contents(&mut self, target: &usize) -> usize {
+        *target
+    }
+
+    fn same_node(&self, x: &usize, y: &usize) -> bool {
+        x == y
+    }
+
+    fn set_quirks_mode(&mut self, _mode: QuirksMode) {}
+
+    fn append_before_sibling(&mut self, _sibling: &usize, _new_node: NodeOrText<usize>) {}
+
+    fn add_attrs_if_missing(&mut self, _target: &usize, _attrs: Vec<Attribute>) {}
+
+    fn remove_from_parent(&mut self, _target: &usize) {}
+
+    fn reparent_children(&mut self, _node: &usize, _new_parent: &usize) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(body: &str) -> String {
+        format!("<!DOCTYPE html>\n<html>\n<head><title>T</title></head>\n<body>\n{}\n</body>\n</html>\n", body)
+    }
+
+    #[test]
+    fn test_validate_html() {
+        assert_eq!(validate_html(&page("<div><p>Text <b>bold</b></p>\n<ul><li>One<li>Two</ul></div>")), vec![]);
+
+        let problems = validate_html(&page("<p>Intro\n<div>Block</div>\n<span><em>Nested</span></em>\n</td>\n<div>Open"));
+        let problem = |line: u64, message: &str| HtmlProblem { line, message: message.to_string() };
+        assert!(problems.contains(&problem(7, "unexpected </span>")), "{:?}", problems);
+        assert!(problems.contains(&problem(8, "end tag that matches no open element")), "{:?}", problems);
+        assert!(problems.contains(&problem(10, "<div> is not closed")), "{:?}", problems);
+        assert!(problems.iter().all(|p| (7..=10).contains(&p.line)), "{:?}", problems);
+
+        let problems = validate_html(&page("<section>\n<div>Open\n</section>"));
+        assert_eq!(problems, vec![problem(7, "</section> closes an element left open inside it")]);
+    }
+}
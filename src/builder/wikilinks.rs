@@ -0,0 +1,303 @@
+//! Obsidian-style wiki links
+//!
+//! With `"wikiLinks": true` in book.json, wiki links in Markdown pages are
+//! turned into regular links before rendering:
+//!
+//! - `[[Page Name]]`, `[[Page Name|label]]` and `[[Page Name#Heading]]` link to
+//!   a page of SUMMARY.md, found by file name (with or without extension or
+//!   directories) or by one of the `aliases` in its front matter
+//! - `![[image.png]]` and `![[image.png|300]]` (a width, or `300x200`) embed a
+//!   file of the book, found by file name or book-relative path
+//!
+//! Names match case-insensitively; when several pages share a name, the first
+//! one in SUMMARY.md wins. Links that match nothing are left as written, with
+//! a warning. Code blocks and inline code are not touched.
+
+use super::renderer::{html_escape, slugify};
+use crate::parser::frontmatter::parse_front_matter;
+use crate::parser::{BookConfig, Summary, SummaryItem};
+use crate::{paths, warning};
+use regex::{Captures, Regex};
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Matches `[[target#heading|label]]`, with `!` for embeds
+const WIKI_LINK_PATTERN: &str = r"(!?)\[\[([^\[\]|#\n]*)(?:#([^\[\]|\n]*))?(?:\|([^\[\]\n]*))?\]\]";
+
+/// Extensions of files wiki links resolve to pages rather than files
+const PAGE_EXTENSIONS: [&str; 3] = ["md", "adoc", "asciidoc"];
+
+/// Pages and files wiki links can point to
+#[derive(Debug, Default)]
+pub struct WikiLinks {
+    /// Lowercased name, path or alias -> book-relative source path
+    pages: HashMap<String, String>,
+    /// Lowercased file name or path -> book-relative path
+    files: HashMap<String, String>,
+}
+
+impl WikiLinks {
+    /// Index the pages of `summary` and the files of the book (empty unless
+    /// `wikiLinks` is enabled)
+    pub fn load(source: &Path, config: &BookConfig, summary: &Summary) -> WikiLinks {
+        let mut links = WikiLinks::default();
+        if !config.wiki_links {
+            return links;
+        }
+
+        let mut pages = Vec::new();
+        collect_pages(&summary.items, &mut pages);
+        for page in pages.into_iter().filter(|p| summary.has_source(source, p)) {
+            let aliases = summary
+                .read_source(source, &page)
+                .ok()
+                .and_then(|content| parse_front_matter(&content).front_matter)
+                .map(|fm| front_matter_aliases(&fm.extra))
+                .unwrap_or_default();
+            let stem = page.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(&page);
+            let name = page.rsplit('/').next().unwrap_or(&page);
+            let stem_name = stem.rsplit('/').next().unwrap_or(stem);
+            for key in [page.as_str(), stem, name, stem_name].into_iter().map(str::to_string).chain(aliases) {
+                links.pages.entry(key.to_lowercase()).or_insert_with(|| page.clone());
+            }
+        }
+
+        for entry in paths::walk(source)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| {
+                let name = e.file_name().to_string_lossy();
+                e.depth() == 0 || !(name.starts_with('.') || name == "_book" || name == "node_modules")
+            })
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_file())
+        {
+            let Some(path) = paths::relative(source, entry.path()) else {
+                continue;
+            };
+            let name = path.rsplit('/').next().unwrap_or(&path).to_lowercase();
+            links.files.entry(path.to_lowercase()).or_insert_with(|| path.clone());
+            links.files.entry(name).or_insert(path);
+        }
+        links
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty() && self.files.is_empty()
+    }
+
+    /// Every name and the page or file it links to, sorted (pages first)
+    pub fn entries(&self) -> Vec<(&str, &str)> {
+        let mut pages: Vec<(&str, &str)> = self.pages.iter().map(|(name, path)| (name.as_str(), path.as_str())).collect();
+        let mut files: Vec<(&str, &str)> = self.files.iter().map(|(name, path)| (name.as_str(), path.as_str())).collect();
+        pages.sort();
+        files.sort();
+        pages.extend(files);
+        pages
+    }
+
+    /// Replace the wiki links of a page's Markdown with Markdown links
+    /// source_path: book-relative path of the page, links are made relative to it
+    pub fn apply(&self, content: &str, source_path: &str) -> String {
+        if self.is_empty() || !content.contains("[[") {
+            return content.to_string();
+        }
+        let re = Regex::new(WIKI_LINK_PATTERN).unwrap();
+        let root = "../".repeat(paths::depth(source_path));
+
+        let mut result = String::with_capacity(content.len());
+        let mut fence: Option<String> = None;
+        for line in content.split_inclusive('\n') {
+            let trimmed = line.trim_start();
+            if let Some(marker) = &fence {
+                if trimmed.starts_with(marker.as_str()) {
+                    fence = None;
+                }
+                result.push_str(line);
+                continue;
+            }
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                fence = Some(trimmed[..3].to_string());
+                result.push_str(line);
+                continue;
+            }
+            // Odd segments between backticks are inline code
+            for (i, segment) in line.split('`').enumerate() {
+                if i > 0 {
+                    result.push('`');
+                }
+                if i % 2 == 1 {
+                    result.push_str(segment);
+                } else {
+                    result.push_str(&re.replace_all(segment, |caps: &Captures| self.replace(caps, source_path, &root)));
+                }
+            }
+        }
+        result
+    }
+
+    /// Markdown for one wiki link; the link as written if it matches nothing
+    fn replace(&self, caps: &Captures, source_path: &str, root: &str) -> String {
+        let embed = !caps[1].is_empty();
+        let target = caps[2].trim();
+        let heading = caps.get(3).map(|m| m.as_str().trim()).filter(|h| !h.is_empty());
+        let label = caps.get(4).map(|m| m.as_str().trim()).filter(|l| !l.is_empty());
+        // Block references (`#^id`) have no anchor in the rendered page
+        let fragment = heading.filter(|h| !h.starts_with('^')).map(|h| format!("#{}", slugify(h))).unwrap_or_default();
+
+        if target.is_empty() {
+            return match heading {
+                Some(heading) => format!("[{}]({})", label.unwrap_or(heading), fragment),
+                None => caps[0].to_string(),
+            };
+        }
+        let key = paths::normalize(target).trim_start_matches('/').to_lowercase();
+
+        let file = self.files.get(&key).filter(|f| !is_page(f));
+        let page = self.pages.get(&key).or_else(|| self.pages.get(&format!("{}.md", key)));
+        match (embed, file, page) {
+            (true, Some(file), _) => embed_file(file, label, root),
+            (_, _, Some(page)) => {
+                let text = match (label, heading) {
+                    (Some(label), _) => label.to_string(),
+                    (None, Some(heading)) => format!("{} > {}", target, heading),
+                    (None, None) => target.to_string(),
+                };
+                format!("[{}]({}{}{})", text, root_or_current(root), paths::link_destination(page), fragment)
+            }
+            (false, Some(file), None) => {
+                format!("[{}]({}{})", label.unwrap_or(target), root_or_current(root), paths::link_destination(file))
+            }
+            _ => {
+                warning!("wiki link {} in {} matches no page or file", &caps[0], source_path);
+                caps[0].to_string()
+            }
+        }
+    }
+}
+
+/// Markdown (or an `<img>` for a size) embedding a book file
+/// label: `300` or `300x200` for a size, otherwise the alt text
+fn embed_file(file: &str, label: Option<&str>, root: &str) -> String {
+    let name = file.rsplit('/').next().unwrap_or(file);
+    let size = label.and_then(|l| {
+        let (width, height) = l.split_once('x').unwrap_or((l, ""));
+        let valid = |n: &str| n.is_empty() || n.chars().all(|c| c.is_ascii_digit());
+        (!width.is_empty() && valid(width) && valid(height)).then_some((width, height))
+    });
+    let src = format!("{}{}", root_or_current(root), file);
+    match size {
+        Some((width, height)) => {
+            let height = if height.is_empty() { String::new() } else { format!(" height=\"{}\"", height) };
+            format!(
+                "<img src=\"{}\" alt=\"{}\" width=\"{}\"{}>",
+                html_escape(&paths::encode_href(&src)),
+                html_escape(name),
+                width,
+                height
+            )
+        }
+        None => format!("![{}]({})", label.unwrap_or(name), paths::link_destination(&src)),
+    }
+}
+
+fn root_or_current(root: &str) -> &str {
+    if root.is_empty() {
+        "./"
+    } else {
+        root
+    }
+}
+
+fn is_page(path: &str) -> bool {
+    path.rsplit_once('.').is_some_and(|(_, ext)| PAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Source paths of the SUMMARY.md entries, in order
+fn collect_pages(items: &[SummaryItem], pages: &mut Vec<String>) {
+    for item in items {
+        if let SummaryItem::Link { path, children, .. } = item {
+            if let Some(path) = path {
+                let base = paths::split_fragment(path).0.trim_start_matches('/');
+                if !base.is_empty() && !pages.iter().any(|p| p == base) {
+                    pages.push(base.to_string());
+                }
+            }
+            collect_pages(children, pages);
+        }
+    }
+}
+
+/// `aliases` (or `alias`) of a page's front matter: a list or a single name
+fn front_matter_aliases(extra: &HashMap<String, Value>) -> Vec<String> {
+    let Some(value) = extra.get("aliases").or_else(|| extra.get("alias")) else {
+        return Vec::new();
+    };
+    match value {
+        Value::String(alias) => vec![alias.clone()],
+        Value::Sequence(aliases) => aliases.iter().filter_map(|a| a.as_str().map(str::to_string)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn book() -> (tempfile::TempDir, WikiLinks) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("guide")).unwrap();
+        fs::create_dir_all(root.join("images")).unwrap();
+        fs::write(root.join("guide/Getting Started.md"), "---\naliases: [Setup, Install]\n---\n# Getting Started\n").unwrap();
+        fs::write(root.join("faq.md"), "# FAQ\n").unwrap();
+        fs::write(root.join("images/Logo.png"), "png").unwrap();
+        let summary = crate::parser::summary::parse_summary(
+            "# Summary\n\n* [Start](<guide/Getting Started.md>)\n* [FAQ](faq.md)\n",
+        )
+        .unwrap();
+        let config: BookConfig = serde_json::from_str(r#"{"wikiLinks": true}"#).unwrap();
+        let links = WikiLinks::load(root, &config, &summary);
+        (dir, links)
+    }
+
+    #[test]
+    fn test_wiki_links_resolve_by_name_and_alias() {
+        let (_dir, links) = book();
+        assert_eq!(links.apply("See [[faq]].", "guide/intro.md"), "See [faq](../faq.md).");
+        assert_eq!(
+            links.apply("[[setup|Installing]] and [[Getting Started#First Steps]]", "faq.md"),
+            "[Installing](./guide/Getting%20Started.md) and [Getting Started > First Steps](./guide/Getting%20Started.md#first-steps)"
+        );
+        assert_eq!(links.apply("[[guide/getting started.md]]", "faq.md"), "[guide/getting started.md](./guide/Getting%20Started.md)");
+        assert_eq!(links.apply("[[#Details]]", "faq.md"), "[Details](#details)");
+        assert_eq!(links.apply("[[Missing]]", "faq.md"), "[[Missing]]");
+        assert_eq!(links.apply("[[Logo.png]]", "faq.md"), "[Logo.png](./images/Logo.png)");
+    }
+
+    #[test]
+    fn test_wiki_embeds() {
+        let (_dir, links) = book();
+        assert_eq!(links.apply("![[logo.png]]", "guide/a.md"), "![Logo.png](../images/Logo.png)");
+        assert_eq!(links.apply("![[images/Logo.png|The logo]]", "a.md"), "![The logo](./images/Logo.png)");
+        assert_eq!(
+            links.apply("![[Logo.png|300x200]]", "a.md"),
+            "<img src=\"./images/Logo.png\" alt=\"Logo.png\" width=\"300\" height=\"200\">"
+        );
+        // Notes are linked, not transcluded
+        assert_eq!(links.apply("![[FAQ]]", "a.md"), "[FAQ](./faq.md)");
+    }
+
+    #[test]
+    fn test_wiki_links_skip_code() {
+        let (_dir, links) = book();
+        let content = "`[[faq]]` [[faq]]\n```\n[[faq]]\n```\n";
+        assert_eq!(links.apply(content, "a.md"), "`[[faq]]` [faq](./faq.md)\n```\n[[faq]]\n```\n");
+
+        let summary = crate::parser::summary::parse_summary("").unwrap();
+        let disabled = WikiLinks::load(Path::new("."), &BookConfig::default(), &summary);
+        assert_eq!(disabled.apply("[[faq]]", "a.md"), "[[faq]]");
+    }
+}
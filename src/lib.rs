@@ -0,0 +1,11 @@
+//! HonKit/GitBook compatible static book generator
+//!
+//! The `guidebook` binary is a thin CLI over this library. Library users can
+//! build books with [`builder::build`] and extend rendering through
+//! [`builder::hooks`]. Console output goes through [`logging`]; book paths
+//! use `/` separators on every platform (see [`paths`]).
+
+pub mod builder;
+pub mod logging;
+pub mod parser;
+pub mod paths;
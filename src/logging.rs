@@ -0,0 +1,115 @@
+//! Leveled console output
+//!
+//! Progress messages ([`info!`](crate::info)) are hidden by `--quiet`; detail
+//! such as per-page timings ([`verbose!`](crate::verbose)) only shows with
+//! `--verbose`. Warnings ([`warning!`](crate::warning)) go to stderr and are
+//! collected so a build can summarize or report them; with `--verbose` they
+//! name the page being rendered. Errors are returned to the caller and always
+//! shown.
+
+use serde::Serialize;
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Errors only
+    Quiet,
+    Normal,
+    /// Per-page timings and warning context
+    Verbose,
+}
+
+/// A warning printed with [`warning!`](crate::warning)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Warning {
+    /// Page being rendered when the warning was printed
+    pub page: Option<String>,
+    pub message: String,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Normal as u8);
+
+thread_local! {
+    /// Page being rendered on this thread, shown with verbose warnings
+    static PAGE: RefCell<Option<String>> = const { RefCell::new(None) };
+    /// Warnings on this thread, so builds running side by side keep their own
+    static WARNINGS: RefCell<Vec<Warning>> = const { RefCell::new(Vec::new()) };
+}
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn level() -> Level {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => Level::Quiet,
+        1 => Level::Normal,
+        _ => Level::Verbose,
+    }
+}
+
+/// Number of warnings on this thread since the last [`reset_warnings`]
+pub fn warning_count() -> usize {
+    WARNINGS.with(|w| w.borrow().len())
+}
+
+/// Warnings on this thread since the last [`reset_warnings`], in the order they were printed
+pub fn warnings() -> Vec<Warning> {
+    WARNINGS.with(|w| w.borrow().clone())
+}
+
+pub fn reset_warnings() {
+    WARNINGS.with(|w| w.borrow_mut().clear());
+}
+
+/// Set (or clear) the page warnings on this thread are reported for
+pub fn set_page(page: Option<&str>) {
+    PAGE.with(|p| *p.borrow_mut() = page.map(String::from));
+}
+
+#[doc(hidden)]
+pub fn print(level: Level, args: fmt::Arguments) {
+    if self::level() >= level {
+        println!("{}", args);
+    }
+}
+
+#[doc(hidden)]
+pub fn print_warning(args: fmt::Arguments) {
+    let warning = Warning { page: PAGE.with(|p| p.borrow().clone()), message: args.to_string() };
+    match (level(), &warning.page) {
+        (Level::Quiet, _) => {}
+        (Level::Verbose, Some(page)) => eprintln!("  Warning ({}): {}", page, warning.message),
+        _ => eprintln!("  Warning: {}", warning.message),
+    }
+    WARNINGS.with(|w| w.borrow_mut().push(warning));
+}
+
+/// Progress message, hidden by `--quiet`
+#[macro_export]
+macro_rules! info {
+    () => {
+        $crate::logging::print($crate::logging::Level::Normal, format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::logging::print($crate::logging::Level::Normal, format_args!($($arg)*))
+    };
+}
+
+/// Detail shown only with `--verbose`
+#[macro_export]
+macro_rules! verbose {
+    ($($arg:tt)*) => {
+        $crate::logging::print($crate::logging::Level::Verbose, format_args!($($arg)*))
+    };
+}
+
+/// Counted warning, printed as "  Warning: ..." unless `--quiet`
+#[macro_export]
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        $crate::logging::print_warning(format_args!($($arg)*))
+    };
+}
@@ -1,9 +1,10 @@
-mod parser;
-mod builder;
+use guidebook::logging::{self, Level};
+use guidebook::{builder, info, parser, paths, warning};
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::fs;
 use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
 use tiny_http::{Server, Response, Header};
@@ -20,6 +21,12 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Show per-page timings and which page each warning comes from
+    #[arg(short, long, global = true, conflicts_with = "quiet")]
+    verbose: bool,
+    /// Only show errors
+    #[arg(short, long, global = true)]
+    quiet: bool,
 }
 
 #[derive(Subcommand)]
@@ -30,6 +37,29 @@ enum Commands {
         #[arg(default_value = ".")]
         path: PathBuf,
     },
+    /// Create a chapter file and add it to SUMMARY.md
+    New {
+        /// Chapter file, relative to the book directory (e.g. guide/install.md)
+        file: String,
+        /// Chapter title (defaults to one derived from the file name)
+        #[arg(short, long)]
+        title: Option<String>,
+        /// Entry to nest the chapter under (its path or title in SUMMARY.md)
+        #[arg(long)]
+        parent: Option<String>,
+        /// Book directory
+        #[arg(short, long, default_value = ".")]
+        path: PathBuf,
+    },
+    /// Print a SUMMARY.md listing every chapter file, nested like the directories
+    Toc {
+        /// Book directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Rewrite SUMMARY.md instead of printing (titles of existing entries are kept)
+        #[arg(short, long)]
+        write: bool,
+    },
     /// Build the book
     Build {
         /// Source directory
@@ -38,6 +68,105 @@ enum Commands {
         /// Output directory
         #[arg(short, long, default_value = "_book")]
         output: PathBuf,
+        /// Keep files from previous builds in the output directory
+        #[arg(long)]
+        no_clean: bool,
+        /// Produce byte-identical output (copied assets, timestamps from SOURCE_DATE_EPOCH)
+        #[arg(long)]
+        reproducible: bool,
+        /// List the pages and assets that would be written, without building
+        #[arg(long)]
+        dry_run: bool,
+        /// Config file to use instead of book.json (e.g. book.staging.json)
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// Write a JSON report of pages, assets, warnings and timings (also when the build fails)
+        #[arg(long, value_name = "FILE")]
+        report: Option<PathBuf>,
+        /// Fail if the build prints any warnings (the output directory is left untouched)
+        #[arg(long)]
+        strict: bool,
+        /// Render only the chapters matching a path or glob (e.g. guide/*.md), keeping
+        /// the rest of the previous output, search index and manifest
+        #[arg(long, value_name = "GLOB")]
+        only: Option<String>,
+        /// Set a book.json variable for this build (repeatable), e.g. --define api_host=staging.example.com
+        #[arg(short = 'D', long = "define", value_name = "KEY=VALUE", value_parser = parse_define)]
+        defines: Vec<(String, String)>,
+        /// URL or path the book is hosted at (e.g. /docs/mybook/), overriding baseUrl in book.json
+        #[arg(long, value_name = "URL")]
+        base_url: Option<String>,
+        /// Keep the pages of the previous build whose sources, @imports and config are unchanged
+        #[arg(long)]
+        incremental: bool,
+        /// Check the HTML of every page and warn about unclosed tags and invalid nesting
+        #[arg(long)]
+        validate_html: bool,
+        /// Keep running and rebuild into the output directory whenever a source file changes
+        #[arg(short, long, conflicts_with = "dry_run")]
+        watch: bool,
+        /// Formats to write, e.g. html,pdf,epub; the PDF and EPUB go into the output
+        /// directory as book.pdf and book.epub, from one rendering of the chapters
+        #[arg(long, value_enum, value_delimiter = ',', default_value = "html")]
+        formats: Vec<BuildFormat>,
+        /// Send the search index to the hosted search set in book.json (search.algolia,
+        /// search.meilisearch or search.typesense), with the admin key from the environment
+        #[arg(long, conflicts_with_all = ["only", "watch"])]
+        push_search: bool,
+    },
+    /// Rebuild the book into the output directory whenever a source file changes
+    Watch {
+        /// Source directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Output directory
+        #[arg(short, long, default_value = "_book")]
+        output: PathBuf,
+        /// Config file to use instead of book.json (e.g. book.staging.json)
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// Build the book and push the output to a git branch (GitHub Pages)
+    Publish {
+        /// Source directory (inside the git repository to publish from)
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Output directory
+        #[arg(short, long, default_value = "_book")]
+        output: PathBuf,
+        /// Config file to use instead of book.json (e.g. book.staging.json)
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// Branch to publish to
+        #[arg(short, long, default_value = builder::publish::DEFAULT_BRANCH)]
+        branch: String,
+        /// Remote to push to (name or URL)
+        #[arg(long, default_value = builder::publish::DEFAULT_REMOTE)]
+        remote: String,
+        /// Commit message (defaults to one naming the source commit)
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+    /// Build the book and upload changed files to the target in the `deploy` section of book.json
+    Deploy {
+        /// Source directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Output directory
+        #[arg(short, long, default_value = "_book")]
+        output: PathBuf,
+        /// Config file to use instead of book.json (e.g. book.staging.json)
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// Also delete deployed files the new build no longer has
+        #[arg(long)]
+        prune: bool,
+        /// List the files that would be uploaded and deleted, without deploying
+        #[arg(long)]
+        dry_run: bool,
+        /// Submit the changed pages to IndexNow (needs deploy.siteUrl and deploy.indexNowKey)
+        #[arg(long)]
+        notify: bool,
     },
     /// Start a local server for preview
     Serve {
@@ -50,27 +179,476 @@ enum Commands {
         /// Open browser automatically
         #[arg(short, long)]
         open: bool,
+        /// Config file to use instead of book.json (e.g. book.staging.json)
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// Serve the build as is: no file watching, rebuilds or live reload
+        #[arg(long)]
+        no_watch: bool,
+    },
+    /// Check internal links, images and anchors (exits non-zero if any are broken)
+    Check {
+        /// Source directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+    /// Report authoring mistakes (rule severities are set in the `lint` section of book.json)
+    Lint {
+        /// Source directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+    /// Build the fixture books under tests/books/ and compare their output with tests/snapshots/
+    Test {
+        /// Directory with the fixture books (books/) and their snapshots (snapshots/)
+        #[arg(default_value = "tests")]
+        path: PathBuf,
+        /// Only test the fixture books whose name contains this
+        #[arg(long)]
+        only: Option<String>,
+        /// Write the output as the new snapshots instead of comparing
+        #[arg(long)]
+        update: bool,
+    },
+    /// Print word, image and heading counts and reading time per chapter
+    Stats {
+        /// Source directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the links between chapters as a Graphviz DOT graph (dead-end chapters dashed)
+    Graph {
+        /// Source directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Print JSON instead of DOT
+        #[arg(long)]
+        json: bool,
+        /// Only list the chapters that link to no other chapter
+        #[arg(long, conflicts_with = "json")]
+        dead_ends: bool,
+    },
+    /// Report missing and outdated translations of a multi-language book, per language
+    TranslateStatus {
+        /// Source directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+        /// Compare file modification times instead of git commit times
+        #[arg(long)]
+        mtime: bool,
+    },
+    /// Remove the output directory and the serve temp directory
+    Clean {
+        /// Output directory
+        #[arg(short, long, default_value = "_book")]
+        output: PathBuf,
+        /// List what would be removed without removing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Inspect and validate book.json
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Compare build manifests (manifest.json) for differential deploys
+    Manifest {
+        #[command(subcommand)]
+        action: ManifestAction,
+    },
+    /// Export the book as a single PDF
+    Pdf {
+        /// Source directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Output PDF file
+        #[arg(short, long, default_value = "book.pdf")]
+        output: PathBuf,
+        /// Also write the print HTML document to this path
+        #[arg(long)]
+        html: Option<PathBuf>,
+    },
+    /// Export the book as an EPUB3 file
+    Epub {
+        /// Source directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Output EPUB file
+        #[arg(short, long, default_value = "book.epub")]
+        output: PathBuf,
+    },
+    /// Export the book to another format
+    Export {
+        #[command(subcommand)]
+        format: ExportFormat,
+    },
+    /// Write each chapter as plain Markdown, @imports, variables and conditionals
+    /// resolved, in the book's layout with SUMMARY.md (same as `export pages`)
+    ExportMarkdown {
+        /// Source directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Output directory
+        #[arg(short, long, default_value = "_markdown")]
+        output: PathBuf,
+    },
+    /// Print a shell completion script (e.g. `guidebook completions bash > ~/.local/share/bash-completion/completions/guidebook`)
+    Completions {
+        /// Shell to complete in
+        shell: clap_complete::Shell,
     },
     /// Update guidebook to the latest version
     Update,
 }
 
+#[derive(Subcommand)]
+enum ExportFormat {
+    /// One Markdown file with every chapter in SUMMARY.md order, @imports and
+    /// templates resolved
+    Md {
+        /// Source directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Output Markdown file
+        #[arg(short, long, default_value = "book.md")]
+        output: PathBuf,
+    },
+    /// Each chapter file as plain Markdown, @imports, variables and
+    /// conditionals resolved, in the book's layout with SUMMARY.md
+    Pages {
+        /// Source directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Output directory
+        #[arg(short, long, default_value = "_markdown")]
+        output: PathBuf,
+    },
+    /// Each chapter as body-only HTML, without the book's layout, with a
+    /// structure.json of the chapters and SUMMARY.md, for portals that
+    /// supply their own layout
+    Fragments {
+        /// Source directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// Output directory
+        #[arg(short, long, default_value = "_fragments")]
+        output: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the JSON Schema for book.json
+    Schema {
+        /// Write the schema to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Validate book.json against the schema
+    Validate {
+        /// Book directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+    /// Print a book.json value (dotted keys like `styles.website`)
+    Get {
+        /// Key to read
+        key: String,
+        /// Book directory
+        #[arg(short, long, default_value = ".")]
+        path: PathBuf,
+    },
+    /// Set a book.json value (dotted keys like `variables.version`)
+    Set {
+        /// Key to write
+        key: String,
+        /// New value (parsed as JSON unless the key holds a string)
+        value: String,
+        /// Book directory
+        #[arg(short, long, default_value = ".")]
+        path: PathBuf,
+    },
+}
+
+/// Output format of `guidebook build --formats`
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum BuildFormat {
+    /// The website
+    Html,
+    /// book.pdf (printed with a local Chrome, Chromium or Edge)
+    Pdf,
+    /// book.epub
+    Epub,
+}
+
+#[derive(Subcommand)]
+enum ManifestAction {
+    /// List files to upload (new or changed) and delete since a previous deploy
+    Diff {
+        /// Manifest of the deployed output
+        previous: PathBuf,
+        /// Manifest of the new build
+        #[arg(default_value = "_book/manifest.json")]
+        current: PathBuf,
+        /// Also list files to delete from the deployed output
+        #[arg(long)]
+        prune: bool,
+    },
+    /// Notify search engines (IndexNow) of pages changed since a previous deploy
+    Notify {
+        /// Manifest of the previously deployed output
+        previous: PathBuf,
+        /// Manifest of the deployed build
+        #[arg(default_value = "_book/manifest.json")]
+        current: PathBuf,
+        /// Book directory (for the deploy section of book.json)
+        #[arg(short, long, default_value = ".")]
+        path: PathBuf,
+        /// Print the URLs without submitting them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
 fn main() -> Result<()> {
     // Check for updates in background (non-blocking)
     check_for_updates();
 
     let cli = Cli::parse();
+    logging::set_level(if cli.quiet {
+        Level::Quiet
+    } else if cli.verbose {
+        Level::Verbose
+    } else {
+        Level::Normal
+    });
 
     match cli.command {
         Commands::Init { path } => {
             init_book(&path)
         }
-        Commands::Build { path, output } => {
-            println!("Building book from {:?} to {:?}", path, output);
-            builder::build(&path, &output)
+        Commands::New { file, title, parent, path } => {
+            new_chapter(&path, &file, title, parent.as_deref())
+        }
+        Commands::Toc { path, write } => {
+            let summary_path = path.join("SUMMARY.md");
+            let existing = fs::read_to_string(&summary_path).ok();
+            let content = parser::summary::generate_summary(&path, existing.as_deref())?;
+            if write {
+                fs::write(&summary_path, content)?;
+                info!("Rewrote {:?}", summary_path);
+            } else {
+                print!("{}", content);
+            }
+            Ok(())
         }
-        Commands::Serve { path, port, open } => {
-            serve_book(&path, port, open)
+        Commands::Build { path, output, dry_run: true, config, .. } => {
+            let plan = builder::plan::plan_build(&path, config.as_deref())?;
+            print_build_plan(&plan, &output);
+            Ok(())
+        }
+        Commands::Build { path, output, no_clean, reproducible, config, report, strict, only, defines, base_url, incremental, validate_html, watch, formats, push_search, .. } => {
+            let exports: Vec<builder::ExportTarget> = formats
+                .iter()
+                .filter_map(|format| match format {
+                    BuildFormat::Html => None,
+                    BuildFormat::Pdf => Some(builder::ExportTarget::Pdf),
+                    BuildFormat::Epub => Some(builder::ExportTarget::Epub),
+                })
+                .collect();
+            if !formats.contains(&BuildFormat::Html) {
+                info!("Exporting book from {:?} to {:?}", path, output);
+                return builder::export_book(&path, &output, &exports);
+            }
+            // A partial build keeps everything it doesn't render
+            let partial = only.is_some();
+            let options = builder::BuildOptions {
+                clean: !no_clean && !partial,
+                skip_search_index: partial,
+                skip_manifest: partial,
+                reproducible,
+                config_file: config,
+                report,
+                strict,
+                only,
+                variables: defines,
+                base_url,
+                validate_html,
+                cache: incremental.then(|| builder::cache_path(&output)),
+                skip_precompress: false,
+                plain_asset_names: false,
+                skip_pwa: false,
+                fast_mode: false,
+                exports,
+                push_search,
+            };
+            if watch {
+                return watch_build(&path, &output, options);
+            }
+            info!("Building book from {:?} to {:?}", path, output);
+            builder::build_atomic(&path, &output, &options)
+        }
+        Commands::Watch { path, output, config } => {
+            let options = builder::BuildOptions { clean: true, config_file: config, ..Default::default() };
+            watch_build(&path, &output, options)
+        }
+        Commands::Publish { path, output, config, branch, remote, message } => {
+            info!("Building book from {:?} to {:?}", path, output);
+            let options = builder::BuildOptions { clean: true, config_file: config, ..Default::default() };
+            builder::build_atomic(&path, &output, &options)?;
+            let options = builder::publish::PublishOptions { remote, branch, message };
+            match builder::publish::publish(&path, &output, &options)? {
+                builder::publish::Published::Commit(commit) => {
+                    info!("Published {} to {} {}", &commit[..commit.len().min(7)], options.remote, options.branch)
+                }
+                builder::publish::Published::Unchanged => info!("{} {} is up to date", options.remote, options.branch),
+            }
+            Ok(())
+        }
+        Commands::Deploy { path, output, config, prune, dry_run, notify } => {
+            deploy_book(&path, &output, config, &builder::deploy::DeployOptions { prune, dry_run }, notify)
+        }
+        Commands::Serve { path, port, open, config, no_watch } => {
+            serve_book(&path, port, open, config, !no_watch)
+        }
+        Commands::Check { path } => {
+            let report = builder::check::check_book(&path)?;
+            for broken in &report.broken {
+                println!("{}", broken);
+            }
+            if report.broken.is_empty() {
+                info!("Checked {} files, no broken links", report.files);
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!(
+                    "{} broken link(s) in {} files",
+                    report.broken.len(),
+                    report.files
+                ))
+            }
+        }
+        Commands::Lint { path } => {
+            let issues = builder::lint::lint_book(&path)?;
+            for issue in &issues {
+                println!("{}", issue);
+            }
+            let errors = issues.iter().filter(|i| i.severity == parser::LintSeverity::Error).count();
+            let warnings = issues.len() - errors;
+            if errors > 0 {
+                Err(anyhow::anyhow!("{} error(s), {} warning(s)", errors, warnings))
+            } else {
+                info!("{} warning(s)", warnings);
+                Ok(())
+            }
+        }
+        Commands::Test { path, only, update } => {
+            let mut books = builder::snapshot::fixture_books(&path.join("books"))?;
+            if let Some(only) = &only {
+                books.retain(|name| name.contains(only.as_str()));
+            }
+            if books.is_empty() {
+                anyhow::bail!("No fixture books in {}", path.join("books").display());
+            }
+            // Build progress would bury the results
+            let level = logging::level();
+            let mut failed = 0;
+            for name in &books {
+                logging::set_level(Level::Quiet);
+                let changes = builder::snapshot::test_book(&path.join("books").join(name), &path.join("snapshots").join(name), update);
+                logging::set_level(level);
+                let changes = changes?;
+                if changes.is_empty() {
+                    info!("ok      {}", name);
+                } else if update {
+                    info!("updated {} ({} files)", name, changes.len());
+                } else {
+                    failed += 1;
+                    println!("FAILED  {}", name);
+                    for change in &changes {
+                        println!("{}", change);
+                    }
+                }
+            }
+            if failed > 0 {
+                Err(anyhow::anyhow!(
+                    "{} of {} fixture books differ from their snapshots (accept the output with `guidebook test --update`)",
+                    failed,
+                    books.len()
+                ))
+            } else {
+                info!("{} fixture books tested", books.len());
+                Ok(())
+            }
+        }
+        Commands::Stats { path, json } => {
+            let stats = builder::stats::book_stats(&path)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+            } else {
+                print_stats(&stats);
+            }
+            Ok(())
+        }
+        Commands::Graph { path, json, dead_ends } => {
+            let graph = builder::graph::link_graph(&path)?;
+            if dead_ends {
+                for node in graph.dead_ends() {
+                    println!("{}", node.id);
+                }
+            } else if json {
+                println!("{}", serde_json::to_string_pretty(&graph)?);
+            } else {
+                print!("{}", graph.to_dot());
+            }
+            Ok(())
+        }
+        Commands::TranslateStatus { path, json, mtime } => {
+            let status = builder::translate::translation_status(&path, mtime)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&status)?);
+            } else {
+                print_translation_status(&status);
+            }
+            Ok(())
+        }
+        Commands::Clean { output, dry_run } => {
+            clean(&output, dry_run)
+        }
+        Commands::Config { action } => {
+            run_config_command(action)
+        }
+        Commands::Manifest { action } => {
+            run_manifest_command(action)
+        }
+        Commands::Pdf { path, output, html } => {
+            info!("Exporting PDF from {:?} to {:?}", path, output);
+            builder::pdf::export_pdf(&path, &output, html.as_deref())
+        }
+        Commands::Epub { path, output } => {
+            info!("Exporting EPUB from {:?} to {:?}", path, output);
+            builder::epub::export_epub(&path, &output)
+        }
+        Commands::Export { format: ExportFormat::Md { path, output } } => {
+            info!("Exporting Markdown from {:?} to {:?}", path, output);
+            builder::markdown::export_markdown(&path, &output)
+        }
+        Commands::Export { format: ExportFormat::Pages { path, output } } | Commands::ExportMarkdown { path, output } => {
+            info!("Exporting Markdown pages from {:?} to {:?}", path, output);
+            builder::markdown::export_markdown_pages(&path, &output)
+        }
+        Commands::Export { format: ExportFormat::Fragments { path, output } } => {
+            info!("Exporting HTML fragments from {:?} to {:?}", path, output);
+            builder::fragments::export_fragments(&path, &output)
+        }
+        Commands::Completions { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), "guidebook", &mut std::io::stdout());
+            Ok(())
         }
         Commands::Update => {
             update_self()
@@ -78,13 +656,169 @@ fn main() -> Result<()> {
     }
 }
 
+fn run_config_command(action: ConfigAction) -> Result<()> {
+    match action {
+        ConfigAction::Schema { output } => {
+            match output {
+                Some(path) => {
+                    fs::write(&path, parser::BOOK_SCHEMA)?;
+                    println!("Wrote book.json schema to {:?}", path);
+                }
+                None => print!("{}", parser::BOOK_SCHEMA),
+            }
+            Ok(())
+        }
+        ConfigAction::Validate { path } => {
+            let config_path = path.join("book.json");
+            let content = fs::read_to_string(&config_path)
+                .map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", config_path, e))?;
+
+            let validation = parser::validate_config(&content);
+            for warning in &validation.warnings {
+                println!("  Warning: {}", warning);
+            }
+            for error in &validation.errors {
+                println!("  Error: {}", error);
+            }
+
+            if validation.is_valid() {
+                println!("{:?} is valid", config_path);
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!(
+                    "{:?} has {} error(s)",
+                    config_path,
+                    validation.errors.len()
+                ))
+            }
+        }
+        ConfigAction::Get { key, path } => {
+            let config_path = path.join("book.json");
+            let content = fs::read_to_string(&config_path)
+                .map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", config_path, e))?;
+            let root: serde_json::Value = serde_json::from_str(&content)?;
+
+            match parser::get_config_value(&root, &key) {
+                Some(serde_json::Value::String(s)) => println!("{}", s),
+                Some(value) => println!("{}", serde_json::to_string_pretty(value)?),
+                None => return Err(anyhow::anyhow!("Key `{}` is not set in {:?}", key, config_path)),
+            }
+            Ok(())
+        }
+        ConfigAction::Set { key, value, path } => {
+            let config_path = path.join("book.json");
+            let content = fs::read_to_string(&config_path)
+                .map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", config_path, e))?;
+
+            let updated = parser::set_config_value(&content, &key, &value)?;
+
+            // Refuse to write a config that would no longer load
+            let validation = parser::validate_config(&updated);
+            if !validation.is_valid() {
+                for error in &validation.errors {
+                    println!("  Error: {}", error);
+                }
+                return Err(anyhow::anyhow!("Not updating {:?}: `{}` would be invalid", config_path, key));
+            }
+
+            fs::write(&config_path, updated)?;
+            println!("Set {} in {:?}", key, config_path);
+            Ok(())
+        }
+    }
+}
+
+fn run_manifest_command(action: ManifestAction) -> Result<()> {
+    match action {
+        ManifestAction::Diff { previous, current, prune } => {
+            let previous = builder::manifest::Manifest::load(&previous)?;
+            let current = builder::manifest::Manifest::load(&current)?;
+            let diff = builder::manifest::diff_manifests(&previous, &current);
+
+            // One "<action> <path>" line per file on stdout for deploy scripts
+            for path in &diff.upload {
+                println!("upload {}", path);
+            }
+            if prune {
+                for path in &diff.delete {
+                    println!("delete {}", path);
+                }
+            }
+            eprintln!(
+                "{} to upload, {} to delete{}, {} unchanged",
+                diff.upload.len(),
+                diff.delete.len(),
+                if prune { "" } else { " (pass --prune to list)" },
+                diff.unchanged
+            );
+            Ok(())
+        }
+        ManifestAction::Notify { previous, current, path, dry_run } => {
+            let deploy = parser::BookConfig::load(&path)?.deploy;
+            if deploy.site_url.is_empty() {
+                return Err(anyhow::anyhow!("Set deploy.siteUrl in book.json to build page URLs"));
+            }
+            let previous = builder::manifest::Manifest::load(&previous)?;
+            let current = builder::manifest::Manifest::load(&current)?;
+            let diff = builder::manifest::diff_manifests(&previous, &current);
+
+            // Removed pages are submitted too so search engines drop them
+            let changed: Vec<String> = diff.upload.into_iter().chain(diff.delete).collect();
+            let urls = builder::indexnow::page_urls(&deploy.site_url, &changed);
+            if urls.is_empty() {
+                println!("No changed pages to submit");
+                return Ok(());
+            }
+            for url in &urls {
+                println!("{}", url);
+            }
+            if dry_run {
+                println!("{} URLs (dry run, not submitted)", urls.len());
+                return Ok(());
+            }
+            builder::indexnow::submit(&deploy, &urls)?;
+            println!("Submitted {} URLs to IndexNow", urls.len());
+            Ok(())
+        }
+    }
+}
+
+fn new_chapter(book_dir: &Path, file: &str, title: Option<String>, parent: Option<&str>) -> Result<()> {
+    let file = paths::normalize(file).trim_start_matches('/').to_string();
+    let file = if Path::new(&file).extension().is_none() { format!("{}.md", file) } else { file };
+    let chapter_path = book_dir.join(&file);
+    if chapter_path.exists() {
+        return Err(anyhow::anyhow!("{:?} already exists", chapter_path));
+    }
+    let title = title.unwrap_or_else(|| parser::summary::title_from_file_name(&file));
+
+    // Update SUMMARY.md first so a bad --parent leaves nothing behind
+    let summary_path = book_dir.join("SUMMARY.md");
+    let summary = fs::read_to_string(&summary_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {:?}: {}", summary_path, e))?;
+    let updated = parser::summary::insert_entry(&summary, &title, &file, parent)?;
+
+    let content = if builder::is_asciidoc_file(&chapter_path) {
+        format!("= {}\n", title)
+    } else {
+        format!("---\ntitle: {}\ndescription: \"\"\n---\n\n# {}\n", serde_json::to_string(&title)?, title)
+    };
+    if let Some(dir) = chapter_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&chapter_path, content)?;
+    fs::write(&summary_path, updated)?;
+    info!("Created {:?} and added it to SUMMARY.md", chapter_path);
+    Ok(())
+}
+
 fn init_book(path: &PathBuf) -> Result<()> {
-    println!("Initializing book in {:?}", path);
+    info!("Initializing book in {:?}", path);
 
     // Create directory if it doesn't exist
     if !path.exists() {
         fs::create_dir_all(path)?;
-        println!("  Created directory {:?}", path);
+        info!("  Created directory {:?}", path);
     }
 
     // Create README.md
@@ -97,9 +831,9 @@ Welcome to your new book!
 This file serves as your book's introduction or preface.
 "#;
         fs::write(&readme_path, readme_content)?;
-        println!("  Created README.md");
+        info!("  Created README.md");
     } else {
-        println!("  README.md already exists, skipping");
+        info!("  README.md already exists, skipping");
     }
 
     // Create SUMMARY.md
@@ -110,9 +844,9 @@ This file serves as your book's introduction or preface.
 * [Introduction](README.md)
 "#;
         fs::write(&summary_path, summary_content)?;
-        println!("  Created SUMMARY.md");
+        info!("  Created SUMMARY.md");
     } else {
-        println!("  SUMMARY.md already exists, skipping");
+        info!("  SUMMARY.md already exists, skipping");
     }
 
     // Create book.json
@@ -144,62 +878,295 @@ This file serves as your book's introduction or preface.
     Ok(())
 }
 
-fn serve_book(source: &PathBuf, port: u16, open_browser: bool) -> Result<()> {
-    // Build to temp directory
-    let temp_dir = std::env::temp_dir().join("guidebook-serve");
-    if temp_dir.exists() {
-        fs::remove_dir_all(&temp_dir)?;
+fn print_translation_status(status: &builder::translate::TranslationStatus) {
+    use builder::stats::display_width;
+    use builder::translate::{format_day, PageState};
+
+    let labels: Vec<String> = status.languages.iter().map(|lang| format!("{} ({})", lang.title, lang.code)).collect();
+    let width = labels.iter().map(|label| display_width(label)).chain(["Language".len()]).max().unwrap_or(0);
+    println!("Compared with {}\n", status.reference);
+    println!("Language{}  {:>10}  {:>5}  {:>7}  {:>6}", " ".repeat(width - "Language".len()), "Translated", "Stale", "Missing", "Shared");
+    for (lang, label) in status.languages.iter().zip(&labels) {
+        let translated = lang.count(PageState::Current) + lang.count(PageState::Stale);
+        println!(
+            "{}{}  {:>10}  {:>5}  {:>7}  {:>6}",
+            label,
+            " ".repeat(width - display_width(label)),
+            format!("{}/{}", translated, lang.pages.len()),
+            lang.count(PageState::Stale),
+            lang.count(PageState::Missing),
+            lang.count(PageState::Shared)
+        );
     }
 
-    println!("Building book...");
-    builder::build(source, &temp_dir)?;
+    if status.languages.iter().any(|lang| lang.count(PageState::Stale) + lang.count(PageState::Missing) > 0) {
+        println!();
+    }
+    for lang in &status.languages {
+        for page in &lang.pages {
+            match (page.state, page.reference_updated, page.updated) {
+                (PageState::Missing, _, _) => println!("{}: missing {}", lang.code, page.path),
+                (PageState::Stale, Some(reference), Some(updated)) => println!(
+                    "{}: stale   {} ({} {}, {} {})",
+                    lang.code,
+                    page.path,
+                    status.reference,
+                    format_day(reference),
+                    lang.code,
+                    format_day(updated)
+                ),
+                _ => {}
+            }
+        }
+    }
+}
 
-    // Version counter for hot reload
-    let version = Arc::new(AtomicU64::new(1));
-    let version_for_watcher = version.clone();
-    let source_for_watcher = source.clone();
-    let temp_dir_for_watcher = temp_dir.clone();
+fn print_stats(stats: &builder::stats::BookStats) {
+    use builder::stats::display_width;
+
+    let total_label = format!("Total ({} chapters)", stats.chapters.len());
+    let width = stats
+        .chapters
+        .iter()
+        .map(|c| display_width(&c.title))
+        .chain([total_label.len()])
+        .max()
+        .unwrap_or(0)
+        .min(48);
+    let row = |title: &str, counts: &builder::stats::Counts| {
+        // Truncate and pad by display width so CJK titles line up
+        let mut shown = String::new();
+        for c in title.chars() {
+            if display_width(&shown) + display_width(&c.to_string()) > width {
+                break;
+            }
+            shown.push(c);
+        }
+        let padding = " ".repeat(width - display_width(&shown));
+        println!(
+            "{}{}  {:>8}  {:>6}  {:>8}  {:>4} min",
+            shown, padding, counts.words, counts.images, counts.headings, counts.reading_minutes
+        );
+    };
 
-    // Setup file watcher
-    let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
-        if let Ok(event) = res {
-            // Only react to file modifications
-            let dominated: bool = matches!(
-                event.kind,
-                EventKind::Modify(ModifyKind::Data(_)) |
-                EventKind::Modify(ModifyKind::Name(_)) |
-                EventKind::Create(_) |
-                EventKind::Remove(_)
-            );
-            if dominated {
-                // Check if it's a relevant file (md, json, css, js)
-                // Exclude _book directory and other build artifacts
-                let dominated = event.paths.iter().any(|p| {
-                    // Skip files in _book directory (build output)
-                    let path_str = p.to_string_lossy();
-                    if path_str.contains("/_book/") || path_str.contains("\\_book\\") {
-                        return false;
-                    }
-                    p.extension()
-                        .and_then(|e| e.to_str())
-                        .map(|e| matches!(e, "md" | "json" | "css" | "js" | "html"))
-                        .unwrap_or(false)
-                });
-                if dominated {
-                    println!("\n🔄 File changed, rebuilding...");
-                    // Skip search index generation on hot reload for performance
-                    if let Err(e) = builder::build_with_options(&source_for_watcher, &temp_dir_for_watcher, true) {
-                        eprintln!("   Build error: {}", e);
-                    } else {
-                        version_for_watcher.fetch_add(1, Ordering::SeqCst);
-                        println!("   Rebuild complete!");
-                    }
-                }
+    println!("{:<width$}  {:>8}  {:>6}  {:>8}  {:>8}", "Chapter", "Words", "Images", "Headings", "Reading");
+    for chapter in &stats.chapters {
+        row(&chapter.title, &chapter.counts);
+    }
+    row(&total_label, &stats.total);
+}
+
+fn print_build_plan(plan: &builder::plan::BuildPlan, output: &Path) {
+    let print_files = |files: &[builder::plan::PlannedFile]| {
+        for file in files {
+            let dest = output.join(&file.dest);
+            match file.note {
+                Some(note) => println!("  {} -> {} ({})", file.source, dest.display(), note),
+                None => println!("  {} -> {}", file.source, dest.display()),
             }
         }
+    };
+
+    println!("Pages:");
+    print_files(&plan.pages);
+    println!("Assets:");
+    print_files(&plan.assets);
+    if !plan.missing.is_empty() {
+        println!("Missing (listed in SUMMARY.md, skipped):");
+        for path in &plan.missing {
+            println!("  {}", path);
+        }
+    }
+    println!();
+    println!("{} pages, {} asset files; nothing was written", plan.pages.len(), plan.assets.len());
+}
+
+/// Directory `guidebook serve` builds into
+fn serve_temp_dir() -> PathBuf {
+    std::env::temp_dir().join("guidebook-serve")
+}
+
+fn clean(output: &Path, dry_run: bool) -> Result<()> {
+    // Guard against `-o .` or a typo pointing at the book itself
+    if output.join("SUMMARY.md").exists() || output.join("book.json").exists() {
+        return Err(anyhow::anyhow!("Refusing to remove {:?}: it contains book sources", output));
+    }
+
+    let mut targets = builder::output_artifacts(output);
+    targets.extend(builder::output_artifacts(&serve_temp_dir()));
+    if targets.is_empty() {
+        info!("Nothing to clean");
+        return Ok(());
+    }
+
+    for target in &targets {
+        if dry_run {
+            info!("Would remove {:?}", target);
+        } else {
+            if target.is_dir() {
+                fs::remove_dir_all(target)?;
+            } else {
+                fs::remove_file(target)?;
+            }
+            info!("Removed {:?}", target);
+        }
+    }
+    Ok(())
+}
+
+/// `key=value` of `build --define`
+fn parse_define(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => Ok((key.trim().to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got `{}`", arg)),
+    }
+}
+
+fn deploy_book(
+    source: &Path,
+    output: &Path,
+    config_file: Option<PathBuf>,
+    options: &builder::deploy::DeployOptions,
+    notify: bool,
+) -> Result<()> {
+    let config = match &config_file {
+        Some(file) => parser::BookConfig::load_file(file)?,
+        None => parser::BookConfig::load(source)?,
+    };
+    let target = builder::deploy::configured_target(&config.deploy)?;
+    if notify && (config.deploy.site_url.is_empty() || config.deploy.index_now_key.is_empty()) {
+        return Err(anyhow::anyhow!("Set deploy.siteUrl and deploy.indexNowKey in book.json to use --notify"));
+    }
+
+    info!("Building book from {:?} to {:?}", source, output);
+    let build_options = builder::BuildOptions { clean: true, config_file, ..Default::default() };
+    builder::build_atomic(source, output, &build_options)?;
+
+    let diff = builder::deploy::deploy(output, target.as_ref(), options)?;
+    if options.dry_run {
+        // Same "<action> <path>" lines as `manifest diff`
+        for path in &diff.upload {
+            println!("upload {}", path);
+        }
+        if options.prune {
+            for path in &diff.delete {
+                println!("delete {}", path);
+            }
+        }
+    }
+    let (heading, uploaded, deleted) =
+        if options.dry_run { ("Dry run for", "to upload", "to delete") } else { ("Deployed to", "uploaded", "deleted") };
+    let deleted = if options.prune {
+        format!("{} {}", diff.delete.len(), deleted)
+    } else {
+        format!("{} no longer built (pass --prune to delete)", diff.delete.len())
+    };
+    info!("{} {}: {} {}, {}, {} unchanged", heading, target.name(), diff.upload.len(), uploaded, deleted, diff.unchanged);
+
+    if notify {
+        // Pages left deployed without --prune are still served
+        let deleted = if options.prune { diff.delete.as_slice() } else { &[] };
+        let changed: Vec<String> = diff.upload.iter().chain(deleted).cloned().collect();
+        let urls = builder::indexnow::page_urls(&config.deploy.site_url, &changed);
+        if urls.is_empty() {
+            info!("No changed pages to submit to IndexNow");
+        } else if options.dry_run {
+            info!("{} URLs to submit to IndexNow", urls.len());
+        } else {
+            builder::indexnow::submit(&config.deploy, &urls)?;
+            info!("Submitted {} URLs to IndexNow", urls.len());
+        }
+    }
+    Ok(())
+}
+
+/// Build into `output` and rebuild it with the same options whenever a
+/// source file changes (`guidebook watch`, `guidebook build --watch`)
+fn watch_build(source: &Path, output: &Path, options: builder::BuildOptions) -> Result<()> {
+    // Rebuilds only render the pages whose inputs changed
+    let options = builder::BuildOptions { cache: Some(options.cache.unwrap_or_else(|| builder::cache_path(output))), ..options };
+    let config_file = options.config_file.clone();
+    let build = {
+        let source = source.to_path_buf();
+        let output = output.to_path_buf();
+        move || {
+            let start = std::time::Instant::now();
+            match builder::build_atomic(&source, &output, &options) {
+                Ok(()) => info!("   Rebuilt in {:.1}s", start.elapsed().as_secs_f64()),
+                // Keep watching: the next change may fix it
+                Err(e) => eprintln!("   Build error: {:#}", e),
+            }
+        }
+    };
+
+    info!("Building book from {:?} to {:?}", source, output);
+    build();
+    let _watcher = watch_book(source, output, config_file.as_deref(), build)?;
+    info!("\n👀 Watching {:?} for changes", source);
+    info!("   Press Ctrl+C to stop\n");
+
+    // The watcher rebuilds from its own thread until the process is stopped
+    loop {
+        std::thread::park();
+    }
+}
+
+fn serve_book(source: &Path, port: u16, open_browser: bool, config_file: Option<PathBuf>, watch: bool) -> Result<()> {
+    // Build to temp directory
+    let temp_dir = serve_temp_dir();
+    if temp_dir.exists() {
+        fs::remove_dir_all(&temp_dir)?;
+    }
+    // Rebuilds only render the pages whose inputs changed
+    let cache = builder::cache_path(&temp_dir);
+    let _ = fs::remove_file(&cache);
+
+    info!("Building book...");
+    // Pages are served from the root, whatever base URL the book is published at
+    builder::build_with_options(source, &temp_dir, &builder::BuildOptions {
+        config_file: config_file.clone(),
+        base_url: Some(String::new()),
+        cache: Some(cache.clone()),
+        plain_asset_names: true,
+        skip_precompress: true,
+        skip_pwa: true,
+        ..Default::default()
     })?;
 
-    watcher.watch(source, RecursiveMode::Recursive)?;
+    // Version counter for hot reload
+    let version = Arc::new(AtomicU64::new(1));
+    // Keep watcher alive
+    let _watcher = if watch {
+        let source_for_watcher = source.to_path_buf();
+        let temp_dir_for_watcher = temp_dir.clone();
+        let config_file_for_watcher = config_file.clone();
+        let version = version.clone();
+        Some(watch_book(source, &temp_dir, config_file.as_deref(), move || {
+            // Skip the slow passes of `serve.fastMode` on hot reload
+            if let Err(e) = builder::build_with_options(
+                &source_for_watcher,
+                &temp_dir_for_watcher,
+                &builder::BuildOptions {
+                    fast_mode: true,
+                    skip_manifest: true,
+                    config_file: config_file_for_watcher.clone(),
+                    base_url: Some(String::new()),
+                    cache: Some(cache.clone()),
+                    plain_asset_names: true,
+                    skip_precompress: true,
+                    skip_pwa: true,
+                    ..Default::default()
+                },
+            ) {
+                eprintln!("   Build error: {}", e);
+            } else {
+                version.fetch_add(1, Ordering::SeqCst);
+                info!("   Rebuild complete!");
+            }
+        })?)
+    } else {
+        None
+    };
 
     let addr = format!("0.0.0.0:{}", port);
     let server = Server::http(&addr).map_err(|e| {
@@ -215,9 +1182,13 @@ fn serve_book(source: &PathBuf, port: u16, open_browser: bool) -> Result<()> {
     })?;
 
     let url = format!("http://localhost:{}/", port);
-    println!("\n📚 Serving book at {}", url);
-    println!("   🔥 Hot reload enabled - changes will auto-refresh");
-    println!("   Press Ctrl+C to stop\n");
+    info!("\n📚 Serving book at {}", url);
+    if watch {
+        info!("   🔥 Hot reload enabled - changes will auto-refresh");
+    } else {
+        info!("   File watching disabled (--no-watch)");
+    }
+    info!("   Press Ctrl+C to stop\n");
 
     // Open browser if requested
     if open_browser {
@@ -226,14 +1197,15 @@ fn serve_book(source: &PathBuf, port: u16, open_browser: bool) -> Result<()> {
         }
     }
 
-    // Keep watcher alive
-    let _watcher = watcher;
+    // search_index.json of each book root, loaded on the first /__search
+    // Rebuilds on change don't regenerate the index, so it is kept for the session
+    let mut search_indexes: HashMap<String, builder::search::SearchIndex> = HashMap::new();
 
     for request in server.incoming_requests() {
         let url = request.url().to_string();
 
         // Handle livereload polling endpoint
-        if url.starts_with("/__livereload") {
+        if watch && url.starts_with("/__livereload") {
             // Extract version from query string
             let client_version: u64 = url
                 .split("?v=")
@@ -256,26 +1228,70 @@ fn serve_book(source: &PathBuf, port: u16, open_browser: bool) -> Result<()> {
             continue;
         }
 
+        // Search answered from the index in memory, so the browser doesn't download it
+        // The endpoint is relative to the book root, which differs per language
+        let url_path = url.split('?').next().unwrap_or("/");
+        if let Some(book_root) = url_path.strip_suffix("/__search") {
+            let book_root = percent_decode_str(book_root).decode_utf8_lossy().trim_start_matches('/').to_string();
+            let index = if book_root.split('/').any(|segment| segment == "..") {
+                None
+            } else {
+                let dir = temp_dir.join(&book_root);
+                Some(search_indexes.entry(book_root).or_insert_with(|| {
+                    builder::search::SearchIndex::load(&dir).unwrap_or_else(|e| {
+                        warning!("{:#}", e);
+                        builder::search::SearchIndex::default()
+                    })
+                }))
+            };
+            let results = match (index, paths::query_param(&url, "q")) {
+                (Some(index), Some(query)) => index.query(&query),
+                _ => Vec::new(),
+            };
+            let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+            let response = Response::from_string(serde_json::to_string(&results)?).with_header(header);
+            let _ = request.respond(response);
+            continue;
+        }
+
+        // Search results and shared links may carry a query string
+        let url = url_path;
         let url_path = if url == "/" {
             "/index.html".to_string()
         } else if url.ends_with('/') {
             format!("{}index.html", url)
         } else {
-            url.clone()
+            url.to_string()
         };
 
-        // URL decode the path to handle Japanese/special characters
+        // URL decode the path to handle Japanese/special characters (`%23` for `#` in file names)
         let decoded_path = percent_decode_str(&url_path)
             .decode_utf8_lossy()
             .to_string();
         let file_path = temp_dir.join(decoded_path.trim_start_matches('/'));
+        // Try with .html extension, then fall back to the book's 404 page
+        let html_path = PathBuf::from(format!("{}.html", file_path.display()));
+        let (file_path, status) = if file_path.is_file() {
+            (file_path, 200)
+        } else if html_path.is_file() {
+            (html_path, 200)
+        } else {
+            (temp_dir.join("404.html"), 404)
+        };
 
-        if file_path.exists() && file_path.is_file() {
+        if file_path.is_file() {
             let mut content = fs::read(&file_path).unwrap_or_default();
-            let content_type = get_content_type(&file_path);
+            let content_type = paths::content_type(&file_path);
 
-            // Inject livereload script into HTML pages
+            // Point search.js at /__search
             if content_type.starts_with("text/html") {
+                let html = String::from_utf8_lossy(&content);
+                let html = html.replacen("data-root-path=", "data-search-api=\"__search\" data-root-path=", 1);
+                content = html.into_bytes();
+            }
+
+            // Inject livereload script into HTML pages
+            if watch && content_type.starts_with("text/html") {
                 let current_version = version.load(Ordering::SeqCst);
                 let livereload_script = format!(
                     r#"<script>
@@ -303,50 +1319,86 @@ fn serve_book(source: &PathBuf, port: u16, open_browser: bool) -> Result<()> {
             }
 
             let header = Header::from_bytes("Content-Type", content_type).unwrap();
-            let response = Response::from_data(content).with_header(header);
+            let response = Response::from_data(content).with_header(header).with_status_code(status);
             let _ = request.respond(response);
         } else {
-            // Try with .html extension
-            let html_path = format!("{}.html", file_path.display());
-            let html_path = PathBuf::from(&html_path);
-            if html_path.exists() {
-                let content = fs::read(&html_path).unwrap_or_default();
-                let header = Header::from_bytes("Content-Type", "text/html; charset=utf-8").unwrap();
-                let response = Response::from_data(content).with_header(header);
-                let _ = request.respond(response);
-            } else {
-                let response = Response::from_string("404 Not Found").with_status_code(404);
-                let _ = request.respond(response);
-            }
+            let response = Response::from_string("404 Not Found").with_status_code(404);
+            let _ = request.respond(response);
         }
     }
 
     Ok(())
 }
 
-fn get_content_type(path: &PathBuf) -> &'static str {
-    match path.extension().and_then(|e| e.to_str()) {
-        Some("html") => "text/html; charset=utf-8",
-        Some("css") => "text/css; charset=utf-8",
-        Some("js") => "application/javascript; charset=utf-8",
-        Some("json") => "application/json; charset=utf-8",
-        Some("png") => "image/png",
-        Some("jpg") | Some("jpeg") => "image/jpeg",
-        Some("gif") => "image/gif",
-        Some("svg") => "image/svg+xml",
-        Some("ico") => "image/x-icon",
-        Some("woff") => "font/woff",
-        Some("woff2") => "font/woff2",
-        Some("ttf") => "font/ttf",
-        _ => "application/octet-stream",
+/// Run `rebuild` when a source file of the book changes
+/// Changes to the build output (`output` and its staging siblings, `_book`) are ignored
+fn watch_book(
+    source: &Path,
+    output: &Path,
+    config_file: Option<&Path>,
+    rebuild: impl Fn() + Send + 'static,
+) -> Result<notify::RecommendedWatcher> {
+    let source = source.canonicalize()?;
+    let output = output.canonicalize().or_else(|_| std::env::current_dir().map(|dir| dir.join(output)))?;
+
+    // Setup file watcher
+    let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+        if let Ok(event) = res {
+            // Only react to file modifications
+            let dominated: bool = matches!(
+                event.kind,
+                EventKind::Modify(ModifyKind::Data(_)) |
+                EventKind::Modify(ModifyKind::Name(_)) |
+                EventKind::Create(_) |
+                EventKind::Remove(_)
+            );
+            if dominated {
+                // Check if it's a relevant file: chapters, config, generator data files, styles, scripts
+                // Exclude _book directory and other build artifacts
+                let dominated = event.paths.iter().any(|p| {
+                    // Skip files in _book directory (build output)
+                    if p.components().any(|c| c.as_os_str() == "_book") || builder::is_output_path(&output, p) {
+                        return false;
+                    }
+                    p.extension()
+                        .and_then(|e| e.to_str())
+                        .map(|e| {
+                            matches!(e, "md" | "adoc" | "asciidoc" | "json" | "yaml" | "yml" | "csv" | "css" | "js" | "html")
+                        })
+                        .unwrap_or(false)
+                });
+                if dominated {
+                    info!("\n🔄 File changed, rebuilding...");
+                    rebuild();
+                }
+            }
+        }
+    })?;
+
+    watcher.watch(&source, RecursiveMode::Recursive)?;
+    // Symlinked chapters and directories change at their target: watch targets outside the book too
+    for target in paths::external_symlink_targets(&source) {
+        let mode = if target.is_dir() { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+        if let Err(e) = watcher.watch(&target, mode) {
+            warning!("can't watch {}: {}", target.display(), e);
+        }
     }
+    // A config file outside the book is watched on its own
+    if let Some(config_file) = config_file {
+        let inside_source = config_file.canonicalize().map(|file| file.starts_with(&source)).unwrap_or(false);
+        if !inside_source {
+            watcher.watch(config_file, RecursiveMode::NonRecursive)?;
+        }
+    }
+
+    Ok(watcher)
 }
 
 fn check_for_updates() {
     // Run in a separate thread to not block startup
     std::thread::spawn(|| {
         if let Some(latest) = get_latest_version() {
-            if is_newer_version(&latest, VERSION) {
+            if is_newer_version(&latest, VERSION) && logging::level() > Level::Quiet {
                 eprintln!(
                     "\n📦 New version available: {} → {}\n   Run: cargo install guidebook --force\n",
                     VERSION, latest
@@ -395,17 +1447,17 @@ fn is_newer_version(latest: &str, current: &str) -> bool {
 fn update_self() -> Result<()> {
     use std::io::{Read, Write};
 
-    println!("Checking for updates...");
+    info!("Checking for updates...");
 
     // Get latest version from GitHub
     let latest_version = get_latest_github_version()
         .ok_or_else(|| anyhow::anyhow!("Failed to check latest version"))?;
 
-    println!("  Current version: {}", VERSION);
-    println!("  Latest version:  {}", latest_version);
+    info!("  Current version: {}", VERSION);
+    info!("  Latest version:  {}", latest_version);
 
     if !is_newer_version(&latest_version, VERSION) {
-        println!("\nYou're already on the latest version!");
+        info!("\nYou're already on the latest version!");
         return Ok(());
     }
 
@@ -413,7 +1465,7 @@ fn update_self() -> Result<()> {
     let artifact_name = get_artifact_name()
         .ok_or_else(|| anyhow::anyhow!("Unsupported platform"))?;
 
-    println!("\nDownloading {}...", artifact_name);
+    info!("\nDownloading {}...", artifact_name);
 
     // Download from GitHub Releases
     let download_url = format!(
@@ -471,7 +1523,7 @@ fn update_self() -> Result<()> {
     // Remove backup
     let _ = fs::remove_file(&backup_path);
 
-    println!("\nSuccessfully updated to v{}!", latest_version);
+    info!("\nSuccessfully updated to v{}!", latest_version);
     Ok(())
 }
 
@@ -1,9 +1,13 @@
+use crate::info;
 use anyhow::Result;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
+/// JSON Schema describing book.json (shipped as templates/book.schema.json)
+pub const BOOK_SCHEMA: &str = include_str!("../../templates/book.schema.json");
+
 /// Plugins that are enabled by default (unless explicitly disabled with "-plugin-name")
 const DEFAULT_ENABLED_PLUGINS: &[&str] = &[
     "collapsible-chapters",
@@ -17,6 +21,25 @@ pub struct BookConfig {
     #[serde(default)]
     pub title: String,
 
+    #[serde(default)]
+    pub description: String,
+
+    #[serde(default)]
+    pub author: String,
+
+    /// Book language (e.g. "en", "ja"), used for the lang attribute of exports
+    #[serde(default)]
+    pub language: String,
+
+    /// URL or path the book is hosted at (e.g. "/docs/mybook/"); when set,
+    /// links, scripts and stylesheets point there instead of being relative
+    #[serde(default, rename = "baseUrl")]
+    pub base_url: String,
+
+    /// Where the languages of a multi-language book are published
+    #[serde(default)]
+    pub languages: LanguagesConfig,
+
     #[serde(default)]
     pub plugins: Vec<String>,
 
@@ -29,9 +52,18 @@ pub struct BookConfig {
 
     /// When true, treat single newlines as hard breaks (<br>)
     /// This makes trailing spaces unnecessary for line breaks
+    /// Pages can override it with `hardbreaks:` in their front matter
     #[serde(default)]
     pub hardbreaks: bool,
 
+    /// How bare URLs in text are turned into links
+    #[serde(default)]
+    pub autolink: AutolinkConfig,
+
+    /// Attributes and icon of links to other sites
+    #[serde(default, rename = "externalLinks")]
+    pub external_links: ExternalLinksConfig,
+
     /// When true, enable KaTeX math rendering
     /// Supports $...$ for inline math and $$...$$ for display math
     #[serde(default)]
@@ -51,6 +83,658 @@ pub struct BookConfig {
     /// Images are cached in _remote_images/ directory with CRC32-based filenames
     #[serde(default, rename = "fetchRemoteImages")]
     pub fetch_remote_images: bool,
+
+    /// When true, minify generated pages and the embedded CSS/JS assets
+    #[serde(default)]
+    pub minify: bool,
+
+    /// When true, give gitbook/ assets and the search index content-hashed
+    /// names (`gitbook.3f2a9c1e.css`) so they can be cached indefinitely
+    #[serde(default)]
+    pub fingerprint: bool,
+
+    /// When true, write gzip and brotli copies (`page.html.gz`, `page.html.br`)
+    /// of the HTML, CSS, JS and JSON output for hosts that serve them
+    #[serde(default)]
+    pub precompress: bool,
+
+    /// When true, show when each page last changed (its last git commit, or
+    /// its modification time outside a git repository) and list it in sitemap.xml
+    #[serde(default, rename = "lastUpdated")]
+    pub last_updated: bool,
+
+    /// When true, show each page's estimated reading time above its content
+    #[serde(default, rename = "readingTime")]
+    pub reading_time: bool,
+
+    /// When true, resolve Obsidian-style `[[Page]]` links and `![[image.png]]` embeds
+    /// Pages are matched by file name or front matter `aliases`
+    #[serde(default, rename = "wikiLinks")]
+    pub wiki_links: bool,
+
+    /// Thumbnails for large images in tables
+    #[serde(default)]
+    pub thumbnails: ThumbnailsConfig,
+
+    /// Search box settings (keyboard shortcut, recent queries)
+    #[serde(default)]
+    pub search: SearchConfig,
+
+    /// Sidebar settings (navigation loaded from a shared file)
+    #[serde(default)]
+    pub sidebar: SidebarConfig,
+
+    /// Output size limits the build warns about
+    #[serde(default)]
+    pub budget: BudgetConfig,
+
+    /// robots.txt written to the output root (none without this section)
+    #[serde(default)]
+    pub robots: Option<RobotsConfig>,
+
+    /// Web app manifest and offline service worker (none without this section)
+    #[serde(default)]
+    pub pwa: Option<PwaConfig>,
+
+    /// Twitter card tags of the pages (none without this section)
+    #[serde(default)]
+    pub twitter: Option<TwitterConfig>,
+
+    /// Favicon and touch icon of the pages
+    #[serde(default)]
+    pub icons: IconsConfig,
+
+    /// Preview images of the pages for shared links (none without this section)
+    #[serde(default, rename = "socialCards")]
+    pub social_cards: Option<SocialCardsConfig>,
+
+    /// Publishing settings (site URL, IndexNow)
+    #[serde(default)]
+    pub deploy: DeployConfig,
+
+    /// Severity of `guidebook lint` rules by rule name (default: warning)
+    #[serde(default)]
+    pub lint: HashMap<String, LintSeverity>,
+
+    /// Pages generated from data files, one per record
+    #[serde(default)]
+    pub generate: Vec<GeneratorConfig>,
+
+    /// External commands run on the final HTML of every page
+    #[serde(default)]
+    pub postprocessors: Vec<PostprocessorConfig>,
+
+    /// Order of the page rendering stages and stages to skip
+    #[serde(default)]
+    pub pipeline: PipelineConfig,
+
+    /// Settings of the preview server (`guidebook serve`)
+    #[serde(default)]
+    pub serve: ServeConfig,
+}
+
+/// Severity of a `guidebook lint` rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    Off,
+    Warning,
+    Error,
+}
+
+/// An entry of the `generate` section of book.json
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct GeneratorConfig {
+    /// Book-relative data file: a JSON or YAML list of objects, or a CSV file with a header row
+    pub data: String,
+
+    /// Page template name (`_templates/<name>.md`); records are available as `page`
+    pub template: String,
+
+    /// Source path of each page, with `{field}` placeholders (e.g. "errors/{code}.md")
+    pub path: String,
+
+    /// SUMMARY.md title of each page, with `{field}` placeholders (defaults to the `title` field)
+    #[serde(default)]
+    pub title: String,
+
+    /// SUMMARY.md part the pages are listed under; created at the end if missing
+    /// (pages are appended to the table of contents without a part)
+    #[serde(default)]
+    pub part: String,
+
+    /// Field the pages are sorted by, in the book language's order (data file order if empty)
+    #[serde(default)]
+    pub sort: String,
+}
+
+/// An entry of the `postprocessors` section of book.json
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct PostprocessorConfig {
+    /// Shell command reading page HTML on stdin and writing the result to stdout
+    pub command: String,
+
+    /// Position among postprocessors; lower runs first
+    #[serde(default)]
+    pub order: i32,
+}
+
+/// `pipeline` section of book.json
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct PipelineConfig {
+    /// Stages in the order they run (default: every stage in [`PipelineStage::ALL`] order)
+    /// Stages left out don't run
+    #[serde(default)]
+    pub stages: Vec<PipelineStage>,
+
+    /// Stages to skip
+    #[serde(default)]
+    pub disable: Vec<PipelineStage>,
+}
+
+/// A named step of page rendering
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PipelineStage {
+    /// Expand `@import` directives
+    Imports,
+    /// Preprocessors registered by library users
+    Preprocessors,
+    /// Nunjucks templating (skipped for pages with `templating: false`)
+    Nunjucks,
+    /// `template:` front matter page templates
+    PageTemplate,
+    /// `[[Page]]` wiki links (with `wikiLinks` enabled)
+    WikiLinks,
+    /// Markdown to HTML (required)
+    Markdown,
+    /// Glossary term links
+    Glossary,
+    /// Target, rel and icon of links to other sites
+    ExternalLinks,
+    /// SVG externalizing or inlining of the full page
+    Svg,
+    /// Thumbnails of large images in tables (with `thumbnails.width` set)
+    Thumbnails,
+    /// Registered postprocessors and `postprocessors` commands
+    Postprocessors,
+}
+
+impl PipelineStage {
+    /// Every stage, in the default order
+    pub const ALL: [PipelineStage; 11] = [
+        PipelineStage::Imports,
+        PipelineStage::Preprocessors,
+        PipelineStage::Nunjucks,
+        PipelineStage::PageTemplate,
+        PipelineStage::WikiLinks,
+        PipelineStage::Markdown,
+        PipelineStage::Glossary,
+        PipelineStage::ExternalLinks,
+        PipelineStage::Svg,
+        PipelineStage::Thumbnails,
+        PipelineStage::Postprocessors,
+    ];
+
+    /// Name used in book.json
+    pub fn name(self) -> &'static str {
+        match self {
+            PipelineStage::Imports => "imports",
+            PipelineStage::Preprocessors => "preprocessors",
+            PipelineStage::Nunjucks => "nunjucks",
+            PipelineStage::PageTemplate => "page-template",
+            PipelineStage::WikiLinks => "wiki-links",
+            PipelineStage::Markdown => "markdown",
+            PipelineStage::Glossary => "glossary",
+            PipelineStage::ExternalLinks => "external-links",
+            PipelineStage::Svg => "svg",
+            PipelineStage::Thumbnails => "thumbnails",
+            PipelineStage::Postprocessors => "postprocessors",
+        }
+    }
+}
+
+/// `serve` section of book.json
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServeConfig {
+    /// Passes the preview server skips when it rebuilds after a change
+    /// (default: the search index); full builds run them all
+    #[serde(default = "default_fast_mode", rename = "fastMode")]
+    pub fast_mode: Vec<ServePass>,
+}
+
+impl Default for ServeConfig {
+    fn default() -> Self {
+        ServeConfig { fast_mode: default_fast_mode() }
+    }
+}
+
+fn default_fast_mode() -> Vec<ServePass> {
+    vec![ServePass::SearchIndex]
+}
+
+/// A slow build pass the preview server can skip (`serve.fastMode`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ServePass {
+    /// search_index.json (search keeps the index of the first build)
+    SearchIndex,
+    /// Downloading remote images (`fetchRemoteImages`); pages load them from their sites
+    RemoteImages,
+    /// The `svg` pipeline stage
+    Svg,
+    /// The `thumbnails` pipeline stage
+    Thumbnails,
+}
+
+/// `thumbnails` section of book.json
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct ThumbnailsConfig {
+    /// Width of the thumbnails shown for images in tables that are wider, linked
+    /// to the full-size image (0, the default, leaves images as they are)
+    #[serde(default)]
+    pub width: u32,
+}
+
+/// `autolink` section of book.json: bare http(s) URLs in text turned into links
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct AutolinkConfig {
+    /// Link bare URLs (default true)
+    #[serde(default)]
+    pub enabled: Option<bool>,
+
+    /// `target` attribute of the links (default "_blank"; "" opens them in
+    /// the same tab)
+    #[serde(default)]
+    pub target: Option<String>,
+
+    /// `rel` attribute of the links (e.g. "noopener noreferrer"; default none)
+    #[serde(default)]
+    pub rel: Option<String>,
+
+    /// URLs left as text, as patterns where `*` matches any characters
+    /// (e.g. "http://localhost*", "https://*.internal.example.com/*")
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl AutolinkConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    /// Regex matching the excluded URLs; None when nothing is excluded
+    pub fn exclude_regex(&self) -> Option<regex::Regex> {
+        if self.exclude.is_empty() {
+            return None;
+        }
+        let patterns: Vec<String> = self
+            .exclude
+            .iter()
+            .map(|pattern| format!("^{}$", regex::escape(pattern).replace(r"\*", ".*")))
+            .collect();
+        regex::Regex::new(&patterns.join("|")).ok()
+    }
+}
+
+/// `externalLinks` section of book.json: links to other sites in page content
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct ExternalLinksConfig {
+    /// Mark external links with an icon after the link text
+    #[serde(default)]
+    pub icon: bool,
+
+    /// `target` of external links that don't set one (default "_blank"; ""
+    /// leaves them in the same tab)
+    #[serde(default)]
+    pub target: Option<String>,
+
+    /// `rel` of external links that don't set a target (default
+    /// "noopener noreferrer"; "" for none)
+    #[serde(default)]
+    pub rel: Option<String>,
+}
+
+/// `languages` section of book.json (multi-language books)
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct LanguagesConfig {
+    /// Language (LANGS.md code) published at the output root instead of in
+    /// its directory; empty publishes every language in its directory under
+    /// a language selection page
+    #[serde(default)]
+    pub default: String,
+
+    /// Output directory of languages whose URL isn't their code (e.g. {"jp": "ja"})
+    #[serde(default)]
+    pub paths: HashMap<String, String>,
+}
+
+impl LanguagesConfig {
+    /// Output directory of a language, "" for the default language
+    pub fn output_dir(&self, code: &str) -> String {
+        if code == self.default {
+            return String::new();
+        }
+        match self.paths.get(code) {
+            Some(path) => path.trim_matches('/').to_string(),
+            None => code.to_string(),
+        }
+    }
+}
+
+/// `search` section of book.json
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct SearchConfig {
+    /// Key that focuses the search box (default "/"; empty disables the shortcut)
+    #[serde(default)]
+    pub shortcut: Option<String>,
+
+    /// Number of recent queries the browser remembers (default 5; 0 disables the history)
+    #[serde(default)]
+    pub history: Option<usize>,
+
+    /// Search with Algolia DocSearch instead of the built-in search
+    #[serde(default)]
+    pub algolia: Option<AlgoliaConfig>,
+
+    /// Search a self-hosted Meilisearch instance instead of the index in the browser
+    #[serde(default)]
+    pub meilisearch: Option<SearchServerConfig>,
+
+    /// Search a self-hosted Typesense instance instead of the index in the browser
+    #[serde(default)]
+    pub typesense: Option<SearchServerConfig>,
+}
+
+/// `search.algolia` section of book.json
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlgoliaConfig {
+    pub app_id: String,
+    /// Search-only API key, public in every page
+    pub api_key: String,
+    /// Index searched (`<indexName>_<code>` for each language of a multi-language book)
+    pub index_name: String,
+    /// Environment variable holding the admin API key `build --push-search`
+    /// writes records with (default ALGOLIA_ADMIN_KEY)
+    #[serde(default)]
+    pub admin_key_env: Option<String>,
+}
+
+impl AlgoliaConfig {
+    /// Index of a language of a multi-language book, or the book's
+    pub fn index(&self, language: Option<&str>) -> String {
+        match language {
+            Some(code) => format!("{}_{}", self.index_name, code),
+            None => self.index_name.clone(),
+        }
+    }
+}
+
+/// `search.meilisearch` and `search.typesense` sections of book.json
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchServerConfig {
+    /// Base URL of the instance (e.g. "https://search.example.com")
+    pub url: String,
+    /// Search-only API key, public in every page
+    pub api_key: String,
+    /// Index (Meilisearch) or collection alias (Typesense) searched;
+    /// `<indexName>_<code>` for each language of a multi-language book
+    pub index_name: String,
+    /// Environment variable holding the admin API key `build --push-search`
+    /// writes documents with (default MEILISEARCH_ADMIN_KEY or TYPESENSE_ADMIN_KEY)
+    #[serde(default)]
+    pub admin_key_env: Option<String>,
+}
+
+impl SearchServerConfig {
+    /// Index of a language of a multi-language book, or the book's
+    pub fn index(&self, language: Option<&str>) -> String {
+        match language {
+            Some(code) => format!("{}_{}", self.index_name, code),
+            None => self.index_name.clone(),
+        }
+    }
+}
+
+/// `sidebar` section of book.json
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct SidebarConfig {
+    /// Write the navigation once to `gitbook/nav.js` instead of into every
+    /// page, for books with many chapters
+    #[serde(default)]
+    pub external: bool,
+}
+
+/// `budget` section of book.json: sizes the build output shouldn't exceed
+/// (a warning each, so `build --strict` fails)
+#[derive(Debug, Clone, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetConfig {
+    /// Largest HTML page
+    #[serde(default)]
+    pub max_page_size: Option<ByteSize>,
+
+    /// Largest other file (images, downloads, search index, ...)
+    #[serde(default)]
+    pub max_asset_size: Option<ByteSize>,
+
+    /// Whole output directory
+    #[serde(default)]
+    pub max_total_size: Option<ByteSize>,
+}
+
+/// A size in bytes, written in book.json as a number of bytes or with a
+/// unit: "500 KB", "5MB", "1 GB" (multiples of 1024)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    pub fn parse(text: &str) -> Option<ByteSize> {
+        let text = text.trim();
+        let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+        let (number, unit) = text.split_at(split);
+        let number: f64 = number.parse().ok()?;
+        let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+            "" | "B" => 1,
+            "KB" | "K" => 1 << 10,
+            "MB" | "M" => 1 << 20,
+            "GB" | "G" => 1 << 30,
+            _ => return None,
+        };
+        Some(ByteSize((number * multiplier as f64) as u64))
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Bytes(u64),
+            Text(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Bytes(bytes) => Ok(ByteSize(bytes)),
+            Raw::Text(text) => ByteSize::parse(&text)
+                .ok_or_else(|| serde::de::Error::custom(format!("invalid size {:?} (expected e.g. \"500 KB\")", text))),
+        }
+    }
+}
+
+/// `robots` section of book.json: rules of the robots.txt written to the output root
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct RobotsConfig {
+    /// Crawlers the rules apply to (default "*")
+    #[serde(default, rename = "userAgent")]
+    pub user_agent: Option<String>,
+
+    /// Paths crawlers may fetch within disallowed ones (e.g. "/drafts/public/")
+    #[serde(default)]
+    pub allow: Vec<String>,
+
+    /// Paths crawlers shouldn't fetch (e.g. "/drafts/"); none allows everything
+    #[serde(default)]
+    pub disallow: Vec<String>,
+}
+
+/// `pwa` section of book.json: an installable book, readable offline
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct PwaConfig {
+    /// App name (default the book title)
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Name under the home screen icon (default the app name)
+    #[serde(default, rename = "shortName")]
+    pub short_name: Option<String>,
+
+    /// Color of the browser UI around the book (e.g. "#336699")
+    #[serde(default, rename = "themeColor")]
+    pub theme_color: Option<String>,
+
+    /// Color of the splash screen while the book loads
+    #[serde(default, rename = "backgroundColor")]
+    pub background_color: Option<String>,
+
+    /// Book paths of the app icons (PNG, JPEG, WebP or SVG; 192x192 and 512x512 are expected)
+    #[serde(default)]
+    pub icons: Vec<String>,
+}
+
+/// `icons` section of book.json: icons of the browser tab and the home screen
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct IconsConfig {
+    /// Book path of the favicon (ICO, PNG, SVG or GIF; default the bundled one)
+    #[serde(default)]
+    pub favicon: Option<String>,
+
+    /// Book path of the iOS home screen icon (PNG, 180x180 is expected)
+    #[serde(default, rename = "touchIcon")]
+    pub touch_icon: Option<String>,
+}
+
+/// `socialCards` section of book.json: a preview image rendered for each page
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct SocialCardsConfig {
+    /// Background color (default "#1f2d3d")
+    #[serde(default)]
+    pub background: Option<String>,
+
+    /// Text color (default "#ffffff")
+    #[serde(default)]
+    pub color: Option<String>,
+
+    /// Book path of a background image (PNG, JPEG or SVG), covering the card
+    #[serde(default)]
+    pub image: Option<String>,
+
+    /// Book path of the font file of the text (default a sans-serif font of the system)
+    #[serde(default)]
+    pub font: Option<String>,
+
+    /// Show the page's first diagram (an SVG image, or a Mermaid diagram
+    /// rendered with mermaid-cli when `mmdc` is installed)
+    #[serde(default)]
+    pub diagram: bool,
+}
+
+/// `twitter` section of book.json: card tags for link previews
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct TwitterConfig {
+    /// Card type (default "summary_large_image" for pages with an image, else "summary")
+    #[serde(default)]
+    pub card: Option<String>,
+
+    /// Account of the site (e.g. "@acme")
+    #[serde(default)]
+    pub site: Option<String>,
+
+    /// Image of pages without an `image` in front matter: a URL, or a
+    /// book-relative path published under `deploy.siteUrl`
+    #[serde(default)]
+    pub image: Option<String>,
+}
+
+/// `deploy` section of book.json
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct DeployConfig {
+    /// Public URL the output directory is served from
+    #[serde(default, rename = "siteUrl")]
+    pub site_url: String,
+
+    /// IndexNow key used to notify search engines of changed pages
+    #[serde(default, rename = "indexNowKey")]
+    pub index_now_key: String,
+
+    /// IndexNow endpoint (defaults to https://api.indexnow.org/indexnow)
+    #[serde(default, rename = "indexNowEndpoint")]
+    pub index_now_endpoint: String,
+
+    /// Where `guidebook deploy` uploads the output directory
+    #[serde(default)]
+    pub target: Option<DeployTarget>,
+
+    /// Bucket settings for the `s3` target
+    #[serde(default)]
+    pub s3: S3Config,
+
+    /// Destination for the `rsync` target
+    #[serde(default)]
+    pub rsync: RsyncConfig,
+}
+
+impl DeployConfig {
+    /// Whether `indexNowKey` is a key IndexNow accepts (8 to 128 letters,
+    /// digits and dashes); it also names the verification file
+    pub fn valid_index_now_key(&self) -> bool {
+        let key = &self.index_now_key;
+        (8..=128).contains(&key.len()) && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    }
+}
+
+/// Upload method of `guidebook deploy`
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeployTarget {
+    /// S3 or S3-compatible storage (R2, MinIO, ...)
+    S3,
+    /// rsync, over SSH for remote destinations
+    Rsync,
+}
+
+/// `deploy.s3` section of book.json
+/// Credentials come from AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct S3Config {
+    /// Bucket name
+    #[serde(default)]
+    pub bucket: String,
+
+    /// Region (defaults to us-east-1)
+    #[serde(default)]
+    pub region: String,
+
+    /// Endpoint of S3-compatible storage (defaults to AWS for the region)
+    #[serde(default)]
+    pub endpoint: String,
+
+    /// Key prefix the book is uploaded under (e.g. docs/)
+    #[serde(default)]
+    pub prefix: String,
+}
+
+/// `deploy.rsync` section of book.json
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct RsyncConfig {
+    /// Destination directory, `host:path` or `user@host:path` for SSH
+    #[serde(default)]
+    pub destination: String,
+
+    /// Extra rsync arguments (e.g. ["-e", "ssh -p 2222"])
+    #[serde(default)]
+    pub options: Vec<String>,
 }
 
 impl BookConfig {
@@ -70,11 +754,18 @@ impl BookConfig {
 }
 "#;
             fs::write(&config_path, default_json)?;
-            println!("  Created default book.json");
+            info!("  Created default book.json");
         }
 
-        let content = fs::read_to_string(&config_path)?;
-        let config: BookConfig = serde_json::from_str(&content)?;
+        Self::load_file(&config_path)
+    }
+
+    /// Load a specific config file (e.g. `book.staging.json` given with `--config`)
+    pub fn load_file(config_path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(config_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", config_path.display(), e))?;
+        let config: BookConfig = serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid {}: {}", config_path.display(), e))?;
         Ok(config)
     }
 
@@ -96,10 +787,375 @@ impl BookConfig {
         DEFAULT_ENABLED_PLUGINS.contains(&name)
     }
 
+    /// Set a variable from the command line (`--define key=value`). The value
+    /// is parsed as JSON (numbers, booleans) unless the variable holds a
+    /// string in book.json or the value isn't valid JSON
+    pub fn define_variable(&mut self, key: &str, raw_value: &str) {
+        let keep_string = self.variables.get(key).is_some_and(|v| v.is_string());
+        let value = match serde_json::from_str(raw_value) {
+            Ok(value) if !keep_string => value,
+            _ => serde_json::Value::String(raw_value.to_string()),
+        };
+        self.variables.insert(key.to_string(), value);
+    }
+
     /// Get custom CSS path for website
     pub fn get_website_style(&self) -> Option<&String> {
         self.styles.get("website")
     }
+
+    /// Path from a page `depth` directories below the book root to the root:
+    /// `baseUrl` (with a trailing `/`) when set, relative ("../", "./") otherwise
+    pub fn root_path(&self, depth: usize) -> String {
+        if !self.base_url.is_empty() {
+            format!("{}/", self.base_url.trim_end_matches('/'))
+        } else if depth > 0 {
+            "../".repeat(depth)
+        } else {
+            "./".to_string()
+        }
+    }
+}
+
+/// Result of validating book.json
+#[derive(Debug, Default)]
+pub struct ConfigValidation {
+    /// Problems that make the configuration invalid
+    pub errors: Vec<String>,
+    /// Suspicious but accepted settings (e.g. unknown keys)
+    pub warnings: Vec<String>,
+}
+
+impl ConfigValidation {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Validate book.json content against BOOK_SCHEMA
+pub fn validate_config(content: &str) -> ConfigValidation {
+    let mut validation = ConfigValidation::default();
+
+    let value: serde_json::Value = match serde_json::from_str(content) {
+        Ok(v) => v,
+        Err(e) => {
+            validation.errors.push(format!("Invalid JSON: {}", e));
+            return validation;
+        }
+    };
+
+    let schema: serde_json::Value = serde_json::from_str(BOOK_SCHEMA).expect("book.schema.json is valid JSON");
+    check_schema(&value, &schema, "", &mut validation.errors);
+
+    // Unknown top-level keys are allowed (HonKit configs carry many), but worth flagging
+    if let (Some(obj), Some(known)) = (value.as_object(), schema["properties"].as_object()) {
+        for key in obj.keys() {
+            if !known.contains_key(key) {
+                validation.warnings.push(format!("Unknown key `{}` (ignored by guidebook)", key));
+            }
+        }
+    }
+
+    // Anything the schema missed will still surface when deserializing
+    if validation.errors.is_empty() {
+        match serde_json::from_value::<BookConfig>(value) {
+            Ok(config) => {
+                if !config.deploy.index_now_key.is_empty() && !config.deploy.valid_index_now_key() {
+                    validation.errors.push("`deploy.indexNowKey` must be 8 to 128 letters, digits or dashes".to_string());
+                }
+                if config.search.meilisearch.is_some() && config.search.typesense.is_some() {
+                    validation.errors.push("`search.meilisearch` and `search.typesense` are both set; keep one".to_string());
+                }
+            }
+            Err(e) => validation.errors.push(e.to_string()),
+        }
+    }
+
+    validation
+}
+
+/// Check `value` against the subset of JSON Schema used by BOOK_SCHEMA
+/// (type, properties, items, additionalProperties)
+fn check_schema(value: &serde_json::Value, schema: &serde_json::Value, path: &str, errors: &mut Vec<String>) {
+    use serde_json::Value;
+
+    if let Some(expected) = schema.get("type").and_then(|t| t.as_str()) {
+        let matches = match expected {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "boolean" => value.is_boolean(),
+            "number" => value.is_number(),
+            "integer" => value.is_i64() || value.is_u64(),
+            _ => true,
+        };
+        if !matches {
+            let name = if path.is_empty() { "book.json" } else { path };
+            errors.push(format!("`{}` must be of type {}, found {}", name, expected, json_type_name(value)));
+            return;
+        }
+    }
+
+    match value {
+        Value::Object(obj) => {
+            let properties = schema.get("properties").and_then(|p| p.as_object());
+            for (key, child) in obj {
+                let child_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                if let Some(child_schema) = properties.and_then(|p| p.get(key)) {
+                    check_schema(child, child_schema, &child_path, errors);
+                } else if let Some(additional) = schema.get("additionalProperties").filter(|a| a.is_object()) {
+                    check_schema(child, additional, &child_path, errors);
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    check_schema(item, item_schema, &format!("{}[{}]", path, i), errors);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Look up a dotted key (e.g. `styles.website`) in parsed book.json
+pub fn get_config_value<'a>(root: &'a serde_json::Value, key: &str) -> Option<&'a serde_json::Value> {
+    key.split('.').try_fold(root, |value, part| value.get(part))
+}
+
+/// Set a dotted key in book.json content, returning the updated file content
+///
+/// `raw_value` is stored as a string when the key holds (or the schema expects)
+/// a string; otherwise it is parsed as JSON, falling back to a string.
+/// Only the text of the value changes (or, for a new key, the member added to
+/// the end of its object), so the rest of the file keeps its formatting.
+pub fn set_config_value(content: &str, key: &str, raw_value: &str) -> Result<String> {
+    use serde_json::Value;
+
+    let root: Value = serde_json::from_str(content)?;
+    let parts: Vec<&str> = key.split('.').filter(|p| !p.is_empty()).collect();
+    if parts.is_empty() {
+        anyhow::bail!("Empty config key");
+    }
+
+    let schema: Value = serde_json::from_str(BOOK_SCHEMA).expect("book.schema.json is valid JSON");
+    let expects_string = get_config_value(&root, key).map(|v| v.is_string()).unwrap_or(false)
+        || schema_type_for_key(&schema, &parts) == Some("string");
+    let new_value = if expects_string {
+        Value::String(raw_value.to_string())
+    } else {
+        serde_json::from_str(raw_value).unwrap_or_else(|_| Value::String(raw_value.to_string()))
+    };
+
+    let edit = JsonEdit { content, indent: detect_indent(content) };
+
+    // Walk down the objects of the key in the text
+    let mut object = skip_whitespace(content, 0);
+    for (i, part) in parts.iter().enumerate() {
+        if !content[object..].starts_with('{') {
+            anyhow::bail!("`{}` is not an object", parts[..i].join("."));
+        }
+        let members = object_members(content, object);
+        // The last of duplicate keys is the one read
+        let Some(member) = members.iter().rev().find(|m| m.key == *part) else {
+            // The rest of the key becomes a new member, with intermediate objects
+            let value = parts[i + 1..].iter().rev().fold(new_value, |value, part| serde_json::json!({ *part: value }));
+            return edit.add_member(object, &members, part, &value);
+        };
+        if i == parts.len() - 1 {
+            return edit.replace(object, member, &new_value);
+        }
+        object = member.value.start;
+    }
+    unreachable!("the key has at least one part")
+}
+
+/// Text of book.json being edited
+struct JsonEdit<'a> {
+    content: &'a str,
+    /// Indentation step of the file
+    indent: String,
+}
+
+impl JsonEdit<'_> {
+    /// JSON of `value` starting on a line indented by `line_indent`; on a
+    /// single line in objects written on a single line
+    fn serialize(&self, value: &serde_json::Value, line_indent: &str, multiline: bool) -> Result<String> {
+        if !multiline {
+            return Ok(value.to_string());
+        }
+        Ok(to_json_with_indent(value, &self.indent)?.replace('\n', &format!("\n{}", line_indent)))
+    }
+
+    /// Whether the object starting at `object` spans several lines
+    fn is_multiline(&self, object: usize) -> bool {
+        self.content[object..value_end(self.content, object)].contains('\n')
+    }
+
+    /// The content with the value of `member` of the object starting at
+    /// `object` replaced
+    fn replace(&self, object: usize, member: &JsonMember, value: &serde_json::Value) -> Result<String> {
+        let content = self.content;
+        let value = self.serialize(value, line_indent(content, member.start), self.is_multiline(object))?;
+        Ok(format!("{}{}{}", &content[..member.value.start], value, &content[member.value.end..]))
+    }
+
+    /// The content with `key` added after the last of the `members` of the
+    /// object starting at `object`
+    fn add_member(&self, object: usize, members: &[JsonMember], key: &str, value: &serde_json::Value) -> Result<String> {
+        let content = self.content;
+        let key = serde_json::to_string(key)?;
+        let object_indent = line_indent(content, object);
+        let Some(first) = members.first() else {
+            // An empty object is rewritten, its whitespace included, over
+            // several lines unless the whole file is on one
+            let end = value_end(content, object);
+            let object_text = if content.trim_end().contains('\n') {
+                let member_indent = format!("{}{}", object_indent, self.indent);
+                format!("{{\n{}{}: {}\n{}}}", member_indent, key, self.serialize(value, &member_indent, true)?, object_indent)
+            } else {
+                format!("{{{}: {}}}", key, self.serialize(value, "", false)?)
+            };
+            return Ok(format!("{}{}{}", &content[..object], object_text, &content[end..]));
+        };
+        // The whitespace between members, or before the first one
+        let multiline = self.is_multiline(object);
+        let whitespace = match members.get(1) {
+            Some(second) => content[first.value.end..second.start].split_once(',').map_or("", |(_, after)| after),
+            None if multiline => &content[object + 1..first.start],
+            None => " ",
+        };
+        let member_indent = whitespace.rsplit_once('\n').map_or(object_indent, |(_, indent)| indent);
+        let last = members[members.len() - 1].value.end;
+        let value = self.serialize(value, member_indent, multiline)?;
+        Ok(format!("{},{}{}: {}{}", &content[..last], whitespace, key, value, &content[last..]))
+    }
+}
+
+/// A member of a JSON object in the text of book.json
+struct JsonMember {
+    key: String,
+    /// Offset of the key
+    start: usize,
+    /// Offsets of the value
+    value: std::ops::Range<usize>,
+}
+
+/// Members of the JSON object starting at `start` of valid JSON text
+fn object_members(content: &str, start: usize) -> Vec<JsonMember> {
+    let bytes = content.as_bytes();
+    let mut members = Vec::new();
+    let mut pos = skip_whitespace(content, start + 1);
+    while bytes.get(pos) == Some(&b'"') {
+        let key_end = string_end(bytes, pos);
+        let key = serde_json::from_str(&content[pos..key_end]).unwrap_or_default();
+        // Past the colon
+        let value_start = skip_whitespace(content, skip_whitespace(content, key_end) + 1);
+        let end = value_end(content, value_start);
+        members.push(JsonMember { key, start: pos, value: value_start..end });
+        pos = skip_whitespace(content, end);
+        if bytes.get(pos) != Some(&b',') {
+            break;
+        }
+        pos = skip_whitespace(content, pos + 1);
+    }
+    members
+}
+
+/// Offset of the first non-whitespace character at or after `pos`
+fn skip_whitespace(content: &str, pos: usize) -> usize {
+    content[pos..].find(|c: char| !c.is_whitespace()).map_or(content.len(), |i| pos + i)
+}
+
+/// End offset of the JSON value starting at `start`
+fn value_end(content: &str, start: usize) -> usize {
+    let bytes = content.as_bytes();
+    match bytes[start] {
+        b'"' => string_end(bytes, start),
+        b'{' | b'[' => {
+            let mut depth = 0;
+            let mut i = start;
+            loop {
+                match bytes[i] {
+                    b'"' => {
+                        i = string_end(bytes, i);
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return i + 1;
+                        }
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+        }
+        _ => content[start..].find(|c: char| matches!(c, ',' | '}' | ']') || c.is_whitespace()).map_or(content.len(), |i| start + i),
+    }
+}
+
+/// End offset of the JSON string starting at `start`
+fn string_end(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while bytes[i] != b'"' {
+        i += if bytes[i] == b'\\' { 2 } else { 1 };
+    }
+    i + 1
+}
+
+/// Indentation of the line `pos` is on
+fn line_indent(content: &str, pos: usize) -> &str {
+    let line = &content[content[..pos].rfind('\n').map_or(0, |i| i + 1)..];
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Resolve the schema `type` for a dotted key path
+fn schema_type_for_key<'a>(schema: &'a serde_json::Value, parts: &[&str]) -> Option<&'a str> {
+    let mut node = schema;
+    for part in parts {
+        node = node
+            .get("properties")
+            .and_then(|p| p.get(*part))
+            .or_else(|| node.get("additionalProperties").filter(|a| a.is_object()))?;
+    }
+    node.get("type").and_then(|t| t.as_str())
+}
+
+/// Indentation used by the first indented line of a JSON file (default: 4 spaces)
+fn detect_indent(content: &str) -> String {
+    content
+        .lines()
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("    ")
+        .to_string()
+}
+
+fn to_json_with_indent(value: &serde_json::Value, indent: &str) -> Result<String> {
+    use serde::Serialize;
+
+    let mut buf = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut buf, formatter);
+    value.serialize(&mut serializer)?;
+    Ok(String::from_utf8(buf)?)
+}
+
+fn json_type_name(value: &serde_json::Value) -> &'static str {
+    use serde_json::Value;
+
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
 }
 
 #[cfg(test)]
@@ -180,6 +1236,30 @@ mod tests {
         assert_eq!(config.variables.get("year").unwrap(), 2024);
     }
 
+    #[test]
+    fn test_define_variable() {
+        let mut config: BookConfig = serde_json::from_str(r#"{"variables": {"version": "1.0"}}"#).unwrap();
+        config.define_variable("version", "2.0");
+        config.define_variable("api_host", "staging.example.com");
+        config.define_variable("beta", "true");
+        config.define_variable("retries", "3");
+        assert_eq!(config.variables["version"], "2.0");
+        assert_eq!(config.variables["api_host"], "staging.example.com");
+        assert_eq!(config.variables["beta"], true);
+        assert_eq!(config.variables["retries"], 3);
+    }
+
+    #[test]
+    fn test_root_path() {
+        let mut config = BookConfig::default();
+        assert_eq!(config.root_path(0), "./");
+        assert_eq!(config.root_path(2), "../../");
+        config.base_url = "/docs/mybook".to_string();
+        assert_eq!(config.root_path(2), "/docs/mybook/");
+        config.base_url = "https://example.com/docs/".to_string();
+        assert_eq!(config.root_path(0), "https://example.com/docs/");
+    }
+
     #[test]
     fn test_empty_variables() {
         let json = r#"{"title": "Test"}"#;
@@ -214,4 +1294,147 @@ mod tests {
         let config: BookConfig = serde_json::from_str(json).unwrap();
         assert!(!config.fetch_remote_images);
     }
+
+    #[test]
+    fn test_schema_covers_all_options() {
+        let json = r#"{
+            "$schema": "./book.schema.json",
+            "title": "Test",
+            "plugins": ["fontsettings", "-mermaid-md-adoc"],
+            "pluginsConfig": {"fontsettings": {}},
+            "styles": {"website": "styles/website.css"},
+            "variables": {"version": "1.0"},
+            "hardbreaks": true,
+            "math": true,
+            "externalize_svg": false,
+            "inline_svg": false,
+            "fetchRemoteImages": true
+        }"#;
+
+        let validation = validate_config(json);
+        assert!(validation.is_valid(), "{:?}", validation.errors);
+        assert!(validation.warnings.is_empty(), "{:?}", validation.warnings);
+    }
+
+    #[test]
+    fn test_validate_reports_type_errors() {
+        let validation = validate_config(r#"{"title": 3, "plugins": ["ok", 5], "styles": {"pdf": true}}"#);
+        assert!(!validation.is_valid());
+        assert!(validation.errors.iter().any(|e| e.contains("`title` must be of type string")));
+        assert!(validation.errors.iter().any(|e| e.contains("`plugins[1]` must be of type string")));
+        assert!(validation.errors.iter().any(|e| e.contains("`styles.pdf` must be of type string")));
+    }
+
+    #[test]
+    fn test_validate_warns_on_unknown_keys() {
+        let validation = validate_config(r#"{"title": "Test", "gitbook": "3.x"}"#);
+        assert!(validation.is_valid());
+        assert_eq!(validation.warnings.len(), 1);
+        assert!(validation.warnings[0].contains("gitbook"));
+    }
+
+    #[test]
+    fn test_validate_index_now_key() {
+        assert!(validate_config(r#"{"deploy": {"indexNowKey": "0123456789abcdef-ABC"}}"#).is_valid());
+        for key in ["short", "../../index", "0123456789abcdef.html"] {
+            let validation = validate_config(&format!(r#"{{"deploy": {{"indexNowKey": "{}"}}}}"#, key));
+            assert!(validation.errors[0].contains("deploy.indexNowKey"), "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_two_search_servers() {
+        let validation = validate_config(concat!(
+            r#"{"search": {"meilisearch": {"url": "https://a.example.com", "apiKey": "a", "indexName": "docs"},"#,
+            r#" "typesense": {"url": "https://b.example.com", "apiKey": "b", "indexName": "docs"}}}"#
+        ));
+        assert!(validation.errors[0].contains("`search.meilisearch` and `search.typesense`"));
+    }
+
+    #[test]
+    fn test_validate_invalid_json() {
+        let validation = validate_config(r#"{"title": "Test",}"#);
+        assert!(!validation.is_valid());
+        assert!(validation.errors[0].starts_with("Invalid JSON"));
+    }
+
+    #[test]
+    fn test_get_config_value() {
+        let root: serde_json::Value = serde_json::from_str(
+            r#"{"title": "Test", "styles": {"website": "a.css"}}"#,
+        ).unwrap();
+        assert_eq!(get_config_value(&root, "title").unwrap(), "Test");
+        assert_eq!(get_config_value(&root, "styles.website").unwrap(), "a.css");
+        assert!(get_config_value(&root, "styles.pdf").is_none());
+    }
+
+    #[test]
+    fn test_set_config_value_preserves_order_and_indent() {
+        let content = "{\n  \"title\": \"Old\",\n  \"plugins\": [\"fontsettings\"]\n}\n";
+        let updated = set_config_value(content, "title", "API Spec v3").unwrap();
+        assert_eq!(updated, "{\n  \"title\": \"API Spec v3\",\n  \"plugins\": [\"fontsettings\"]\n}\n");
+    }
+
+    #[test]
+    fn test_set_config_value_adds_members() {
+        let content = "{\n  \"title\": \"Test\",\n  \"plugins\": [\"fontsettings\"],\n  \"variables\": {}\n}\n";
+        assert_eq!(
+            set_config_value(content, "math", "true").unwrap(),
+            "{\n  \"title\": \"Test\",\n  \"plugins\": [\"fontsettings\"],\n  \"variables\": {},\n  \"math\": true\n}\n"
+        );
+        assert_eq!(
+            set_config_value(content, "variables.version", "1.0").unwrap(),
+            "{\n  \"title\": \"Test\",\n  \"plugins\": [\"fontsettings\"],\n  \"variables\": {\n    \"version\": 1.0\n  }\n}\n"
+        );
+        assert_eq!(
+            set_config_value(content, "deploy.s3.bucket", "docs").unwrap(),
+            concat!(
+                "{\n  \"title\": \"Test\",\n  \"plugins\": [\"fontsettings\"],\n  \"variables\": {},\n",
+                "  \"deploy\": {\n    \"s3\": {\n      \"bucket\": \"docs\"\n    }\n  }\n}\n"
+            )
+        );
+        // One-line files stay on one line
+        assert_eq!(set_config_value(r#"{"title": "Test"}"#, "styles.website", "a.css").unwrap(), r#"{"title": "Test", "styles": {"website":"a.css"}}"#);
+        assert_eq!(set_config_value("{}", "title", "Test").unwrap(), r#"{"title": "Test"}"#);
+    }
+
+    #[test]
+    fn test_set_config_value_types() {
+        let content = r#"{"title": "Test", "variables": {"version": "1.0"}}"#;
+
+        // Booleans are parsed as JSON
+        let updated = set_config_value(content, "math", "true").unwrap();
+        let config: BookConfig = serde_json::from_str(&updated).unwrap();
+        assert!(config.math);
+
+        // Existing string values stay strings even if they look like numbers
+        let updated = set_config_value(content, "variables.version", "1.10").unwrap();
+        let config: BookConfig = serde_json::from_str(&updated).unwrap();
+        assert_eq!(config.variables.get("version").unwrap(), "1.10");
+
+        // Schema string fields stay strings
+        let updated = set_config_value(content, "styles.website", "123").unwrap();
+        let config: BookConfig = serde_json::from_str(&updated).unwrap();
+        assert_eq!(config.get_website_style(), Some(&"123".to_string()));
+    }
+
+    #[test]
+    fn test_set_config_value_rejects_non_object_parent() {
+        let content = r#"{"title": "Test"}"#;
+        assert!(set_config_value(content, "title.sub", "x").is_err());
+    }
+
+    #[test]
+    fn test_budget_sizes() {
+        assert_eq!(ByteSize::parse("500 KB"), Some(ByteSize(512_000)));
+        assert_eq!(ByteSize::parse("1.5mb"), Some(ByteSize(1_572_864)));
+        assert_eq!(ByteSize::parse("2048"), Some(ByteSize(2048)));
+        assert_eq!(ByteSize::parse("5 TB"), None);
+
+        let config: BookConfig = serde_json::from_str(r#"{"budget": {"maxPageSize": 200000, "maxTotalSize": "200 MB"}}"#).unwrap();
+        assert_eq!(config.budget.max_page_size, Some(ByteSize(200_000)));
+        assert_eq!(config.budget.max_asset_size, None);
+        assert_eq!(config.budget.max_total_size, Some(ByteSize(200 << 20)));
+        assert!(validate_config(r#"{"budget": {"maxAssetSize": "lots"}}"#).errors[0].contains("invalid size"));
+    }
 }
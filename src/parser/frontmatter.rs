@@ -10,7 +10,16 @@
 //! # Content
 //! ```
 
+use crate::{paths, warning};
+use anyhow::Result;
 use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of per-directory front matter defaults
+pub const DEFAULTS_FILE_NAME: &str = "_defaults.yaml";
 
 /// Front matter metadata extracted from markdown files
 #[derive(Debug, Clone, Default, Deserialize)]
@@ -23,10 +32,87 @@ pub struct FrontMatter {
     #[serde(default)]
     pub description: Option<String>,
 
-    /// Additional custom fields (for extensibility)
+    /// Set to false to skip Nunjucks template processing for this page
+    #[serde(default)]
+    pub templating: Option<bool>,
+
+    /// Publish each section at this heading level as its own page (e.g. "h2")
+    #[serde(default)]
+    pub split: Option<String>,
+
+    /// Page template to render the page with (`_templates/<name>.md`)
+    #[serde(default)]
+    pub template: Option<String>,
+
+    /// Sort key among sibling pages generated from a SUMMARY.md glob
+    /// (lower first; `order` is accepted as an alias)
+    #[serde(default, alias = "order")]
+    pub weight: Option<i64>,
+
+    /// Content of the page's robots meta tag (e.g. "noindex" or
+    /// "noindex, nofollow"); noindex pages are left out of the search index
+    #[serde(default)]
+    pub robots: Option<String>,
+
+    /// Render single line breaks as `<br>`, overriding `hardbreaks` of book.json
+    #[serde(default)]
+    pub hardbreaks: Option<bool>,
+
+    /// Image of the page's Twitter card (a URL, or a book-relative path)
+    #[serde(default)]
+    pub image: Option<String>,
+
+    /// Old paths of the page, published as redirects to it (a path or a list)
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub redirect_from: Vec<String>,
+
+    /// Additional custom fields (for extensibility, and page templates)
     #[serde(flatten)]
-    #[allow(dead_code)]
-    pub extra: std::collections::HashMap<String, serde_yaml::Value>,
+    pub extra: HashMap<String, Value>,
+}
+
+impl FrontMatter {
+    /// Whether Nunjucks template processing is enabled for this page (default: true)
+    pub fn templating_enabled(&self) -> bool {
+        self.templating.unwrap_or(true)
+    }
+
+    /// Whether search engines are asked not to index the page (`robots: noindex`)
+    pub fn is_noindex(&self) -> bool {
+        self.robots.as_deref().is_some_and(|robots| {
+            robots.split(',').any(|directive| matches!(directive.trim().to_ascii_lowercase().as_str(), "noindex" | "none"))
+        })
+    }
+
+    /// Fields passed to page templates as `page`: custom fields plus title and description
+    pub fn template_fields(&self) -> serde_json::Value {
+        let mut fields: serde_json::Map<String, serde_json::Value> = self
+            .extra
+            .iter()
+            .filter_map(|(key, value)| Some((key.clone(), serde_json::to_value(value).ok()?)))
+            .collect();
+        if let Some(title) = &self.title {
+            fields.insert("title".to_string(), title.clone().into());
+        }
+        if let Some(description) = &self.description {
+            fields.insert("description".to_string(), description.clone().into());
+        }
+        serde_json::Value::Object(fields)
+    }
+}
+
+/// A single string or a list of strings
+fn one_or_many<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match Raw::deserialize(deserializer)? {
+        Raw::One(value) => vec![value],
+        Raw::Many(values) => values,
+    })
 }
 
 /// Result of parsing front matter from markdown content
@@ -39,6 +125,17 @@ pub struct ParsedContent {
     pub content: String,
 }
 
+impl ParsedContent {
+    /// Number of source lines that precede `content` (the front matter block)
+    pub fn body_line_offset(&self, raw: &str) -> usize {
+        raw.len()
+            .checked_sub(self.content.len())
+            .and_then(|start| raw.get(..start))
+            .map(|prefix| prefix.matches('\n').count())
+            .unwrap_or(0)
+    }
+}
+
 /// Parse front matter from markdown content
 ///
 /// Front matter must be at the very beginning of the file and enclosed by `---` delimiters.
@@ -62,49 +159,85 @@ pub struct ParsedContent {
 /// assert_eq!(fm.title.as_deref(), Some("My Page"));
 /// assert_eq!(fm.description.as_deref(), Some("This is my page"));
 /// ```
+#[allow(dead_code)]
 pub fn parse_front_matter(content: &str) -> ParsedContent {
-    // Check if content starts with front matter delimiter
-    let trimmed = content.trim_start();
-    if !trimmed.starts_with("---") {
-        return ParsedContent {
-            front_matter: None,
-            content: content.to_string(),
-        };
-    }
+    parse_front_matter_with_defaults(content, &Mapping::new())
+}
 
-    // Find the end of the opening delimiter line
-    let after_opening = match trimmed.strip_prefix("---") {
-        Some(rest) => rest,
+/// Parse front matter from markdown content, filling in keys missing from the
+/// page's own front matter with `defaults` (see [`FrontMatterDefaults`])
+pub fn parse_front_matter_with_defaults(content: &str, defaults: &Mapping) -> ParsedContent {
+    let (yaml_content, remaining) = match split_front_matter(content) {
+        Some(parts) => parts,
         None => {
+            // No front matter block: the page still inherits any defaults
+            let front_matter = if defaults.is_empty() {
+                None
+            } else {
+                serde_yaml::from_value(Value::Mapping(defaults.clone())).ok()
+            };
             return ParsedContent {
+                front_matter,
+                content: content.to_string(),
+            };
+        }
+    };
+
+    // Parse the YAML content and merge it over the defaults
+    let front_matter = serde_yaml::from_str::<Value>(yaml_content)
+        .ok()
+        .and_then(|value| match value {
+            Value::Null => Some(defaults.clone()),
+            Value::Mapping(page) => Some(merge_mappings(defaults, page)),
+            _ => None,
+        })
+        .and_then(|merged| serde_yaml::from_value::<FrontMatter>(Value::Mapping(merged)).ok());
+
+    match front_matter {
+        Some(fm) => ParsedContent {
+            front_matter: Some(fm),
+            content: remaining.to_string(),
+        },
+        None => {
+            // YAML parsing failed, return original content
+            ParsedContent {
                 front_matter: None,
                 content: content.to_string(),
             }
         }
-    };
+    }
+}
+
+/// Locate the front matter block, returning (yaml, remaining content)
+fn split_front_matter(content: &str) -> Option<(&str, &str)> {
+    // Check if content starts with front matter delimiter
+    let trimmed = content.trim_start();
+    if !trimmed.starts_with("---") {
+        return None;
+    }
+
+    // Find the end of the opening delimiter line
+    let after_opening = trimmed.strip_prefix("---")?;
 
     // Skip any whitespace/newline after opening ---
     let after_opening = after_opening.trim_start_matches([' ', '\t']);
-    let after_opening = if after_opening.starts_with('\n') {
-        &after_opening[1..]
-    } else if after_opening.starts_with("\r\n") {
-        &after_opening[2..]
+    let after_opening = if let Some(rest) = after_opening.strip_prefix('\n') {
+        rest
+    } else if let Some(rest) = after_opening.strip_prefix("\r\n") {
+        rest
     } else if after_opening.is_empty() {
         after_opening
     } else {
         // Something unexpected after ---, not valid front matter
-        return ParsedContent {
-            front_matter: None,
-            content: content.to_string(),
-        };
+        return None;
     };
 
     // Find the closing ---
     // First, check if the content starts with --- (empty front matter case)
-    let (yaml_content, remaining) = if after_opening.starts_with("---\n") {
-        ("", &after_opening[4..])
-    } else if after_opening.starts_with("---\r\n") {
-        ("", &after_opening[5..])
+    let (yaml_content, remaining) = if let Some(rest) = after_opening.strip_prefix("---\n") {
+        ("", rest)
+    } else if let Some(rest) = after_opening.strip_prefix("---\r\n") {
+        ("", rest)
     } else if after_opening == "---" {
         ("", "")
     } else {
@@ -131,27 +264,107 @@ pub fn parse_front_matter(content: &str) -> ParsedContent {
             }
             None => {
                 // No closing delimiter found
-                return ParsedContent {
-                    front_matter: None,
-                    content: content.to_string(),
-                };
+                return None;
             }
         }
     };
 
-    // Parse the YAML content
-    match serde_yaml::from_str::<FrontMatter>(yaml_content) {
-        Ok(fm) => ParsedContent {
-            front_matter: Some(fm),
-            content: remaining.to_string(),
-        },
-        Err(_) => {
-            // YAML parsing failed, return original content
-            ParsedContent {
-                front_matter: None,
-                content: content.to_string(),
+    Some((yaml_content, remaining))
+}
+
+/// Overlay `overrides` on top of `base` (shallow, key by key)
+fn merge_mappings(base: &Mapping, overrides: Mapping) -> Mapping {
+    let mut merged = base.clone();
+    for (key, value) in overrides {
+        merged.insert(key, value);
+    }
+    merged
+}
+
+/// Front matter defaults loaded from `_defaults.yaml` files
+///
+/// A `_defaults.yaml` file applies to every page in its directory and all
+/// subdirectories. Deeper files override shallower ones key by key, and a
+/// page's own front matter overrides them all.
+#[derive(Debug, Default)]
+pub struct FrontMatterDefaults {
+    /// Directory (relative to the book root, "" for the root) -> defaults
+    dirs: HashMap<PathBuf, Mapping>,
+}
+
+impl FrontMatterDefaults {
+    pub fn load(book_dir: &Path) -> Result<Self> {
+        let mut dirs = HashMap::new();
+
+        // Unreadable entries (broken symlinks) are warned about where the book's files are read
+        for entry in paths::walk(book_dir)
+            .into_iter()
+            .filter_entry(|e| {
+                let name = e.file_name().to_string_lossy();
+                name != "_book" && name != "node_modules" && !(e.depth() > 0 && name.starts_with('.'))
+            })
+            .filter_map(Result::ok)
+        {
+            if !entry.file_type().is_file() || entry.file_name() != DEFAULTS_FILE_NAME {
+                continue;
+            }
+
+            let content = fs::read_to_string(entry.path())?;
+            let mapping = match serde_yaml::from_str::<Value>(&content) {
+                Ok(Value::Mapping(mapping)) => mapping,
+                Ok(Value::Null) => Mapping::new(),
+                Ok(_) => {
+                    warning!("{} must contain a YAML mapping", entry.path().display());
+                    continue;
+                }
+                Err(e) => {
+                    warning!("Failed to parse {}: {}", entry.path().display(), e);
+                    continue;
+                }
+            };
+
+            let dir = entry
+                .path()
+                .parent()
+                .and_then(|p| p.strip_prefix(book_dir).ok())
+                .map(Path::to_path_buf)
+                .unwrap_or_default();
+            dirs.insert(dir, mapping);
+        }
+
+        Ok(Self { dirs })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dirs.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.dirs.len()
+    }
+
+    /// Merged defaults for a page, given its path relative to the book root
+    pub fn for_page(&self, page_path: &str) -> Mapping {
+        let mut merged = Mapping::new();
+        if self.dirs.is_empty() {
+            return merged;
+        }
+
+        // Walk from the book root down to the page's directory
+        let mut dir = PathBuf::new();
+        if let Some(root) = self.dirs.get(&dir) {
+            merged = merge_mappings(&merged, root.clone());
+        }
+        if let Some(parent) = Path::new(page_path.trim_start_matches('/')).parent() {
+            for component in parent.components() {
+                dir.push(component);
+                if let Some(defaults) = self.dirs.get(&dir) {
+                    merged = merge_mappings(&merged, defaults.clone());
+                }
             }
         }
+
+        merged
     }
 }
 
@@ -299,4 +512,99 @@ description: Japanese description
         assert_eq!(fm.title.as_deref(), Some("Japanese Title"));
         assert_eq!(fm.description.as_deref(), Some("Japanese description"));
     }
+
+    #[test]
+    fn test_body_line_offset() {
+        let raw = "---\ntitle: Test\n---\n# Heading\n";
+        let parsed = parse_front_matter(raw);
+        assert_eq!(parsed.body_line_offset(raw), 3);
+
+        let raw = "# No front matter\n";
+        let parsed = parse_front_matter(raw);
+        assert_eq!(parsed.body_line_offset(raw), 0);
+    }
+
+    #[test]
+    fn test_parse_templating_switch() {
+        let content = "---\ntemplating: false\n---\n\n{{ literal }}\n";
+        let fm = parse_front_matter(content).front_matter.unwrap();
+        assert!(!fm.templating_enabled());
+
+        let content = "---\ntitle: Test\n---\n\nContent\n";
+        let fm = parse_front_matter(content).front_matter.unwrap();
+        assert!(fm.templating_enabled());
+    }
+
+    #[test]
+    fn test_parse_robots() {
+        let fm = parse_front_matter("---\nrobots: NoIndex, nofollow\n---\n").front_matter.unwrap();
+        assert_eq!(fm.robots.as_deref(), Some("NoIndex, nofollow"));
+        assert!(fm.is_noindex());
+
+        let fm = parse_front_matter("---\nrobots: nofollow\n---\n").front_matter.unwrap();
+        assert!(!fm.is_noindex());
+        assert!(!parse_front_matter("---\ntitle: Test\n---\n").front_matter.unwrap().is_noindex());
+    }
+
+    #[test]
+    fn test_parse_hardbreaks() {
+        assert_eq!(parse_front_matter("---\nhardbreaks: true\n---\n").front_matter.unwrap().hardbreaks, Some(true));
+        assert_eq!(parse_front_matter("---\nhardbreaks: false\n---\n").front_matter.unwrap().hardbreaks, Some(false));
+        assert_eq!(parse_front_matter("---\ntitle: Test\n---\n").front_matter.unwrap().hardbreaks, None);
+    }
+
+    #[test]
+    fn test_parse_redirect_from() {
+        let fm = parse_front_matter("---\nredirect_from:\n  - /old.html\n  - setup/\n---\n").front_matter.unwrap();
+        assert_eq!(fm.redirect_from, vec!["/old.html", "setup/"]);
+        let fm = parse_front_matter("---\nredirect_from: /old.html\n---\n").front_matter.unwrap();
+        assert_eq!(fm.redirect_from, vec!["/old.html"]);
+        assert!(parse_front_matter("---\ntitle: Test\n---\n").front_matter.unwrap().redirect_from.is_empty());
+    }
+
+    fn yaml_mapping(yaml: &str) -> Mapping {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_defaults_merged_under_page_front_matter() {
+        let defaults = yaml_mapping("title: Default Title\naudience: internal\n");
+        let content = "---\ntitle: Page Title\n---\n\nContent\n";
+        let parsed = parse_front_matter_with_defaults(content, &defaults);
+
+        let fm = parsed.front_matter.unwrap();
+        assert_eq!(fm.title.as_deref(), Some("Page Title"));
+        assert_eq!(fm.extra.get("audience").and_then(|v| v.as_str()), Some("internal"));
+        assert_eq!(parsed.content, "\nContent\n");
+    }
+
+    #[test]
+    fn test_defaults_apply_without_front_matter() {
+        let defaults = yaml_mapping("templating: false\n");
+        let parsed = parse_front_matter_with_defaults("# Plain page\n", &defaults);
+
+        let fm = parsed.front_matter.unwrap();
+        assert!(!fm.templating_enabled());
+        assert_eq!(parsed.content, "# Plain page\n");
+    }
+
+    #[test]
+    fn test_directory_defaults_nesting() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(DEFAULTS_FILE_NAME), "layout: chapter\naudience: public\n").unwrap();
+        fs::create_dir_all(dir.path().join("api/v2")).unwrap();
+        fs::write(dir.path().join("api").join(DEFAULTS_FILE_NAME), "audience: developers\ntags: [api]\n").unwrap();
+
+        let defaults = FrontMatterDefaults::load(dir.path()).unwrap();
+        assert_eq!(defaults.len(), 2);
+
+        let root = defaults.for_page("intro.md");
+        assert_eq!(root.get("audience").and_then(|v| v.as_str()), Some("public"));
+        assert!(root.get("tags").is_none());
+
+        let nested = defaults.for_page("api/v2/users.md");
+        assert_eq!(nested.get("layout").and_then(|v| v.as_str()), Some("chapter"));
+        assert_eq!(nested.get("audience").and_then(|v| v.as_str()), Some("developers"));
+        assert!(nested.get("tags").is_some());
+    }
 }
@@ -39,7 +39,7 @@ impl Glossary {
             }
 
             // Check for term heading (## Term)
-            if trimmed.starts_with("## ") {
+            if let Some(heading) = trimmed.strip_prefix("## ") {
                 // Save previous entry if exists
                 if let Some(term) = current_term.take() {
                     let definition = current_definition.trim().to_string();
@@ -49,7 +49,7 @@ impl Glossary {
                 }
 
                 // Start new entry
-                current_term = Some(trimmed[3..].trim().to_string());
+                current_term = Some(heading.trim().to_string());
                 current_definition.clear();
                 continue;
             }
@@ -71,9 +71,10 @@ impl Glossary {
             }
         }
 
-        // Sort terms by length (longest first) to avoid partial replacements
+        // Sort terms by length (longest first) to avoid partial replacements,
+        // then alphabetically so output doesn't depend on hash order
         let mut sorted_terms: Vec<String> = entries.keys().cloned().collect();
-        sorted_terms.sort_by(|a, b| b.len().cmp(&a.len()));
+        sorted_terms.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
 
         Ok(Self {
             entries,
@@ -457,4 +458,11 @@ Representational State Transfer
         // API outside should be wrapped
         assert!(result.contains(r#"<span class="glossary-term" data-definition="Interface">API</span>."#));
     }
+
+    #[test]
+    fn test_sorted_terms_are_deterministic() {
+        let content = "# Glossary\n\n## API\nA\n\n## CLI\nC\n\n## SDK\nS\n\n## Gateway\nG\n";
+        let glossary = Glossary::parse(content).unwrap();
+        assert_eq!(glossary.sorted_terms, vec!["Gateway", "API", "CLI", "SDK"]);
+    }
 }
@@ -4,8 +4,8 @@ pub mod glossary;
 pub mod langs;
 pub mod summary;
 
-pub use book_config::BookConfig;
-pub use frontmatter::{parse_front_matter, FrontMatter};
+pub use book_config::{get_config_value, set_config_value, validate_config, AlgoliaConfig, AutolinkConfig, BookConfig, BudgetConfig, ByteSize, ExternalLinksConfig, IconsConfig, RobotsConfig, PwaConfig, SearchConfig, SearchServerConfig, SocialCardsConfig, TwitterConfig, GeneratorConfig, LanguagesConfig, LintSeverity, PipelineConfig, PipelineStage, PostprocessorConfig, ServeConfig, ServePass, ThumbnailsConfig, BOOK_SCHEMA};
+pub use frontmatter::{parse_front_matter_with_defaults, FrontMatter, FrontMatterDefaults};
 pub use glossary::{apply_glossary, Glossary};
 pub use langs::Language;
-pub use summary::{Summary, SummaryItem};
+pub use summary::{default_html_path, shared_source, GeneratedPage, PageLink, Summary, SummaryItem, SummaryPosition};
@@ -1,5 +1,11 @@
-use anyhow::Result;
-use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use anyhow::{anyhow, bail, Result};
+use super::frontmatter::parse_front_matter;
+use crate::{paths, warning};
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 
@@ -9,6 +15,22 @@ pub struct Summary {
     #[allow(dead_code)]
     pub title: Option<String>,
     pub items: Vec<SummaryItem>,
+    /// Pages published elsewhere than their default path (part URL prefixes,
+    /// merged entries): default html path -> published html path
+    pub remapped: HashMap<String, String>,
+    /// Entries merging several files (`* [API](api/*.md "merge")`): first file -> all files
+    pub merged: HashMap<String, Vec<String>>,
+    /// Pages generated from data files (`generate` in book.json), by source path
+    pub generated: HashMap<String, GeneratedPage>,
+}
+
+/// A page generated from a data record, rendered like a source file
+#[derive(Debug, Clone)]
+pub struct GeneratedPage {
+    /// Markdown source of the page
+    pub content: String,
+    /// Book-relative data file the page was generated from
+    pub data_file: String,
 }
 
 #[derive(Debug, Clone)]
@@ -25,20 +47,627 @@ pub enum SummaryItem {
     PartTitle(String),
 }
 
+/// A SUMMARY.md entry as seen from another page
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PageLink {
+    pub title: String,
+    /// Published HTML path (None for entries without a link)
+    pub path: Option<String>,
+}
+
+/// Where a page sits in SUMMARY.md
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SummaryPosition {
+    /// The page's own entry
+    pub entry: PageLink,
+    /// Zero-based index among linked entries, in reading order
+    pub index: usize,
+    /// Number of linked entries
+    pub total: usize,
+    pub prev: Option<PageLink>,
+    pub next: Option<PageLink>,
+    /// Enclosing entries, outermost first (breadcrumbs)
+    pub parents: Vec<PageLink>,
+    /// Title of the part (`## Part`) the entry is listed under
+    pub part: Option<String>,
+}
+
 impl Summary {
     pub fn parse(book_dir: &Path) -> Result<Self> {
         let summary_path = book_dir.join("SUMMARY.md");
         let content = fs::read_to_string(&summary_path)?;
-        parse_summary(&content)
+        parse_summary_in(&content, Some(book_dir))
+    }
+
+    /// Whether `source_path` is a chapter source: a file in the book or a generated page
+    pub fn has_source(&self, book_dir: &Path, source_path: &str) -> bool {
+        self.generated.contains_key(source_path) || book_dir.join(source_path).is_file()
+    }
+
+    /// Content of a chapter source (see [`Summary::has_source`])
+    pub fn read_source(&self, book_dir: &Path, source_path: &str) -> std::io::Result<String> {
+        match self.generated.get(source_path) {
+            Some(page) => Ok(page.content.clone()),
+            None => fs::read_to_string(book_dir.join(source_path)),
+        }
+    }
+
+    /// Source files rendered into the page of a SUMMARY.md entry (several for merged entries)
+    pub fn page_sources(&self, source_path: &str) -> Vec<String> {
+        self.merged
+            .get(source_path)
+            .cloned()
+            .unwrap_or_else(|| vec![source_path.to_string()])
+    }
+
+    /// Published HTML path for a source path from SUMMARY.md (anchors are kept)
+    /// e.g. "reference/auth.md#tokens" -> "api/auth.html#tokens" under `## API {prefix=api}`
+    pub fn html_path(&self, source_path: &str) -> String {
+        let source_path = source_path.trim_start_matches('/');
+        let (base, fragment) = paths::split_fragment(source_path);
+        let default = default_html_path(base);
+        let published = self.remapped.get(&default).cloned().unwrap_or(default);
+        format!("{}{}", published, fragment)
+    }
+
+    /// Position of the entry published at `html_path` (the first one if it is listed twice)
+    pub fn position(&self, html_path: &str) -> Option<SummaryPosition> {
+        let mut entries = Vec::new();
+        self.collect_entries(&self.items, &mut Vec::new(), &mut entries);
+        let index = entries.iter().position(|(link, _)| link.path.as_deref() == Some(html_path))?;
+        let (entry, parents) = entries[index].clone();
+        Some(SummaryPosition {
+            entry,
+            index,
+            total: entries.len(),
+            prev: index.checked_sub(1).map(|i| entries[i].0.clone()),
+            next: entries.get(index + 1).map(|(link, _)| link.clone()),
+            parents,
+            part: self.part_of(index),
+        })
+    }
+
+    /// Title of the part holding the `index`th linked entry
+    fn part_of(&self, index: usize) -> Option<String> {
+        let mut part = None;
+        let mut seen = 0;
+        for item in &self.items {
+            match item {
+                SummaryItem::PartTitle(title) => part = Some(title),
+                SummaryItem::Link { .. } => {
+                    let mut entries = Vec::new();
+                    self.collect_entries(std::slice::from_ref(item), &mut Vec::new(), &mut entries);
+                    seen += entries.len();
+                    if index < seen {
+                        return part.cloned();
+                    }
+                }
+                SummaryItem::Separator => {}
+            }
+        }
+        None
+    }
+
+    /// Published HTML paths of all linked entries, without anchors
+    pub fn published_pages(&self) -> HashSet<String> {
+        let mut entries = Vec::new();
+        self.collect_entries(&self.items, &mut Vec::new(), &mut entries);
+        entries
+            .into_iter()
+            .filter_map(|(link, _)| link.path.map(|path| paths::split_fragment(&path).0.to_string()))
+            .collect()
+    }
+
+    /// Linked entries in reading order, with their enclosing entries
+    fn collect_entries(&self, items: &[SummaryItem], parents: &mut Vec<PageLink>, entries: &mut Vec<(PageLink, Vec<PageLink>)>) {
+        for item in items {
+            if let SummaryItem::Link { title, path, children } = item {
+                let link = PageLink { title: title.clone(), path: path.as_ref().map(|p| self.html_path(p)) };
+                if link.path.is_some() {
+                    entries.push((link.clone(), parents.clone()));
+                }
+                parents.push(link);
+                self.collect_entries(children, parents, entries);
+                parents.pop();
+            }
+        }
+    }
+}
+
+/// Language directory and path of a chapter shared from another language of
+/// a multi-language book ("../en/api/ref.md" -> ("en", "api/ref.md"))
+pub fn shared_source(source_path: &str) -> Option<(&str, &str)> {
+    let (dir, path) = source_path.strip_prefix("../")?.split_once('/')?;
+    if dir.is_empty() || dir == ".." || path.is_empty() || path.split('/').any(|part| part == "..") {
+        return None;
+    }
+    Some((dir, path))
+}
+
+/// Longest file name most file systems accept, in bytes
+const MAX_FILE_NAME: usize = 255;
+
+/// Default HTML output path for a source file (.md, .adoc, .asciidoc -> .html)
+/// File names too long for the file system with `.html` are shortened to a
+/// prefix and a hash of the source name, so the output path stays unique
+pub fn default_html_path(source_path: &str) -> String {
+    let source_path = source_path.trim_start_matches('/');
+    let (dir, name) = match source_path.rfind('/') {
+        Some(pos) => source_path.split_at(pos + 1),
+        None => ("", source_path),
+    };
+    let stem = [".md", ".adoc", ".asciidoc"]
+        .iter()
+        .find_map(|ext| name.strip_suffix(ext))
+        .map(|stem| format!("{}.html", stem));
+    let Some(html_name) = stem else {
+        return source_path.to_string();
+    };
+    if html_name.len() <= MAX_FILE_NAME {
+        return format!("{}{}", dir, html_name);
+    }
+
+    let hash = Sha256::digest(name.as_bytes());
+    let suffix = format!("-{:02x}{:02x}{:02x}{:02x}.html", hash[0], hash[1], hash[2], hash[3]);
+    let mut end = MAX_FILE_NAME - suffix.len();
+    while !html_name.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}{}", dir, &html_name[..end], suffix)
+}
+
+/// Read a `prefix=...` attribute from a part heading (`## API {prefix=api}`)
+fn part_prefix(attrs: &[(pulldown_cmark::CowStr, Option<pulldown_cmark::CowStr>)]) -> Option<String> {
+    attrs
+        .iter()
+        .find(|(key, _)| key.as_ref() == "prefix")
+        .and_then(|(_, value)| value.as_ref())
+        .map(|v| v.trim_matches('/').to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Compute published paths for pages under prefixed parts
+///
+/// Pages keep their layout relative to the deepest directory shared by all
+/// source files of the part, so sources can move without changing URLs:
+/// `reference/auth.md` and `reference/v2/users.md` under `{prefix=api}`
+/// publish as `api/auth.html` and `api/v2/users.html`.
+fn compute_remapped_paths(part_pages: &[(String, Vec<String>)]) -> HashMap<String, String> {
+    let mut remapped = HashMap::new();
+
+    for (prefix, pages) in part_pages {
+        let dirs: Vec<Vec<&str>> = pages
+            .iter()
+            .map(|p| {
+                let mut parts: Vec<&str> = p.split('/').collect();
+                parts.pop();
+                parts
+            })
+            .collect();
+        let common_len = dirs
+            .iter()
+            .map(|d| d.len())
+            .min()
+            .map(|min| {
+                (0..min)
+                    .take_while(|&i| dirs.iter().all(|d| d[i] == dirs[0][i]))
+                    .count()
+            })
+            .unwrap_or(0);
+
+        for page in pages {
+            let relative: Vec<&str> = page.split('/').skip(common_len).collect();
+            let default = default_html_path(page);
+            let published = default_html_path(&format!("{}/{}", prefix, relative.join("/")));
+            remapped.insert(default, published);
+        }
+    }
+
+    remapped
+}
+
+/// Collect source paths (without anchors) of all links in `items`
+fn collect_page_paths(items: &[SummaryItem], pages: &mut Vec<String>) {
+    for item in items {
+        if let SummaryItem::Link { path, children, .. } = item {
+            if let Some(p) = path {
+                let base = paths::split_fragment(p).0.trim_start_matches('/');
+                if !base.is_empty() && !pages.iter().any(|existing| existing == base) {
+                    pages.push(base.to_string());
+                }
+            }
+            collect_page_paths(children, pages);
+        }
+    }
+}
+
+/// Whether a SUMMARY.md link merges several files into one page:
+/// a list (`a.md,b.md`) or a glob titled "merge" (`[API](api/*.md "merge")`)
+fn is_merge_spec(path: &str, link_title: &str) -> bool {
+    path.contains(',') || (is_glob(path) && link_title == "merge")
+}
+
+fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?'])
+}
+
+/// Expand a file spec into book-relative files: comma-separated paths or
+/// globs (`*` and `?` within a path segment, `**/` for any directories).
+/// Glob matches are sorted by front matter weight, then title, then path.
+fn expand_file_spec(book_dir: &Path, spec: &str) -> Vec<String> {
+    let mut files: Vec<String> = Vec::new();
+
+    for pattern in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        if !pattern.contains(['*', '?']) {
+            if book_dir.join(pattern).is_file() {
+                files.push(pattern.to_string());
+            } else {
+                warning!("{} not found (in {})", pattern, spec);
+            }
+            continue;
+        }
+
+        let re = glob_regex(pattern);
+        let matched: Vec<String> = paths::walk(book_dir)
+            .into_iter()
+            .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(paths::readable)
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| {
+                paths::relative(book_dir, e.path())
+            })
+            .filter(|p| p != "SUMMARY.md" && re.is_match(p))
+            .collect();
+        files.extend(sort_pages(book_dir, matched).into_iter().map(|(file, _)| file));
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    files.retain(|f| seen.insert(f.clone()));
+    files
+}
+
+/// Regex matching book-relative paths against a glob (`*` and `?` within a
+/// path segment, `**/` for any directories); a plain path matches itself
+pub fn glob_regex(pattern: &str) -> Regex {
+    let mut re = String::from("^");
+    let mut rest = pattern;
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("**/") {
+            re.push_str("(?:.*/)?");
+            rest = after;
+            continue;
+        }
+        match c {
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            _ => re.push_str(&regex::escape(&c.to_string())),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    re.push('$');
+    Regex::new(&re).unwrap()
+}
+
+/// Order sibling pages by front matter `weight` (pages without one last),
+/// then title, then path; returns (file, title)
+fn sort_pages(book_dir: &Path, files: Vec<String>) -> Vec<(String, String)> {
+    let mut pages: Vec<(Option<i64>, String, String)> = files
+        .into_iter()
+        .map(|file| {
+            let (title, weight) = page_info(book_dir, &file);
+            (weight, title, file)
+        })
+        .collect();
+    pages.sort_by(|a, b| {
+        (a.0.is_none(), a.0, a.1.to_lowercase(), &a.2).cmp(&(b.0.is_none(), b.0, b.1.to_lowercase(), &b.2))
+    });
+    pages.into_iter().map(|(_, title, file)| (file, title)).collect()
+}
+
+/// Replace glob entries (`* [API](api/*.md)`) with an unlinked entry listing
+/// one chapter per matching file, in [`sort_pages`] order
+fn expand_glob_entries(book_dir: &Path, items: &mut [SummaryItem]) {
+    for item in items {
+        if let SummaryItem::Link { path, children, .. } = item {
+            expand_glob_entries(book_dir, children);
+            let Some(spec) = path.as_deref().filter(|p| is_glob(p)) else {
+                continue;
+            };
+
+            let files = expand_file_spec(book_dir, spec);
+            if files.is_empty() {
+                warning!("{} matches no files", spec);
+                continue;
+            }
+
+            let expanded = files.into_iter().map(|file| SummaryItem::Link {
+                title: page_info(book_dir, &file).0,
+                path: Some(file),
+                children: Vec::new(),
+            });
+            *children = expanded.chain(children.drain(..)).collect();
+            *path = None;
+        }
+    }
+}
+
+/// Title and sort weight of a page for generated entries: front matter
+/// `title`, else the first `# heading` (`= title` in AsciiDoc), else the file name
+fn page_info(book_dir: &Path, file: &str) -> (String, Option<i64>) {
+    let content = fs::read_to_string(book_dir.join(file)).unwrap_or_default();
+    let parsed = parse_front_matter(&content);
+    let front_matter = parsed.front_matter.unwrap_or_default();
+    let heading_marker = if file.ends_with(".adoc") || file.ends_with(".asciidoc") { "= " } else { "# " };
+    let title = front_matter
+        .title
+        .or_else(|| {
+            parsed
+                .content
+                .lines()
+                .find_map(|line| line.strip_prefix(heading_marker))
+                .map(|heading| heading.trim().to_string())
+        })
+        .unwrap_or_else(|| {
+            let name = file.rsplit('/').next().unwrap_or(file);
+            name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(name).to_string()
+        });
+    (title, front_matter.weight)
+}
+
+/// A list entry line of SUMMARY.md: `<indent><bullet> [title](path)`
+const ENTRY_PATTERN: &str = r"^(\s*)([*+-])\s+\[([^\]]*)\]\(([^)]*)\)";
+
+/// Add `* [title](path)` to SUMMARY.md content as the last child of `parent`
+/// (an entry path or title), or at the end of the list without a parent.
+/// The bullet character, indentation and line endings of the file are kept.
+pub fn insert_entry(content: &str, title: &str, path: &str, parent: Option<&str>) -> Result<String> {
+    let entry_re = Regex::new(ENTRY_PATTERN).unwrap();
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+
+    // (line index, indent, bullet, title, path without anchor)
+    let entries: Vec<(usize, String, String, String, String)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let cap = entry_re.captures(line)?;
+            let target = cap[4].split('#').next().unwrap_or("").trim_start_matches("./").trim_start_matches('/');
+            Some((i, cap[1].to_string(), cap[2].to_string(), cap[3].trim().to_string(), paths::decode(target)))
+        })
+        .collect();
+    if entries.iter().any(|e| e.4 == path) {
+        bail!("{} is already listed in SUMMARY.md", path);
+    }
+
+    let (insert_at, indent, bullet) = match parent {
+        Some(parent) => {
+            let wanted = parent.trim_start_matches("./").trim_start_matches('/');
+            let (index, parent_indent, bullet, _, _) = entries
+                .iter()
+                .find(|e| e.4 == wanted || e.3 == parent)
+                .ok_or_else(|| anyhow!("Parent entry `{}` not found in SUMMARY.md", parent))?;
+            // Insert after the parent's last descendant
+            let width = indent_width(parent_indent);
+            let mut last = *index;
+            for (i, line) in lines.iter().enumerate().skip(index + 1) {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if indent_width(line) <= width {
+                    break;
+                }
+                last = i;
+            }
+            (last + 1, format!("{}{}", parent_indent, indent_unit(&entries)), bullet.clone())
+        }
+        None => {
+            let last = entries.last().map(|e| e.0);
+            let last_list_line = lines
+                .iter()
+                .enumerate()
+                .skip(last.unwrap_or(0))
+                .take_while(|(_, line)| !line.trim_start().starts_with('#'))
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(i, _)| i)
+                .last();
+            let insert_at = match (last, last_list_line) {
+                (Some(_), Some(i)) => i + 1,
+                _ => lines.len(),
+            };
+            let bullet = entries.first().map(|e| e.2.clone()).unwrap_or_else(|| "*".to_string());
+            (insert_at, String::new(), bullet)
+        }
+    };
+
+    lines.insert(insert_at, format!("{}{} [{}]({})", indent, bullet, title, paths::link_destination(path)));
+    let mut result = lines.join(newline);
+    result.push_str(newline);
+    Ok(result)
+}
+
+/// Indentation width of a line (tabs count as 4 columns)
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+/// One nesting level as used by the file: a tab, or the smallest indent of a
+/// nested entry (4 spaces when nothing is nested yet)
+fn indent_unit(entries: &[(usize, String, String, String, String)]) -> String {
+    if entries.iter().any(|e| e.1.starts_with('\t')) {
+        return "\t".to_string();
+    }
+    entries
+        .iter()
+        .map(|e| e.1.len())
+        .filter(|&len| len > 0)
+        .min()
+        .map(|len| " ".repeat(len))
+        .unwrap_or_else(|| "    ".to_string())
+}
+
+/// Files of a book that are never chapters
+const SPECIAL_FILES: [&str; 3] = ["SUMMARY.md", "GLOSSARY.md", "LANGS.md"];
+
+/// Book-relative Markdown and AsciiDoc files, sorted, skipping hidden, `_`
+/// prefixed (e.g. _book) and node_modules directories
+pub fn chapter_files(book_dir: &Path) -> Vec<String> {
+    let mut files: Vec<String> = paths::walk(book_dir)
+        .into_iter()
+        .filter_entry(|e| {
+            let name = e.file_name().to_string_lossy();
+            e.depth() == 0 || !(e.file_type().is_dir() && (name.starts_with('.') || name.starts_with('_') || name == "node_modules"))
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let relative = paths::relative(book_dir, e.path())?;
+            [".md", ".adoc", ".asciidoc"].iter().any(|ext| relative.ends_with(ext)).then_some(relative)
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// SUMMARY.md content listing every chapter file of the book, nested like
+/// its directories. A directory's entry links to its README or index file
+/// (unlinked without one). Entries are ordered like glob entries (front
+/// matter `weight`, then title), files before directories.
+/// existing: current SUMMARY.md content, whose entry titles are kept
+pub fn generate_summary(book_dir: &Path, existing: Option<&str>) -> Result<String> {
+    if book_dir.join("LANGS.md").is_file() {
+        bail!("{:?} is a multi-language book; run this in a language directory", book_dir);
+    }
+    let existing = existing.map(|content| parse_summary_in(content, None)).transpose()?;
+    let mut titles = HashMap::new();
+    if let Some(summary) = &existing {
+        collect_titles(&summary.items, &mut titles);
+    }
+
+    let mut root = DirEntries::default();
+    for file in chapter_files(book_dir).into_iter().filter(|f| !SPECIAL_FILES.contains(&f.as_str())) {
+        let mut dir = &mut root;
+        let mut parts: Vec<&str> = file.split('/').collect();
+        parts.pop();
+        for part in parts {
+            dir = dir.dirs.entry(part.to_string()).or_default();
+        }
+        dir.files.push(file);
+    }
+
+    let heading = existing.and_then(|s| s.title).unwrap_or_else(|| "Summary".to_string());
+    let mut content = format!("# {}\n\n", heading);
+    // The root README is the introduction, not a directory entry
+    let intro = root.files.iter().find(|f| is_index_file(f) && f.to_lowercase().starts_with("readme")).cloned();
+    if let Some(intro) = &intro {
+        let title = titles.get(intro).cloned().unwrap_or_else(|| "Introduction".to_string());
+        content.push_str(&format!("* [{}]({})\n", title, paths::link_destination(intro)));
+    }
+    write_dir_entries(book_dir, &root, intro.as_deref(), &titles, "", &mut content);
+    Ok(content)
+}
+
+/// Files and subdirectories of a directory, for [`generate_summary`]
+#[derive(Default)]
+struct DirEntries {
+    files: Vec<String>,
+    dirs: BTreeMap<String, DirEntries>,
+}
+
+/// Append the entries of `dir` at `indent`, leaving out its index file `skip`
+fn write_dir_entries(
+    book_dir: &Path,
+    dir: &DirEntries,
+    skip: Option<&str>,
+    titles: &HashMap<String, String>,
+    indent: &str,
+    out: &mut String,
+) {
+    let title_of = |file: &str, title: String| titles.get(file).cloned().unwrap_or(title);
+
+    let files: Vec<String> = dir.files.iter().filter(|f| Some(f.as_str()) != skip).cloned().collect();
+    for (file, title) in sort_pages(book_dir, files) {
+        out.push_str(&format!("{}* [{}]({})\n", indent, title_of(&file, title), paths::link_destination(&file)));
+    }
+
+    let mut subdirs: Vec<(String, Option<&String>, &DirEntries)> = dir
+        .dirs
+        .iter()
+        .map(|(name, sub)| {
+            let index = sub.files.iter().find(|f| is_index_file(f));
+            let title = match index {
+                Some(index) => title_of(index, page_info(book_dir, index).0),
+                None => title_from_file_name(name),
+            };
+            (title, index, sub)
+        })
+        .collect();
+    subdirs.sort_by_key(|(title, _, _)| title.to_lowercase());
+
+    for (title, index, sub) in subdirs {
+        match index {
+            Some(index) => out.push_str(&format!("{}* [{}]({})\n", indent, title, paths::link_destination(index))),
+            None => out.push_str(&format!("{}* {}\n", indent, title)),
+        }
+        write_dir_entries(book_dir, sub, index.map(String::as_str), titles, &format!("{}    ", indent), out);
     }
 }
 
+/// README or index file of a directory
+fn is_index_file(file: &str) -> bool {
+    let name = file.rsplit('/').next().unwrap_or(file);
+    let stem = name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(name);
+    stem.eq_ignore_ascii_case("readme") || stem.eq_ignore_ascii_case("index")
+}
+
+/// Titles of the linked entries of a summary, by source path
+fn collect_titles(items: &[SummaryItem], titles: &mut HashMap<String, String>) {
+    for item in items {
+        if let SummaryItem::Link { title, path, children } = item {
+            if let Some(path) = path {
+                let (base, fragment) = paths::split_fragment(path);
+                if fragment.is_empty() {
+                    titles.entry(base.to_string()).or_insert_with(|| title.clone());
+                }
+            }
+            collect_titles(children, titles);
+        }
+    }
+}
+
+/// "getting-started.md" -> "Getting started"
+pub fn title_from_file_name(file: &str) -> String {
+    let name = file.rsplit('/').next().unwrap_or(file);
+    let stem = name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(name);
+    let words = stem.replace(['-', '_'], " ");
+    let mut chars = words.trim().chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => stem.to_string(),
+    }
+}
+
+/// Parse SUMMARY.md content without a book directory (merged entries are not expanded)
+#[cfg(test)]
+pub fn parse_summary(content: &str) -> Result<Summary> {
+    parse_summary_in(content, None)
+}
+
 /// Parse SUMMARY.md content into a Summary structure
 /// Uses pulldown-cmark to parse Markdown structure (like HonKit)
-pub fn parse_summary(content: &str) -> Result<Summary> {
+/// Merged entries are expanded against `book_dir`
+fn parse_summary_in(content: &str, book_dir: Option<&Path>) -> Result<Summary> {
     let mut title = None;
     let mut items = Vec::new();
-    let parser = Parser::new(content);
+    // Heading attributes carry part options such as `## API {prefix=api}`
+    let parser = Parser::new_ext(content, Options::ENABLE_HEADING_ATTRIBUTES);
+
+    // Pages under parts with a URL prefix: (prefix, source paths)
+    let mut part_pages: Vec<(String, Vec<String>)> = Vec::new();
+    let mut current_prefix: Option<String> = None;
+    let mut merged: HashMap<String, Vec<String>> = HashMap::new();
 
     // State tracking
     let mut in_list_stack: Vec<Vec<SummaryItem>> = Vec::new(); // Stack of list items at each depth
@@ -51,10 +680,14 @@ pub fn parse_summary(content: &str) -> Result<Summary> {
     for event in parser {
         match event {
             // Heading (# Title, ## Part, ### Part)
-            Event::Start(Tag::Heading { level, .. }) => {
+            Event::Start(Tag::Heading { level, attrs, .. }) => {
                 in_heading = true;
                 heading_level = level as usize;
                 current_text.clear();
+                if heading_level == 2 || heading_level == 3 {
+                    // A new part starts; it only has a prefix if it declares one
+                    current_prefix = part_prefix(&attrs);
+                }
             }
             Event::End(TagEnd::Heading(_)) => {
                 in_heading = false;
@@ -99,6 +732,18 @@ pub fn parse_summary(content: &str) -> Result<Summary> {
                 if let Some(completed_items) = in_list_stack.pop() {
                     if in_list_stack.is_empty() {
                         // Top-level list completed, add to items
+                        let mut completed_items = completed_items;
+                        if let Some(dir) = book_dir {
+                            expand_glob_entries(dir, &mut completed_items);
+                        }
+                        if let Some(prefix) = &current_prefix {
+                            let mut pages = Vec::new();
+                            collect_page_paths(&completed_items, &mut pages);
+                            match part_pages.iter_mut().find(|(p, _)| p == prefix) {
+                                Some((_, existing)) => existing.extend(pages),
+                                None => part_pages.push((prefix.clone(), pages)),
+                            }
+                        }
                         items.extend(completed_items);
                     } else {
                         // Nested list completed, attach as children to last item in parent
@@ -140,14 +785,44 @@ pub fn parse_summary(content: &str) -> Result<Summary> {
             }
 
             // Link
-            Event::Start(Tag::Link { dest_url, .. }) => {
+            Event::Start(Tag::Link { dest_url, title: link_title, .. }) => {
                 current_text.clear();
                 let path = dest_url.to_string();
                 let path = if path.is_empty() || path == "#" {
                     None
                 } else {
-                    // Normalize path: remove leading ./ and / if present (HonKit compatibility)
-                    Some(path.trim_start_matches("./").trim_start_matches('/').to_string())
+                    // Normalize path: remove leading ./ and / if present (HonKit compatibility),
+                    // and Windows separators. The path is percent-decoded (`my%20notes.md`,
+                    // `C%23.md`); the fragment is kept as written
+                    // A directory link is its README (`guide/` -> `guide/README.md`)
+                    let directory = path[..path.find('#').unwrap_or(path.len())].ends_with('/');
+                    let path = paths::normalize(&path);
+                    let (base, fragment) = path.split_at(path.find('#').unwrap_or(path.len()));
+                    let base = paths::decode(base.trim_start_matches('/'));
+                    let base = match base.trim_end_matches('/') {
+                        _ if !directory => base,
+                        "" => "README.md".to_string(),
+                        dir => format!("{}/README.md", dir),
+                    };
+                    Some(format!("{}{}", base, fragment))
+                };
+                // A merged entry links to its first file, which renders them all
+                let path = match (path, book_dir) {
+                    (Some(spec), Some(dir)) if is_merge_spec(&spec, &link_title) => {
+                        let files = expand_file_spec(dir, &spec);
+                        match files.first() {
+                            Some(first) => {
+                                let first = first.clone();
+                                merged.insert(first.clone(), files);
+                                Some(first)
+                            }
+                            None => {
+                                warning!("{} matches no files", spec);
+                                Some(spec)
+                            }
+                        }
+                    }
+                    (path, _) => path,
                 };
                 current_link = Some((String::new(), path));
             }
@@ -190,13 +865,69 @@ pub fn parse_summary(content: &str) -> Result<Summary> {
         }
     }
 
-    Ok(Summary { title, items })
+    let mut remapped = compute_remapped_paths(&part_pages);
+    // Chapters shared from another language are published at their path there
+    let mut pages = Vec::new();
+    collect_page_paths(&items, &mut pages);
+    for page in &pages {
+        if let Some((_, path)) = shared_source(page) {
+            remapped.entry(default_html_path(page)).or_insert_with(|| default_html_path(path));
+        }
+    }
+    // A directory's README is its index page, unless it has an index.md too
+    for page in &pages {
+        let Some((dir, name)) = page.rsplit_once('/') else { continue };
+        if !name.eq_ignore_ascii_case("README.md") || pages.contains(&format!("{}/index.md", dir)) {
+            continue;
+        }
+        let default = default_html_path(page);
+        let published = remapped.get(&default).cloned().unwrap_or_else(|| default.clone());
+        if let Some((published_dir, _)) = published.rsplit_once('/') {
+            remapped.insert(default, format!("{}/index.html", published_dir));
+        }
+    }
+    // Links to the other files of a merged entry go to the merged page
+    for files in merged.values() {
+        let first = default_html_path(&files[0]);
+        let published = remapped.get(&first).cloned().unwrap_or(first);
+        for file in &files[1..] {
+            remapped.insert(default_html_path(file), published.clone());
+        }
+    }
+
+    Ok(Summary { title, items, remapped, merged, generated: HashMap::new() })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_position() {
+        let summary = parse_summary(
+            "# Summary\n\n* [Intro](README.md)\n* Guide\n    * [Start](guide/start.md)\n        * [Setup](guide/start.md#setup)\n* [API](api.md)\n",
+        )
+        .unwrap();
+        let position = summary.position("guide/start.html").unwrap();
+        assert_eq!(position.index, 1);
+        assert_eq!(position.total, 4);
+        assert_eq!(position.entry.title, "Start");
+        assert_eq!(position.prev.unwrap().path.as_deref(), Some("README.html"));
+        assert_eq!(position.next.unwrap().path.as_deref(), Some("guide/start.html#setup"));
+        assert_eq!(position.parents, vec![PageLink { title: "Guide".to_string(), path: None }]);
+        assert_eq!(position.part, None);
+
+        let last = summary.position("api.html").unwrap();
+        assert!(last.next.is_none());
+        assert!(summary.position("index.html").is_none());
+
+        let summary = parse_summary("# Summary\n\n* [Intro](README.md)\n\n## Reference\n\n* [API](api.md)\n    * [Auth](api/auth.md)\n").unwrap();
+        assert_eq!(summary.position("README.html").unwrap().part, None);
+        let position = summary.position("api/auth.html").unwrap();
+        assert_eq!(position.part.as_deref(), Some("Reference"));
+        assert_eq!(position.parents, vec![PageLink { title: "API".to_string(), path: Some("api.html".to_string()) }]);
+    }
+
     #[test]
     fn test_parse_simple_summary() {
         let content = r#"# Summary
@@ -385,4 +1116,250 @@ mod tests {
             assert_eq!(path.as_deref(), Some("dir/chapter4.md"), "Leading / should be removed from nested path");
         }
     }
+
+    #[test]
+    fn test_directory_readme_is_index() {
+        let content = "# Summary\n\n* [Intro](README.md)\n* [Guide](guide/README.md)\n* [API](./api/#auth)\n* [Ref](ref/README.md)\n* [Ref index](ref/index.md)\n\n## Tools {prefix=tools}\n\n* [CLI](cli/README.md)\n";
+        let summary = parse_summary(content).unwrap();
+        assert_eq!(summary.html_path("README.md"), "README.html");
+        assert_eq!(summary.html_path("guide/README.md"), "guide/index.html");
+        assert_eq!(summary.html_path("api/README.md#auth"), "api/index.html#auth");
+        assert_eq!(summary.html_path("ref/README.md"), "ref/README.html");
+        assert_eq!(summary.html_path("cli/README.md"), "tools/index.html");
+        let position = summary.position("api/index.html#auth").unwrap();
+        assert_eq!(position.prev.unwrap().path.as_deref(), Some("guide/index.html"));
+    }
+
+    #[test]
+    fn test_part_prefix_remaps_pages() {
+        let content = r#"# Summary
+
+* [Introduction](README.md)
+
+## Guide
+
+* [Start](guide/start.md)
+
+## API {prefix=api}
+
+* [Auth](reference/auth.md)
+    * [Users](reference/v2/users.md#list)
+"#;
+
+        let summary = parse_summary(content).unwrap();
+        assert_eq!(summary.items.len(), 5);
+        assert_eq!(summary.remapped.len(), 2);
+        assert_eq!(summary.html_path("README.md"), "README.html");
+        assert_eq!(summary.html_path("guide/start.md"), "guide/start.html");
+        assert_eq!(summary.html_path("reference/auth.md"), "api/auth.html");
+        assert_eq!(summary.html_path("/reference/v2/users.md#list"), "api/v2/users.html#list");
+    }
+
+    #[test]
+    fn test_part_prefix_heading_text() {
+        let content = r#"# Summary
+
+### Reference {prefix=/docs/ref/}
+
+* [Overview](overview.md)
+"#;
+
+        let summary = parse_summary(content).unwrap();
+        if let SummaryItem::PartTitle(title) = &summary.items[0] {
+            assert_eq!(title, "Reference");
+        } else {
+            panic!("Expected part title");
+        }
+        assert_eq!(summary.html_path("overview.md"), "docs/ref/overview.html");
+    }
+
+    #[test]
+    fn test_merged_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("api/v2")).unwrap();
+        for file in ["api/b.md", "api/a.md", "api/v2/c.md", "intro.md", "outro.md"] {
+            fs::write(dir.path().join(file), "# Page").unwrap();
+        }
+        let content = "# Summary\n\n* [API](api/*.md \"merge\")\n* [V2](api/**/c.md \"merge\")\n* [Book](<intro.md, outro.md>)\n";
+
+        let summary = parse_summary_in(content, Some(dir.path())).unwrap();
+        let paths: Vec<&str> = summary
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                SummaryItem::Link { path, .. } => path.as_deref(),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(paths, vec!["api/a.md", "api/v2/c.md", "intro.md"]);
+        assert_eq!(summary.page_sources("api/a.md"), vec!["api/a.md", "api/b.md"]);
+        assert_eq!(summary.page_sources("intro.md"), vec!["intro.md", "outro.md"]);
+        assert_eq!(summary.page_sources("other.md"), vec!["other.md"]);
+        assert_eq!(summary.html_path("outro.md#end"), "intro.html#end");
+    }
+
+    #[test]
+    fn test_glob_regex() {
+        let re = glob_regex("api/*.md");
+        assert!(re.is_match("api/a.md"));
+        assert!(!re.is_match("api/v2/a.md"));
+        let re = glob_regex("**/ch?.md");
+        assert!(re.is_match("ch1.md"));
+        assert!(re.is_match("part/one/ch2.md"));
+        assert!(!re.is_match("ch10.md"));
+    }
+
+    #[test]
+    fn test_glob_entries_expand_to_chapters() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("api")).unwrap();
+        fs::write(dir.path().join("api/users.md"), "# Users API\n").unwrap();
+        fs::write(dir.path().join("api/auth.md"), "---\ntitle: Authentication\n---\n# Auth\n").unwrap();
+        fs::write(dir.path().join("api/zz-first.md"), "---\nweight: 1\n---\nNo heading\n").unwrap();
+        let content = "# Summary\n\n## API {prefix=ref}\n\n* [API](api/*.md)\n    * [Changelog](changelog.md)\n";
+
+        let summary = parse_summary_in(content, Some(dir.path())).unwrap();
+        let SummaryItem::Link { title, path, children } = &summary.items[1] else {
+            panic!("Expected link");
+        };
+        assert_eq!(title, "API");
+        assert_eq!(path, &None);
+        let entries: Vec<(&str, &str)> = children
+            .iter()
+            .filter_map(|item| match item {
+                SummaryItem::Link { title, path: Some(path), .. } => Some((title.as_str(), path.as_str())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("zz-first", "api/zz-first.md"),
+                ("Authentication", "api/auth.md"),
+                ("Users API", "api/users.md"),
+                ("Changelog", "changelog.md"),
+            ]
+        );
+        assert_eq!(summary.html_path("api/users.md"), "ref/api/users.html");
+    }
+
+    #[test]
+    fn test_sort_pages_by_weight_then_title() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.md"), "# Zebra\n").unwrap();
+        fs::write(dir.path().join("b.md"), "# apple\n").unwrap();
+        fs::write(dir.path().join("c.md"), "---\norder: 2\n---\n# Cat\n").unwrap();
+        fs::write(dir.path().join("d.md"), "---\nweight: -1\n---\n# Dog\n").unwrap();
+        fs::write(dir.path().join("e.md"), "# apple\n").unwrap();
+
+        let files = ["a.md", "b.md", "c.md", "d.md", "e.md"].iter().map(|f| f.to_string()).collect();
+        let sorted: Vec<String> = sort_pages(dir.path(), files).into_iter().map(|(file, _)| file).collect();
+        assert_eq!(sorted, vec!["d.md", "c.md", "b.md", "e.md", "a.md"]);
+
+        // Merged globs use the same order
+        assert_eq!(expand_file_spec(dir.path(), "*.md"), vec!["d.md", "c.md", "b.md", "e.md", "a.md"]);
+    }
+
+    #[test]
+    fn test_insert_entry_under_parent() {
+        let content = "# Summary\n\n* [Intro](README.md)\n* [Guide](guide/README.md)\n  * [Install](guide/install.md)\n    * [Linux](guide/linux.md)\n\n* [API](api.md)\n";
+        let updated = insert_entry(content, "Usage", "guide/usage.md", Some("guide/README.md")).unwrap();
+        assert_eq!(
+            updated,
+            "# Summary\n\n* [Intro](README.md)\n* [Guide](guide/README.md)\n  * [Install](guide/install.md)\n    * [Linux](guide/linux.md)\n  * [Usage](guide/usage.md)\n\n* [API](api.md)\n"
+        );
+
+        // Parents can be given by title; tabs and CRLF are kept
+        let content = "# Summary\r\n\r\n- [Guide](guide.md)\r\n\t- [Install](install.md)\r\n";
+        let updated = insert_entry(content, "Usage", "usage.md", Some("Guide")).unwrap();
+        assert_eq!(updated, "# Summary\r\n\r\n- [Guide](guide.md)\r\n\t- [Install](install.md)\r\n\t- [Usage](usage.md)\r\n");
+    }
+
+    #[test]
+    fn test_insert_entry_at_end() {
+        let content = "# Summary\n\n- [Intro](README.md)\n  - [Setup](setup.md)\n\n## Appendix\n\n- [FAQ](faq.md)\n";
+        let updated = insert_entry(content, "Glossary", "glossary.md", None).unwrap();
+        assert!(updated.ends_with("- [FAQ](faq.md)\n- [Glossary](glossary.md)\n"));
+
+        assert!(insert_entry(content, "Again", "setup.md", None).is_err());
+        assert!(insert_entry(content, "Orphan", "orphan.md", Some("Missing")).is_err());
+        let updated = insert_entry(content, "Child", "child.md", Some("FAQ")).unwrap();
+        assert!(updated.ends_with("- [FAQ](faq.md)\n  - [Child](child.md)\n"));
+    }
+
+    #[test]
+    fn test_insert_entry_escapes_path() {
+        let content = "# Summary\n\n- [C#](C%23.md)\n";
+        assert!(insert_entry(content, "Again", "C#.md", None).is_err());
+        let updated = insert_entry(content, "Notes", "my notes (2).md", None).unwrap();
+        assert!(updated.ends_with("- [Notes](my%20notes%20%282%29.md)\n"));
+        let summary = parse_summary(&updated).unwrap();
+        assert!(matches!(&summary.items[1], SummaryItem::Link { path: Some(p), .. } if p == "my notes (2).md"));
+    }
+
+    #[test]
+    fn test_generate_summary_mirrors_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for sub in ["guide/advanced", "api", "_book", ".git"] {
+            fs::create_dir_all(root.join(sub)).unwrap();
+        }
+        fs::write(root.join("README.md"), "# Welcome\n").unwrap();
+        fs::write(root.join("GLOSSARY.md"), "## Term\n").unwrap();
+        fs::write(root.join("my notes.md"), "# Notes\n").unwrap();
+        fs::write(root.join("guide/README.md"), "# Guide\n").unwrap();
+        fs::write(root.join("guide/install.md"), "# Install\n").unwrap();
+        fs::write(root.join("guide/zzz.md"), "---\nweight: 1\n---\n# Zzz first\n").unwrap();
+        fs::write(root.join("guide/advanced/deep.md"), "# Deep\n").unwrap();
+        fs::write(root.join("api/index.adoc"), "= API\n").unwrap();
+        fs::write(root.join("_book/x.md"), "# Output\n").unwrap();
+
+        let existing = "# My Book\n\n* [Start here](README.md)\n* [Installing](guide/install.md)\n* [Gone](gone.md)\n";
+        assert_eq!(
+            generate_summary(root, Some(existing)).unwrap(),
+            "# My Book\n\n\
+             * [Start here](README.md)\n\
+             * [Notes](my%20notes.md)\n\
+             * [API](api/index.adoc)\n\
+             * [Guide](guide/README.md)\n\
+             \x20   * [Zzz first](guide/zzz.md)\n\
+             \x20   * [Installing](guide/install.md)\n\
+             \x20   * Advanced\n\
+             \x20       * [Deep](guide/advanced/deep.md)\n"
+        );
+        assert!(generate_summary(root, None).unwrap().starts_with("# Summary\n\n* [Introduction](README.md)\n"));
+    }
+
+    #[test]
+    fn test_exotic_paths_are_decoded() {
+        let summary = parse_summary(
+            "# Summary\n\n* [Notes](<my notes.md>)\n* [Sharp](C%23/intro.md#set%20up)\n* [Done](100%25.md)\n* [日本語](ガイド/はじめに.md)\n",
+        )
+        .unwrap();
+        let paths: Vec<&str> = summary
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                SummaryItem::Link { path, .. } => path.as_deref(),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(paths, vec!["my notes.md", "C#/intro.md#set%20up", "100%.md", "ガイド/はじめに.md"]);
+        assert_eq!(summary.html_path("C#/intro.md#set%20up"), "C#/intro.html#set%20up");
+    }
+
+    #[test]
+    fn test_default_html_path() {
+        assert_eq!(default_html_path("/guide/start.md"), "guide/start.html");
+        assert_eq!(default_html_path("notes.md.bak/intro.adoc"), "notes.md.bak/intro.html");
+        assert_eq!(default_html_path("C#/100%.asciidoc"), "C#/100%.html");
+        assert_eq!(default_html_path("image.png"), "image.png");
+
+        let name = format!("{}.md", "é".repeat(126));
+        let html = default_html_path(&format!("docs/{}", name));
+        let file = html.strip_prefix("docs/").unwrap();
+        assert!(file.len() <= 255 && file.ends_with(".html"), "{}", file);
+        assert_eq!(html, default_html_path(&format!("docs/{}", name)));
+        assert_ne!(html, default_html_path(&format!("docs/{}.md", "é".repeat(125) + "e")));
+    }
 }
@@ -0,0 +1,303 @@
+//! Book paths on every platform
+//!
+//! Book-relative paths (SUMMARY.md entries, links, output paths) are strings
+//! with `/` separators on every platform. Native paths are converted with
+//! [`to_slash`] or [`relative`] where they enter the book, and paths authored
+//! with Windows separators (`guide\start.md`) are cleaned with [`normalize`].
+//!
+//! Book paths are kept decoded ("my notes.md", "C#.md"): SUMMARY.md links are
+//! percent-decoded when parsed, and hrefs to pages are built with [`encode_href`].
+//!
+//! Symlinks are followed wherever a book is read ([`walk`] for directory
+//! walks): a symlinked chapter or directory (e.g. shared from an Obsidian
+//! vault) is part of the book at the link's path, and its relative links and
+//! `@import`s resolve from there, not from the link target. Broken symlinks
+//! and symlink loops are skipped with a warning.
+
+use crate::warning;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use std::path::{Component, Path, PathBuf};
+use walkdir::{DirEntry, WalkDir};
+
+/// Extensions of files a `#fragment` can follow
+const PAGE_EXTENSIONS: [&str; 4] = [".md", ".adoc", ".asciidoc", ".html"];
+
+/// Characters escaped in the path of an href (non-ASCII is always escaped)
+const HREF_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'\\')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// A native path with `/` separators ("C:/book" for "C:\book")
+pub fn to_slash(path: &Path) -> String {
+    let mut result = String::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(prefix) => result.push_str(&prefix.as_os_str().to_string_lossy()),
+            Component::RootDir => result.push('/'),
+            Component::CurDir => continue,
+            Component::ParentDir | Component::Normal(_) => {
+                if !result.is_empty() && !result.ends_with('/') {
+                    result.push('/');
+                }
+                result.push_str(&component.as_os_str().to_string_lossy());
+            }
+        }
+    }
+    result
+}
+
+/// `path` relative to `root`, with `/` separators; None if it is outside `root`
+pub fn relative(root: &Path, path: &Path) -> Option<String> {
+    path.strip_prefix(root).ok().map(to_slash)
+}
+
+/// A book path with `/` separators, without `.` segments or repeated separators
+/// A leading `/` is kept; `..` segments are left for the caller to resolve
+pub fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty() && *s != ".").collect();
+    let joined = segments.join("/");
+    if path.starts_with('/') {
+        format!("/{}", joined)
+    } else {
+        joined
+    }
+}
+
+/// Number of directories above a book-relative file ("a/b/page.md" -> 2)
+pub fn depth(path: &str) -> usize {
+    normalize(path).trim_start_matches('/').matches('/').count()
+}
+
+/// Whether a path starts with a Windows drive letter ("C:", "C:\", "c:/")
+/// Such paths are absolute on the machine that wrote them, never book-relative
+pub fn has_drive_letter(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes.len() == 2 || bytes[2] == b'/' || bytes[2] == b'\\')
+}
+
+/// Split a book path into the path and its `#fragment` ("a.md#setup" -> ("a.md", "#setup"))
+/// File names may contain `#`: the fragment starts at the first `#` after a
+/// page file name ("C#.md#setup" -> ("C#.md", "#setup"))
+pub fn split_fragment(path: &str) -> (&str, &str) {
+    let is_page = |p: &str| {
+        let p = p.to_ascii_lowercase();
+        PAGE_EXTENSIONS.iter().any(|ext| p.ends_with(ext))
+    };
+    let split = path
+        .match_indices('#')
+        .map(|(i, _)| i)
+        .find(|&i| is_page(&path[..i]))
+        .or_else(|| if is_page(path) { None } else { path.find('#') });
+    match split {
+        Some(i) => path.split_at(i),
+        None => (path, ""),
+    }
+}
+
+/// A percent-encoded URL path decoded to a book path ("my%20notes.md" -> "my notes.md")
+pub fn decode(path: &str) -> String {
+    percent_decode_str(path).decode_utf8_lossy().into_owned()
+}
+
+/// href of a book path: the path is percent-encoded, a `#fragment` is kept as written
+/// ("C#/100% done.html#top" -> "C%23/100%25%20done.html#top")
+pub fn encode_href(path: &str) -> String {
+    let (path, fragment) = split_fragment(path);
+    format!("{}{}", utf8_percent_encode(path, HREF_ENCODE_SET), fragment)
+}
+
+/// A book path written as a Markdown link destination: characters that
+/// would end the link or be decoded are escaped ("C# notes.md" -> "C%23%20notes.md")
+pub fn link_destination(path: &str) -> String {
+    let mut result = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            ' ' => result.push_str("%20"),
+            '#' => result.push_str("%23"),
+            '%' => result.push_str("%25"),
+            '(' => result.push_str("%28"),
+            ')' => result.push_str("%29"),
+            '<' => result.push_str("%3C"),
+            '>' => result.push_str("%3E"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Decoded value of a parameter in the query string of a request URL
+/// ("/__search?q=c%23+basics", "q" -> "c# basics")
+pub fn query_param(url: &str, name: &str) -> Option<String> {
+    let query = url.split_once('?')?.1;
+    query.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        (key == name).then(|| percent_decode_str(&value.replace('+', " ")).decode_utf8_lossy().into_owned())
+    })
+}
+
+/// Walk a book directory, following symlinks
+pub fn walk(dir: &Path) -> WalkDir {
+    WalkDir::new(dir).follow_links(true)
+}
+
+/// An entry of a [`walk`]; None, with a warning, for a broken symlink or a symlink loop
+pub fn readable(entry: walkdir::Result<DirEntry>) -> Option<DirEntry> {
+    entry.map_err(|e| warning!("skipping {}", e)).ok()
+}
+
+/// Targets of the symlinks under `dir` that are outside it, for watching
+/// (hidden, `_book` and node_modules directories are skipped)
+pub fn external_symlink_targets(dir: &Path) -> Vec<PathBuf> {
+    let Ok(root) = dir.canonicalize() else {
+        return Vec::new();
+    };
+    let mut targets: Vec<PathBuf> = walk(&root)
+        .into_iter()
+        .filter_entry(|e| {
+            let name = e.file_name().to_string_lossy();
+            e.depth() == 0 || !(name.starts_with('.') || name == "_book" || name == "node_modules")
+        })
+        .filter_map(Result::ok)
+        .filter(|e| e.path_is_symlink())
+        .filter_map(|e| e.path().canonicalize().ok())
+        .filter(|target| !target.starts_with(&root))
+        .collect();
+    targets.sort();
+    targets.dedup();
+    targets
+}
+
+/// Content-Type of an output file, by extension
+pub fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "application/javascript; charset=utf-8",
+        Some("json") => "application/json; charset=utf-8",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("ico") => "image/x-icon",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_to_slash() {
+        let path: PathBuf = ["guide", "img", "shot.png"].iter().collect();
+        assert_eq!(to_slash(&path), "guide/img/shot.png");
+        assert_eq!(to_slash(Path::new("./guide/../x.md")), "guide/../x.md");
+        assert_eq!(to_slash(Path::new("")), "");
+        let root: PathBuf = ["book"].iter().collect();
+        assert_eq!(relative(&root, &root.join("guide").join("start.md")).as_deref(), Some("guide/start.md"));
+        assert_eq!(relative(&root, Path::new("other/x.md")), None);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_to_slash_windows() {
+        assert_eq!(to_slash(Path::new(r"guide\img\shot.png")), "guide/img/shot.png");
+        assert_eq!(to_slash(Path::new(r"C:\books\guide")), "C:/books/guide");
+        assert_eq!(relative(Path::new(r"C:\book"), Path::new(r"C:\book\a\b.md")).as_deref(), Some("a/b.md"));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(r"guide\setup\start.md"), "guide/setup/start.md");
+        assert_eq!(normalize(r".\guide\\.\start.md"), "guide/start.md");
+        assert_eq!(normalize("/guide//start.md"), "/guide/start.md");
+        assert_eq!(normalize(r"..\shared\intro.md"), "../shared/intro.md");
+        assert_eq!(normalize("start.md#setup"), "start.md#setup");
+    }
+
+    #[test]
+    fn test_depth() {
+        assert_eq!(depth("start.md"), 0);
+        assert_eq!(depth(r"Customer\AssetStatus\PortfolioTop.md"), 2);
+        assert_eq!(depth("./a/b.md"), 1);
+        assert_eq!(depth("/a/b.md"), 1);
+    }
+
+    #[test]
+    fn test_split_fragment() {
+        assert_eq!(split_fragment("guide/start.md#setup"), ("guide/start.md", "#setup"));
+        assert_eq!(split_fragment("C#.md"), ("C#.md", ""));
+        assert_eq!(split_fragment("C#/intro.md#a#b"), ("C#/intro.md", "#a#b"));
+        assert_eq!(split_fragment("Notes.HTML#x"), ("Notes.HTML", "#x"));
+        assert_eq!(split_fragment("guide/#setup"), ("guide/", "#setup"));
+        assert_eq!(split_fragment("start.md"), ("start.md", ""));
+    }
+
+    #[test]
+    fn test_encode_href() {
+        assert_eq!(encode_href("C#/100% done.html#top"), "C%23/100%25%20done.html#top");
+        assert_eq!(encode_href("ガイド/はじめに.html"), "%E3%82%AC%E3%82%A4%E3%83%89/%E3%81%AF%E3%81%98%E3%82%81%E3%81%AB.html");
+        assert_eq!(encode_href("a-b_c.d~(1).html"), "a-b_c.d~(1).html");
+        assert_eq!(decode(&encode_href("C#/100% done?.html")), "C#/100% done?.html");
+        assert_eq!(decode("100%.md"), "100%.md");
+    }
+
+    #[test]
+    fn test_query_param() {
+        assert_eq!(query_param("/__search?q=c%23+basics", "q").as_deref(), Some("c# basics"));
+        assert_eq!(query_param("/__search?v=1&q=%E3%82%AC", "q").as_deref(), Some("ガ"));
+        assert_eq!(query_param("/__search?q", "q").as_deref(), Some(""));
+        assert_eq!(query_param("/__search?qq=x", "q"), None);
+        assert_eq!(query_param("/__search", "q"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_external_symlink_targets() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let book = dir.path().join("book");
+        let vault = dir.path().join("vault");
+        std::fs::create_dir_all(book.join("guide")).unwrap();
+        std::fs::create_dir_all(vault.join("notes")).unwrap();
+        std::fs::write(vault.join("shared.md"), "").unwrap();
+        std::fs::write(book.join("intro.md"), "").unwrap();
+        symlink(vault.join("notes"), book.join("notes")).unwrap();
+        symlink(vault.join("shared.md"), book.join("guide/shared.md")).unwrap();
+        symlink(book.join("intro.md"), book.join("guide/intro.md")).unwrap();
+        symlink(vault.join("shared.md"), book.join("notes-shared.md")).unwrap();
+
+        let vault = vault.canonicalize().unwrap();
+        assert_eq!(external_symlink_targets(&book), vec![vault.join("notes"), vault.join("shared.md")]);
+    }
+
+    #[test]
+    fn test_has_drive_letter() {
+        assert!(has_drive_letter(r"C:\images\logo.png"));
+        assert!(has_drive_letter("d:/book"));
+        assert!(has_drive_letter("C:"));
+        assert!(!has_drive_letter("images/logo.png"));
+        assert!(!has_drive_letter("mailto:someone@example.com"));
+        assert!(!has_drive_letter("ab:c"));
+    }
+}
@@ -0,0 +1,59 @@
+// Sidebar of books built with "sidebar": {"external": true}
+// Renders the navigation (window.guidebookNav, written before this script in
+// gitbook/nav.js) into the page's sidebar the way the build would: links
+// start from the page's root path and its own entry is marked active.
+// Loaded right after the sidebar list, so gitbook.js and collapsible.js find
+// it rendered.
+
+(function() {
+    'use strict';
+
+    var nav = window.guidebookNav;
+    var list = document.querySelector('.book-summary ul.summary');
+    if (!nav || !list) return;
+
+    var rootPath = document.body.getAttribute('data-root-path') || '';
+    var activeHref = list.getAttribute('data-active');
+
+    function render(items, parent) {
+        items.forEach(function(item) {
+            var li = document.createElement('li');
+            if (item.divider) {
+                li.className = 'divider';
+            } else if (item.part !== undefined) {
+                li.className = 'part-title';
+                var span = document.createElement('span');
+                span.textContent = item.part;
+                li.appendChild(span);
+            } else {
+                var children = item.children || [];
+                var classes = ['chapter'];
+                if (item.href !== undefined && item.href === activeHref) classes.push('active');
+                if (children.length && nav.collapsible) classes.push('expandable');
+                if (children.length) classes.push('expanded');
+                li.className = classes.join(' ');
+
+                var title;
+                if (item.href !== undefined) {
+                    title = document.createElement('a');
+                    title.setAttribute('href', rootPath + item.href);
+                } else {
+                    title = document.createElement('span');
+                    title.className = 'chapter-title';
+                }
+                title.textContent = item.title;
+                li.appendChild(title);
+
+                if (children.length) {
+                    var articles = document.createElement('ul');
+                    articles.className = 'articles';
+                    render(children, articles);
+                    li.appendChild(articles);
+                }
+            }
+            parent.appendChild(li);
+        });
+    }
+
+    render(nav.items, list);
+})();
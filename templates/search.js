@@ -8,9 +8,21 @@
     var searchResults = document.querySelector('.search-results');
     var searchWrapper = document.querySelector('.search-wrapper');
     var rootPath = document.body.getAttribute('data-root-path') || '';
+    // Set by `guidebook serve`, which answers queries itself
+    var searchApi = document.body.getAttribute('data-search-api');
 
     if (!searchInput || !searchResults) return;
 
+    // Settings from the `search` section of book.json
+    var shortcut = searchWrapper ? searchWrapper.getAttribute('data-shortcut') || '' : '';
+    var historySize = searchWrapper ? parseInt(searchWrapper.getAttribute('data-history'), 10) || 0 : 0;
+    // Meilisearch or Typesense instance answering the queries
+    var searchServer = searchWrapper && searchWrapper.getAttribute('data-search-server') ?
+        JSON.parse(searchWrapper.getAttribute('data-search-server')) : null;
+
+    // Recent queries are kept per book, books on one site don't share them
+    var historyKey = 'guidebook-search-history:' + new URL(rootPath || './', window.location.href).pathname;
+
     // Load search index
     function loadSearchIndex() {
         if (searchIndex) return Promise.resolve(searchIndex);
@@ -40,7 +52,9 @@
         var results = [];
 
         searchIndex.forEach(function(entry) {
-            var titleMatch = entry.title.toLowerCase().indexOf(lowerQuery);
+            // Sections are matched by their heading, pages by their title
+            var heading = entry.section || entry.title;
+            var titleMatch = heading.toLowerCase().indexOf(lowerQuery);
             var contentMatch = entry.content.toLowerCase().indexOf(lowerQuery);
 
             if (titleMatch !== -1 || contentMatch !== -1) {
@@ -48,20 +62,13 @@
                 if (titleMatch !== -1) score += 10;
                 if (contentMatch !== -1) score += 1;
 
-                // Extract snippet around match
-                var snippet = '';
-                if (contentMatch !== -1) {
-                    var start = Math.max(0, contentMatch - 50);
-                    var end = Math.min(entry.content.length, contentMatch + query.length + 50);
-                    snippet = (start > 0 ? '...' : '') +
-                              entry.content.substring(start, end) +
-                              (end < entry.content.length ? '...' : '');
-                }
-
                 results.push({
                     title: entry.title,
+                    section: entry.section,
+                    part: entry.part,
                     path: entry.path,
-                    snippet: snippet,
+                    snippet: snippetOf(entry.content, query),
+                    reading_minutes: entry.reading_minutes,
                     score: score
                 });
             }
@@ -75,6 +82,67 @@
         return results.slice(0, 10); // Limit to 10 results
     }
 
+    // Text around the first match in the content
+    function snippetOf(content, query) {
+        var match = content.toLowerCase().indexOf(query.toLowerCase());
+        if (match === -1) return '';
+        var start = Math.max(0, match - 50);
+        var end = Math.min(content.length, match + query.length + 50);
+        return (start > 0 ? '...' : '') +
+               content.substring(start, end) +
+               (end < content.length ? '...' : '');
+    }
+
+    // Query the Meilisearch or Typesense instance; its documents are the
+    // entries of the search index
+    function searchOnServer(query) {
+        var index = encodeURIComponent(searchServer.index);
+        var request = searchServer.engine === 'typesense' ?
+            fetch(searchServer.url + '/collections/' + index + '/documents/search?per_page=10' +
+                  '&query_by=title,heading,content&q=' + encodeURIComponent(query), {
+                headers: { 'X-TYPESENSE-API-KEY': searchServer.apiKey }
+            }) :
+            fetch(searchServer.url + '/indexes/' + index + '/search', {
+                method: 'POST',
+                headers: { 'Authorization': 'Bearer ' + searchServer.apiKey, 'Content-Type': 'application/json' },
+                body: JSON.stringify({ q: query, limit: 10 })
+            });
+        return request
+            .then(function(response) {
+                if (!response.ok) throw new Error('Search request failed');
+                return response.json();
+            })
+            .then(function(data) {
+                return data.hits.map(function(hit) {
+                    var document = hit.document || hit;
+                    return {
+                        title: document.title,
+                        section: document.heading,
+                        part: document.part,
+                        path: document.url,
+                        snippet: snippetOf(document.content, query),
+                        reading_minutes: document.reading_minutes
+                    };
+                });
+            });
+    }
+
+    // Group results by the SUMMARY.md part of their page, parts in the order
+    // of their best result
+    function groupByPart(results) {
+        var groups = [];
+        var byPart = {};
+        results.forEach(function(result) {
+            var part = result.part || '';
+            if (!byPart.hasOwnProperty(part)) {
+                byPart[part] = { part: part, results: [] };
+                groups.push(byPart[part]);
+            }
+            byPart[part].results.push(result);
+        });
+        return groups;
+    }
+
     // Render search results
     function renderResults(results, query) {
         if (results.length === 0) {
@@ -82,19 +150,114 @@
             return;
         }
 
-        var html = results.map(function(result) {
-            var highlightedTitle = highlightMatch(result.title, query);
-            var highlightedSnippet = result.snippet ? highlightMatch(result.snippet, query) : '';
+        var groups = groupByPart(results);
+        // Books without parts get a plain list
+        var showParts = groups.length > 1 || groups[0].part;
 
-            return '<a class="search-result-item" href="' + rootPath + result.path + '">' +
-                   '<div class="search-result-title">' + highlightedTitle + '</div>' +
-                   (highlightedSnippet ? '<div class="search-result-snippet">' + highlightedSnippet + '</div>' : '') +
-                   '</a>';
+        var html = groups.map(function(group) {
+            var header = showParts && group.part ?
+                '<div class="search-result-group">' + escapeHtml(group.part) + '</div>' : '';
+            return header + group.results.map(function(result) {
+                var highlightedTitle = highlightMatch(result.title, query);
+                if (result.section) {
+                    highlightedTitle += ' <span class="search-result-section">› ' + highlightMatch(result.section, query) + '</span>';
+                } else if (result.reading_minutes) {
+                    highlightedTitle += ' <span class="search-result-time">' + result.reading_minutes + ' min</span>';
+                }
+                var highlightedSnippet = result.snippet ? highlightMatch(result.snippet, query) : '';
+
+                return '<a class="search-result-item" href="' + rootPath + result.path + '">' +
+                       '<div class="search-result-title">' + highlightedTitle + '</div>' +
+                       (highlightedSnippet ? '<div class="search-result-snippet">' + highlightedSnippet + '</div>' : '') +
+                       '</a>';
+            }).join('');
         }).join('');
 
         searchResults.innerHTML = html;
     }
 
+    // Recent queries, most recent first
+    function loadHistory() {
+        if (historySize <= 0) return [];
+        try {
+            var stored = JSON.parse(window.localStorage.getItem(historyKey) || '[]');
+            return Array.isArray(stored) ? stored.slice(0, historySize) : [];
+        } catch (e) {
+            // Storage disabled or corrupted
+            return [];
+        }
+    }
+
+    function saveQuery(query) {
+        if (historySize <= 0 || !query) return;
+        var history = loadHistory().filter(function(previous) {
+            return previous !== query;
+        });
+        history.unshift(query);
+        try {
+            window.localStorage.setItem(historyKey, JSON.stringify(history.slice(0, historySize)));
+        } catch (e) {
+            // Storage disabled or full: the history is only a convenience
+        }
+    }
+
+    // Show recent queries in place of results while the search box is empty
+    function renderHistory() {
+        var history = loadHistory();
+        if (history.length === 0) {
+            searchResults.classList.remove('visible');
+            return;
+        }
+
+        searchResults.innerHTML = '<div class="search-result-group">Recent searches</div>' +
+            history.map(function(query) {
+                return '<a class="search-result-item" href="#" data-query="' + escapeHtml(query) + '">' +
+                       '<div class="search-result-title">' + escapeHtml(query) + '</div>' +
+                       '</a>';
+            }).join('');
+        searchResults.classList.add('visible');
+    }
+
+    // Results of the latest query only: earlier responses may arrive late
+    var latestQuery = null;
+
+    function showResults(results, query) {
+        if (query !== latestQuery) return;
+        renderResults(results, query);
+        searchResults.classList.add('visible');
+    }
+
+    function searchLocally(query) {
+        return loadSearchIndex().then(function() {
+            return search(query);
+        });
+    }
+
+    function runSearch(query) {
+        latestQuery = query;
+        var results = searchApi ?
+            fetch(rootPath + searchApi + '?q=' + encodeURIComponent(query))
+                .then(function(response) {
+                    if (!response.ok) throw new Error('Search request failed');
+                    return response.json();
+                })
+                .catch(function() {
+                    // Server gone or too old: search the index in the browser
+                    searchApi = null;
+                    return searchLocally(query);
+                }) :
+            searchServer ?
+            searchOnServer(query).catch(function(err) {
+                console.error('Search server failed:', err);
+                searchServer = null;
+                return searchLocally(query);
+            }) :
+            searchLocally(query);
+        results.then(function(results) {
+            showResults(results, query);
+        });
+    }
+
     // Highlight matching text
     function highlightMatch(text, query) {
         if (!query) return escapeHtml(text);
@@ -107,13 +270,25 @@
     function escapeHtml(text) {
         var div = document.createElement('div');
         div.textContent = text;
-        return div.innerHTML;
+        return div.innerHTML.replace(/"/g, '&quot;');
     }
 
     function escapeRegex(str) {
         return str.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
     }
 
+    // Move the keyboard selection through the listed items
+    function moveSelection(step) {
+        var items = Array.prototype.slice.call(searchResults.querySelectorAll('.search-result-item'));
+        if (items.length === 0) return;
+
+        var current = items.indexOf(searchResults.querySelector('.search-result-item.active'));
+        var next = current === -1 ? (step > 0 ? 0 : items.length - 1) : (current + step + items.length) % items.length;
+        if (current !== -1) items[current].classList.remove('active');
+        items[next].classList.add('active');
+        items[next].scrollIntoView({ block: 'nearest' });
+    }
+
     // Event handlers
     var debounceTimer = null;
 
@@ -123,22 +298,36 @@
         clearTimeout(debounceTimer);
 
         if (query.length < 1) {
-            searchResults.classList.remove('visible');
+            renderHistory();
             return;
         }
 
         debounceTimer = setTimeout(function() {
-            loadSearchIndex().then(function() {
-                var results = search(query);
-                renderResults(results, query);
-                searchResults.classList.add('visible');
-            });
+            runSearch(query);
         }, 200);
     });
 
     searchInput.addEventListener('focus', function() {
         if (this.value.trim().length >= 1) {
             searchResults.classList.add('visible');
+        } else {
+            renderHistory();
+        }
+    });
+
+    // Opening a result remembers its query, picking a recent query runs it again
+    searchResults.addEventListener('click', function(e) {
+        var item = e.target.closest('.search-result-item');
+        if (!item) return;
+
+        var query = item.getAttribute('data-query');
+        if (query !== null) {
+            e.preventDefault();
+            searchInput.value = query;
+            searchInput.focus();
+            runSearch(query);
+        } else {
+            saveQuery(searchInput.value.trim());
         }
     });
 
@@ -154,11 +343,38 @@
         if (e.key === 'Escape') {
             searchResults.classList.remove('visible');
             this.blur();
+        } else if (e.key === 'ArrowDown' || e.key === 'ArrowUp') {
+            e.preventDefault();
+            moveSelection(e.key === 'ArrowDown' ? 1 : -1);
+        } else if (e.key === 'Enter') {
+            var active = searchResults.querySelector('.search-result-item.active') ||
+                         searchResults.querySelector('.search-result-item');
+            if (active) {
+                e.preventDefault();
+                active.click();
+            }
         }
     });
 
+    // Keyboard shortcut to open the search from anywhere on the page
+    document.addEventListener('keydown', function(e) {
+        if (!shortcut || e.key !== shortcut || e.ctrlKey || e.metaKey || e.altKey) return;
+
+        // Typing in a field isn't a shortcut
+        var target = e.target;
+        if (target.isContentEditable || /^(INPUT|TEXTAREA|SELECT)$/.test(target.tagName)) return;
+
+        e.preventDefault();
+        searchInput.focus();
+        searchInput.select();
+    });
+
+    if (shortcut) {
+        searchInput.setAttribute('placeholder', 'Search... (' + shortcut + ')');
+    }
+
     // Preload search index on first focus
     searchInput.addEventListener('focus', function() {
-        loadSearchIndex();
+        if (!searchApi) loadSearchIndex();
     }, { once: true });
 })();
@@ -0,0 +1,74 @@
+// Service worker for Guidebook (`pwa` in book.json)
+// The build fills in its version and the files it precaches
+
+(function() {
+    'use strict';
+
+    var VERSION = '__VERSION__';
+    var PRECACHE = [/* __PRECACHE__ */];
+
+    // Books on one site keep their caches apart
+    var prefix = 'guidebook:' + self.registration.scope + ':';
+    var cacheName = prefix + VERSION;
+
+    self.addEventListener('install', function(event) {
+        event.waitUntil(
+            caches.open(cacheName)
+                .then(function(cache) { return cache.addAll(PRECACHE); })
+                .then(function() { return self.skipWaiting(); })
+        );
+    });
+
+    // Drop the copies of previous builds
+    self.addEventListener('activate', function(event) {
+        event.waitUntil(
+            caches.keys()
+                .then(function(names) {
+                    return Promise.all(names
+                        .filter(function(name) { return name.indexOf(prefix) === 0 && name !== cacheName; })
+                        .map(function(name) { return caches.delete(name); }));
+                })
+                .then(function() { return self.clients.claim(); })
+        );
+    });
+
+    // Cached copy first; "guide/" is "guide/index.html", "guide/start" is "guide/start.html"
+    function cached(request) {
+        var url = new URL(request.url);
+        url.hash = '';
+        var candidates = [url.href];
+        if (url.pathname.slice(-1) === '/') {
+            candidates.push(url.href.replace(/(\?|$)/, 'index.html$1'));
+        } else if (!/\.[^/]*$/.test(url.pathname)) {
+            candidates.push(url.href.replace(/(\?|$)/, '.html$1'));
+        }
+        return candidates.reduce(function(found, candidate) {
+            return found.then(function(response) {
+                return response || caches.match(candidate, { cacheName: cacheName, ignoreSearch: true });
+            });
+        }, Promise.resolve(null));
+    }
+
+    self.addEventListener('fetch', function(event) {
+        var request = event.request;
+        if (request.method !== 'GET' || new URL(request.url).origin !== self.location.origin) return;
+
+        event.respondWith(cached(request).then(function(response) {
+            if (response) return response;
+            return fetch(request)
+                .then(function(response) {
+                    if (response.ok) {
+                        var copy = response.clone();
+                        caches.open(cacheName).then(function(cache) { cache.put(request, copy); });
+                    }
+                    return response;
+                })
+                .catch(function(error) {
+                    // Offline, at a page the book doesn't have
+                    if (request.mode !== 'navigate') throw error;
+                    return caches.match(new URL('404.html', self.registration.scope).href, { cacheName: cacheName })
+                        .then(function(page) { if (page) return page; throw error; });
+                });
+        }));
+    });
+})();
@@ -0,0 +1,14 @@
+//! Fixture books under tests/books/ against their snapshots (see `guidebook test`)
+
+use guidebook::builder::snapshot;
+use std::path::Path;
+
+#[test]
+fn test_fixture_books_match_snapshots() {
+    let tests = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    for name in snapshot::fixture_books(&tests.join("books")).unwrap() {
+        let changes = snapshot::test_book(&tests.join("books").join(&name), &tests.join("snapshots").join(&name), false).unwrap();
+        let changes: Vec<String> = changes.iter().map(ToString::to_string).collect();
+        assert!(changes.is_empty(), "{} differs from its snapshot (accept with `guidebook test --update`):\n{}", name, changes.join("\n"));
+    }
+}